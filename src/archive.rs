@@ -0,0 +1,339 @@
+// Module for exporting/importing a whole project as a single ZIP archive, for
+// sharing and backups. Only the subset of the ZIP format needed for this is
+// supported: entries are written deflate-compressed, and may be read either
+// stored or deflated. ZIP64, encryption, and multi-disk archives are not supported.
+use std::{
+    fs,
+    io::{Read, Write},
+    path::{Component, Path},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use hashbrown::{HashMap, HashSet};
+use log::info;
+
+use crate::{
+    persist::{save_project, PROJECT_DATA_LOCATIONS},
+    state::{Area, EditorState, Palette, Stamp, Theme, WorldMap},
+};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIR_SIGNATURE: u32 = 0x06054b50;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+// Flag indicating that file names are encoded as UTF-8:
+const FLAG_UTF8: u16 = 0x0800;
+const VERSION: u16 = 20;
+// Fixed modification date of 1980-01-01 (in MS-DOS format), to keep archives reproducible:
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+fn put_u16(out: &mut Vec<u8>, x: u16) {
+    out.extend(x.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, x: u32) {
+    out.extend(x.to_le_bytes());
+}
+
+fn get_u16(data: &[u8], pos: usize) -> Result<u16> {
+    let bytes = data.get(pos..pos + 2).context("truncated archive")?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn get_u32(data: &[u8], pos: usize) -> Result<u32> {
+    let bytes = data.get(pos..pos + 4).context("truncated archive")?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// Encode the given (name, contents) pairs as a ZIP archive.
+pub fn write_zip(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+    let mut out: Vec<u8> = vec![];
+    let mut central_dir: Vec<u8> = vec![];
+    for (name, data) in files {
+        let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let crc = crc32fast::hash(data);
+        let offset = out.len() as u32;
+
+        // Fields shared by the local and central headers, from "version needed" to "extra length":
+        let mut common: Vec<u8> = vec![];
+        put_u16(&mut common, VERSION);
+        put_u16(&mut common, FLAG_UTF8);
+        put_u16(&mut common, METHOD_DEFLATE);
+        put_u16(&mut common, 0);
+        put_u16(&mut common, DOS_DATE);
+        put_u32(&mut common, crc);
+        put_u32(&mut common, compressed.len() as u32);
+        put_u32(&mut common, data.len() as u32);
+        put_u16(&mut common, name.len() as u16);
+        put_u16(&mut common, 0);
+
+        put_u32(&mut out, LOCAL_HEADER_SIGNATURE);
+        out.extend(&common);
+        out.extend(name.as_bytes());
+        out.extend(&compressed);
+
+        put_u32(&mut central_dir, CENTRAL_HEADER_SIGNATURE);
+        put_u16(&mut central_dir, VERSION);
+        central_dir.extend(&common);
+        put_u16(&mut central_dir, 0); // comment length
+        put_u16(&mut central_dir, 0); // disk number
+        put_u16(&mut central_dir, 0); // internal attributes
+        put_u32(&mut central_dir, 0); // external attributes
+        put_u32(&mut central_dir, offset);
+        central_dir.extend(name.as_bytes());
+    }
+    let central_dir_offset = out.len() as u32;
+    out.extend(&central_dir);
+    put_u32(&mut out, END_OF_CENTRAL_DIR_SIGNATURE);
+    put_u16(&mut out, 0);
+    put_u16(&mut out, 0);
+    put_u16(&mut out, files.len() as u16);
+    put_u16(&mut out, files.len() as u16);
+    put_u32(&mut out, central_dir.len() as u32);
+    put_u32(&mut out, central_dir_offset);
+    put_u16(&mut out, 0);
+    Ok(out)
+}
+
+// Decode a ZIP archive into (name, contents) pairs. Directory entries are skipped.
+pub fn read_zip(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    // The end-of-central-directory record is at the end, possibly followed by a comment:
+    let eocd = (0..data.len().saturating_sub(21))
+        .rev()
+        .find(|&i| get_u32(data, i).ok() == Some(END_OF_CENTRAL_DIR_SIGNATURE))
+        .context("not a ZIP archive")?;
+    let num_entries = get_u16(data, eocd + 10)? as usize;
+    let mut pos = get_u32(data, eocd + 16)? as usize;
+
+    let mut files = vec![];
+    for _ in 0..num_entries {
+        ensure!(
+            get_u32(data, pos)? == CENTRAL_HEADER_SIGNATURE,
+            "invalid central directory"
+        );
+        let flags = get_u16(data, pos + 8)?;
+        let method = get_u16(data, pos + 10)?;
+        let crc = get_u32(data, pos + 16)?;
+        let compressed_size = get_u32(data, pos + 20)? as usize;
+        let size = get_u32(data, pos + 24)? as usize;
+        let name_len = get_u16(data, pos + 28)? as usize;
+        let extra_len = get_u16(data, pos + 30)? as usize;
+        let comment_len = get_u16(data, pos + 32)? as usize;
+        let offset = get_u32(data, pos + 42)? as usize;
+        let name_bytes = data
+            .get(pos + 46..pos + 46 + name_len)
+            .context("truncated archive")?;
+        let name = String::from_utf8(name_bytes.to_vec()).context("invalid file name")?;
+        pos += 46 + name_len + extra_len + comment_len;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        ensure!(flags & 1 == 0, "encrypted entry {} is not supported", name);
+        ensure!(
+            get_u32(data, offset)? == LOCAL_HEADER_SIGNATURE,
+            "invalid local header for {}",
+            name
+        );
+        let start = offset
+            + 30
+            + get_u16(data, offset + 26)? as usize
+            + get_u16(data, offset + 28)? as usize;
+        let compressed = data
+            .get(start..start + compressed_size)
+            .context("truncated archive")?;
+        let contents = match method {
+            METHOD_STORED => compressed.to_vec(),
+            METHOD_DEFLATE => {
+                let mut contents = Vec::with_capacity(size);
+                DeflateDecoder::new(compressed).read_to_end(&mut contents)?;
+                contents
+            }
+            _ => bail!("unsupported compression method {} for {}", method, name),
+        };
+        ensure!(
+            contents.len() == size && crc32fast::hash(&contents) == crc,
+            "corrupt data for {}",
+            name
+        );
+        files.push((name, contents));
+    }
+    Ok(files)
+}
+
+// Bundle all project data (palettes, themes, stamps, the world map, and the areas in every
+// theme) into a ZIP archive.
+pub fn export_project_archive(state: &mut EditorState, path: &Path) -> Result<()> {
+    save_project(state)?;
+    let project_dir = state
+        .global_config
+        .project_dir
+        .clone()
+        .context("Project directory not set.")?;
+    let mut files: Vec<(String, Vec<u8>)> = vec![];
+    for loc in PROJECT_DATA_LOCATIONS {
+        let pattern = format!("{}/**/*.json", project_dir.join(loc).display());
+        for entry in glob::glob(&pattern)? {
+            let entry = entry?;
+            let rel_path = entry.strip_prefix(&project_dir)?;
+            // Use forward slashes, as required by the ZIP format:
+            let name = rel_path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((name, fs::read(&entry)?));
+        }
+    }
+    files.sort();
+    info!("Saving {} ({} files)", path.display(), files.len());
+    fs::write(path, write_zip(&files)?)?;
+    Ok(())
+}
+
+// Unpack a project archive into the given (new or empty) directory. Invalid entries
+// are skipped. Returns a report of the import, including any missing or invalid pieces.
+pub fn import_project_archive(archive_path: &Path, project_dir: &Path) -> Result<String> {
+    if project_dir.exists() && fs::read_dir(project_dir)?.next().is_some() {
+        bail!(
+            "Destination directory is not empty: {}",
+            project_dir.display()
+        );
+    }
+    let files = read_zip(&fs::read(archive_path)?)?;
+
+    let mut problems: Vec<String> = vec![];
+    let mut palette_ids = HashSet::new();
+    let mut themes_by_area: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut used_palette_ids = HashSet::new();
+    let mut valid_files = vec![];
+    for (name, data) in files {
+        let rel_path = Path::new(&name);
+        let parts: Vec<&str> = rel_path
+            .components()
+            .map(|c| match c {
+                Component::Normal(s) => s.to_str().unwrap_or(""),
+                _ => "",
+            })
+            .collect();
+        let is_json = rel_path.extension().is_some_and(|e| e == "json");
+        let stem = rel_path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        match parts.as_slice() {
+            ["Palettes", _] if is_json => match serde_json::from_slice::<Palette>(&data) {
+                Ok(pal) => {
+                    palette_ids.insert(pal.id);
+                }
+                Err(e) => {
+                    problems.push(format!("Skipped invalid palette {}: {}", name, e));
+                    continue;
+                }
+            },
+            ["Themes", _] if is_json => {
+                if let Err(e) = serde_json::from_slice::<Theme>(&data) {
+                    problems.push(format!("Skipped invalid theme {}: {}", name, e));
+                    continue;
+                }
+            }
+            ["Stamps", _] if is_json => {
+                if let Err(e) = serde_json::from_slice::<Stamp>(&data) {
+                    problems.push(format!("Skipped invalid stamp {}: {}", name, e));
+                    continue;
+                }
+            }
+            ["World", "Links.json"] => {
+                if let Err(e) = serde_json::from_slice::<WorldMap>(&data) {
+                    problems.push(format!("Skipped invalid world map {}: {}", name, e));
+                    continue;
+                }
+            }
+            ["Areas", area_name, _] if is_json => match serde_json::from_slice::<Area>(&data) {
+                Ok(area) => {
+                    for screen in &area.screens {
+                        used_palette_ids.extend(screen.palettes.iter().flatten().copied());
+                    }
+                    themes_by_area
+                        .entry(area_name.to_string())
+                        .or_default()
+                        .insert(stem.to_string());
+                }
+                Err(e) => {
+                    problems.push(format!("Skipped invalid area {}: {}", name, e));
+                    continue;
+                }
+            },
+            _ => {
+                problems.push(format!("Skipped unrecognized file {}", name));
+                continue;
+            }
+        }
+        valid_files.push((rel_path.to_owned(), data));
+    }
+
+    // Check for missing pieces:
+    if palette_ids.is_empty() {
+        problems.push("No palettes found.".to_string());
+    }
+    if themes_by_area.is_empty() {
+        problems.push("No areas found.".to_string());
+    }
+    let mut missing_ids: Vec<_> = used_palette_ids.difference(&palette_ids).collect();
+    missing_ids.sort();
+    if !missing_ids.is_empty() {
+        problems.push(format!(
+            "Areas use palettes which are missing: {}",
+            missing_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    let all_themes: HashSet<&String> = themes_by_area.values().flatten().collect();
+    let mut area_names: Vec<&String> = themes_by_area.keys().collect();
+    area_names.sort();
+    for area_name in area_names {
+        let mut missing: Vec<&&String> = all_themes
+            .iter()
+            .filter(|t| !themes_by_area[area_name].contains(**t))
+            .collect();
+        missing.sort();
+        if !missing.is_empty() {
+            problems.push(format!(
+                "Area {} is missing themes: {}",
+                area_name,
+                missing
+                    .iter()
+                    .map(|t| t.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+    }
+
+    info!(
+        "Unpacking {} into {}",
+        archive_path.display(),
+        project_dir.display()
+    );
+    for (rel_path, data) in &valid_files {
+        let path = project_dir.join(rel_path);
+        fs::create_dir_all(path.parent().context("invalid parent directory")?)?;
+        fs::write(&path, data)?;
+    }
+
+    let mut report = format!(
+        "Imported {} palettes and {} areas ({} files).",
+        palette_ids.len(),
+        themes_by_area.len(),
+        valid_files.len()
+    );
+    for p in problems {
+        report += "\n";
+        report += &p;
+    }
+    Ok(report)
+}
@@ -0,0 +1,219 @@
+// Module for exporting a "contact sheet": a single PNG image with labeled
+// thumbnails of every screen in the project (for the current theme).
+use std::{fs::File, io::BufWriter, path::Path};
+
+use anyhow::Result;
+use log::info;
+
+use crate::{
+    persist::{area_color_bytes, draw_screen, load_area},
+    state::{AreaId, EditorState, ImageFilter},
+};
+
+// Labels are drawn using a tiny 3x5 pixel font, scaled up by this factor:
+const LABEL_SCALE: usize = 2;
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+const LABEL_HEIGHT: usize = (GLYPH_HEIGHT + 2) * LABEL_SCALE;
+const PADDING: usize = 4;
+const BG_COLOR: [u8; 3] = [32, 32, 32];
+const LABEL_COLOR: [u8; 3] = [255, 255, 255];
+
+// Rows of a 3x5 glyph, with the most significant of the 3 bits on the left.
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        ' ' => [0b000; 5],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+struct Image {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+}
+
+impl Image {
+    fn set_pixel(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        if x < self.width && y < self.height {
+            let addr = (y * self.width + x) * 3;
+            self.data[addr..addr + 3].copy_from_slice(&color);
+        }
+    }
+
+    // Draw text starting at (x, y), clipped to the given maximum width.
+    fn draw_label(&mut self, x: usize, y: usize, max_width: usize, label: &str) {
+        let advance = (GLYPH_WIDTH + 1) * LABEL_SCALE;
+        for (i, c) in label.chars().enumerate() {
+            let gx = x + i * advance;
+            if gx + GLYPH_WIDTH * LABEL_SCALE > x + max_width {
+                break;
+            }
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                        continue;
+                    }
+                    for dy in 0..LABEL_SCALE {
+                        for dx in 0..LABEL_SCALE {
+                            self.set_pixel(
+                                gx + col * LABEL_SCALE + dx,
+                                y + row * LABEL_SCALE + dy,
+                                LABEL_COLOR,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Draw a 256x256 RGB screen image downscaled (by box filtering) to a thumbnail of the given size.
+    fn draw_thumbnail(
+        &mut self,
+        x: usize,
+        y: usize,
+        size: usize,
+        screen: &[u8],
+        filter: ImageFilter,
+    ) {
+        for oy in 0..size {
+            let (y0, y1) = (
+                oy * 256 / size,
+                ((oy + 1) * 256 / size).max(oy * 256 / size + 1),
+            );
+            for ox in 0..size {
+                let (x0, x1) = (
+                    ox * 256 / size,
+                    ((ox + 1) * 256 / size).max(ox * 256 / size + 1),
+                );
+                if filter == ImageFilter::Nearest {
+                    let addr = (y0 * 256 + x0) * 3;
+                    self.set_pixel(
+                        x + ox,
+                        y + oy,
+                        [screen[addr], screen[addr + 1], screen[addr + 2]],
+                    );
+                    continue;
+                }
+                let mut sum = [0usize; 3];
+                for sy in y0..y1 {
+                    for sx in x0..x1 {
+                        let addr = (sy * 256 + sx) * 3;
+                        for c in 0..3 {
+                            sum[c] += screen[addr + c] as usize;
+                        }
+                    }
+                }
+                let n = (x1 - x0) * (y1 - y0);
+                self.set_pixel(x + ox, y + oy, sum.map(|s| (s / n) as u8));
+            }
+        }
+    }
+}
+
+// Render thumbnails of every screen of every area (in the theme of the main area),
+// labeled by area name and screen position, and save them as a PNG.
+pub fn export_contact_sheet(state: &EditorState, path: &Path) -> Result<()> {
+    let thumbnail_size = state.global_config.contact_sheet_thumbnail_size as usize;
+    let columns = state.global_config.contact_sheet_columns as usize;
+    let theme = state.main_area_id.theme.clone();
+
+    // Collect all screens, loading areas from disk if they aren't already loaded:
+    let mut screens: Vec<(String, Vec<u8>)> = vec![];
+    for area_name in &state.area_names {
+        let area_id = AreaId {
+            area: area_name.clone(),
+            theme: theme.clone(),
+        };
+        let loaded;
+        let area = match state.areas.get(&area_id) {
+            Some(area) => area,
+            None => {
+                loaded = load_area(state, &area_id)?;
+                &loaded
+            }
+        };
+        let color_bytes = area_color_bytes(state, area);
+        for sy in 0..area.size.1 {
+            for sx in 0..area.size.0 {
+                let screen = &area.screens[sy as usize * area.size.0 as usize + sx as usize];
+                let mut data = vec![0; 256 * 256 * 3];
+                draw_screen(state, screen, &color_bytes, &mut data, 0, 256 * 3);
+                screens.push((format!("{} {},{}", area_name, sx, sy), data));
+            }
+        }
+    }
+
+    let cell_width = thumbnail_size + PADDING;
+    let cell_height = thumbnail_size + LABEL_HEIGHT + PADDING;
+    let rows = screens.len().div_ceil(columns).max(1);
+    let mut image = Image {
+        width: columns.min(screens.len().max(1)) * cell_width + PADDING,
+        height: rows * cell_height + PADDING,
+        data: vec![],
+    };
+    image.data = BG_COLOR.repeat(image.width * image.height);
+    for (i, (label, data)) in screens.iter().enumerate() {
+        let x = PADDING + (i % columns) * cell_width;
+        let y = PADDING + (i / columns) * cell_height;
+        image.draw_label(x, y, thumbnail_size, label);
+        image.draw_thumbnail(
+            x,
+            y + LABEL_HEIGHT,
+            thumbnail_size,
+            data,
+            state.global_config.preview_filter,
+        );
+    }
+
+    info!("Saving {}", path.display());
+    let file = File::create(path)?;
+    let w = &mut BufWriter::new(file);
+    let mut encoder = png::Encoder::new(w, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.data)?;
+    Ok(())
+}
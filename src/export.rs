@@ -0,0 +1,280 @@
+// Module for exporting project data in the binary formats used by the ROM, and as
+// standalone files for sharing.
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::{
+    helpers::scale_color,
+    state::{ColorRGB, ColorValue, Palette, Tile, TILE_SIZE},
+};
+
+// Pack a color into an SNES BGR555 word: bits 0-4 are red, bits 5-9 green, and bits
+// 10-14 blue, with bit 15 unused (zero).
+pub fn color_to_bgr555(color: ColorRGB) -> u16 {
+    let [r, g, b] = color.map(|c| (c & 31) as u16);
+    r | (g << 5) | (b << 10)
+}
+
+// Unpack an SNES BGR555 word (the inverse of `color_to_bgr555`), ignoring bit 15.
+pub fn color_from_bgr555(w: u16) -> ColorRGB {
+    [
+        (w & 31) as ColorValue,
+        ((w >> 5) & 31) as ColorValue,
+        ((w >> 10) & 31) as ColorValue,
+    ]
+}
+
+// Write the 16 colors of every palette, ordered by palette ID, as consecutive
+// little-endian BGR555 words (32 bytes per palette).
+pub fn export_all_palettes_bin(palettes: &[Palette], path: &Path) -> Result<()> {
+    let mut sorted: Vec<&Palette> = palettes.iter().collect();
+    sorted.sort_by_key(|p| p.id);
+
+    // Gaps in the IDs aren't represented in the output, so the position of a palette
+    // in the file would no longer match its ID:
+    for w in sorted.windows(2) {
+        if w[1].id != w[0].id + 1 {
+            warn!(
+                "Palette IDs are not contiguous: {} is followed by {}",
+                w[0].id, w[1].id
+            );
+        }
+    }
+
+    let mut data: Vec<u8> = Vec::with_capacity(sorted.len() * 32);
+    for pal in &sorted {
+        for &c in &pal.colors {
+            data.extend(color_to_bgr555(c).to_le_bytes());
+        }
+    }
+    std::fs::write(path, &data)?;
+    info!(
+        "Exported {} palettes ({} bytes) to {}",
+        sorted.len(),
+        data.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+// Write the 16 colors of a palette as 8-bit "#rrggbb" hex codes, for use in web tools and
+// documentation: as CSS custom properties (`--color-0` to `--color-15`) if the path has a
+// ".css" extension, or otherwise as a JSON array.
+pub fn export_palette_colors(palette: &Palette, path: &Path) -> Result<()> {
+    let hex_colors: Vec<String> = palette
+        .colors
+        .iter()
+        .map(|c| {
+            let [r, g, b] = c.map(scale_color);
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        })
+        .collect();
+    let is_css = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("css"));
+    let data = if is_css {
+        let mut css = format!(
+            "/* Palette {}: {} */
+:root {{
+",
+            palette.id, palette.name
+        );
+        for (i, c) in hex_colors.iter().enumerate() {
+            css += &format!(
+                "  --color-{}: {};
+",
+                i, c
+            );
+        }
+        css += "}
+";
+        css.into_bytes()
+    } else {
+        serde_json::to_vec_pretty(&hex_colors)?
+    };
+    std::fs::write(path, data)?;
+    info!("Exported palette {} to {}", palette.name, path.display());
+    Ok(())
+}
+
+// Standalone tileset written alongside the PNG by `export_selection_tileset`:
+#[derive(Serialize)]
+struct SelectionTileset<'a> {
+    colors: [ColorRGB; 16],
+    // Width and height, in tiles:
+    size: (usize, usize),
+    // Tiles in row-major order:
+    tiles: Vec<&'a Tile>,
+}
+
+// Write a block of tiles as a compact tileset PNG (at one image pixel per SNES pixel, laid out
+// as in the block), each tile drawn in its own palette's colors, and as a JSON file next to
+// it containing the tiles and the colors of `colors`. Returns the path of the JSON file.
+pub fn export_selection_tileset(
+    path: &Path,
+    tiles: &[Vec<Tile>],
+    tile_colors: &[Vec<[ColorRGB; 16]>],
+    colors: [ColorRGB; 16],
+) -> Result<PathBuf> {
+    let height = tiles.len();
+    let width = tiles.first().map_or(0, |row| row.len());
+
+    let mut data: Vec<u8> = Vec::with_capacity(width * height * TILE_SIZE * TILE_SIZE * 3);
+    for (row, row_colors) in tiles.iter().zip(tile_colors) {
+        for py in 0..TILE_SIZE {
+            for (tile, colors) in row.iter().zip(row_colors) {
+                for &c in &tile.pixels[py] {
+                    data.extend(colors[c as usize].map(scale_color));
+                }
+            }
+        }
+    }
+    info!("Saving {}", path.display());
+    let file = File::create(path)?;
+    let w = &mut BufWriter::new(file);
+    let mut encoder = png::Encoder::new(w, (width * TILE_SIZE) as u32, (height * TILE_SIZE) as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+
+    let json_path = path.with_extension("json");
+    let tileset = SelectionTileset {
+        colors,
+        size: (width, height),
+        tiles: tiles.iter().flatten().collect(),
+    };
+    info!("Saving {}", json_path.display());
+    std::fs::write(&json_path, serde_json::to_vec_pretty(&tileset)?)?;
+    Ok(json_path)
+}
+
+// Pack a tile into the 32 bytes of the SNES 4bpp planar format. Each row of 8 pixels takes two
+// bytes in each half of the tile: bytes 2y and 2y+1 hold bitplanes 0 and 1 of row y, and bytes
+// 16+2y and 17+2y hold bitplanes 2 and 3. Within each byte, bit 7 is the leftmost pixel.
+pub fn tile_to_4bpp(tile: &Tile) -> [u8; 32] {
+    let mut data = [0; 32];
+    for (y, row) in tile.pixels.iter().enumerate() {
+        for (x, &color_idx) in row.iter().enumerate() {
+            let bit = 7 - x;
+            for plane in 0..4 {
+                let addr = (plane / 2) * 16 + y * 2 + plane % 2;
+                data[addr] |= ((color_idx >> plane) & 1) << bit;
+            }
+        }
+    }
+    data
+}
+
+// Write the tiles of a palette as a raw SNES 4bpp ".chr" file (32 bytes per tile, in tile
+// order), for tile editors such as YY-CHR, and its colors as a ".pal" file of the same name
+// (16 little-endian BGR555 words). Returns the path of the ".pal" file.
+pub fn export_chr(palette: &Palette, path: &Path) -> Result<PathBuf> {
+    let mut chr_data: Vec<u8> = Vec::with_capacity(palette.tiles.len() * 32);
+    for tile in &palette.tiles {
+        chr_data.extend(tile_to_4bpp(tile));
+    }
+    info!("Saving {}", path.display());
+    std::fs::write(path, &chr_data)?;
+
+    let pal_path = path.with_extension("pal");
+    let pal_data: Vec<u8> = palette
+        .colors
+        .iter()
+        .flat_map(|&c| color_to_bgr555(c).to_le_bytes())
+        .collect();
+    info!("Saving {}", pal_path.display());
+    std::fs::write(&pal_path, pal_data)?;
+    Ok(pal_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::{import_chr, import_palettes_bin};
+    use crate::state::{ColorIdx, TilePixels};
+
+    #[test]
+    fn palettes_bin_round_trip() {
+        let mut pal1 = Palette {
+            id: 1,
+            ..Default::default()
+        };
+        pal1.colors[0] = [31, 0, 0];
+        pal1.colors[1] = [0, 31, 0];
+        pal1.colors[2] = [0, 0, 31];
+        pal1.colors[15] = [31, 31, 31];
+        let mut pal2 = Palette {
+            id: 2,
+            ..Default::default()
+        };
+        pal2.colors[0] = [1, 2, 3];
+
+        let path = std::env::temp_dir().join(format!(
+            "z3_overworld_editor_test_palettes_{}.bin",
+            std::process::id()
+        ));
+        // The palettes are written in ID order, regardless of their order in the project:
+        export_all_palettes_bin(&[pal2.clone(), pal1.clone()], &path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(data.len(), 64);
+        assert_eq!(&data[0..6], &[0x1F, 0x00, 0xE0, 0x03, 0x00, 0x7C]);
+        assert_eq!(&data[30..32], &[0xFF, 0x7F]);
+        assert_eq!(&data[32..34], &[0x41, 0x0C]);
+
+        let palettes = import_palettes_bin(&path, 1).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(palettes.len(), 2);
+        assert_eq!(palettes[0].id, 1);
+        assert_eq!(palettes[0].colors, pal1.colors);
+        assert_eq!(palettes[1].id, 2);
+        assert_eq!(palettes[1].colors, pal2.colors);
+    }
+
+    #[test]
+    fn chr_round_trip() {
+        let mut tile = Tile::default();
+        tile.pixels[0][0] = 0xF;
+        tile.pixels[1][7] = 0x5;
+        tile.pixels[7][3] = 0xA;
+        let data = tile_to_4bpp(&tile);
+        let mut expected = [0; 32];
+        // Bitplanes 0 and 1 are interleaved by row in the first 16 bytes, 2 and 3 in the rest:
+        for addr in [0, 1, 16, 17] {
+            expected[addr] = 0x80;
+        }
+        for addr in [2, 18] {
+            expected[addr] = 0x01;
+        }
+        for addr in [15, 31] {
+            expected[addr] = 0x10;
+        }
+        assert_eq!(data, expected);
+
+        let mut noise = Tile::default();
+        for (i, p) in noise.pixels.iter_mut().flatten().enumerate() {
+            *p = (i * 7 % 16) as ColorIdx;
+        }
+        let palette = Palette {
+            tiles: vec![tile, noise],
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join(format!(
+            "z3_overworld_editor_test_tiles_{}.chr",
+            std::process::id()
+        ));
+        let pal_path = export_chr(&palette, &path).unwrap();
+        let tiles = import_chr(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&pal_path).unwrap();
+        let pixels: Vec<TilePixels> = tiles.iter().map(|t| t.pixels).collect();
+        assert_eq!(pixels, vec![tile.pixels, noise.pixels]);
+    }
+}
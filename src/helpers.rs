@@ -1,9 +1,192 @@
-use crate::state::ColorRGB;
+use std::cell::RefCell;
+
+use anyhow::{bail, Result};
+
+use crate::{
+    quantize::nearest_color,
+    state::{
+        ColorIdx, ColorProfile, ColorRGB, ImageFilter, Palette, PaletteId, TilePixels, TILE_SIZE,
+    },
+};
 
 pub fn scale_color(c: u8) -> u8 {
     ((c as u16) * 255 / 31) as u8
 }
 
+// Approximate gamma of a CRT television, and of the sRGB displays the editor is shown on:
+const CRT_GAMMA: f32 = 2.5;
+const DISPLAY_GAMMA: f32 = 2.2;
+
+// Approximate how a 5-bit SNES color value looks on a CRT. The SNES outputs a voltage
+// roughly linear in the color value, which the CRT turns into light intensity with its
+// gamma of ~2.5. Modern displays apply a gamma of ~2.2 to the 8-bit value, so to produce
+// the same intensity we need `(v / 255)^2.2 = (c / 31)^2.5`, i.e.
+// `v = 255 * (c / 31)^(2.5 / 2.2)`. This darkens midtones while keeping black and white fixed.
+pub fn crt_scale_color(c: u8) -> u8 {
+    (255.0 * (c as f32 / 31.0).powf(CRT_GAMMA / DISPLAY_GAMMA)).round() as u8
+}
+
+// Convert a SNES color to an 8-bit RGB color for display in the editor.
+pub fn display_color(color: ColorRGB, profile: ColorProfile) -> [u8; 3] {
+    match profile {
+        ColorProfile::Linear => color.map(scale_color),
+        ColorProfile::Crt => color.map(crt_scale_color),
+    }
+}
+
+// Convert an 8-bit color channel to the nearest 5-bit SNES color value.
+pub fn unscale_color(c: u8) -> u8 {
+    ((c as u16 * 31 + 127) / 255) as u8
+}
+
+// Lookup table giving the nearest palette color for each of the 32768 5-bit colors,
+// for the palette colors it was built from.
+struct NearestColorTable {
+    colors: [ColorRGB; 16],
+    table: Vec<ColorIdx>,
+}
+
+thread_local! {
+    // Table for the most recently used palette colors; rebuilt when they change.
+    static NEAREST_COLOR_TABLE: RefCell<Option<NearestColorTable>> = const { RefCell::new(None) };
+}
+
+fn nearest_color_table_idx(c: ColorRGB) -> usize {
+    let [r, g, b] = c.map(|x| x.min(31) as usize);
+    (r << 10) | (g << 5) | b
+}
+
+// Index of the palette color closest to the given 5-bit color (in squared RGB distance,
+// preferring the lowest index on ties). This is used for mapping many pixels at once
+// (e.g. importing images), so results come from a table cached for the palette colors.
+pub fn nearest_color_idx(palette: &Palette, rgb: ColorRGB) -> ColorIdx {
+    NEAREST_COLOR_TABLE.with_borrow_mut(|cache| {
+        let table = match cache {
+            Some(t) if t.colors == palette.colors => t,
+            _ => {
+                let mut table = vec![0; 32 * 32 * 32];
+                for r in 0..32 {
+                    for g in 0..32 {
+                        for b in 0..32 {
+                            let c = [r, g, b];
+                            table[nearest_color_table_idx(c)] = nearest_color(&palette.colors, c);
+                        }
+                    }
+                }
+                cache.insert(NearestColorTable {
+                    colors: palette.colors,
+                    table,
+                })
+            }
+        };
+        table.table[nearest_color_table_idx(rgb)]
+    })
+}
+
+// Format a tile number or palette ID for display, in hex (e.g. "$1F") or decimal.
+pub fn format_number(x: u16, hex: bool) -> String {
+    if hex {
+        format!("${:02X}", x)
+    } else {
+        x.to_string()
+    }
+}
+
+// Parse a number entered by the user in the given base. A "$" or "0x" prefix is optional for hex.
+pub fn parse_number(s: &str, hex: bool) -> Option<u16> {
+    let s = s.trim();
+    if hex {
+        let digits = s
+            .strip_prefix('$')
+            .or_else(|| s.strip_prefix("0x"))
+            .unwrap_or(s);
+        u16::from_str_radix(digits, 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+// Name under which a palette is listed in the palette selection menus.
+pub fn palette_label(pal: &Palette, hex: bool) -> String {
+    format!("{}: {}", format_number(pal.id, hex), pal.name)
+}
+
+// Width of selection/highlight outlines for a given zoom level (screen pixels per
+// SNES pixel), so that the outlines stay legible without becoming chunky.
+pub fn outline_thickness(pixel_size: f32) -> f32 {
+    (pixel_size / 2.0).round().clamp(1.0, 3.0)
+}
+
+// Color of pixel (x, y) of the error pattern drawn in place of tiles that can't be rendered
+// (e.g. referencing a missing palette or a tile index beyond the palette's tile count):
+// a magenta and black checkerboard of 2x2 squares.
+pub fn broken_tile_color(x: usize, y: usize) -> [u8; 3] {
+    if (x / 2 + y / 2).is_multiple_of(2) {
+        [255, 0, 255]
+    } else {
+        [0, 0, 0]
+    }
+}
+
+// A distinct hue for each palette ID, for telling palettes apart at a glance. Hues are
+// spaced by the golden angle so that nearby IDs get clearly different colors.
+pub fn palette_id_color(id: PaletteId) -> [u8; 3] {
+    let h = (id as f32 * 137.508).rem_euclid(360.0) / 60.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    let (r, g, b) = match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    [r, g, b].map(|c| (c * 255.0) as u8)
+}
+
+// Color of output pixel (x, y) when shrinking an RGB image (`width` pixels wide) by the
+// given factor: the top-left pixel of the block for `Nearest`, or the block's average.
+pub fn downscale_pixel(
+    data: &[u8],
+    width: usize,
+    x: usize,
+    y: usize,
+    factor: usize,
+    filter: ImageFilter,
+) -> [u8; 3] {
+    let (x0, y0) = (x * factor, y * factor);
+    match filter {
+        ImageFilter::Nearest => {
+            let addr = (y0 * width + x0) * 3;
+            [data[addr], data[addr + 1], data[addr + 2]]
+        }
+        ImageFilter::Linear => {
+            let mut sum = [0usize; 3];
+            for sy in y0..y0 + factor {
+                for sx in x0..x0 + factor {
+                    let addr = (sy * width + sx) * 3;
+                    for c in 0..3 {
+                        sum[c] += data[addr + c] as usize;
+                    }
+                }
+            }
+            sum.map(|s| (s / (factor * factor)) as u8)
+        }
+    }
+}
+
+// Pseudo-random number in [0, 1) for a cell, which is the same for the same seed and
+// coordinates (so that brushing over a cell again within a stroke gives the same result):
+pub fn cell_noise(seed: u32, x: u16, y: u16) -> f32 {
+    // SplitMix64 finalizer:
+    let mut z =
+        ((seed as u64) << 32 | (x as u64) << 16 | y as u64).wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
+
 pub fn alpha_blend(bg: ColorRGB, fg: ColorRGB, alpha: f32) -> ColorRGB {
     let gamma = 2.2;
     let mut out: ColorRGB = [0, 0, 0];
@@ -15,3 +198,112 @@ pub fn alpha_blend(bg: ColorRGB, fg: ColorRGB, alpha: f32) -> ColorRGB {
     }
     out
 }
+
+// Text form of a tile's pixels: `TILE_SIZE` rows of `TILE_SIZE` hex digits, each digit being a
+// color index.
+pub fn tile_pixels_to_text(pixels: &TilePixels) -> String {
+    pixels
+        .iter()
+        .map(|row| row.iter().map(|&c| format!("{:X}", c)).collect::<String>() + "\n")
+        .collect()
+}
+
+// Parse the text form of a tile's pixels. Blank lines and whitespace within rows are ignored.
+pub fn tile_pixels_from_text(text: &str) -> Result<TilePixels> {
+    let rows: Vec<Vec<char>> = text
+        .lines()
+        .map(|line| {
+            line.chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<Vec<char>>()
+        })
+        .filter(|row| !row.is_empty())
+        .collect();
+    if rows.len() != TILE_SIZE {
+        bail!(
+            "Expected {} rows of pixels, found {}.",
+            TILE_SIZE,
+            rows.len()
+        );
+    }
+    let mut pixels = [[0; TILE_SIZE]; TILE_SIZE];
+    for (y, row) in rows.iter().enumerate() {
+        if row.len() != TILE_SIZE {
+            bail!(
+                "Expected {} pixels in row {}, found {}.",
+                TILE_SIZE,
+                y,
+                row.len()
+            );
+        }
+        for (x, &c) in row.iter().enumerate() {
+            let Some(d) = c.to_digit(16) else {
+                bail!("Invalid color index '{}' at row {}, column {}.", c, y, x);
+            };
+            pixels[y][x] = d as ColorIdx;
+        }
+    }
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crt_color_profile_conversion() {
+        // Black and white stay fixed, while midtones come out darker than the linear mapping:
+        let expected = [
+            (0, 0, 0),
+            (1, 5, 8),
+            (8, 55, 65),
+            (16, 120, 131),
+            (24, 191, 197),
+        ];
+        for (c, crt, linear) in expected {
+            assert_eq!(crt_scale_color(c), crt, "CRT value of {}", c);
+            assert_eq!(scale_color(c), linear, "linear value of {}", c);
+        }
+        assert_eq!(crt_scale_color(31), 255);
+        assert_eq!(display_color([0, 16, 31], ColorProfile::Crt), [0, 120, 255]);
+        assert_eq!(
+            display_color([0, 16, 31], ColorProfile::Linear),
+            [0, 131, 255]
+        );
+    }
+
+    // Index of the nearest color by a linear scan, preferring the lowest index on ties:
+    fn brute_force_nearest(colors: &[ColorRGB; 16], c: ColorRGB) -> ColorIdx {
+        let dist =
+            |d: &ColorRGB| -> i32 { (0..3).map(|i| (c[i] as i32 - d[i] as i32).pow(2)).sum() };
+        let best = colors.iter().map(dist).min().unwrap();
+        colors.iter().position(|d| dist(d) == best).unwrap() as ColorIdx
+    }
+
+    #[test]
+    fn nearest_color_idx_matches_brute_force() {
+        // Check every 5-bit color against a few palettes, so the cached table is also rebuilt
+        // each time the palette colors change:
+        for seed in 0..3 {
+            let mut pal = Palette::default();
+            for (i, c) in pal.colors.iter_mut().enumerate() {
+                *c = [0, 1, 2].map(|k| (cell_noise(seed, i as u16, k) * 32.0) as u8);
+            }
+            // Include a duplicated color, to check the tie-breaking:
+            pal.colors[9] = pal.colors[4];
+            for r in 0..32 {
+                for g in 0..32 {
+                    for b in 0..32 {
+                        let c = [r, g, b];
+                        assert_eq!(
+                            nearest_color_idx(&pal, c),
+                            brute_force_nearest(&pal.colors, c),
+                            "nearest color to {:?}",
+                            c
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,18 +1,23 @@
 use anyhow::{bail, ensure, Result};
-use hashbrown::{hash_map::Entry, HashMap};
+use hashbrown::{hash_map::Entry, HashMap, HashSet};
 use itertools::Itertools;
 use log::{info, warn};
 use std::{
+    collections::BTreeMap,
     fmt::Display,
-    ops::{Add, AddAssign},
+    ops::{Add, AddAssign, Range},
     path::Path,
 };
 
 use crate::{
-    persist::{load_area, load_project, save_area_json, save_area_png, save_project},
+    export::color_from_bgr555,
+    persist::{
+        copy_project_data, load_area, load_project, save_area_json, save_area_png, save_project,
+    },
     state::{
-        Area, AreaId, AreaName, ColorRGB, ColorValue, EditorState, Flip, Palette, PaletteId,
-        Screen, Tile, TileIdx,
+        new_editor_state, Area, AreaId, AreaName, AreaPosition, CollisionType, ColorIdx, ColorRGB,
+        ColorValue, EditorState, Flip, Palette, PaletteId, Screen, Tile, TileAnimation, TileIdx,
+        TilePixels, TILE_SIZE,
     },
     update::update_palette_order,
 };
@@ -244,13 +249,7 @@ impl Tile8 {
             gfx_char: w & 0x3FF,
             pal_idx: ((w >> 10) & 7) as u8,
             priority: (w >> 13) & 1 == 1,
-            flip: match w >> 14 {
-                0 => Flip::None,
-                1 => Flip::Horizontal,
-                2 => Flip::Vertical,
-                3 => Flip::Both,
-                _ => panic!("error decoding flip"),
-            },
+            flip: Flip::from_flags((w >> 14) & 1 == 1, (w >> 15) & 1 == 1),
         }
     }
 }
@@ -267,6 +266,23 @@ type Tile32Idx = u16;
 
 type MapIdx = u16;
 
+// Animated tiles (e.g. water): the game overwrites the area graphics in this range with
+// successive frames of 32 tiles, taken from a pair of consecutive graphics sheets (0x59-0x5A
+// for the Death Mountain areas, 0x5B-0x5C elsewhere). The frames are the first and second
+// halves of the first sheet, and then the first half of the second sheet, cycling
+// continuously. Without the `animated_tiles` import option, only the first frame is imported.
+const ANIMATED_GFX_CHARS: Range<u16> = 0x1C0..0x1E0;
+const NUM_ANIMATION_FRAMES: usize = 3;
+
+// Clear the fields of a tile which vary over the course of importing, for looking up
+// matching tiles:
+fn strip_tile(mut tile: Tile) -> Tile {
+    tile.id = None;
+    tile.h_flippable = false;
+    tile.v_flippable = false;
+    tile
+}
+
 #[derive(Debug)]
 struct MapPalettes {
     main: u8,
@@ -285,7 +301,7 @@ pub struct Importer<'a> {
     main_palette_ids: Vec<[PaletteId; 5]>,
     aux_palette_ids: Vec<[PaletteId; 3]>,
     animated_palette_ids: Vec<PaletteId>,
-    tiles8: Vec<[[u8; 8]; 8]>, // 3bpp tile color indices (0-7)
+    tiles8: Vec<TilePixels>, // 3bpp tile color indices (0-7)
     tiles16: Vec<Tile16>,
     tiles32: Vec<Tile32>,
     map_tiles: Vec<[[Tile32Idx; 16]; 16]>,
@@ -465,6 +481,9 @@ impl<'a> Importer<'a> {
                             id: next_id,
                             colors,
                             tiles: vec![],
+                            pages: vec![],
+                            tags: BTreeMap::new(),
+                            animations: vec![],
                         });
                         pal_by_colors.insert(colors, next_id);
                         palette_ids.push(next_id);
@@ -509,9 +528,9 @@ impl<'a> Importer<'a> {
             }
 
             for j in 0..64 {
-                let mut tile: [[u8; 8]; 8] = [[0; 8]; 8];
-                for y in 0..8 {
-                    for x in 0..8 {
+                let mut tile: TilePixels = [[0; TILE_SIZE]; TILE_SIZE];
+                for y in 0..TILE_SIZE {
+                    for x in 0..TILE_SIZE {
                         let c0 = (data[j * 24 + y * 2] >> (7 - x)) & 1;
                         let c1 = (data[j * 24 + y * 2 + 1] >> (7 - x)) & 1;
                         let c2 = (data[j * 24 + y + 16] >> (7 - x)) & 1;
@@ -782,15 +801,6 @@ impl<'a> Importer<'a> {
         let mut tile_lookup: Vec<HashMap<Tile, (TileIdx, Flip)>> =
             vec![HashMap::new(); self.state.palettes.len()];
 
-        fn strip_tile(mut tile: Tile) -> Tile {
-            // We clear these fields when looking up matching tiles, since these
-            // fields will vary over the course of processing.
-            tile.id = None;
-            tile.h_flippable = false;
-            tile.v_flippable = false;
-            tile
-        }
-
         for palette_idx in 0..self.state.palettes.len() {
             for (idx, tile) in self.state.palettes[palette_idx].tiles.iter().enumerate() {
                 for flip in [Flip::None, Flip::Horizontal, Flip::Vertical, Flip::Both] {
@@ -823,7 +833,7 @@ impl<'a> Importer<'a> {
             } else {
                 0x5B
             };
-            gfx_idxs[0x1C0..0x1E0]
+            gfx_idxs[ANIMATED_GFX_CHARS.start as usize..ANIMATED_GFX_CHARS.end as usize]
                 .copy_from_slice(&((animated_gfx * 64)..(animated_gfx * 64 + 32)).collect_vec());
 
             let pal = &self.map_palettes[parent];
@@ -863,6 +873,7 @@ impl<'a> Importer<'a> {
                 bg_color,
                 size: (size.0 * 2, size.1 * 2),
                 screens: vec![],
+                num_broken_cells: 0,
             };
             self.state.area_names.push(area.name.clone());
             for y in 0..size.1 * 2 {
@@ -872,6 +883,8 @@ impl<'a> Importer<'a> {
                         palettes: [[0; 32]; 32],
                         tiles: [[0; 32]; 32],
                         flips: [[Flip::None; 32]; 32],
+                        priorities: [[false; 32]; 32],
+                        notes: String::new(),
                     });
                 }
             }
@@ -952,26 +965,23 @@ impl<'a> Importer<'a> {
                                         }
                                     };
 
-                                    match flip {
-                                        Flip::None => {}
-                                        Flip::Horizontal => {
-                                            self.state.palettes[palette_idx].tiles
-                                                [tile_idx as usize]
-                                                .h_flippable = true;
-                                        }
-                                        Flip::Vertical => {
-                                            self.state.palettes[palette_idx].tiles
-                                                [tile_idx as usize]
-                                                .v_flippable = true;
-                                        }
-                                        Flip::Both => {
-                                            self.state.palettes[palette_idx].tiles
-                                                [tile_idx as usize]
-                                                .h_flippable = true;
-                                            self.state.palettes[palette_idx].tiles
-                                                [tile_idx as usize]
-                                                .v_flippable = true;
-                                        }
+                                    let dst_tile = &mut self.state.palettes[palette_idx].tiles
+                                        [tile_idx as usize];
+                                    dst_tile.h_flippable |= flip.is_horizontal();
+                                    dst_tile.v_flippable |= flip.is_vertical();
+
+                                    if self.state.global_config.import_options.animated_tiles
+                                        && ANIMATED_GFX_CHARS.contains(&t8.gfx_char)
+                                    {
+                                        Self::add_tile_animation(
+                                            &mut self.state.palettes[palette_idx],
+                                            &mut tile_lookup[palette_idx],
+                                            &self.tiles8,
+                                            (tile_idx, flip),
+                                            t8,
+                                            collision,
+                                            animated_gfx,
+                                        )?;
                                     }
 
                                     area.set_tile(x as u16, y as u16, tile_idx as u16).unwrap();
@@ -1002,6 +1012,59 @@ impl<'a> Importer<'a> {
         Ok(())
     }
 
+    // Add the animation of a tile using the animated graphics, unless it already has one: the
+    // tile of the first frame (used in the area with the given flip), followed by the tiles of
+    // the later frames in the same orientation.
+    fn add_tile_animation(
+        pal: &mut Palette,
+        tile_lookup: &mut HashMap<Tile, (TileIdx, Flip)>,
+        tiles8: &[TilePixels],
+        (tile_idx, flip): (TileIdx, Flip),
+        t8: Tile8,
+        collision: CollisionType,
+        animated_gfx: u16,
+    ) -> Result<()> {
+        if pal
+            .animations
+            .iter()
+            .any(|a| a.frames.first() == Some(&tile_idx))
+        {
+            return Ok(());
+        }
+        let offset = (t8.gfx_char - ANIMATED_GFX_CHARS.start) as usize;
+        let mut frames = vec![tile_idx];
+        for k in 1..NUM_ANIMATION_FRAMES {
+            let tiles8_idx = animated_gfx as usize * 64 + k * 32 + offset;
+            let Some(&pixels) = tiles8.get(tiles8_idx) else {
+                bail!("animated graphics tile out of range: {:x}", tiles8_idx);
+            };
+            let tile = Tile {
+                id: None,
+                priority: t8.priority,
+                h_flippable: false,
+                v_flippable: false,
+                collision,
+                pixels: flip.apply_to_pixels(t8.flip.apply_to_pixels(pixels)),
+            };
+            // Reuse an identical tile, but not a flipped one, since all frames are shown with
+            // the flip of the first:
+            let frame_idx = match tile_lookup.get(&tile) {
+                Some(&(idx, Flip::None)) => idx,
+                _ => {
+                    let idx = pal.tiles.len() as TileIdx;
+                    pal.tiles.push(tile);
+                    for f in [Flip::None, Flip::Horizontal, Flip::Vertical, Flip::Both] {
+                        tile_lookup.insert(strip_tile(f.apply_to_tile(tile)), (idx, f));
+                    }
+                    idx
+                }
+            };
+            frames.push(frame_idx);
+        }
+        pal.animations.push(TileAnimation { frames });
+        Ok(())
+    }
+
     fn ensure_palette_full_rows(&mut self) -> Result<()> {
         for pal in &mut self.state.palettes {
             let size = ((pal.tiles.len() + 15) / 16 * 16).max(16);
@@ -1024,6 +1087,236 @@ impl<'a> Importer<'a> {
     }
 }
 
+// Names in a report list, shortened to the first few:
+fn report_names(names: &[String]) -> String {
+    const MAX_NAMES: usize = 10;
+    let mut out = names.iter().take(MAX_NAMES).join(", ");
+    if names.len() > MAX_NAMES {
+        out += &format!(", and {} more", names.len() - MAX_NAMES);
+    }
+    out
+}
+
+// Compare the project with the data of a ROM, returning a report of the differences: palettes
+// and tiles in the ROM which the project lacks, and areas whose screens differ. The ROM is
+// imported into a scratch copy of the project's saved files, leaving the project untouched.
+pub fn diff_against_rom(state: &EditorState, path: &Path) -> Result<String> {
+    let scratch_dir = std::env::temp_dir().join(format!("z3-rom-diff-{}", std::process::id()));
+    if scratch_dir.exists() {
+        std::fs::remove_dir_all(&scratch_dir)?;
+    }
+    copy_project_data(state, &scratch_dir)?;
+    let result = diff_against_rom_in(state, path, &scratch_dir);
+    if let Err(e) = std::fs::remove_dir_all(&scratch_dir) {
+        warn!("Unable to remove {}: {}", scratch_dir.display(), e);
+    }
+    result
+}
+
+fn diff_against_rom_in(state: &EditorState, path: &Path, scratch_dir: &Path) -> Result<String> {
+    // The scratch state has its own global config file, so that the user's isn't overwritten:
+    let mut rom_state = new_editor_state(scratch_dir.join("config.json"));
+    rom_state.global_config = state.global_config.clone();
+    rom_state.global_config.project_dir = Some(scratch_dir.to_owned());
+    load_project(&mut rom_state)?;
+    // Import into the theme being edited, as an import into the project would:
+    if rom_state.theme_names.contains(&state.main_area_id.theme) {
+        let area_id = AreaId {
+            area: rom_state.area_names[0].clone(),
+            theme: state.main_area_id.theme.clone(),
+        };
+        rom_state.switch_area(AreaPosition::Main, &area_id)?;
+    }
+    let theme = rom_state.main_area_id.theme.clone();
+    Importer::import(&mut rom_state, path)?;
+
+    let mut lines = vec![format!("Compared with {}:", path.display())];
+
+    // Palettes are matched to the project's by ID, since the import reuses the ID of the
+    // project palette with the same colors. Tiles are compared regardless of flips:
+    let tile_forms = |tiles: &[Tile]| -> HashSet<TilePixels> {
+        tiles
+            .iter()
+            .flat_map(|t| {
+                [Flip::None, Flip::Horizontal, Flip::Vertical, Flip::Both]
+                    .map(|f| f.apply_to_pixels(t.pixels))
+            })
+            .collect()
+    };
+    let mut new_palettes: Vec<String> = vec![];
+    let mut num_new_tiles = 0;
+    let mut palettes_with_new_tiles: Vec<String> = vec![];
+    for pal in &rom_state.palettes {
+        match state.palettes_id_idx_map.get(&pal.id) {
+            None => new_palettes.push(pal.name.clone()),
+            Some(&i) => {
+                let known = tile_forms(&state.palettes[i].tiles);
+                let n = pal
+                    .tiles
+                    .iter()
+                    .filter(|t| !known.contains(&t.pixels))
+                    .count();
+                if n > 0 {
+                    num_new_tiles += n;
+                    palettes_with_new_tiles.push(state.palettes[i].name.clone());
+                }
+            }
+        }
+    }
+    if !new_palettes.is_empty() {
+        lines.push(format!(
+            "{} palettes in the ROM are not in the project: {}",
+            new_palettes.len(),
+            report_names(&new_palettes)
+        ));
+    }
+    if num_new_tiles > 0 {
+        lines.push(format!(
+            "{} tiles in the ROM are missing from the tilesets of: {}",
+            num_new_tiles,
+            report_names(&palettes_with_new_tiles)
+        ));
+    }
+
+    // Only areas which were imported (i.e. that correspond to a vanilla map) are compared:
+    let mut new_areas: Vec<String> = vec![];
+    let mut resized_areas: Vec<String> = vec![];
+    let mut changed_areas: Vec<String> = vec![];
+    let mut num_changed_screens = 0;
+    for area_name in &rom_state.area_names {
+        let area_id = AreaId {
+            area: area_name.clone(),
+            theme: theme.clone(),
+        };
+        let rom_area = load_area(&rom_state, &area_id)?;
+        if rom_area.vanilla_map_id.is_none() {
+            continue;
+        }
+        if !state.area_names.contains(area_name) || !state.theme_names.contains(&area_id.theme) {
+            new_areas.push(area_name.clone());
+            continue;
+        }
+        let loaded;
+        let area = match state.areas.get(&area_id) {
+            Some(area) => area,
+            None => {
+                loaded = load_area(state, &area_id)?;
+                &loaded
+            }
+        };
+        if area.size != rom_area.size {
+            resized_areas.push(area_name.clone());
+            continue;
+        }
+        let n = area
+            .screens
+            .iter()
+            .zip(&rom_area.screens)
+            .filter(|(a, b)| a.palettes != b.palettes || a.tiles != b.tiles || a.flips != b.flips)
+            .count();
+        if n > 0 {
+            num_changed_screens += n;
+            changed_areas.push(area_name.clone());
+        }
+    }
+    if !new_areas.is_empty() {
+        lines.push(format!(
+            "{} areas in the ROM are not in the project: {}",
+            new_areas.len(),
+            report_names(&new_areas)
+        ));
+    }
+    if !resized_areas.is_empty() {
+        lines.push(format!(
+            "{} areas have a different size: {}",
+            resized_areas.len(),
+            report_names(&resized_areas)
+        ));
+    }
+    if !changed_areas.is_empty() {
+        lines.push(format!(
+            "{} screens differ, in {} areas: {}",
+            num_changed_screens,
+            changed_areas.len(),
+            report_names(&changed_areas)
+        ));
+    }
+    if lines.len() == 1 {
+        lines.push("No differences found.".to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+// Read a raw dump of palettes (e.g. as written by `export_all_palettes_bin`): 16
+// little-endian BGR555 colors per palette. The palettes are given sequential IDs starting
+// at `first_id`, and are named after the file.
+pub fn import_palettes_bin(path: &Path, first_id: PaletteId) -> Result<Vec<Palette>> {
+    let data = std::fs::read(path)?;
+    ensure!(
+        data.len() % 32 == 0,
+        "File size {} is not a multiple of 32 bytes (16 colors per palette)",
+        data.len()
+    );
+    ensure!(
+        first_id as usize + data.len() / 32 <= PaletteId::MAX as usize + 1,
+        "Too many palettes to assign IDs starting at {}",
+        first_id
+    );
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut palettes = vec![];
+    for (i, chunk) in data.chunks_exact(32).enumerate() {
+        let mut colors = [[0, 0, 0]; 16];
+        for (j, c) in chunk.chunks_exact(2).enumerate() {
+            colors[j] = color_from_bgr555(u16::from_le_bytes([c[0], c[1]]));
+        }
+        palettes.push(Palette {
+            modified: true,
+            name: format!("{} {}", stem, i),
+            id: first_id + i as PaletteId,
+            colors,
+            tiles: vec![],
+            pages: vec![],
+            tags: BTreeMap::new(),
+            animations: vec![],
+        });
+    }
+    Ok(palettes)
+}
+
+// Unpack a tile from the 32 bytes of the SNES 4bpp planar format (the inverse of
+// `export::tile_to_4bpp`): bytes 2y and 2y+1 hold bitplanes 0 and 1 of row y, and bytes
+// 16+2y and 17+2y hold bitplanes 2 and 3, with bit 7 being the leftmost pixel.
+fn tile_from_4bpp(data: &[u8]) -> Tile {
+    let mut tile = Tile::default();
+    for y in 0..TILE_SIZE {
+        for x in 0..TILE_SIZE {
+            let bit = 7 - x;
+            let mut color_idx: ColorIdx = 0;
+            for plane in 0..4 {
+                let addr = (plane / 2) * 16 + y * 2 + plane % 2;
+                color_idx |= ((data[addr] >> bit) & 1) << plane;
+            }
+            tile.pixels[y][x] = color_idx;
+        }
+    }
+    tile
+}
+
+// Read a raw SNES 4bpp ".chr" file (e.g. as written by `export_chr`, or by YY-CHR): 32 bytes
+// per tile, in tile order.
+pub fn import_chr(path: &Path) -> Result<Vec<Tile>> {
+    let data = std::fs::read(path)?;
+    ensure!(
+        data.len() % 32 == 0,
+        "File size {} is not a multiple of 32 bytes (one 4bpp tile)",
+        data.len()
+    );
+    Ok(data.chunks_exact(32).map(tile_from_4bpp).collect())
+}
+
 fn decompress(rom: &Rom, mut addr: PcAddr, big_endian_offset: bool) -> Result<Vec<u8>> {
     let mut out: Vec<u8> = Vec::new();
     loop {
@@ -1,8 +1,13 @@
+pub mod archive;
+pub mod contact_sheet;
+pub mod export;
 pub mod helpers;
 pub mod import;
 pub mod message;
 pub mod persist;
+pub mod quantize;
 pub mod state;
 pub mod undo;
 pub mod update;
 pub mod view;
+pub mod world;
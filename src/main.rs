@@ -14,12 +14,26 @@ fn theme(_state: &EditorState) -> Theme {
     }
 }
 
-fn subscription(_state: &EditorState) -> Subscription<Message> {
-    Subscription::batch(vec![
+fn subscription(state: &EditorState) -> Subscription<Message> {
+    let mut subscriptions = vec![
         iced::window::close_requests().map(Message::WindowClose),
         iced::time::every(Duration::from_secs(1)).map(|_| Message::SaveProject),
         iced::event::listen().map(Message::Event),
-    ])
+    ];
+    // Only animate the selection outline while a selection is being made:
+    if state.start_coords.is_some() && state.end_coords.is_some() {
+        subscriptions
+            .push(iced::time::every(Duration::from_millis(100)).map(|_| Message::AnimateSelection));
+    }
+    // Only play tile animations if there are any:
+    if state.global_config.play_animations
+        && state.palettes.iter().any(|p| !p.animations.is_empty())
+    {
+        subscriptions.push(
+            iced::time::every(state::ANIMATION_FRAME_INTERVAL).map(|_| Message::AnimateTiles),
+        );
+    }
+    Subscription::batch(subscriptions)
 }
 
 pub fn main() -> Result<()> {
@@ -34,7 +48,10 @@ pub fn main() -> Result<()> {
     let editor_state = state::get_initial_state()?;
     let initial_task = match &editor_state.global_config.project_dir {
         None => Task::perform(view::open_project(), Message::ProjectOpened),
-        Some(_) => Task::none(),
+        Some(_) => Task::batch([
+            view::scroll_to_last_screen(&editor_state),
+            Task::done(Message::RecoverAutosaveDialogue),
+        ]),
     };
     iced::application("Z3 Overworld Editor", update::update, view::view)
         .font(iced_fonts::REQUIRED_FONT_BYTES)
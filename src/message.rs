@@ -1,10 +1,14 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, ops::Range, path::PathBuf};
 
-use iced::Point;
+use iced::{widget::text_editor, Point, Vector};
 
 use crate::state::{
-    AreaId, AreaPosition, CollisionType, ColorIdx, ColorRGB, ColorValue, Focus, Palette, PaletteId,
-    PaletteIdx, PixelCoord, Tile, TileBlock, TileCoord, TileIdx,
+    AreaCell, AreaId, AreaPosition, CollisionType, ColorIdx, ColorLimitPolicy, ColorMatchPolicy,
+    ColorProfile, ColorRGB, ColorValue, ConfirmExit, FillMode, Focus, ImageFilter,
+    LineBrushModifier, MirrorAxis, MouseButtonMapping, MoveCollision, MoveSnap, OutOfBoundsMove,
+    Palette, PaletteFileNaming, PaletteId, PaletteIdx, PaletteLayout, PixelCoord, Screen, ScreenId,
+    ScreenLayer, ScreenLink, Side, SidePanelView, StartupTool, Tile, TileBlock, TileCoord, TileIdx,
+    TilesetBackdrop,
 };
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -19,22 +23,117 @@ pub enum Message {
     Event(iced::Event),
     Focus(Focus),
     WindowClose(iced::window::Id),
+    ConfirmWindowClose(iced::window::Id),
+    SaveAndCloseWindow(iced::window::Id),
+    DiscardAndCloseWindow(iced::window::Id),
     SaveProject,
+    AnimateSelection,
+    AnimateTiles,
+    SaveProjectFiles,
+    RecoverAutosaveDialogue,
+    RecoverAutosave(Vec<PathBuf>),
+    DiscardAutosave,
     OpenProject,
     ModifiedReload,
+    ExternalChange(Vec<PathBuf>),
     RebuildProjectDialogue,
     RebuildProject,
     ProjectOpened(Option<PathBuf>),
     SettingsDialogue,
     HelpDialogue,
+    ProjectStatsDialogue,
     SetPixelSize(f32),
     SetGridAlpha(f32),
+    SetGridColor(ColorRGB),
+    SetMajorGridInterval(u16),
+    SetMajorGridColor(ColorRGB),
+    SetSelectionDashColor(usize, ColorRGB),
+    SetSelectionDashSpeed(u8),
+    SetMajorGridAlpha(f32),
+    SetHighlightColor(ColorRGB),
+    SetHighlightAlpha(f32),
+    SetTileHoverHighlight(bool),
+    SetDoubleClickRename(bool),
+    SetKeepSelectionOnToolSwitch(bool),
+    SetTilePreviewZoom(u8),
+    SetPixelGridMinSize(u8),
+    SetAutoAdvanceColor(bool),
+    SetTrimEmptyEdgesOnSave(bool),
+    SetPlayAnimations(bool),
+    SetImportAnimatedTiles(bool),
+    SetPerAreaZoom(bool),
+    SetAutosaveToShadow(bool),
+    SetLinearSelect(bool),
+    SetDisplayHex(bool),
+    SetMaxUndoLevels(u32),
+    SetUndoMemoryMb(u32),
+    SetFillConfirmThreshold(u32),
+    SetBrushOpacity(f32),
+    SetTilesetBrushPalette(bool),
+    SetContactSheetThumbnailSize(u16),
+    SetContactSheetColumns(u16),
+    SetLineBrushModifier(LineBrushModifier),
+    SetOutOfBoundsMove(OutOfBoundsMove),
+    SetMoveSnap(MoveSnap),
+    SetMoveSnapStep(TileCoord),
+    SetSelectionSnapStep(TileCoord),
+    SetTilesetBackdrop(TilesetBackdrop),
+    SetTilesetBackdropColor(ColorRGB),
+    SetColorProfile(ColorProfile),
+    SetPreviewFilter(ImageFilter),
+    SetPaletteLayout(PaletteLayout),
+    SetMouseButtons(MouseButtonMapping),
+    SetConfirmExit(ConfirmExit),
+    SetStartupTool(StartupTool),
+    SetPaletteFileNaming(PaletteFileNaming),
+    SetColorLimitPolicy(ColorLimitPolicy),
+    SetColorMatchPolicy(ColorMatchPolicy),
+    SetAreaMargin(u16),
+    SetCenterArea(bool),
+    ExportContactSheetDialogue,
+    ExportContactSheet(Option<PathBuf>),
+    ExportPalettesBinDialogue,
+    ExportSelectionTilesetDialogue,
+    ExportSelectionTileset(Option<PathBuf>),
+    ExportPalettesBin(Option<PathBuf>),
+    ExportPaletteJsonDialogue,
+    ExportPaletteJson(Option<PathBuf>),
+    ExportPaletteChrDialogue,
+    ExportPaletteChr(Option<PathBuf>),
+    ImportPalettesBinDialogue,
+    ImportPalettesBin(Option<PathBuf>),
+    ExportProjectArchiveDialogue,
+    ExportProjectArchive(Option<PathBuf>),
+    ImportProjectArchiveDialogue,
+    ImportProjectArchive(Option<PathBuf>),
+    ImportProjectArchiveTo {
+        archive: PathBuf,
+        dir: Option<PathBuf>,
+    },
+    ProjectArchiveReport(String),
     CloseDialogue,
     ImportDialogue,
     ImportConfirm(Option<PathBuf>),
     ImportROMProgress,
     ImportROM,
+    DiffAgainstRomDialogue,
+    DiffAgainstRom(Option<PathBuf>),
+    RunRomDiff(PathBuf),
+    ImportTilesetPNGDialogue,
+    ImportChr(PaletteId),
+    ImportChrFile {
+        palette_id: PaletteId,
+        path: Option<PathBuf>,
+    },
+    ImportTilesetPNGConfirm(Option<PathBuf>),
+    SetImportTilesetPNGQuantize(bool),
+    ImportTilesetPNG {
+        palette_id: PaletteId,
+        path: PathBuf,
+        quantize: bool,
+    },
     SelectPalette(String),
+    SelectPaletteIdx(PaletteIdx),
     AddPaletteDialogue,
     SetAddPaletteName(String),
     SetAddPaletteID(PaletteId),
@@ -44,7 +143,32 @@ pub enum Message {
     },
     DeletePaletteDialogue,
     DeletePalette(PaletteId),
+    SetDeletePaletteRemapTo(String),
+    RemapPalette {
+        from: PaletteId,
+        to: PaletteId,
+    },
     RestorePalette(Palette),
+    RemapPaletteInRegionDialogue,
+    SetRemapRegionFrom(String),
+    SetRemapRegionTo(String),
+    // Change the palette of cells using palette `from` to `to`, within the selection in the
+    // main area (or the whole main area, if there is no selection):
+    RemapPaletteInRegion {
+        from: PaletteId,
+        to: PaletteId,
+    },
+    ReplacePaletteReferencesDialogue,
+    SetReplaceReferencesFrom(String),
+    SetReplaceReferencesTo(String),
+    // Change the palette of all cells using palette `from` to `to`, in every area of the project:
+    ReplacePaletteReferences {
+        from: PaletteId,
+        to: PaletteId,
+    },
+    MergePalettesDialogue,
+    SetMergePalettesIdenticalTiles(bool),
+    MergePalettes(Vec<Vec<PaletteId>>),
     RenamePaletteDialogue,
     SetRenamePaletteName(String),
     RenamePalette {
@@ -53,17 +177,64 @@ pub enum Message {
     },
     HideModal,
     SelectColor(PaletteIdx, ColorIdx),
+    ToggleIdentifyColor(ColorIdx),
+    SetTilesetSortByUsage(bool),
+    SetSidePanelView(SidePanelView),
+    // Undo or redo until the given number of edits in the history are applied:
+    JumpToHistory(usize),
     BrushColor {
         palette_id: PaletteId,
         color_idx: ColorIdx,
         color: ColorRGB,
     },
+    ClickPaletteName(PaletteId),
+    ClearPaletteSelection,
+    AdjustPalettesBrightness {
+        palette_ids: Vec<PaletteId>,
+        delta: i8,
+    },
+    SetPalettesColors(Vec<(PaletteId, [ColorRGB; 16])>),
     ChangeRed(ColorValue),
     ChangeGreen(ColorValue),
     ChangeBlue(ColorValue),
+    SelectPreviewPalette(String),
     AddTileRow(PaletteId),
     DeleteTileRow(PaletteId),
-    RestoreTileRow(PaletteId, Vec<Tile>),
+    // Add back a deleted last row of tiles, along with the tags of its tiles:
+    RestoreTileRow(PaletteId, Vec<Tile>, BTreeMap<TileIdx, Vec<String>>),
+    // Insert a blank row of tiles before the given row, or delete the given row, shifting
+    // the following tiles (and the references to them):
+    InsertTileRow(PaletteId, usize),
+    DeleteTileRowAt(PaletteId, usize),
+    // Add back a deleted row of tiles at the given row, along with the tags of its tiles:
+    RestoreTileRowAt(PaletteId, usize, Vec<Tile>, BTreeMap<TileIdx, Vec<String>>),
+    CompareTiles(TileIdx, TileIdx),
+    CopyTileAsText(TileIdx),
+    PasteTileFromClipboard,
+    PasteTileFromText(String),
+    TilePagesDialogue,
+    SetTilePageName(String),
+    SetTilePages {
+        palette_id: PaletteId,
+        pages: Vec<(String, Range<TileIdx>)>,
+    },
+    ScrollToTilePage(usize),
+    SetTileTagSearch(String),
+    SetTileTagInput(String),
+    TagSelectedTiles,
+    UntagSelectedTiles,
+    SetTileTags {
+        palette_id: PaletteId,
+        tags: BTreeMap<TileIdx, Vec<String>>,
+    },
+    SetReplaceColorFrom(ColorIdx),
+    ReplaceColorInSelectedTiles,
+    ReplaceColorInTiles {
+        palette_id: PaletteId,
+        tiles: Vec<TileIdx>,
+        from: ColorIdx,
+        to: ColorIdx,
+    },
     SetTilePriority {
         palette_id: PaletteId,
         tile_idx: TileIdx,
@@ -120,6 +291,46 @@ pub enum Message {
         color: ColorRGB,
     },
     DeleteAreaDialogue,
+    ScreenNotesDialogue,
+    SelectNotesScreen(String),
+    EditScreenNotes(text_editor::Action),
+    SaveScreenNotes,
+    SetScreenNotes {
+        area_id: AreaId,
+        screen: (u8, u8),
+        notes: String,
+    },
+    EditAreaCell(AreaCell),
+    SetAreaCell {
+        area_id: AreaId,
+        coords: (TileCoord, TileCoord),
+        cell: AreaCell,
+    },
+    WorldOverviewDialogue,
+    OpenWorldScreen(ScreenId),
+    SelectLinkScreen(ScreenId),
+    SetLinkSide(Side),
+    SetLinkTargetArea(String),
+    SetLinkTargetX(u8),
+    SetLinkTargetY(u8),
+    LinkScreens {
+        from: ScreenId,
+        side: Side,
+        to: Option<ScreenId>,
+    },
+    SetWorldLinks(Vec<ScreenLink>),
+    TrimEmptyEdges(AreaId),
+    DeleteSelection,
+    MirrorSelection {
+        axis: MirrorAxis,
+    },
+    SelectNextBrokenCell,
+    CheckBrokenCells,
+    SetAreaScreens {
+        area_id: AreaId,
+        size: (u8, u8),
+        screens: Vec<Screen>,
+    },
     DeleteArea(String),
     SelectTheme(AreaPosition, String),
     AddThemeDialogue,
@@ -133,11 +344,33 @@ pub enum Message {
     },
     DeleteThemeDialogue,
     DeleteTheme(String),
+    ThemeColorsDialogue,
+    // Edit a color override of the current palette in the main area's theme:
+    EditThemeColor(ColorIdx, Option<ColorRGB>),
+    SetThemeColor {
+        theme: String,
+        palette_id: PaletteId,
+        color_idx: ColorIdx,
+        // `None` removes the override, restoring the palette's own color:
+        color: Option<ColorRGB>,
+    },
     HoverArea(Point<TileCoord>),
     HoverAreaEnd,
+    ScrollArea(AreaPosition, Vector),
     StartTileSelection(Point<TileCoord>, SelectionSource),
     ProgressTileSelection(Point<TileCoord>),
     EndTileSelection(Point<TileCoord>),
+    SelectTileRun(TileIdx, TileIdx),
+    MagicWandSelect {
+        position: AreaPosition,
+        coords: Point<TileCoord>,
+    },
+    SetReferenceSelection(Option<(Point<TileCoord>, Point<TileCoord>)>),
+    SwapReferenceSelection,
+    // Stamp the selected tile block onto the focused area, at the coordinates it was copied
+    // from or at the cursor:
+    PasteInPlace,
+    PasteAtCursor,
     AreaBrush {
         position: AreaPosition,
         area_id: AreaId,
@@ -145,6 +378,25 @@ pub enum Message {
         selection: TileBlock,
         palette_only: bool,
     },
+    AreaFill {
+        position: AreaPosition,
+        area_id: AreaId,
+        coords: Point<TileCoord>,
+        selection: TileBlock,
+        mode: FillMode,
+        palette_only: bool,
+        // Whether to ask for confirmation if the fill is large:
+        check_size: bool,
+    },
+    SetFillMode(FillMode),
+    SetScreenLayer(ScreenLayer),
+    // Set the selected color to the color displayed at a pixel of an area:
+    PickScreenColor {
+        position: AreaPosition,
+        x: TileCoord,
+        y: TileCoord,
+        pixel: (PixelCoord, PixelCoord),
+    },
     OpenTile {
         palette_id: PaletteId,
         tile_idx: TileIdx,
@@ -159,8 +411,22 @@ pub enum Message {
         dst_selection: TileBlock,
         check_reversible: bool,
     },
+    MoveTilesStep,
+    MoveProgress(f32),
+    CancelMove,
     MoveTilesConfirmDialogue {
         src_selection: TileBlock,
         dst_selection: TileBlock,
+        collisions: Vec<MoveCollision>,
     },
+    CreateSnapshotDialogue,
+    SetCreateSnapshotName(String),
+    CreateSnapshot(String),
+    RestoreSnapshotDialogue(String),
+    RestoreSnapshot(String),
+    SaveStampDialogue,
+    SetSaveStampName(String),
+    SaveStamp(String),
+    LoadStamp(String),
+    DeleteStamp(String),
 }
@@ -1,5 +1,6 @@
 use std::{
     fs::{self, File},
+    hash::{DefaultHasher, Hash, Hasher},
     io::BufWriter,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
@@ -8,31 +9,62 @@ use std::{
 use anyhow::{bail, Context, Result};
 use hashbrown::{HashMap, HashSet};
 use json_pretty_compact::PrettyCompactFormatter;
-use log::info;
+use log::{info, warn};
 use notify::{recommended_watcher, EventHandler};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Serializer;
 
 use crate::{
-    helpers::scale_color,
+    helpers::{broken_tile_color, nearest_color_idx, scale_color, unscale_color},
+    quantize::{quantization_error, quantize_to_palette},
     state::{
         ensure_areas_non_empty, ensure_palettes_non_empty, ensure_themes_non_empty, Area, AreaId,
-        AreaPosition, EditorState, Flip, Palette, PaletteId, TileIdx,
+        AreaPosition, ColorIdx, ColorLimitPolicy, ColorMatchPolicy, ColorRGB, EditorState, Flip,
+        Palette, PaletteFileNaming, PaletteId, ProjectStats, Screen, Stamp, Theme, Tile, TileIdx,
+        WorldMap, MAX_CONTACT_SHEET_COLUMNS, MAX_FILL_CONFIRM_THRESHOLD, MAX_THUMBNAIL_SIZE,
+        MIN_THUMBNAIL_SIZE, TILE_SIZE,
     },
     update::update_palette_order,
 };
 
-fn save_json<T: Serialize>(path: &Path, data: &T) -> Result<()> {
-    info!("Saving {}", path.display());
+// Subdirectories of the project containing the project data:
+pub const PROJECT_DATA_LOCATIONS: [&str; 5] = ["Areas", "Palettes", "Stamps", "Themes", "World"];
+
+// Subdirectory of the project mirroring the project data, used for autosaves when
+// `autosave_to_shadow` is enabled. The lock file exists while it holds unsaved data.
+const AUTOSAVE_DIR: &str = "autosave";
+const AUTOSAVE_LOCK: &str = "lock";
+
+fn json_bytes<T: Serialize>(data: &T) -> Vec<u8> {
     let formatter = PrettyCompactFormatter::new().with_max_line_length(200);
     let mut data_bytes = vec![];
     let mut ser = Serializer::with_formatter(&mut data_bytes, formatter);
     data.serialize(&mut ser).unwrap();
+    data_bytes
+}
+
+fn save_json<T: Serialize>(path: &Path, data: &T) -> Result<()> {
+    info!("Saving {}", path.display());
+    let data_bytes = json_bytes(data);
     fs::create_dir_all(path.parent().context("invalid parent directory")?)?;
     fs::write(path, &data_bytes)?;
     Ok(())
 }
 
+// Names of snapshots, stamps, etc. are used as file names in the project, so they must not
+// contain path separators or refer to other directories:
+pub fn check_file_name(kind: &str, name: &str) -> Result<()> {
+    if name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains(['/', '\\'])
+        || Path::new(name).file_name() != Some(std::ffi::OsStr::new(name))
+    {
+        bail!("Invalid {} name: {:?}", kind, name);
+    }
+    Ok(())
+}
+
 fn load_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
     info!("Loading {}", path.display());
     let data_bytes = std::fs::read(path)?;
@@ -42,6 +74,17 @@ fn load_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
 
 pub fn load_global_config(state: &mut EditorState) -> Result<()> {
     state.global_config = load_json(&state.global_config_path)?;
+    // Keep hand-edited or outdated values within the ranges allowed in the settings:
+    let config = &mut state.global_config;
+    config.contact_sheet_columns = config
+        .contact_sheet_columns
+        .clamp(1, MAX_CONTACT_SHEET_COLUMNS);
+    config.contact_sheet_thumbnail_size = config
+        .contact_sheet_thumbnail_size
+        .clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE);
+    config.fill_confirm_threshold = config
+        .fill_confirm_threshold
+        .min(MAX_FILL_CONFIRM_THRESHOLD);
     Ok(())
 }
 
@@ -110,13 +153,13 @@ fn save_palette_tiles_png(png_path: &Path, palette: &Palette) -> Result<()> {
     let num_rows = (tiles.len() + num_cols - 1) / num_cols;
 
     let mut data: Vec<u8> = vec![];
-    data.reserve_exact(num_rows * num_cols * 64 * 3);
-    for y in 0..num_rows * (8 * pixel_size) {
-        for x in 0..num_cols * (8 * pixel_size) {
-            let tile_x = x / (8 * pixel_size);
-            let tile_y = y / (8 * pixel_size);
-            let pixel_x = x / pixel_size % 8;
-            let pixel_y = y / pixel_size % 8;
+    data.reserve_exact(num_rows * num_cols * TILE_SIZE * TILE_SIZE * 3);
+    for y in 0..num_rows * (TILE_SIZE * pixel_size) {
+        for x in 0..num_cols * (TILE_SIZE * pixel_size) {
+            let tile_x = x / (TILE_SIZE * pixel_size);
+            let tile_y = y / (TILE_SIZE * pixel_size);
+            let pixel_x = x / pixel_size % TILE_SIZE;
+            let pixel_y = y / pixel_size % TILE_SIZE;
             let tile_idx = tile_y * num_cols + tile_x;
             if tile_idx >= tiles.len() {
                 data.extend([0, 0, 0, 0]);
@@ -134,8 +177,8 @@ fn save_palette_tiles_png(png_path: &Path, palette: &Palette) -> Result<()> {
     let ref mut w = BufWriter::new(file);
     let mut encoder = png::Encoder::new(
         w,
-        num_cols as u32 * 8 * pixel_size as u32,
-        num_rows as u32 * 8 * pixel_size as u32,
+        (num_cols * TILE_SIZE * pixel_size) as u32,
+        (num_rows * TILE_SIZE * pixel_size) as u32,
     );
     encoder.set_color(png::ColorType::Rgb);
     encoder.set_depth(png::BitDepth::Eight);
@@ -145,6 +188,257 @@ fn save_palette_tiles_png(png_path: &Path, palette: &Palette) -> Result<()> {
     Ok(())
 }
 
+// Load a PNG image as 5-bit colors, returning its width, height, and pixels (in row-major order).
+fn load_png_colors(png_path: &Path) -> Result<(usize, usize, Vec<ColorRGB>)> {
+    let mut decoder = png::Decoder::new(File::open(png_path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let bytes = &buf[..info.buffer_size()];
+    let pixels: Vec<ColorRGB> = match info.color_type {
+        png::ColorType::Rgb => bytes.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect(),
+        png::ColorType::Rgba => bytes.chunks_exact(4).map(|p| [p[0], p[1], p[2]]).collect(),
+        png::ColorType::Grayscale => bytes.iter().map(|&p| [p, p, p]).collect(),
+        png::ColorType::GrayscaleAlpha => {
+            bytes.chunks_exact(2).map(|p| [p[0], p[0], p[0]]).collect()
+        }
+        png::ColorType::Indexed => bail!("Unexpected indexed PNG output"),
+    };
+    let pixels = pixels.into_iter().map(|c| c.map(unscale_color)).collect();
+    Ok((info.width as usize, info.height as usize, pixels))
+}
+
+// Overwrite the pixels of a palette's tiles, starting from the first tile, with those in a PNG
+// image (read as 8x8 tiles in row-major order). Tile rows are added if needed.
+// If `quantize` is set, the image colors are written into the palette, with images of more than
+// 16 colors handled according to the configured `ColorLimitPolicy`; otherwise image colors are
+// matched to the palette colors according to the `ColorMatchPolicy`. Returns a report for the
+// user.
+pub fn import_tileset_png(
+    state: &mut EditorState,
+    palette_id: PaletteId,
+    png_path: &Path,
+    quantize: bool,
+) -> Result<String> {
+    let (width, height, pixels) = load_png_colors(png_path)?;
+    if width % 8 != 0 || height % 8 != 0 {
+        bail!(
+            "PNG dimensions {}x{} are not a multiple of 8 pixels.",
+            width,
+            height
+        );
+    }
+    let palette_idx = *state
+        .palettes_id_idx_map
+        .get(&palette_id)
+        .context("palette not found")?;
+    let num_distinct = pixels.iter().collect::<HashSet<_>>().len();
+    if quantize && num_distinct > 16 {
+        match state.global_config.color_limit_policy {
+            ColorLimitPolicy::Error => bail!(
+                "The image has {} distinct colors, more than the 16 that fit in a palette.",
+                num_distinct
+            ),
+            ColorLimitPolicy::Quantize => {}
+            ColorLimitPolicy::Split => {
+                return import_tileset_png_split(state, palette_idx, width, height, &pixels);
+            }
+        }
+    }
+    let match_policy = state.global_config.color_match_policy;
+    let pal = &mut state.palettes[palette_idx];
+
+    let report;
+    let mapping: Vec<ColorIdx> = if quantize {
+        let (colors, mapping) = quantize_to_palette(&pixels, 16);
+        let error = quantization_error(&pixels, &colors, &mapping);
+        report = if num_distinct > 16 {
+            format!(
+                "Reduced {} distinct colors to 16 (RMS error {:.2}, in 5-bit color units).",
+                num_distinct, error
+            )
+        } else {
+            format!("Wrote the {} image colors into the palette.", num_distinct)
+        };
+        pal.colors = colors;
+        mapping
+    } else {
+        let mapping: Vec<ColorIdx> = pixels.iter().map(|&c| nearest_color_idx(pal, c)).collect();
+        let unmatched: HashSet<ColorRGB> = pixels
+            .iter()
+            .zip(&mapping)
+            .filter(|&(&c, &i)| pal.colors[i as usize] != c)
+            .map(|(&c, _)| c)
+            .collect();
+        if unmatched.is_empty() {
+            report = "All image colors matched the palette exactly.".to_string();
+        } else {
+            match match_policy {
+                // Reject the import (before anything is changed):
+                ColorMatchPolicy::Exact => bail!(
+                    "{} colors in the image do not match any color in palette {}. No changes were made.",
+                    unmatched.len(),
+                    pal.id
+                ),
+                ColorMatchPolicy::Nearest => {
+                    let num_approximated = pixels
+                        .iter()
+                        .filter(|c| unmatched.contains(*c))
+                        .count();
+                    report = format!(
+                        "{} colors in the image did not match the palette exactly, so {} pixels were mapped to the nearest palette color.",
+                        unmatched.len(),
+                        num_approximated
+                    );
+                }
+            }
+        }
+        mapping
+    };
+
+    let num_tiles_x = width / TILE_SIZE;
+    let num_tiles = num_tiles_x * (height / TILE_SIZE);
+    let new_len = num_tiles.div_ceil(16).max(1) * 16;
+    pal.tiles
+        .resize(new_len.max(pal.tiles.len()), Tile::default());
+    for t in 0..num_tiles {
+        let (tx, ty) = (t % num_tiles_x, t / num_tiles_x);
+        for py in 0..TILE_SIZE {
+            for px in 0..TILE_SIZE {
+                pal.tiles[t].pixels[py][px] =
+                    mapping[(ty * TILE_SIZE + py) * width + tx * TILE_SIZE + px];
+            }
+        }
+    }
+    pal.modified = true;
+    Ok(format!(
+        "Imported {} tiles into palette {}. {}",
+        num_tiles, pal.id, report
+    ))
+}
+
+// Import the tiles of an image with more than 16 colors by grouping the tiles into sets of at
+// most 16 colors (in image order, each tile joining the first set it fits in). The first set
+// goes into the given palette, and each other set into a new palette numbered after the
+// highest palette ID. Every palette gets the tiles of its own set, leaving the other tiles
+// blank, so that tile indices match the image in each palette. A tile with more than 16 colors
+// by itself is quantized.
+fn import_tileset_png_split(
+    state: &mut EditorState,
+    palette_idx: usize,
+    width: usize,
+    height: usize,
+    pixels: &[ColorRGB],
+) -> Result<String> {
+    let num_tiles_x = width / TILE_SIZE;
+    let num_tiles = num_tiles_x * (height / TILE_SIZE);
+    let tile_pixels = |t: usize| -> Vec<ColorRGB> {
+        let (tx, ty) = (t % num_tiles_x, t / num_tiles_x);
+        (0..TILE_SIZE)
+            .flat_map(|py| (0..TILE_SIZE).map(move |px| (px, py)))
+            .map(|(px, py)| pixels[(ty * TILE_SIZE + py) * width + tx * TILE_SIZE + px])
+            .collect()
+    };
+
+    let mut groups: Vec<(HashSet<ColorRGB>, Vec<usize>)> = vec![];
+    for t in 0..num_tiles {
+        let colors: HashSet<ColorRGB> = tile_pixels(t).into_iter().collect();
+        match groups
+            .iter_mut()
+            .find(|(c, _)| c.union(&colors).count() <= 16)
+        {
+            Some((c, tiles)) => {
+                c.extend(colors);
+                tiles.push(t);
+            }
+            None => groups.push((colors, vec![t])),
+        }
+    }
+
+    let new_len = num_tiles.div_ceil(16).max(1) * 16;
+    let base_name = state.palettes[palette_idx].name.clone();
+    let base_id = state.palettes[palette_idx].id;
+    let max_id = state.palettes.iter().map(|p| p.id).max().unwrap_or(0);
+    let num_new = groups.len().saturating_sub(1);
+    if num_new > (PaletteId::MAX - max_id) as usize {
+        bail!(
+            "The image needs {} new palettes, but only {} palette IDs are left after the highest palette ID. No changes were made.",
+            num_new,
+            PaletteId::MAX - max_id
+        );
+    }
+    let mut pal_indices = vec![palette_idx];
+    let mut new_ids = vec![];
+    for k in 1..=num_new {
+        let id = max_id + k as PaletteId;
+        let mut name = format!("{}-{}", base_name, k + 1);
+        while state.palettes.iter().any(|p| p.name == name) {
+            name.push('_');
+        }
+        state.palettes.push(Palette {
+            modified: true,
+            name,
+            id,
+            tiles: vec![Tile::default(); new_len],
+            ..Default::default()
+        });
+        pal_indices.push(state.palettes.len() - 1);
+        new_ids.push(id);
+    }
+
+    let target = &mut state.palettes[palette_idx];
+    target
+        .tiles
+        .resize(new_len.max(target.tiles.len()), Tile::default());
+    let mut num_quantized = 0;
+    for (k, ((colors, tiles), &idx)) in groups.iter().zip(&pal_indices).enumerate() {
+        let group_pixels: Vec<ColorRGB> = tiles.iter().flat_map(|&t| tile_pixels(t)).collect();
+        let (pal_colors, mapping) = quantize_to_palette(&group_pixels, 16);
+        if colors.len() > 16 {
+            num_quantized += tiles.len();
+        }
+        let pal = &mut state.palettes[idx];
+        pal.colors = pal_colors;
+        pal.modified = true;
+        for (i, &t) in tiles.iter().enumerate() {
+            for py in 0..TILE_SIZE {
+                for px in 0..TILE_SIZE {
+                    pal.tiles[t].pixels[py][px] =
+                        mapping[i * TILE_SIZE * TILE_SIZE + py * TILE_SIZE + px];
+                }
+            }
+        }
+        if k > 0 {
+            // The tile belongs to this palette, so it's blank in the target palette:
+            for &t in tiles {
+                state.palettes[palette_idx].tiles[t].pixels = Default::default();
+            }
+        }
+    }
+    update_palette_order(state);
+
+    let mut report = format!(
+        "Imported {} tiles, split by color into {} palettes: {} and {} new ({}).",
+        num_tiles,
+        groups.len(),
+        base_id,
+        new_ids.len(),
+        new_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if num_quantized > 0 {
+        report += &format!(
+            " {} tiles had more than 16 colors by themselves and were quantized.",
+            num_quantized
+        );
+    }
+    Ok(report)
+}
+
 pub fn clear_pngs(state: &EditorState) -> Result<()> {
     let project_dir = state
         .global_config
@@ -161,23 +455,57 @@ pub fn clear_pngs(state: &EditorState) -> Result<()> {
     Ok(())
 }
 
+// File name (without extension) of a palette's JSON and PNG files, under the given naming scheme:
+pub fn palette_file_stem(naming: PaletteFileNaming, id: PaletteId, name: &str) -> String {
+    match naming {
+        PaletteFileNaming::Name => name.to_string(),
+        PaletteFileNaming::IdPrefix => format!("{:03}-{}", id, name),
+    }
+}
+
+// Name of the palette stored in a file with the given stem, i.e. without the ID prefix:
+fn palette_name_from_stem(naming: PaletteFileNaming, stem: &str) -> &str {
+    match naming {
+        PaletteFileNaming::Name => stem,
+        PaletteFileNaming::IdPrefix => match stem.split_once('-') {
+            Some((id, name)) if !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) => name,
+            _ => stem,
+        },
+    }
+}
+
+// Rename the JSON and PNG files of a palette, from one file stem to another:
+fn rename_palette_file_set(pal_dir: &Path, old_stem: &str, new_stem: &str) -> Result<()> {
+    for suffix in [".json", "-colors.png", "-tiles.png"] {
+        let old_path = pal_dir.join(format!("{}{}", old_stem, suffix));
+        let new_path = pal_dir.join(format!("{}{}", new_stem, suffix));
+        if old_path.exists() {
+            info!("Renaming {} to {}", old_path.display(), new_path.display());
+            fs::rename(old_path, new_path)?;
+        }
+    }
+    Ok(())
+}
+
 pub fn save_palettes(state: &mut EditorState) -> Result<()> {
     let pal_dir = get_palette_dir(state)?;
+    let naming = state.global_config.palette_file_naming;
     state.disable_watch_file_changes()?;
     for pal in &mut state.palettes {
         if pal.modified {
-            let pal_json_filename = format!("{}.json", pal.name);
+            let stem = palette_file_stem(naming, pal.id, &pal.name);
+            let pal_json_filename = format!("{}.json", stem);
             let pal_json_path = pal_dir.join(pal_json_filename);
             for i in 0..pal.tiles.len() {
                 pal.tiles[i].id = Some(i as TileIdx);
             }
             save_json(&pal_json_path, pal)?;
 
-            let pal_colors_png_filename = format!("{}-colors.png", pal.name);
+            let pal_colors_png_filename = format!("{}-colors.png", stem);
             let pal_colors_png_path = pal_dir.join(pal_colors_png_filename);
             save_palette_colors_png(&pal_colors_png_path, pal)?;
 
-            let pal_tiles_png_filename = format!("{}-tiles.png", pal.name);
+            let pal_tiles_png_filename = format!("{}-tiles.png", stem);
             let pal_tiles_png_path = pal_dir.join(pal_tiles_png_filename);
             save_palette_tiles_png(&pal_tiles_png_path, pal)?;
 
@@ -200,7 +528,16 @@ fn load_palettes(state: &mut EditorState) -> Result<()> {
             .to_str()
             .context("bad file stem")?;
         let mut pal: Palette = load_json(&path)?;
-        pal.name = name.to_owned();
+        let naming = state.global_config.palette_file_naming;
+        pal.name = palette_name_from_stem(naming, name).to_owned();
+        // Rename files following another naming scheme (e.g. from a project imported or
+        // edited elsewhere), so that they aren't duplicated when the palette is next saved:
+        let expected_stem = palette_file_stem(naming, pal.id, &pal.name);
+        if name != expected_stem && !state.read_only {
+            state.disable_watch_file_changes()?;
+            rename_palette_file_set(&pal_dir, name, &expected_stem)?;
+            state.enable_watch_file_changes()?;
+        }
         state.palettes.push(pal);
     }
     ensure_palettes_non_empty(state);
@@ -209,9 +546,10 @@ fn load_palettes(state: &mut EditorState) -> Result<()> {
     Ok(())
 }
 
-pub fn delete_palette(state: &mut EditorState, name: &str) -> Result<()> {
+pub fn delete_palette(state: &mut EditorState, id: PaletteId, name: &str) -> Result<()> {
     let pal_dir = get_palette_dir(state)?;
-    let path = pal_dir.join(format!("{}.json", name));
+    let stem = palette_file_stem(state.global_config.palette_file_naming, id, name);
+    let path = pal_dir.join(format!("{}.json", stem));
     info!("Deleting {}", path.display());
     state.disable_watch_file_changes()?;
     std::fs::remove_file(path)?;
@@ -246,6 +584,158 @@ pub fn load_area_list(state: &mut EditorState) -> Result<()> {
     Ok(())
 }
 
+fn get_theme_dir(state: &EditorState) -> Result<PathBuf> {
+    Ok(get_project_dir(state)?.join("Themes"))
+}
+
+fn load_themes(state: &mut EditorState) -> Result<()> {
+    let theme_dir = get_theme_dir(state)?;
+    let pattern = format!("{}/*.json", theme_dir.display());
+    state.themes.clear();
+    for entry in glob::glob(&pattern)? {
+        let path = entry?;
+        let name = path
+            .file_stem()
+            .context(format!("bad file name: {}", path.display()))?
+            .to_str()
+            .context("bad file stem")?;
+        let mut theme: Theme = load_json(&path)?;
+        theme.name = name.to_owned();
+        state.themes.insert(name.to_owned(), theme);
+    }
+    Ok(())
+}
+
+pub fn save_themes(state: &mut EditorState) -> Result<()> {
+    let theme_dir = get_theme_dir(state)?;
+    state.disable_watch_file_changes()?;
+    for theme in state.themes.values_mut() {
+        if theme.modified {
+            save_json(&theme_dir.join(format!("{}.json", theme.name)), theme)?;
+            theme.modified = false;
+        }
+    }
+    state.enable_watch_file_changes()?;
+    Ok(())
+}
+
+// Rename the files of all palettes from the given naming scheme to the configured one.
+pub fn rename_palette_files(state: &mut EditorState, old_naming: PaletteFileNaming) -> Result<()> {
+    let pal_dir = get_palette_dir(state)?;
+    let naming = state.global_config.palette_file_naming;
+    state.disable_watch_file_changes()?;
+    for pal in &state.palettes {
+        let old_stem = palette_file_stem(old_naming, pal.id, &pal.name);
+        let new_stem = palette_file_stem(naming, pal.id, &pal.name);
+        rename_palette_file_set(&pal_dir, &old_stem, &new_stem)?;
+    }
+    state.enable_watch_file_changes()?;
+    Ok(())
+}
+
+pub fn rename_theme(state: &mut EditorState, old_name: &str, new_name: &str) -> Result<()> {
+    let Some(mut theme) = state.themes.remove(old_name) else {
+        return Ok(());
+    };
+    let theme_dir = get_theme_dir(state)?;
+    let old_path = theme_dir.join(format!("{}.json", old_name));
+    let new_path = theme_dir.join(format!("{}.json", new_name));
+    if old_path.exists() {
+        info!("Renaming {} to {}", old_path.display(), new_path.display());
+        state.disable_watch_file_changes()?;
+        std::fs::rename(old_path, new_path)?;
+        state.enable_watch_file_changes()?;
+    }
+    theme.name = new_name.to_owned();
+    state.themes.insert(new_name.to_owned(), theme);
+    Ok(())
+}
+
+pub fn delete_theme(state: &mut EditorState, name: &str) -> Result<()> {
+    state.themes.remove(name);
+    let path = get_theme_dir(state)?.join(format!("{}.json", name));
+    if path.exists() {
+        info!("Deleting {}", path.display());
+        state.disable_watch_file_changes()?;
+        std::fs::remove_file(path)?;
+        state.enable_watch_file_changes()?;
+    }
+    Ok(())
+}
+
+fn get_stamp_dir(state: &EditorState) -> Result<PathBuf> {
+    Ok(get_project_dir(state)?.join("Stamps"))
+}
+
+fn load_stamps(state: &mut EditorState) -> Result<()> {
+    let stamp_dir = get_stamp_dir(state)?;
+    let pattern = format!("{}/*.json", stamp_dir.display());
+    state.stamps.clear();
+    for entry in glob::glob(&pattern)? {
+        let path = entry?;
+        let name = path
+            .file_stem()
+            .context(format!("bad file name: {}", path.display()))?
+            .to_str()
+            .context("bad file stem")?;
+        let mut stamp: Stamp = load_json(&path)?;
+        stamp.name = name.to_owned();
+        state.stamps.insert(name.to_owned(), stamp);
+    }
+    Ok(())
+}
+
+// Stamps aren't edited after being created, so they're saved right away rather than
+// along with the rest of the project:
+pub fn save_stamp(state: &mut EditorState, stamp: Stamp) -> Result<()> {
+    let path = get_stamp_dir(state)?.join(format!("{}.json", stamp.name));
+    state.disable_watch_file_changes()?;
+    save_json(&path, &stamp)?;
+    state.enable_watch_file_changes()?;
+    state.stamps.insert(stamp.name.clone(), stamp);
+    Ok(())
+}
+
+pub fn delete_stamp(state: &mut EditorState, name: &str) -> Result<()> {
+    state.stamps.remove(name);
+    let path = get_stamp_dir(state)?.join(format!("{}.json", name));
+    if path.exists() {
+        info!("Deleting {}", path.display());
+        state.disable_watch_file_changes()?;
+        std::fs::remove_file(path)?;
+        state.enable_watch_file_changes()?;
+    }
+    Ok(())
+}
+
+fn get_world_map_path(state: &EditorState) -> Result<PathBuf> {
+    Ok(get_project_dir(state)?.join("World").join("Links.json"))
+}
+
+// Load the screen adjacency of the project. A project without a world map file has no links.
+fn load_world_map(state: &mut EditorState) -> Result<()> {
+    let path = get_world_map_path(state)?;
+    state.world_map = if path.exists() {
+        load_json(&path)?
+    } else {
+        WorldMap::default()
+    };
+    Ok(())
+}
+
+pub fn save_world_map(state: &mut EditorState) -> Result<()> {
+    if !state.world_map.modified {
+        return Ok(());
+    }
+    let path = get_world_map_path(state)?;
+    state.disable_watch_file_changes()?;
+    fs::create_dir_all(path.parent().context("invalid parent directory")?)?;
+    save_json(&path, &state.world_map)?;
+    state.enable_watch_file_changes()?;
+    state.world_map.modified = false;
+    Ok(())
+}
+
 pub fn load_area(state: &EditorState, area_id: &AreaId) -> Result<Area> {
     let area_path = get_area_dir(state)?
         .join(area_id.area.clone())
@@ -253,14 +743,26 @@ pub fn load_area(state: &EditorState, area_id: &AreaId) -> Result<Area> {
     let mut area: Area = load_json(&area_path)?;
     area.name = area_id.area.to_owned();
     area.theme = area_id.theme.to_owned();
+    let num_fixed = area
+        .fix_screen_positions()
+        .with_context(|| format!("invalid area file {}", area_path.display()))?;
+    if num_fixed > 0 {
+        warn!(
+            "Corrected the position of {} screen(s) in {} to match their order in the file.",
+            num_fixed,
+            area_path.display()
+        );
+        area.modified = true;
+    }
     Ok(area)
 }
 
-pub fn save_area_png(state: &mut EditorState, area_id: &AreaId) -> Result<()> {
+// RGB colors of each palette (indexed like `state.palettes`), with color 0 replaced
+// by the background color of the area.
+pub fn area_color_bytes(state: &EditorState, area: &Area) -> Vec<Vec<[u8; 3]>> {
     let mut color_bytes: Vec<Vec<[u8; 3]>> = vec![];
-    let area = &state.areas[area_id];
     for i in 0..state.palettes.len() {
-        let mut colors = state.palettes[i].colors.clone();
+        let mut colors = state.theme_palette_colors(&area.theme, &state.palettes[i]);
         colors[0] = area.bg_color;
         let cb = colors
             .iter()
@@ -268,6 +770,67 @@ pub fn save_area_png(state: &mut EditorState, area_id: &AreaId) -> Result<()> {
             .collect();
         color_bytes.push(cb);
     }
+    color_bytes
+}
+
+// Draw a 256x256 screen into an RGB image buffer, starting at byte offset `screen_addr`.
+pub fn draw_screen(
+    state: &EditorState,
+    screen: &Screen,
+    color_bytes: &[Vec<[u8; 3]>],
+    data: &mut [u8],
+    screen_addr: usize,
+    row_stride: usize,
+) {
+    let col_stride = 3;
+    for ty in 0..32 {
+        for tx in 0..32 {
+            let palette_id = screen.palettes[ty][tx];
+            let tile_idx = screen.tiles[ty][tx];
+            let mut tile_addr =
+                screen_addr + ty * TILE_SIZE * row_stride + tx * TILE_SIZE * col_stride;
+            let Some(palette_idx) = state
+                .palettes_id_idx_map
+                .get(&palette_id)
+                .copied()
+                .filter(|&i| (tile_idx as usize) < state.palettes[i].tiles.len())
+            else {
+                // Draw an error pattern for a cell referencing a missing palette or tile:
+                for py in 0..TILE_SIZE {
+                    let mut addr = tile_addr;
+                    for px in 0..TILE_SIZE {
+                        data[addr..(addr + 3)].copy_from_slice(&broken_tile_color(px, py));
+                        addr += 3;
+                    }
+                    tile_addr += row_stride;
+                }
+                continue;
+            };
+            let flip = screen.flips[ty][tx];
+            let tile = state.palettes[palette_idx].tiles[tile_idx as usize];
+            let tile = flip.apply_to_tile(tile);
+            let cb = &color_bytes[palette_idx];
+
+            for py in 0..TILE_SIZE {
+                let mut addr = tile_addr;
+                for px in 0..TILE_SIZE {
+                    let color_idx = tile.pixels[py][px];
+                    let color = cb
+                        .get(color_idx as usize)
+                        .copied()
+                        .unwrap_or_else(|| broken_tile_color(px, py));
+                    data[addr..(addr + 3)].copy_from_slice(&color);
+                    addr += 3;
+                }
+                tile_addr += row_stride;
+            }
+        }
+    }
+}
+
+pub fn save_area_png(state: &mut EditorState, area_id: &AreaId) -> Result<()> {
+    let area = &state.areas[area_id];
+    let color_bytes = area_color_bytes(state, area);
 
     let num_cols = area.size.1 as usize * 256;
     let num_rows = area.size.0 as usize * 256;
@@ -278,36 +841,14 @@ pub fn save_area_png(state: &mut EditorState, area_id: &AreaId) -> Result<()> {
         for sx in 0..area.size.0 as usize {
             let screen = &area.screens[sy * area.size.0 as usize + sx];
             let screen_addr = sy * 256 * row_stride + sx * 256 * col_stride;
-            for ty in 0..32 {
-                for tx in 0..32 {
-                    let palette_id = screen.palettes[ty][tx];
-                    let Some(&palette_idx) = state.palettes_id_idx_map.get(&palette_id) else {
-                        // TODO: draw some indicator of the broken tile (due to invalid palette reference)
-                        continue;
-                    };
-                    let tile_idx = screen.tiles[ty][tx];
-                    if tile_idx as usize >= state.palettes[palette_idx].tiles.len() {
-                        // TODO: draw some indicator of the broken tile (due to invalid palette reference)
-                        continue;
-                    }
-                    let flip = screen.flips[ty][tx];
-                    let tile = state.palettes[palette_idx].tiles[tile_idx as usize];
-                    let tile = flip.apply_to_tile(tile);
-                    let cb = &color_bytes[palette_idx];
-                    let mut tile_addr = screen_addr + ty * 8 * row_stride + tx * 8 * col_stride;
-
-                    for py in 0..8 {
-                        let mut addr = tile_addr;
-                        for px in 0..8 {
-                            let color_idx = tile.pixels[py][px];
-                            let color = cb[color_idx as usize];
-                            data[addr..(addr + 3)].copy_from_slice(&color);
-                            addr += 3;
-                        }
-                        tile_addr += row_stride;
-                    }
-                }
-            }
+            draw_screen(
+                state,
+                screen,
+                &color_bytes,
+                &mut data,
+                screen_addr,
+                row_stride,
+            );
         }
     }
 
@@ -329,7 +870,10 @@ pub fn save_area_json(state: &mut EditorState, area_id: &AreaId) -> Result<()> {
     let area_dir = get_area_dir(state)?;
     let area_json_filename = format!("{}.json", area_id.theme);
     let area_json_path = area_dir.join(&area_id.area).join(area_json_filename);
-    save_json(&area_json_path, &state.areas[area_id])?;
+    let area = &state.areas[area_id];
+    area.check_screens()
+        .with_context(|| format!("not saving inconsistent area {}", area_json_path.display()))?;
+    save_json(&area_json_path, area)?;
     Ok(())
 }
 
@@ -384,6 +928,14 @@ pub fn rename_area(state: &mut EditorState, old_name: &str, new_name: &str) -> R
     for k in keys {
         state.areas.remove(&k);
     }
+    for link in &mut state.world_map.links {
+        for screen in [&mut link.from, &mut link.to] {
+            if screen.area == old_name {
+                screen.area = new_name.to_string();
+                state.world_map.modified = true;
+            }
+        }
+    }
     Ok(())
 }
 
@@ -422,6 +974,14 @@ pub fn delete_area(state: &mut EditorState, name: &str) -> Result<()> {
     for k in keys {
         state.areas.remove(&k);
     }
+    let num_links = state.world_map.links.len();
+    state
+        .world_map
+        .links
+        .retain(|link| link.from.area != name && link.to.area != name);
+    if state.world_map.links.len() != num_links {
+        state.world_map.modified = true;
+    }
     Ok(())
 }
 
@@ -439,23 +999,234 @@ pub fn delete_area_theme(state: &mut EditorState, area_name: &str, theme: &str)
     Ok(())
 }
 
-pub fn scan_used_tiles(state: &mut EditorState) -> Result<HashSet<(PaletteId, TileIdx)>> {
-    let area_names = state.area_names.clone();
-    let theme_names = state.theme_names.clone();
-    let mut out = HashSet::new();
-    for area_name in &area_names {
-        for theme_name in &theme_names {
+// Areas (in any theme) which use the given palette. Loaded areas are checked in memory,
+// so that unsaved edits are taken into account.
+pub fn find_palette_references(state: &EditorState, palette_id: PaletteId) -> Result<Vec<AreaId>> {
+    let uses_palette = |area: &Area| {
+        area.screens
+            .iter()
+            .any(|s| s.palettes.iter().flatten().any(|&p| p == palette_id))
+    };
+    let mut out = vec![];
+    for area_name in &state.area_names {
+        for theme_name in &state.theme_names {
             let area_id = AreaId {
                 area: area_name.clone(),
                 theme: theme_name.clone(),
             };
-            let area =
-                load_area(state, &area_id).context(format!("Error loading {:?}", area_id))?;
-            for y in 0..area.size.1 as u16 * 32 {
-                for x in 0..area.size.0 as u16 * 32 {
-                    let pal = area.get_palette(x, y).unwrap();
-                    let tile_idx = area.get_tile(x, y).unwrap();
-                    out.insert((pal, tile_idx));
+            let used = match state.areas.get(&area_id) {
+                Some(area) => uses_palette(area),
+                None => uses_palette(
+                    &load_area(state, &area_id).context(format!("Error loading {:?}", area_id))?,
+                ),
+            };
+            if used {
+                out.push(area_id);
+            }
+        }
+    }
+    Ok(out)
+}
+
+// Number of cells using the given palette, and the number of areas (in any theme)
+// containing them. Loaded areas are checked in memory.
+pub fn count_palette_references(
+    state: &EditorState,
+    palette_id: PaletteId,
+) -> Result<(usize, usize)> {
+    let count = |area: &Area| {
+        area.screens
+            .iter()
+            .map(|s| {
+                s.palettes
+                    .iter()
+                    .flatten()
+                    .filter(|&&p| p == palette_id)
+                    .count()
+            })
+            .sum::<usize>()
+    };
+    let mut num_cells = 0;
+    let mut num_areas = 0;
+    for area_id in all_area_ids(state) {
+        let n = match state.areas.get(&area_id) {
+            Some(area) => count(area),
+            None => {
+                count(&load_area(state, &area_id).context(format!("Error loading {:?}", area_id))?)
+            }
+        };
+        if n > 0 {
+            num_cells += n;
+            num_areas += 1;
+        }
+    }
+    Ok((num_cells, num_areas))
+}
+
+// Change the palette of every cell using `from` to `to` in one area, saving the area if it
+// changed. Returns the original area and the number of cells changed, or `None` if no cells
+// use `from`.
+fn replace_area_palette_references(
+    state: &mut EditorState,
+    area_id: &AreaId,
+    from: PaletteId,
+    to: PaletteId,
+) -> Result<Option<(Area, usize)>> {
+    let mut area = match state.areas.get(area_id) {
+        Some(area) => area.clone(),
+        None => load_area(state, area_id)?,
+    };
+    let original = area.clone();
+    let mut n = 0;
+    for screen in &mut area.screens {
+        for p in screen.palettes.iter_mut().flatten() {
+            if *p == from {
+                *p = to;
+                n += 1;
+            }
+        }
+    }
+    if n == 0 {
+        return Ok(None);
+    }
+    area.modified = true;
+    state.areas.insert(area_id.clone(), area);
+    if let Err(e) = save_area(state, area_id) {
+        // Don't leave the changed area in memory, to be saved later:
+        state.areas.insert(area_id.clone(), original);
+        state.cleanup_areas()?;
+        return Err(e);
+    }
+    state.cleanup_areas()?;
+    Ok(Some((original, n)))
+}
+
+// Change the palette of every cell using `from` to `to`, in all areas of all themes,
+// returning the number of cells and areas changed. If any area fails, the areas already
+// changed are restored, so the project isn't left partly changed.
+pub fn replace_palette_references(
+    state: &mut EditorState,
+    from: PaletteId,
+    to: PaletteId,
+) -> Result<(usize, usize)> {
+    let mut num_cells = 0;
+    let mut originals: Vec<(AreaId, Area)> = vec![];
+    for area_id in all_area_ids(state) {
+        match replace_area_palette_references(state, &area_id, from, to) {
+            Ok(Some((original, n))) => {
+                num_cells += n;
+                originals.push((area_id, original));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                for (area_id, area) in originals.into_iter().rev() {
+                    restore_area(state, &area_id, area)?;
+                }
+                return Err(e.context(format!(
+                    "Error replacing palette {} in {:?}; no areas were changed",
+                    from, area_id
+                )));
+            }
+        }
+    }
+    Ok((num_cells, originals.len()))
+}
+
+// Number of broken cells (see `EditorState::is_broken_cell`) of each area in each theme,
+// for the areas which have any. Loaded areas are checked in memory.
+pub fn find_broken_cells(state: &EditorState) -> Result<Vec<(AreaId, usize)>> {
+    let mut out = vec![];
+    for area_id in all_area_ids(state) {
+        let n = match state.areas.get(&area_id) {
+            Some(area) => state.broken_cells(area).len(),
+            None => state
+                .broken_cells(
+                    &load_area(state, &area_id).context(format!("Error loading {:?}", area_id))?,
+                )
+                .len(),
+        };
+        if n > 0 {
+            out.push((area_id, n));
+        }
+    }
+    Ok(out)
+}
+
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+// Counts and sizes of the project data. Loaded areas are counted in memory, while the size
+// on disk is that of the saved project files.
+pub fn project_stats(state: &EditorState) -> Result<ProjectStats> {
+    let palette_tiles = state
+        .palettes
+        .iter()
+        .map(|p| (p.name.clone(), p.id, p.tiles.len()))
+        .collect();
+    let area_ids = all_area_ids(state);
+    let mut num_screens = 0;
+    for area_id in &area_ids {
+        num_screens += match state.areas.get(area_id) {
+            Some(area) => area.screens.len(),
+            None => load_area(state, area_id)
+                .context(format!("Error loading {:?}", area_id))?
+                .screens
+                .len(),
+        };
+    }
+    let project_dir = get_project_dir(state)?;
+    let mut disk_bytes = 0;
+    for location in PROJECT_DATA_LOCATIONS {
+        let path = project_dir.join(location);
+        if path.exists() {
+            disk_bytes += dir_size(&path)?;
+        }
+    }
+    Ok(ProjectStats {
+        palette_tiles,
+        num_areas: area_ids.len(),
+        num_screens,
+        disk_bytes,
+    })
+}
+
+// Number of cells using each tile of each palette, across all areas (using the in-memory copy
+// of the areas that are loaded, so that unsaved edits are counted):
+pub fn count_tile_uses(state: &EditorState) -> Result<HashMap<PaletteId, Vec<usize>>> {
+    let mut out: HashMap<PaletteId, Vec<usize>> = state
+        .palettes
+        .iter()
+        .map(|p| (p.id, vec![0; p.tiles.len()]))
+        .collect();
+    for area_id in all_area_ids(state) {
+        let loaded;
+        let area = match state.areas.get(&area_id) {
+            Some(area) => area,
+            None => {
+                loaded =
+                    load_area(state, &area_id).context(format!("Error loading {:?}", area_id))?;
+                &loaded
+            }
+        };
+        for screen in &area.screens {
+            for (pal_row, tile_row) in screen.palettes.iter().zip(&screen.tiles) {
+                for (pal_id, &tile_idx) in pal_row.iter().zip(tile_row) {
+                    if let Some(c) = out
+                        .get_mut(pal_id)
+                        .and_then(|counts| counts.get_mut(tile_idx as usize))
+                    {
+                        *c += 1;
+                    }
                 }
             }
         }
@@ -463,37 +1234,211 @@ pub fn scan_used_tiles(state: &mut EditorState) -> Result<HashSet<(PaletteId, Ti
     Ok(out)
 }
 
+// Every area in every theme:
+pub fn all_area_ids(state: &EditorState) -> Vec<AreaId> {
+    let mut out = vec![];
+    for area_name in &state.area_names {
+        for theme_name in &state.theme_names {
+            out.push(AreaId {
+                area: area_name.clone(),
+                theme: theme_name.clone(),
+            });
+        }
+    }
+    out
+}
+
 pub fn remap_tiles(
     state: &mut EditorState,
     map: &HashMap<(PaletteId, TileIdx), (PaletteId, TileIdx, Flip)>,
 ) -> Result<()> {
-    let area_names = state.area_names.clone();
-    let theme_names = state.theme_names.clone();
-    for area_name in &area_names {
-        for theme_name in &theme_names {
-            let area_id = AreaId {
-                area: area_name.clone(),
-                theme: theme_name.clone(),
-            };
-            let mut area = load_area(state, &area_id)?;
-            for y in 0..area.size.1 as u16 * 32 {
-                for x in 0..area.size.0 as u16 * 32 {
-                    let pal = area.get_palette(x, y).unwrap();
-                    let tile_idx = area.get_tile(x, y).unwrap();
-                    if let Some(&(p, t, f)) = map.get(&(pal, tile_idx)) {
-                        let flip = area.get_flip(x, y)?;
-                        area.set_palette(x, y, p)?;
-                        area.set_tile(x, y, t)?;
-                        area.set_flip(x, y, f.apply_to_flip(flip))?;
-                        area.modified = true;
-                    }
+    for area_id in all_area_ids(state) {
+        remap_area_tiles(state, &area_id, map)?;
+    }
+    Ok(())
+}
+
+// Remap the tile references of a single area and save it, returning the area as it was
+// before remapping. A loaded area is remapped as it is in memory, since its file may not have
+// its latest edits (e.g. when autosaving to the shadow copy).
+pub fn remap_area_tiles(
+    state: &mut EditorState,
+    area_id: &AreaId,
+    map: &HashMap<(PaletteId, TileIdx), (PaletteId, TileIdx, Flip)>,
+) -> Result<Area> {
+    let mut area = match state.areas.get(area_id) {
+        Some(area) => area.clone(),
+        None => load_area(state, area_id)?,
+    };
+    let original = area.clone();
+    for y in 0..area.size.1 as u16 * 32 {
+        for x in 0..area.size.0 as u16 * 32 {
+            let pal = area.get_palette(x, y).unwrap();
+            let tile_idx = area.get_tile(x, y).unwrap();
+            if let Some(&(p, t, f)) = map.get(&(pal, tile_idx)) {
+                let flip = area.get_flip(x, y)?;
+                area.set_palette(x, y, p)?;
+                area.set_tile(x, y, t)?;
+                area.set_flip(x, y, f.apply_to_flip(flip))?;
+                area.modified = true;
+            }
+        }
+    }
+    state.areas.insert(area_id.clone(), area);
+    if let Err(e) = save_area(state, area_id) {
+        // Don't leave the remapped area in memory, to be saved later:
+        state.areas.insert(area_id.clone(), original);
+        state.cleanup_areas()?;
+        return Err(e);
+    }
+    state.cleanup_areas()?;
+    Ok(original)
+}
+
+// Put back an area as returned by `remap_area_tiles`, saving it.
+pub fn restore_area(state: &mut EditorState, area_id: &AreaId, mut area: Area) -> Result<()> {
+    area.modified = true;
+    state.areas.insert(area_id.clone(), area);
+    save_area(state, area_id)?;
+    state.cleanup_areas()?;
+    Ok(())
+}
+
+fn get_snapshot_dir(state: &EditorState) -> Result<PathBuf> {
+    Ok(get_project_dir(state)?.join("Snapshots"))
+}
+
+fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let dst_path = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &dst_path)?;
+        } else {
+            fs::copy(entry.path(), dst_path)?;
+        }
+    }
+    Ok(())
+}
+
+// Copy the project data into another directory (e.g. to work on a scratch copy of the project):
+pub fn copy_project_data(state: &EditorState, dst: &Path) -> Result<()> {
+    let project_dir = get_project_dir(state)?;
+    for loc in PROJECT_DATA_LOCATIONS {
+        let src_path = project_dir.join(loc);
+        if src_path.exists() {
+            copy_dir_all(&src_path, &dst.join(loc))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn load_snapshot_list(state: &mut EditorState) -> Result<()> {
+    let snapshot_dir = get_snapshot_dir(state)?;
+    state.snapshot_names.clear();
+    if !snapshot_dir.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(snapshot_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            if let Some(name) = entry.file_name().to_str() {
+                state.snapshot_names.push(name.to_string());
+            }
+        }
+    }
+    state.snapshot_names.sort();
+    Ok(())
+}
+
+pub fn create_snapshot(state: &mut EditorState, name: &str) -> Result<()> {
+    check_file_name("snapshot", name)?;
+    save_project(state)?;
+    let project_dir = get_project_dir(state)?;
+    let snapshot_path = get_snapshot_dir(state)?.join(name);
+    if snapshot_path.exists() {
+        bail!("Snapshot already exists: {}", snapshot_path.display());
+    }
+    info!("Creating snapshot {}", snapshot_path.display());
+    for loc in PROJECT_DATA_LOCATIONS {
+        let src_path = project_dir.join(loc);
+        if src_path.exists() {
+            copy_dir_all(&src_path, &snapshot_path.join(loc))?;
+        }
+    }
+    load_snapshot_list(state)?;
+    Ok(())
+}
+
+// Replace the project data with that of a snapshot. The snapshot is first copied into a
+// staging directory in the project, so that a failed copy leaves the project data untouched,
+// and then swapped in by renaming (moving the replaced data back if that fails).
+fn swap_in_snapshot(project_dir: &Path, snapshot_path: &Path) -> Result<()> {
+    let staging = project_dir.join(format!(".restore-{}", std::process::id()));
+    let new_dir = staging.join("new");
+    let old_dir = staging.join("old");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    let copied = PROJECT_DATA_LOCATIONS.iter().try_for_each(|loc| {
+        let src_path = snapshot_path.join(loc);
+        if src_path.exists() {
+            copy_dir_all(&src_path, &new_dir.join(loc))?;
+        }
+        Ok::<(), anyhow::Error>(())
+    });
+    if let Err(e) = copied {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    fs::create_dir_all(&old_dir)?;
+    let swapped = PROJECT_DATA_LOCATIONS.iter().try_for_each(|loc| {
+        let dst_path = project_dir.join(loc);
+        if dst_path.exists() {
+            fs::rename(&dst_path, old_dir.join(loc))?;
+        }
+        let src_path = new_dir.join(loc);
+        if src_path.exists() {
+            fs::rename(&src_path, &dst_path)?;
+        }
+        Ok::<(), anyhow::Error>(())
+    });
+    if let Err(e) = swapped {
+        for loc in PROJECT_DATA_LOCATIONS {
+            let old_path = old_dir.join(loc);
+            if old_path.exists() {
+                let dst_path = project_dir.join(loc);
+                if dst_path.exists() {
+                    let _ = fs::remove_dir_all(&dst_path);
                 }
+                let _ = fs::rename(&old_path, &dst_path);
             }
-            state.areas.insert(area_id.clone(), area);
-            save_area(state, &area_id)?;
-            state.cleanup_areas()?;
         }
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
     }
+    fs::remove_dir_all(&staging)?;
+    Ok(())
+}
+
+pub fn restore_snapshot(state: &mut EditorState, name: &str) -> Result<()> {
+    check_file_name("snapshot", name)?;
+    let project_dir = get_project_dir(state)?;
+    let snapshot_path = get_snapshot_dir(state)?.join(name);
+    if !snapshot_path.exists() {
+        bail!("Snapshot does not exist: {}", snapshot_path.display());
+    }
+    info!("Restoring snapshot {}", snapshot_path.display());
+    state.disable_watch_file_changes()?;
+    let result = swap_in_snapshot(&project_dir, &snapshot_path);
+    // Re-enable the watcher whether or not the restore succeeded:
+    state.enable_watch_file_changes()?;
+    result?;
+
+    // Drop the in-memory areas without saving them, so they don't overwrite the restored data:
+    state.areas.clear();
+    load_project(state)?;
     Ok(())
 }
 
@@ -502,18 +1447,147 @@ pub fn save_project(state: &mut EditorState) -> Result<()> {
         return Ok(());
     }
     save_global_config(state)?;
+    if state.read_only {
+        return Ok(());
+    }
     save_palettes(state)?;
+    save_themes(state)?;
+    save_world_map(state)?;
     save_area(state, &state.main_area_id.clone())?;
     save_area(state, &state.side_area_id.clone())?;
+    // Everything in memory is now persisted, so data autosaved by this session is obsolete.
+    // (Autosaves left by a previous session are kept until recovered or discarded.)
+    if !state.autosave_hashes.is_empty() {
+        clear_autosave(state)?;
+    }
+    Ok(())
+}
+
+fn get_autosave_dir(state: &EditorState) -> Result<PathBuf> {
+    Ok(get_project_dir(state)?.join(AUTOSAVE_DIR))
+}
+
+// Write the modified palettes and areas into the autosave directory, leaving the project
+// files untouched. The data stays marked as modified, to be saved by `save_project`.
+pub fn autosave_project(state: &mut EditorState) -> Result<()> {
+    if state.global_config.project_dir.is_none() {
+        return Ok(());
+    }
+    save_global_config(state)?;
+    if state.read_only {
+        return Ok(());
+    }
+    let autosave_dir = get_autosave_dir(state)?;
+    let mut files: Vec<(PathBuf, Vec<u8>)> = vec![];
+    let naming = state.global_config.palette_file_naming;
+    for pal in &mut state.palettes {
+        if pal.modified {
+            for i in 0..pal.tiles.len() {
+                pal.tiles[i].id = Some(i as TileIdx);
+            }
+            let stem = palette_file_stem(naming, pal.id, &pal.name);
+            let path = autosave_dir.join("Palettes").join(format!("{}.json", stem));
+            files.push((path, json_bytes(pal)));
+        }
+    }
+    for theme in state.themes.values() {
+        if theme.modified {
+            let path = autosave_dir
+                .join("Themes")
+                .join(format!("{}.json", theme.name));
+            files.push((path, json_bytes(theme)));
+        }
+    }
+    if state.world_map.modified {
+        let path = autosave_dir.join("World").join("Links.json");
+        files.push((path, json_bytes(&state.world_map)));
+    }
+    for area in state.areas.values() {
+        if area.modified {
+            let path = autosave_dir
+                .join("Areas")
+                .join(&area.name)
+                .join(format!("{}.json", area.theme));
+            files.push((path, json_bytes(area)));
+        }
+    }
+    for (path, data) in files {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        let hash = hasher.finish();
+        if state.autosave_hashes.get(&path) == Some(&hash) {
+            continue;
+        }
+        let lock_path = autosave_dir.join(AUTOSAVE_LOCK);
+        if !lock_path.exists() {
+            fs::create_dir_all(&autosave_dir)?;
+            fs::write(&lock_path, std::process::id().to_string())?;
+        }
+        info!("Autosaving {}", path.display());
+        fs::create_dir_all(path.parent().context("invalid parent directory")?)?;
+        fs::write(&path, &data)?;
+        state.autosave_hashes.insert(path, hash);
+    }
+    Ok(())
+}
+
+pub fn clear_autosave(state: &mut EditorState) -> Result<()> {
+    let autosave_dir = get_autosave_dir(state)?;
+    if autosave_dir.exists() {
+        info!("Removing {}", autosave_dir.display());
+        fs::remove_dir_all(&autosave_dir)?;
+    }
+    state.autosave_hashes.clear();
+    Ok(())
+}
+
+// If a previous session left a stale autosave lock (e.g. due to a crash), return the
+// project-relative paths of autosaved files which differ from the project files.
+pub fn autosave_changes(state: &EditorState) -> Result<Vec<PathBuf>> {
+    let project_dir = get_project_dir(state)?;
+    let autosave_dir = get_autosave_dir(state)?;
+    if !autosave_dir.join(AUTOSAVE_LOCK).exists() {
+        return Ok(vec![]);
+    }
+    let mut changes = vec![];
+    for path in glob::glob(&format!("{}/**/*.json", autosave_dir.display()))? {
+        let path = path?;
+        let rel_path = path.strip_prefix(&autosave_dir)?.to_owned();
+        let project_path = project_dir.join(&rel_path);
+        if !project_path.exists() || fs::read(&path)? != fs::read(&project_path)? {
+            changes.push(rel_path);
+        }
+    }
+    changes.sort();
+    Ok(changes)
+}
+
+// Copy the given autosaved files over the project files, and reload the project.
+pub fn recover_autosave(state: &mut EditorState, changes: &[PathBuf]) -> Result<()> {
+    let project_dir = get_project_dir(state)?;
+    let autosave_dir = get_autosave_dir(state)?;
+    state.disable_watch_file_changes()?;
+    for rel_path in changes {
+        let dst_path = project_dir.join(rel_path);
+        info!("Recovering {}", dst_path.display());
+        fs::create_dir_all(dst_path.parent().context("invalid parent directory")?)?;
+        fs::copy(autosave_dir.join(rel_path), &dst_path)?;
+    }
+    state.enable_watch_file_changes()?;
+    clear_autosave(state)?;
+
+    // Drop the in-memory areas without saving them, so they don't overwrite the recovered data:
+    state.areas.clear();
+    load_project(state)?;
     Ok(())
 }
 
 struct FileModificationHandler {
-    modified: Arc<Mutex<bool>>,
+    modified: Arc<Mutex<HashSet<PathBuf>>>,
 }
 
 impl FileModificationHandler {
-    fn new(modified: Arc<Mutex<bool>>) -> Self {
+    fn new(modified: Arc<Mutex<HashSet<PathBuf>>>) -> Self {
         FileModificationHandler { modified }
     }
 }
@@ -524,15 +1598,167 @@ impl EventHandler for FileModificationHandler {
             return;
         };
         match e.kind {
-            notify::EventKind::Modify(_) => {
+            notify::EventKind::Create(_)
+            | notify::EventKind::Modify(_)
+            | notify::EventKind::Remove(_) => {
                 let mut data = self.modified.lock().unwrap();
-                *data = true;
+                data.extend(e.paths);
             }
             _ => {}
         }
     }
 }
 
+// Identifies the palette, theme, area, or world map that a project file belongs to.
+enum ProjectFile {
+    Palette(String),
+    Theme(String),
+    Stamp(String),
+    Area(AreaId),
+    WorldMap,
+    Other,
+}
+
+fn classify_project_file(state: &EditorState, path: &Path) -> Result<ProjectFile> {
+    if path == get_world_map_path(state)? {
+        return Ok(ProjectFile::WorldMap);
+    }
+    let Some(stem) = path.file_stem().and_then(|x| x.to_str()) else {
+        return Ok(ProjectFile::Other);
+    };
+    let Some(parent) = path.parent() else {
+        return Ok(ProjectFile::Other);
+    };
+    if parent == get_palette_dir(state)? {
+        let name = palette_name_from_stem(state.global_config.palette_file_naming, stem);
+        return Ok(ProjectFile::Palette(name.to_string()));
+    }
+    if parent == get_theme_dir(state)? {
+        return Ok(ProjectFile::Theme(stem.to_string()));
+    }
+    if parent == get_stamp_dir(state)? {
+        return Ok(ProjectFile::Stamp(stem.to_string()));
+    }
+    if parent.parent() == Some(get_area_dir(state)?.as_path()) {
+        if let Some(area_name) = parent.file_name().and_then(|x| x.to_str()) {
+            return Ok(ProjectFile::Area(AreaId {
+                area: area_name.to_string(),
+                theme: stem.to_string(),
+            }));
+        }
+    }
+    Ok(ProjectFile::Other)
+}
+
+// Returns true if reloading the given file would discard unsaved edits in the editor,
+// or if the change can't be applied without reloading the whole project.
+pub fn external_change_conflicts(state: &EditorState, path: &Path) -> Result<bool> {
+    let conflict = match classify_project_file(state, path)? {
+        ProjectFile::Palette(name) => state.palettes.iter().any(|p| p.name == name && p.modified),
+        ProjectFile::Theme(name) => state.themes.get(&name).is_some_and(|t| t.modified),
+        ProjectFile::WorldMap => state.world_map.modified,
+        // Stamps are saved as soon as they're created, so there are no unsaved edits to lose:
+        ProjectFile::Stamp(_) => false,
+        ProjectFile::Area(area_id) => match state.areas.get(&area_id) {
+            Some(area) => area.modified || !path.exists(),
+            None => false,
+        },
+        ProjectFile::Other => false,
+    };
+    Ok(conflict)
+}
+
+// Reload a single palette, theme, stamp, area, or the world map from disk, following an external change to the file.
+pub fn reload_external_change(state: &mut EditorState, path: &Path) -> Result<()> {
+    match classify_project_file(state, path)? {
+        ProjectFile::Palette(name) => {
+            let idx = state.palettes.iter().position(|p| p.name == name);
+            if path.exists() {
+                info!("Reloading externally changed palette {}", path.display());
+                let mut pal: Palette = load_json(path)?;
+                pal.name = name;
+                match idx {
+                    Some(i) => state.palettes[i] = pal,
+                    None => state.palettes.push(pal),
+                }
+            } else if let Some(i) = idx {
+                let id = state.palettes[i].id;
+                let uses = match count_palette_references(state, id) {
+                    Ok((num_cells, num_areas)) => {
+                        format!("{} cells in {} areas use it", num_cells, num_areas)
+                    }
+                    Err(e) => format!("unable to count its uses: {}", e),
+                };
+                if state.palettes.len() > 1 {
+                    warn!(
+                        "Palette {} ({}) was deleted outside the editor, so it is removed; cells using it will show as broken ({}).",
+                        id, name, uses
+                    );
+                    state.palettes.remove(i);
+                    if state.palette_idx >= state.palettes.len() {
+                        state.palette_idx = state.palettes.len() - 1;
+                    }
+                } else {
+                    // The project needs at least one palette, so the last one is kept, and
+                    // marked as modified so that the next save writes it back:
+                    warn!(
+                        "Palette {} ({}) was deleted outside the editor, but is kept since it is the last palette, and will be saved again; {}.",
+                        id, name, uses
+                    );
+                    state.palettes[i].modified = true;
+                }
+            }
+            update_palette_order(state);
+        }
+        ProjectFile::Theme(name) => {
+            if path.exists() {
+                info!("Reloading externally changed theme {}", path.display());
+                let mut theme: Theme = load_json(path)?;
+                theme.name = name.clone();
+                state.themes.insert(name, theme);
+            } else {
+                state.themes.remove(&name);
+            }
+        }
+        ProjectFile::Stamp(name) => {
+            if path.exists() {
+                info!("Reloading externally changed stamp {}", path.display());
+                let mut stamp: Stamp = load_json(path)?;
+                stamp.name = name.clone();
+                state.stamps.insert(name, stamp);
+            } else {
+                state.stamps.remove(&name);
+            }
+        }
+        ProjectFile::WorldMap => {
+            info!("Reloading externally changed world map {}", path.display());
+            load_world_map(state)?;
+        }
+        ProjectFile::Area(area_id) => {
+            load_area_list(state)?;
+            if state.areas.contains_key(&area_id) {
+                info!("Reloading externally changed area {}", path.display());
+                state.load_area(&area_id)?;
+            }
+        }
+        ProjectFile::Other => {}
+    }
+    Ok(())
+}
+
+// Whether files can be created in the directory, checked by creating (and removing) one, since
+// permissions alone don't reflect e.g. read-only mounts:
+fn is_dir_writable(dir: &Path) -> bool {
+    let path = dir.join(format!(".write-test-{}", std::process::id()));
+    match fs::write(&path, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&path);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
 pub fn load_project(state: &mut EditorState) -> Result<()> {
     if state.global_config.project_dir.is_none() {
         bail!("Project directory not set");
@@ -544,10 +1770,18 @@ pub fn load_project(state: &mut EditorState) -> Result<()> {
         );
     }
 
+    let project_dir = state.global_config.project_dir.clone().unwrap();
+    state.read_only = !is_dir_writable(&project_dir);
+    if state.read_only {
+        warn!(
+            "Project directory {} is not writable; opening it as view-only.",
+            project_dir.display()
+        );
+    }
+
     // Set up watcher on the project directories:
-    let watch_locations = ["Areas", "Palettes"];
     state.watch_paths.clear();
-    for loc in watch_locations {
+    for loc in PROJECT_DATA_LOCATIONS {
         state
             .watch_paths
             .push(state.global_config.project_dir.as_ref().unwrap().join(loc));
@@ -559,11 +1793,28 @@ pub fn load_project(state: &mut EditorState) -> Result<()> {
     state.enable_watch_file_changes()?;
 
     load_palettes(state)?;
+    load_themes(state)?;
+    load_world_map(state)?;
+    load_stamps(state)?;
     load_area_list(state)?;
-    let area_id = AreaId {
-        area: state.area_names[0].clone(),
-        theme: state.theme_names[0].clone(),
-    };
+    load_snapshot_list(state)?;
+    // Reopen the area and theme that were last edited, if they still exist:
+    let area = state
+        .global_config
+        .last_screen
+        .as_ref()
+        .map(|s| &s.area)
+        .filter(|&a| state.area_names.contains(a))
+        .unwrap_or(&state.area_names[0])
+        .clone();
+    let theme = state
+        .global_config
+        .last_theme
+        .as_ref()
+        .filter(|&t| state.theme_names.contains(t))
+        .unwrap_or(&state.theme_names[0])
+        .clone();
+    let area_id = AreaId { area, theme };
     state.load_area(&area_id)?;
     state.switch_area(AreaPosition::Main, &area_id)?;
     state.switch_area(AreaPosition::Side, &area_id)?;
@@ -0,0 +1,135 @@
+// Color quantization, used for importing art which may contain more colors
+// than fit in a palette. Colors are reduced using median cut: starting with a
+// single box containing all the distinct colors, the box with the widest
+// channel range is repeatedly split at its (pixel-weighted) median, until the
+// desired number of boxes is reached. Each box is then represented by the
+// weighted average of its colors.
+use hashbrown::HashMap;
+
+use crate::state::{ColorIdx, ColorRGB};
+
+// Reduce the given pixels to at most `num_colors` colors (up to 16), returning
+// the resulting palette colors along with the color index of each pixel.
+// Unused palette entries are left black.
+pub fn quantize_to_palette(
+    pixels: &[ColorRGB],
+    num_colors: usize,
+) -> ([ColorRGB; 16], Vec<ColorIdx>) {
+    let num_colors = num_colors.clamp(1, 16);
+    let mut colors: [ColorRGB; 16] = [[0, 0, 0]; 16];
+    if pixels.is_empty() {
+        return (colors, vec![]);
+    }
+
+    let mut counts: HashMap<ColorRGB, usize> = HashMap::new();
+    for &c in pixels {
+        *counts.entry(c).or_default() += 1;
+    }
+    let mut boxes: Vec<Vec<(ColorRGB, usize)>> = vec![counts.into_iter().collect()];
+
+    while boxes.len() < num_colors {
+        let Some((box_idx, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range)
+        else {
+            // Every box has a single color, so no further splitting is possible.
+            break;
+        };
+        let mut b = boxes.swap_remove(box_idx);
+        b.sort_by_key(|&(c, _)| c[channel]);
+        let total: usize = b.iter().map(|&(_, n)| n).sum();
+        let mut split = b.len() - 1;
+        let mut acc = 0;
+        for (i, &(_, n)) in b.iter().enumerate() {
+            acc += n;
+            if acc * 2 >= total {
+                split = (i + 1).clamp(1, b.len() - 1);
+                break;
+            }
+        }
+        let rest = b.split_off(split);
+        boxes.push(b);
+        boxes.push(rest);
+    }
+
+    // Order the colors from darkest to brightest, to give a more natural palette.
+    let mut box_colors: Vec<ColorRGB> = boxes.iter().map(|b| average_color(b)).collect();
+    box_colors.sort_by_key(|c| c.iter().map(|&x| x as u32).sum::<u32>());
+    colors[..box_colors.len()].copy_from_slice(&box_colors);
+
+    let mut nearest: HashMap<ColorRGB, ColorIdx> = HashMap::new();
+    let mapping = pixels
+        .iter()
+        .map(|&c| {
+            *nearest
+                .entry(c)
+                .or_insert_with(|| nearest_color(&box_colors, c))
+        })
+        .collect();
+    (colors, mapping)
+}
+
+// Find the index of the color closest to `c` (in squared RGB distance).
+pub fn nearest_color(colors: &[ColorRGB], c: ColorRGB) -> ColorIdx {
+    let mut best_idx = 0;
+    let mut best_dist = u32::MAX;
+    for (i, &d) in colors.iter().enumerate() {
+        let dist = color_distance(c, d);
+        if dist < best_dist {
+            best_idx = i;
+            best_dist = dist;
+        }
+    }
+    best_idx as ColorIdx
+}
+
+pub fn color_distance(a: ColorRGB, b: ColorRGB) -> u32 {
+    (0..3)
+        .map(|i| {
+            let d = a[i] as i32 - b[i] as i32;
+            (d * d) as u32
+        })
+        .sum()
+}
+
+// Root-mean-square distance between the original pixels and their quantized
+// colors, in units of the 5-bit color channels.
+pub fn quantization_error(pixels: &[ColorRGB], colors: &[ColorRGB], mapping: &[ColorIdx]) -> f32 {
+    if pixels.is_empty() {
+        return 0.0;
+    }
+    let total: u64 = pixels
+        .iter()
+        .zip(mapping)
+        .map(|(&c, &i)| color_distance(c, colors[i as usize]) as u64)
+        .sum();
+    (total as f32 / pixels.len() as f32).sqrt()
+}
+
+fn widest_channel(colors: &[(ColorRGB, usize)]) -> (usize, u8) {
+    let mut best = (0, 0);
+    for channel in 0..3 {
+        let min = colors.iter().map(|&(c, _)| c[channel]).min().unwrap();
+        let max = colors.iter().map(|&(c, _)| c[channel]).max().unwrap();
+        if max - min > best.1 {
+            best = (channel, max - min);
+        }
+    }
+    best
+}
+
+fn average_color(colors: &[(ColorRGB, usize)]) -> ColorRGB {
+    let total: usize = colors.iter().map(|&(_, n)| n).sum();
+    let mut out: ColorRGB = [0, 0, 0];
+    for (channel, x) in out.iter_mut().enumerate() {
+        let sum: usize = colors.iter().map(|&(c, n)| c[channel] as usize * n).sum();
+        *x = ((sum + total / 2) / total) as u8;
+    }
+    out
+}
@@ -1,11 +1,15 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use hashbrown::{HashMap, HashSet};
-use log::info;
+use iced::{mouse, Vector};
+use log::{info, warn};
 use notify::Watcher;
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::{
+    collections::BTreeMap,
+    ops::Range,
     path::PathBuf,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use serde::{Deserialize, Serialize};
@@ -13,6 +17,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     message::{Message, SelectionSource},
     persist::{self, load_area, save_area},
+    world::WorldOverview,
 };
 
 pub type ColorValue = u8; // Color value (0-31)
@@ -33,6 +38,14 @@ pub struct AreaId {
     pub theme: ThemeName,
 }
 
+// Width and height of a tile, in pixels. Everything follows the 8x8 tiles of the SNES modes
+// used by the overworld; other sizes would also need changes to the ROM import and to the
+// area layout, which assumes screens of 32x32 tiles. (Serialized tiles have this many rows
+// of this many pixels, so projects saved with one size can't be loaded with another.)
+pub const TILE_SIZE: usize = 8;
+
+pub type TilePixels = [[ColorIdx; TILE_SIZE]; TILE_SIZE];
+
 #[derive(Copy, Clone, Serialize, Deserialize, Default, Debug, PartialEq, Eq, Hash)]
 pub struct Tile {
     pub id: Option<TileIdx>,
@@ -40,7 +53,7 @@ pub struct Tile {
     pub collision: CollisionType,
     pub h_flippable: bool,
     pub v_flippable: bool,
-    pub pixels: [[ColorIdx; 8]; 8],
+    pub pixels: TilePixels,
 }
 
 #[derive(Clone, Serialize, Deserialize, Default, Debug)]
@@ -52,8 +65,54 @@ pub struct Palette {
     pub id: PaletteId,
     pub colors: [ColorRGB; 16],
     pub tiles: Vec<Tile>,
+    // Named ranges of tiles, used only for navigating the tileset:
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pages: Vec<(String, Range<TileIdx>)>,
+    // Tags (e.g. "grass", "water-edge") of individual tiles, used for searching the tileset:
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<TileIdx, Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub animations: Vec<TileAnimation>,
+}
+
+impl Palette {
+    // The tile shown in place of the given tile at the given animation frame. Tiles which
+    // aren't the first frame of an animation (or whose frame is out of range) show as is.
+    pub fn animated_tile(&self, tile_idx: TileIdx, frame: u32) -> TileIdx {
+        let Some(animation) = self
+            .animations
+            .iter()
+            .find(|a| a.frames.first() == Some(&tile_idx))
+        else {
+            return tile_idx;
+        };
+        let t = animation.frames[frame as usize % animation.frames.len()];
+        if (t as usize) < self.tiles.len() {
+            t
+        } else {
+            tile_idx
+        }
+    }
+
+    // Whether any of the given tiles is a frame of an animation:
+    pub fn animates_any(&self, tiles: Range<TileIdx>) -> bool {
+        self.animations
+            .iter()
+            .any(|a| a.frames.iter().any(|t| tiles.contains(t)))
+    }
 }
 
+// Tiles shown in turn (e.g. for animated water) wherever the first of them is used in an
+// area. The tiles of the later frames are in the same orientation as the first, so the flip
+// of the cell applies to all of them.
+#[derive(Clone, Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+pub struct TileAnimation {
+    pub frames: Vec<TileIdx>,
+}
+
+// Interval between frames when playing tile animations (approximately the game's rate):
+pub const ANIMATION_FRAME_INTERVAL: Duration = Duration::from_millis(150);
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct GlobalConfig {
     #[serde(skip_serializing, skip_deserializing)]
@@ -61,32 +120,802 @@ pub struct GlobalConfig {
     pub project_dir: Option<PathBuf>,
     #[serde(default = "default_pixel_size")]
     pub pixel_size: f32,
+    // Remember the zoom of each area (by name) and restore it when switching areas:
+    #[serde(default)]
+    pub per_area_zoom: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub area_zoom: BTreeMap<String, f32>,
     #[serde(default = "default_grid_alpha")]
     pub grid_alpha: f32,
+    #[serde(default = "default_grid_color")]
+    pub grid_color: ColorRGB,
+    // Interval (in 8x8 tiles) of additional grid lines drawn over areas, or 0 for none, and
+    // the color and opacity of these lines:
+    #[serde(default)]
+    pub major_grid_interval: u16,
+    #[serde(default = "default_major_grid_color")]
+    pub major_grid_color: ColorRGB,
+    #[serde(default = "default_major_grid_alpha")]
+    pub major_grid_alpha: f32,
+    // The two alternating dash colors of the outline of a selection being made, and how many
+    // pixels it advances per animation tick (0 holds it still):
+    #[serde(default = "default_selection_dash_colors")]
+    pub selection_dash_colors: [ColorRGB; 2],
+    #[serde(default = "default_selection_dash_speed")]
+    pub selection_dash_speed: u8,
+    // Color used to highlight the uses of the selected (or pinned) colors and tile, and the
+    // opacity with which it is blended over the highlighted pixels:
+    #[serde(default = "default_highlight_color")]
+    pub highlight_color: ColorRGB,
+    #[serde(default = "default_highlight_alpha")]
+    pub highlight_alpha: f32,
+    #[serde(default = "default_tile_hover_highlight")]
+    pub tile_hover_highlight: bool,
+    // Open the rename dialogue when double-clicking a palette name in the list of used palettes:
+    #[serde(default = "default_double_click_rename")]
+    pub double_click_rename: bool,
+    // Keep the selection when switching tools (otherwise it is cleared, as with Escape):
+    #[serde(default = "default_keep_selection_on_tool_switch")]
+    pub keep_selection_on_tool_switch: bool,
+    // Magnification (screen pixels per SNES pixel) of the preview shown when hovering a
+    // tileset tile, or 0 to disable it:
+    #[serde(default = "default_tile_preview_zoom")]
+    pub tile_preview_zoom: u8,
+    // Minimum size (in screen pixels) of a pixel in the tile editor for a grid to be drawn
+    // between its pixels, or 0 to never draw it:
+    #[serde(default = "default_pixel_grid_min_size")]
+    pub pixel_grid_min_size: u8,
+    // Advance the selected color (wrapping from 15 to 0) after each pixel drawn in the tile
+    // editor:
+    #[serde(default)]
+    pub auto_advance_color: bool,
+    #[serde(default = "default_contact_sheet_thumbnail_size")]
+    pub contact_sheet_thumbnail_size: u16,
+    #[serde(default = "default_contact_sheet_columns")]
+    pub contact_sheet_columns: u16,
+    #[serde(default)]
+    pub line_brush_modifier: LineBrushModifier,
+    #[serde(default)]
+    pub out_of_bounds_move: OutOfBoundsMove,
+    // Alignment of the destination of the Move tool, and the step used by `MoveSnap::Step`:
+    #[serde(default)]
+    pub move_snap: MoveSnap,
+    #[serde(default = "default_move_snap_step")]
+    pub move_snap_step: TileCoord,
+    // Interval (in tiles) to which the start of a selection in an area is snapped, so that
+    // selections begin on clean boundaries (1 doesn't snap):
+    #[serde(default = "default_selection_snap_step")]
+    pub selection_snap_step: TileCoord,
+    // What to show in place of color 0 (transparent) pixels in the tileset:
+    #[serde(default)]
+    pub tileset_backdrop: TilesetBackdrop,
+    #[serde(default = "default_tileset_backdrop_color")]
+    pub tileset_backdrop_color: ColorRGB,
+    #[serde(default)]
+    pub color_profile: ColorProfile,
+    // Filtering of the magnified tile preview, the world overview, and contact sheet thumbnails:
+    #[serde(default)]
+    pub preview_filter: ImageFilter,
+    #[serde(default)]
+    pub palette_layout: PaletteLayout,
+    #[serde(default)]
+    pub mouse_buttons: MouseButtonMapping,
+    #[serde(default)]
+    pub confirm_exit: ConfirmExit,
+    #[serde(default)]
+    pub palette_file_naming: PaletteFileNaming,
+    #[serde(default)]
+    pub color_limit_policy: ColorLimitPolicy,
+    #[serde(default)]
+    pub color_match_policy: ColorMatchPolicy,
+    // Layout of the area views: the space around the area (in screen pixels), and whether
+    // to center areas that are smaller than the view:
+    #[serde(default)]
+    pub area_margin: u16,
+    #[serde(default)]
+    pub center_area: bool,
+    #[serde(default)]
+    pub trim_empty_edges_on_save: bool,
+    // Write autosaves into the project's autosave directory rather than the project files:
+    #[serde(default)]
+    pub autosave_to_shadow: bool,
+    // Select runs of consecutive tiles in the tileset (wrapping across rows) instead of rectangles:
+    #[serde(default)]
+    pub linear_select: bool,
+    // Show tile numbers and palette IDs in hexadecimal (and accept hex in the matching inputs):
+    #[serde(default)]
+    pub display_hex: bool,
+    // Limits on the undo history; the oldest entries are dropped beyond either of them:
+    #[serde(default = "default_max_undo_levels")]
+    pub max_undo_levels: u32,
+    #[serde(default = "default_undo_memory_mb")]
+    pub undo_memory_mb: u32,
+    // Flood fills changing more than this many cells ask for confirmation first (0 never asks):
+    #[serde(default = "default_fill_confirm_threshold")]
+    pub fill_confirm_threshold: u32,
+    // Fraction of the cells under the area brush which it paints, chosen at random for each
+    // stroke, for dithered transitions (1.0 paints every cell):
+    #[serde(default = "default_brush_opacity")]
+    pub brush_opacity: f32,
+    // Brushing a selection from the tileset also assigns its palette to the painted cells
+    // (otherwise only the tiles are replaced, keeping the cells' existing palettes):
+    #[serde(default = "default_tileset_brush_palette")]
+    pub tileset_brush_palette: bool,
+    // Where editing left off (the top-left visible screen of the main area, its theme, and
+    // the tool), restored when a project is opened:
+    #[serde(default)]
+    pub last_screen: Option<ScreenId>,
+    #[serde(default)]
+    pub last_theme: Option<ThemeName>,
+    #[serde(default)]
+    pub last_tool: Tool,
+    // Tool active when the editor starts; unless set to the last used tool, this takes
+    // precedence over `last_tool`:
+    #[serde(default)]
+    pub startup_tool: StartupTool,
+    #[serde(default)]
+    pub import_options: ImportOptions,
+    #[serde(default = "default_play_animations")]
+    pub play_animations: bool,
+}
+
+// Options for importing a project from the ROM:
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct ImportOptions {
+    // Also import the later frames of animated tiles, as tile animations:
+    #[serde(default)]
+    pub animated_tiles: bool,
+}
+
+fn default_play_animations() -> bool {
+    true
+}
+
+// Conversion used for displaying colors in the editor (PNG exports always use the linear mapping):
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorProfile {
+    #[default]
+    Linear,
+    Crt,
+}
+
+impl ColorProfile {
+    pub const ALL: [ColorProfile; 2] = [ColorProfile::Linear, ColorProfile::Crt];
+}
+
+impl std::fmt::Display for ColorProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ColorProfile::Linear => "Linear",
+                ColorProfile::Crt => "SNES/CRT",
+            }
+        )
+    }
+}
+
+// How images are resampled in previews and overviews (the editing surfaces always use
+// `Nearest`, to keep pixels sharp):
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageFilter {
+    #[default]
+    Nearest,
+    Linear,
+}
+
+impl ImageFilter {
+    pub const ALL: [ImageFilter; 2] = [ImageFilter::Nearest, ImageFilter::Linear];
+
+    pub fn filter_method(self) -> iced::widget::image::FilterMethod {
+        match self {
+            ImageFilter::Nearest => iced::widget::image::FilterMethod::Nearest,
+            ImageFilter::Linear => iced::widget::image::FilterMethod::Linear,
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ImageFilter::Nearest => "Nearest",
+                ImageFilter::Linear => "Linear",
+            }
+        )
+    }
+}
+
+// Assignment of the left and right mouse buttons on the area and tileset canvases. With the
+// standard mapping, the left button uses the current tool and the right button selects.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseButtonMapping {
+    #[default]
+    Standard,
+    Swapped,
+}
+
+impl MouseButtonMapping {
+    pub const ALL: [MouseButtonMapping; 2] =
+        [MouseButtonMapping::Standard, MouseButtonMapping::Swapped];
+
+    // The button of the standard mapping that performs the action of the given button:
+    pub fn map(self, button: mouse::Button) -> mouse::Button {
+        match (self, button) {
+            (MouseButtonMapping::Swapped, mouse::Button::Left) => mouse::Button::Right,
+            (MouseButtonMapping::Swapped, mouse::Button::Right) => mouse::Button::Left,
+            _ => button,
+        }
+    }
+}
+
+// How the files of each palette are named: by the palette name alone, or prefixed by the
+// zero-padded palette ID (e.g. "007-Grass.json"), so that they sort in ID order:
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaletteFileNaming {
+    #[default]
+    Name,
+    IdPrefix,
+}
+
+impl PaletteFileNaming {
+    pub const ALL: [PaletteFileNaming; 2] = [PaletteFileNaming::Name, PaletteFileNaming::IdPrefix];
+}
+
+impl std::fmt::Display for PaletteFileNaming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                PaletteFileNaming::Name => "Name",
+                PaletteFileNaming::IdPrefix => "ID and name",
+            }
+        )
+    }
+}
+
+// How to import a PNG image with more colors than fit in a palette, when writing the image
+// colors into the palette. (CHR and ROM graphics are 4bpp, so they always fit.)
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorLimitPolicy {
+    // Reject the image:
+    Error,
+    // Reduce the image to the 16 most representative colors:
+    #[default]
+    Quantize,
+    // Group the tiles by their colors, adding palettes for the groups beyond the first:
+    Split,
+}
+
+impl ColorLimitPolicy {
+    pub const ALL: [ColorLimitPolicy; 3] = [
+        ColorLimitPolicy::Error,
+        ColorLimitPolicy::Quantize,
+        ColorLimitPolicy::Split,
+    ];
+}
+
+impl std::fmt::Display for ColorLimitPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ColorLimitPolicy::Error => "Error",
+                ColorLimitPolicy::Quantize => "Quantize",
+                ColorLimitPolicy::Split => "Split palettes",
+            }
+        )
+    }
+}
+
+// How to import a PNG image into a palette's existing colors, when some image colors aren't
+// among them:
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorMatchPolicy {
+    // Reject the image:
+    #[default]
+    Exact,
+    // Map each image color to the nearest palette color:
+    Nearest,
+}
+
+impl ColorMatchPolicy {
+    pub const ALL: [ColorMatchPolicy; 2] = [ColorMatchPolicy::Exact, ColorMatchPolicy::Nearest];
+}
+
+impl std::fmt::Display for ColorMatchPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ColorMatchPolicy::Exact => "Exact only",
+                ColorMatchPolicy::Nearest => "Nearest color",
+            }
+        )
+    }
+}
+
+// When to ask for confirmation before closing the window (which saves the project):
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfirmExit {
+    Never,
+    // Only if there are changes not yet saved to the project files (e.g. when autosaving to
+    // a separate folder):
+    #[default]
+    IfModified,
+    Always,
+}
+
+impl ConfirmExit {
+    pub const ALL: [ConfirmExit; 3] = [
+        ConfirmExit::Never,
+        ConfirmExit::IfModified,
+        ConfirmExit::Always,
+    ];
+}
+
+impl std::fmt::Display for ConfirmExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ConfirmExit::Never => "Never",
+                ConfirmExit::IfModified => "If modified",
+                ConfirmExit::Always => "Always",
+            }
+        )
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StartupTool {
+    #[default]
+    LastUsed,
+    Select,
+    Brush,
+    Move,
+    Fill,
+    Wand,
+}
+
+impl StartupTool {
+    pub const ALL: [StartupTool; 6] = [
+        StartupTool::LastUsed,
+        StartupTool::Select,
+        StartupTool::Brush,
+        StartupTool::Move,
+        StartupTool::Fill,
+        StartupTool::Wand,
+    ];
+
+    // The tool to start with, given the tool that was active when the editor was last closed:
+    pub fn tool(self, last_tool: Tool) -> Tool {
+        match self {
+            StartupTool::LastUsed => last_tool,
+            StartupTool::Select => Tool::Select,
+            StartupTool::Brush => Tool::Brush,
+            StartupTool::Move => Tool::Move,
+            StartupTool::Fill => Tool::Fill,
+            StartupTool::Wand => Tool::Wand,
+        }
+    }
+}
+
+impl std::fmt::Display for StartupTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                StartupTool::LastUsed => "Last used",
+                StartupTool::Select => "Select",
+                StartupTool::Brush => "Brush",
+                StartupTool::Move => "Move",
+                StartupTool::Fill => "Fill",
+                StartupTool::Wand => "Wand",
+            }
+        )
+    }
+}
+
+impl std::fmt::Display for MouseButtonMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                MouseButtonMapping::Standard => "Standard",
+                MouseButtonMapping::Swapped => "Swapped",
+            }
+        )
+    }
+}
+
+// Arrangement of the 16 color boxes of the selected palette:
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaletteLayout {
+    #[default]
+    Row16x1,
+    Rows8x2,
+    Grid4x4,
+}
+
+impl PaletteLayout {
+    pub const ALL: [PaletteLayout; 3] = [
+        PaletteLayout::Row16x1,
+        PaletteLayout::Rows8x2,
+        PaletteLayout::Grid4x4,
+    ];
+
+    pub fn columns(self) -> usize {
+        match self {
+            PaletteLayout::Row16x1 => 16,
+            PaletteLayout::Rows8x2 => 8,
+            PaletteLayout::Grid4x4 => 4,
+        }
+    }
+}
+
+impl std::fmt::Display for PaletteLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                PaletteLayout::Row16x1 => "16x1",
+                PaletteLayout::Rows8x2 => "8x2",
+                PaletteLayout::Grid4x4 => "4x4",
+            }
+        )
+    }
+}
+
+// How the destination of the Move tool is aligned, relative to the tile under the cursor:
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveSnap {
+    // Place the selection at the cursor:
+    #[default]
+    Off,
+    // Place the selection on the grid of selection-sized blocks through its source position:
+    Source,
+    // Place the selection at multiples of a configurable step:
+    Step,
+}
+
+impl MoveSnap {
+    pub const ALL: [MoveSnap; 3] = [MoveSnap::Off, MoveSnap::Source, MoveSnap::Step];
+
+    // Destination of a move of a block of the given size whose top-left corner is at `origin`
+    // (if known), with the cursor at `p`. The snapped destination contains `p`, or is
+    // the nearest one to it that stays in bounds.
+    pub fn snap(
+        self,
+        p: (TileCoord, TileCoord),
+        size: (TileCoord, TileCoord),
+        origin: Option<(TileCoord, TileCoord)>,
+        step: TileCoord,
+    ) -> (TileCoord, TileCoord) {
+        let snap_axis = |p: TileCoord, origin: TileCoord, step: TileCoord| {
+            let step = step.max(1) as i32;
+            let mut x = p as i32 - (p as i32 - origin as i32).rem_euclid(step);
+            if x < 0 {
+                x += step;
+            }
+            x as TileCoord
+        };
+        match (self, origin) {
+            (MoveSnap::Off, _) | (MoveSnap::Source, None) => p,
+            (MoveSnap::Source, Some(o)) => {
+                (snap_axis(p.0, o.0, size.0), snap_axis(p.1, o.1, size.1))
+            }
+            (MoveSnap::Step, _) => (snap_axis(p.0, 0, step), snap_axis(p.1, 0, step)),
+        }
+    }
+}
+
+impl std::fmt::Display for MoveSnap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                MoveSnap::Off => "Off",
+                MoveSnap::Source => "Source grid",
+                MoveSnap::Step => "Step",
+            }
+        )
+    }
+}
+
+// What to do when the Move tool would move selected tiles past the end of the tileset:
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutOfBoundsMove {
+    // Don't move any tiles:
+    #[default]
+    Cancel,
+    // Move only the tiles whose destination is in bounds:
+    Clip,
+}
+
+impl OutOfBoundsMove {
+    pub const ALL: [OutOfBoundsMove; 2] = [OutOfBoundsMove::Cancel, OutOfBoundsMove::Clip];
+}
+
+impl std::fmt::Display for OutOfBoundsMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OutOfBoundsMove::Cancel => "Cancel",
+                OutOfBoundsMove::Clip => "Clip",
+            }
+        )
+    }
+}
+
+// Modifier key which constrains area brush strokes to a horizontal or vertical line:
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineBrushModifier {
+    #[default]
+    Alt,
+    Shift,
+    Control,
+}
+
+impl LineBrushModifier {
+    pub const ALL: [LineBrushModifier; 3] = [
+        LineBrushModifier::Alt,
+        LineBrushModifier::Shift,
+        LineBrushModifier::Control,
+    ];
+
+    pub fn is_held(&self, modifiers: iced::keyboard::Modifiers) -> bool {
+        match self {
+            LineBrushModifier::Alt => modifiers.alt(),
+            LineBrushModifier::Shift => modifiers.shift(),
+            LineBrushModifier::Control => modifiers.control(),
+        }
+    }
+}
+
+impl std::fmt::Display for LineBrushModifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                LineBrushModifier::Alt => "Alt",
+                LineBrushModifier::Shift => "Shift",
+                LineBrushModifier::Control => "Ctrl",
+            }
+        )
+    }
+}
+
+// Backdrop drawn behind the tileset, in place of color 0 (transparent) pixels:
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TilesetBackdrop {
+    // Show color 0 of the palette, as it would appear on the backdrop of a screen:
+    #[default]
+    None,
+    Solid,
+    // Alternating squares of the backdrop color and a darker shade, half a tile wide:
+    Checkerboard,
+}
+
+impl TilesetBackdrop {
+    pub const ALL: [TilesetBackdrop; 3] = [
+        TilesetBackdrop::None,
+        TilesetBackdrop::Solid,
+        TilesetBackdrop::Checkerboard,
+    ];
+}
+
+impl std::fmt::Display for TilesetBackdrop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TilesetBackdrop::None => "None",
+                TilesetBackdrop::Solid => "Solid",
+                TilesetBackdrop::Checkerboard => "Checkerboard",
+            }
+        )
+    }
 }
 
 pub const MIN_PIXEL_SIZE: f32 = 1.0;
 pub const MAX_PIXEL_SIZE: f32 = 8.0;
+pub const DEFAULT_PIXEL_SIZE: f32 = 3.0;
 
 fn default_pixel_size() -> f32 {
-    3.0
+    DEFAULT_PIXEL_SIZE
 }
 
 fn default_grid_alpha() -> f32 {
     0.1
 }
 
+fn default_grid_color() -> ColorRGB {
+    [16, 16, 16]
+}
+
+fn default_move_snap_step() -> TileCoord {
+    2
+}
+
+fn default_selection_snap_step() -> TileCoord {
+    1
+}
+
+fn default_major_grid_color() -> ColorRGB {
+    // Cyan:
+    [0, 24, 31]
+}
+
+fn default_major_grid_alpha() -> f32 {
+    0.4
+}
+
+fn default_selection_dash_colors() -> [ColorRGB; 2] {
+    // White and black:
+    [[31, 31, 31], [0, 0, 0]]
+}
+
+fn default_selection_dash_speed() -> u8 {
+    1
+}
+
+fn default_highlight_color() -> ColorRGB {
+    // Pink:
+    [31, 13, 22]
+}
+
+fn default_highlight_alpha() -> f32 {
+    0.5
+}
+
+fn default_tileset_backdrop_color() -> ColorRGB {
+    [24, 24, 24]
+}
+
+fn default_tile_hover_highlight() -> bool {
+    true
+}
+
+fn default_double_click_rename() -> bool {
+    true
+}
+
+fn default_keep_selection_on_tool_switch() -> bool {
+    true
+}
+
+// Maximum time between two clicks on the same list item for them to count as a double-click:
+pub const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+pub const MAX_TILE_PREVIEW_ZOOM: u8 = 16;
+pub const MAX_PIXEL_GRID_MIN_SIZE: u8 = 64;
+pub const MAX_AREA_MARGIN: u16 = 512;
+pub const MAX_MAJOR_GRID_INTERVAL: u16 = 256;
+pub const MAX_SELECTION_DASH_SPEED: u8 = 4;
+pub const MAX_MOVE_SNAP_STEP: TileCoord = 16;
+pub const MAX_SELECTION_SNAP_STEP: TileCoord = 32;
+
+fn default_tile_preview_zoom() -> u8 {
+    8
+}
+
+fn default_pixel_grid_min_size() -> u8 {
+    16
+}
+
+pub const MIN_THUMBNAIL_SIZE: u16 = 16;
+pub const MAX_THUMBNAIL_SIZE: u16 = 256;
+pub const MAX_CONTACT_SHEET_COLUMNS: u16 = 64;
+
+fn default_contact_sheet_thumbnail_size() -> u16 {
+    64
+}
+
+fn default_contact_sheet_columns() -> u16 {
+    8
+}
+
+fn default_max_undo_levels() -> u32 {
+    1000
+}
+
+fn default_fill_confirm_threshold() -> u32 {
+    1000
+}
+
+fn default_brush_opacity() -> f32 {
+    1.0
+}
+
+fn default_tileset_brush_palette() -> bool {
+    true
+}
+
+fn default_undo_memory_mb() -> u32 {
+    256
+}
+
 impl Default for GlobalConfig {
     fn default() -> Self {
         Self {
             modified: true,
             project_dir: None,
             pixel_size: default_pixel_size(),
+            per_area_zoom: false,
+            area_zoom: BTreeMap::new(),
             grid_alpha: default_grid_alpha(),
+            grid_color: default_grid_color(),
+            major_grid_interval: 0,
+            major_grid_color: default_major_grid_color(),
+            major_grid_alpha: default_major_grid_alpha(),
+            selection_dash_colors: default_selection_dash_colors(),
+            selection_dash_speed: default_selection_dash_speed(),
+            highlight_color: default_highlight_color(),
+            highlight_alpha: default_highlight_alpha(),
+            tile_hover_highlight: default_tile_hover_highlight(),
+            double_click_rename: default_double_click_rename(),
+            keep_selection_on_tool_switch: default_keep_selection_on_tool_switch(),
+            tile_preview_zoom: default_tile_preview_zoom(),
+            pixel_grid_min_size: default_pixel_grid_min_size(),
+            auto_advance_color: false,
+            contact_sheet_thumbnail_size: default_contact_sheet_thumbnail_size(),
+            contact_sheet_columns: default_contact_sheet_columns(),
+            line_brush_modifier: LineBrushModifier::default(),
+            out_of_bounds_move: OutOfBoundsMove::default(),
+            move_snap: MoveSnap::default(),
+            move_snap_step: default_move_snap_step(),
+            selection_snap_step: default_selection_snap_step(),
+            tileset_backdrop: TilesetBackdrop::default(),
+            tileset_backdrop_color: default_tileset_backdrop_color(),
+            color_profile: ColorProfile::default(),
+            preview_filter: ImageFilter::default(),
+            palette_layout: PaletteLayout::default(),
+            mouse_buttons: MouseButtonMapping::default(),
+            confirm_exit: ConfirmExit::default(),
+            palette_file_naming: PaletteFileNaming::default(),
+            color_limit_policy: ColorLimitPolicy::default(),
+            color_match_policy: ColorMatchPolicy::default(),
+            area_margin: 0,
+            center_area: false,
+            trim_empty_edges_on_save: false,
+            autosave_to_shadow: false,
+            linear_select: false,
+            display_hex: false,
+            max_undo_levels: default_max_undo_levels(),
+            undo_memory_mb: default_undo_memory_mb(),
+            fill_confirm_threshold: default_fill_confirm_threshold(),
+            brush_opacity: default_brush_opacity(),
+            tileset_brush_palette: default_tileset_brush_palette(),
+            last_screen: None,
+            last_theme: None,
+            last_tool: Tool::default(),
+            startup_tool: StartupTool::default(),
+            import_options: ImportOptions::default(),
+            play_animations: default_play_animations(),
         }
     }
 }
 
+// Flip of a tile, combining a horizontal and a vertical flag like the H and V bits of a SNES
+// tilemap entry (bits 14 and 15, so the value is the entry shifted right by 14). The two
+// flips commute, so `Both` is a 180 degree rotation whichever flip is considered first, and
+// flipping twice in the same direction gives back the original.
 #[derive(Clone, Copy, Serialize_repr, Deserialize_repr, Default, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Flip {
@@ -98,42 +927,40 @@ pub enum Flip {
 }
 
 impl Flip {
-    pub fn flip_horizontally(self) -> Self {
-        match self {
-            Flip::None => Flip::Horizontal,
-            Flip::Horizontal => Flip::None,
-            Flip::Vertical => Flip::Both,
-            Flip::Both => Flip::Vertical,
+    pub fn from_flags(horizontal: bool, vertical: bool) -> Self {
+        match (horizontal, vertical) {
+            (false, false) => Flip::None,
+            (true, false) => Flip::Horizontal,
+            (false, true) => Flip::Vertical,
+            (true, true) => Flip::Both,
         }
     }
 
+    pub fn is_horizontal(self) -> bool {
+        matches!(self, Flip::Horizontal | Flip::Both)
+    }
+
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Flip::Vertical | Flip::Both)
+    }
+
+    pub fn flip_horizontally(self) -> Self {
+        Flip::from_flags(!self.is_horizontal(), self.is_vertical())
+    }
+
     pub fn flip_vertically(self) -> Self {
-        match self {
-            Flip::None => Flip::Vertical,
-            Flip::Horizontal => Flip::Both,
-            Flip::Vertical => Flip::None,
-            Flip::Both => Flip::Horizontal,
-        }
+        Flip::from_flags(self.is_horizontal(), !self.is_vertical())
     }
 
-    pub fn apply_to_pixels(self, mut pixels: [[ColorIdx; 8]; 8]) -> [[ColorIdx; 8]; 8] {
-        match self {
-            Flip::None => {}
-            Flip::Horizontal => {
-                for row in pixels.iter_mut() {
-                    row.reverse();
-                }
-            }
-            Flip::Vertical => {
-                pixels.reverse();
-            }
-            Flip::Both => {
-                for row in pixels.iter_mut() {
-                    row.reverse();
-                }
-                pixels.reverse();
+    pub fn apply_to_pixels(self, mut pixels: TilePixels) -> TilePixels {
+        if self.is_horizontal() {
+            for row in pixels.iter_mut() {
+                row.reverse();
             }
         }
+        if self.is_vertical() {
+            pixels.reverse();
+        }
         pixels
     }
 
@@ -143,25 +970,16 @@ impl Flip {
         tile
     }
 
-    pub fn apply_to_flip(self, mut flip: Flip) -> Flip {
-        match self {
-            Flip::None => {}
-            Flip::Horizontal => {
-                flip = flip.flip_horizontally();
-            }
-            Flip::Vertical => {
-                flip = flip.flip_vertically();
-            }
-            Flip::Both => {
-                flip = flip.flip_horizontally();
-                flip = flip.flip_vertically();
-            }
-        }
-        flip
+    // Combine with another flip, as if `flip` were applied first and then `self`:
+    pub fn apply_to_flip(self, flip: Flip) -> Flip {
+        Flip::from_flags(
+            self.is_horizontal() != flip.is_horizontal(),
+            self.is_vertical() != flip.is_vertical(),
+        )
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct Screen {
     // X and Y position of the screen (256 x 256 block) within the area, in screen counts:
     // The screens are always listed in row-major order, so `position` is
@@ -170,9 +988,189 @@ pub struct Screen {
     pub palettes: [[PaletteId; 32]; 32],
     pub tiles: [[TileIdx; 32]; 32],
     pub flips: [[Flip; 32]; 32],
+    // Priority bit of each cell, set in the tilemap in addition to the tile's own priority.
+    // (Omitted from the JSON if no cell has it set.)
+    #[serde(default, skip_serializing_if = "no_priorities")]
+    pub priorities: [[bool; 32]; 32],
+    // Freeform notes about the screen (not used for rendering):
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
 }
 
-#[derive(Serialize, Deserialize, Default)]
+fn no_priorities(priorities: &[[bool; 32]; 32]) -> bool {
+    priorities.iter().flatten().all(|&p| !p)
+}
+
+// Tilemap data of a single cell (8x8 tile position) of an area:
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AreaCell {
+    pub palette_id: PaletteId,
+    pub tile_idx: TileIdx,
+    pub flip: Flip,
+    pub priority: bool,
+}
+
+impl Screen {
+    // A screen is considered empty if it contains only tile 0 (regardless of palettes and flips).
+    pub fn is_empty(&self) -> bool {
+        self.tiles.iter().flatten().all(|&t| t == 0)
+    }
+}
+
+// Data of a theme, stored in the project's "Themes" directory. Areas in the theme are
+// rendered with the theme's colors in place of the corresponding palette colors.
+#[derive(Clone, Serialize, Deserialize, Default, Debug)]
+pub struct Theme {
+    #[serde(skip_serializing, skip_deserializing)]
+    pub modified: bool,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub name: ThemeName,
+    // Replacement colors, by palette ID and color index:
+    #[serde(default)]
+    pub color_overrides: BTreeMap<PaletteId, BTreeMap<ColorIdx, ColorRGB>>,
+}
+
+impl Theme {
+    // Colors of the palette as rendered in this theme.
+    pub fn palette_colors(&self, pal: &Palette) -> [ColorRGB; 16] {
+        let mut colors = pal.colors;
+        if let Some(overrides) = self.color_overrides.get(&pal.id) {
+            for (&i, &c) in overrides {
+                if let Some(color) = colors.get_mut(i as usize) {
+                    *color = c;
+                }
+            }
+        }
+        colors
+    }
+}
+
+// Side of a screen, for defining which screens border each other:
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Side {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::North, Side::South, Side::East, Side::West];
+
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::North => Side::South,
+            Side::South => Side::North,
+            Side::East => Side::West,
+            Side::West => Side::East,
+        }
+    }
+
+    // Offset (in screens) of the neighboring screen on this side:
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Side::North => (0, -1),
+            Side::South => (0, 1),
+            Side::East => (1, 0),
+            Side::West => (-1, 0),
+        }
+    }
+}
+
+impl std::fmt::Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+// Identifies a screen by its area and its position (in screens) within the area:
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ScreenId {
+    pub area: AreaName,
+    pub x: u8,
+    pub y: u8,
+}
+
+impl std::fmt::Display for ScreenId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {},{}", self.area, self.x, self.y)
+    }
+}
+
+// A link indicating that screen `to` borders screen `from` on the given side
+// (and so `from` borders `to` on the opposite side).
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ScreenLink {
+    pub from: ScreenId,
+    pub side: Side,
+    pub to: ScreenId,
+}
+
+// Adjacency of screens across areas, stored in the project's "World" directory.
+// Screens within an area implicitly border each other according to their positions,
+// so only links between areas need to be listed.
+#[derive(Clone, Serialize, Deserialize, Default, Debug)]
+pub struct WorldMap {
+    #[serde(skip_serializing, skip_deserializing)]
+    pub modified: bool,
+    #[serde(default)]
+    pub links: Vec<ScreenLink>,
+}
+
+impl WorldMap {
+    // The screen linked to the given screen on the given side, if any.
+    pub fn neighbor(&self, screen: &ScreenId, side: Side) -> Option<&ScreenId> {
+        self.links.iter().find_map(|link| {
+            if &link.from == screen && link.side == side {
+                Some(&link.to)
+            } else if &link.to == screen && link.side == side.opposite() {
+                Some(&link.from)
+            } else {
+                None
+            }
+        })
+    }
+
+    // Links resulting from linking (or with `to` of `None`, unlinking) the given side of a
+    // screen. Existing links on either of the joined sides are replaced. The links are kept
+    // in a canonical form and order (independent of the order of editing), to keep diffs of
+    // the saved file minimal.
+    pub fn with_link(&self, from: &ScreenId, side: Side, to: Option<&ScreenId>) -> Vec<ScreenLink> {
+        let occupies = |link: &ScreenLink, screen: &ScreenId, side: Side| {
+            (&link.from == screen && link.side == side)
+                || (&link.to == screen && link.side == side.opposite())
+        };
+        let mut links: Vec<ScreenLink> = self
+            .links
+            .iter()
+            .filter(|link| {
+                !occupies(link, from, side)
+                    && to.is_none_or(|to| !occupies(link, to, side.opposite()))
+            })
+            .cloned()
+            .collect();
+        if let Some(to) = to {
+            // Store each link from the lesser of the two screens:
+            links.push(if from <= to {
+                ScreenLink {
+                    from: from.clone(),
+                    side,
+                    to: to.clone(),
+                }
+            } else {
+                ScreenLink {
+                    from: to.clone(),
+                    side: side.opposite(),
+                    to: from.clone(),
+                }
+            });
+        }
+        links.sort();
+        links
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Area {
     #[serde(skip_serializing, skip_deserializing)]
     pub modified: bool,
@@ -187,6 +1185,9 @@ pub struct Area {
     // A 'screen' is a 256x256 pixel section, roughly the size that fits on camera at once.
     // Splitting it up like this helps with formatting of the JSON, e.g. for viewing git diffs.
     pub screens: Vec<Screen>,
+    // Number of cells that can't be rendered, kept up to date by `update_broken_cells`:
+    #[serde(skip_serializing, skip_deserializing)]
+    pub num_broken_cells: usize,
 }
 
 impl Area {
@@ -197,6 +1198,52 @@ impl Area {
         }
     }
 
+    // Check that there is exactly one screen for each position within the area's size; the
+    // cell accessors index the screens assuming this holds.
+    pub fn check_screens(&self) -> Result<()> {
+        ensure!(
+            self.screens.len() == self.size.0 as usize * self.size.1 as usize,
+            "area has {} screens, but its size is {}x{}",
+            self.screens.len(),
+            self.size.0,
+            self.size.1
+        );
+        Ok(())
+    }
+
+    // Set each screen's `position` to match its place in the (row-major) screen order, which
+    // is what determines where the screen is drawn. Returns the number of screens whose
+    // position was wrong (e.g. from hand-editing the JSON).
+    pub fn fix_screen_positions(&mut self) -> Result<usize> {
+        self.check_screens()?;
+        let mut num_fixed = 0;
+        for (i, screen) in self.screens.iter_mut().enumerate() {
+            let position = (
+                (i % self.size.0 as usize) as u8,
+                (i / self.size.0 as usize) as u8,
+            );
+            if screen.position != position {
+                screen.position = position;
+                num_fixed += 1;
+            }
+        }
+        Ok(num_fixed)
+    }
+
+    // Ranges of screen columns and rows remaining after removing empty screens from the edges.
+    // At least one screen is always kept.
+    pub fn trimmed_bounds(&self) -> (Range<u8>, Range<u8>) {
+        let (w, h) = self.size;
+        let screen = |x: u8, y: u8| &self.screens[y as usize * w as usize + x as usize];
+        let col_empty = |x: u8| (0..h).all(|y| screen(x, y).is_empty());
+        let row_empty = |y: u8| (0..w).all(|x| screen(x, y).is_empty());
+        let x0 = (0..w).find(|&x| !col_empty(x)).unwrap_or(0);
+        let x1 = (0..w).rev().find(|&x| !col_empty(x)).unwrap_or(0) + 1;
+        let y0 = (0..h).find(|&y| !row_empty(y)).unwrap_or(0);
+        let y1 = (0..h).rev().find(|&y| !row_empty(y)).unwrap_or(0) + 1;
+        (x0..x1, y0..y1)
+    }
+
     pub fn get_screen_coords(&self, x: TileCoord, y: TileCoord) -> Result<(usize, usize, usize)> {
         if x >= self.size.0 as TileCoord * 32 || y >= self.size.1 as TileCoord * 32 {
             bail!("out of range");
@@ -222,6 +1269,27 @@ impl Area {
         Ok(self.screens[i].flips[sy][sx])
     }
 
+    pub fn get_cell(&self, x: TileCoord, y: TileCoord) -> Result<AreaCell> {
+        let (i, sx, sy) = self.get_screen_coords(x, y)?;
+        let screen = &self.screens[i];
+        Ok(AreaCell {
+            palette_id: screen.palettes[sy][sx],
+            tile_idx: screen.tiles[sy][sx],
+            flip: screen.flips[sy][sx],
+            priority: screen.priorities[sy][sx],
+        })
+    }
+
+    pub fn set_cell(&mut self, x: TileCoord, y: TileCoord, cell: AreaCell) -> Result<()> {
+        let (i, sx, sy) = self.get_screen_coords(x, y)?;
+        let screen = &mut self.screens[i];
+        screen.palettes[sy][sx] = cell.palette_id;
+        screen.tiles[sy][sx] = cell.tile_idx;
+        screen.flips[sy][sx] = cell.flip;
+        screen.priorities[sy][sx] = cell.priority;
+        Ok(())
+    }
+
     pub fn set_tile(&mut self, x: TileCoord, y: TileCoord, tile_idx: TileIdx) -> Result<()> {
         let (i, sx, sy) = self.get_screen_coords(x, y)?;
         self.screens[i].tiles[sy][sx] = tile_idx;
@@ -240,6 +1308,60 @@ impl Area {
         Ok(())
     }
 
+    // Find the connected region of tiles to be filled starting from (x, y),
+    // limited to MAX_FILL_TILES tiles.
+    pub fn fill_region(
+        &self,
+        x: TileCoord,
+        y: TileCoord,
+        mode: FillMode,
+    ) -> Result<Vec<(TileCoord, TileCoord)>> {
+        let cell = |x, y| -> Result<(PaletteId, TileIdx, Flip)> {
+            Ok((
+                self.get_palette(x, y)?,
+                self.get_tile(x, y)?,
+                self.get_flip(x, y)?,
+            ))
+        };
+        let start = cell(x, y)?;
+        let matches = |c: (PaletteId, TileIdx, Flip)| match mode {
+            FillMode::Exact => c == start,
+            FillMode::SameTile => c.1 == start.1 && c.2 == start.2,
+            FillMode::SamePalette => c.0 == start.0,
+        };
+
+        let mut region = vec![];
+        let mut visited: HashSet<(TileCoord, TileCoord)> = HashSet::new();
+        let mut stack = vec![(x, y)];
+        visited.insert((x, y));
+        while let Some((x, y)) = stack.pop() {
+            if region.len() >= MAX_FILL_TILES {
+                warn!("Fill stopped after reaching {} tiles.", MAX_FILL_TILES);
+                break;
+            }
+            region.push((x, y));
+            let mut neighbors = vec![(x + 1, y), (x, y + 1)];
+            if x > 0 {
+                neighbors.push((x - 1, y));
+            }
+            if y > 0 {
+                neighbors.push((x, y - 1));
+            }
+            for (nx, ny) in neighbors {
+                if visited.contains(&(nx, ny)) {
+                    continue;
+                }
+                if let Ok(c) = cell(nx, ny) {
+                    if matches(c) {
+                        visited.insert((nx, ny));
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+        Ok(region)
+    }
+
     pub fn get_unique_palettes(&self) -> Vec<PaletteId> {
         let mut palettes: HashSet<PaletteId> = HashSet::new();
         for s in &self.screens {
@@ -255,10 +1377,109 @@ impl Area {
     }
 }
 
+// A `MoveTiles` in progress, which remaps the tile references one area at a time so that
+// progress can be shown and the move can be cancelled:
+pub struct MoveTilesJob {
+    pub mapping: HashMap<(PaletteId, TileIdx), (PaletteId, TileIdx, Flip)>,
+    pub remaining: Vec<AreaId>,
+    pub total: usize,
+    // Contents of the areas processed so far from before they were remapped, for rolling
+    // back the move if it is cancelled:
+    pub originals: Vec<(AreaId, Area)>,
+    // How the undo history was changed when the move started, so that the change can be
+    // reverted along with the move (`None` if the history was left alone):
+    pub history: Option<MoveHistory>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum MoveHistory {
+    // The move was a new edit, pushed onto the undo stack:
+    Pushed,
+    // The move was undoing the last entry of the undo stack (now on the redo stack):
+    Undone,
+    // The move was redoing the last entry of the redo stack (now on the undo stack):
+    Redone,
+}
+
+// Sizes of the project data in the ROM: 4bpp 8x8 tiles, palettes of 16 BGR555 colors, and
+// tilemaps of 32x32 two-byte entries per screen.
+pub const TILE_ROM_BYTES: usize = 32;
+pub const PALETTE_ROM_BYTES: usize = 32;
+pub const SCREEN_ROM_BYTES: usize = 32 * 32 * 2;
+
+pub struct ProjectStats {
+    // Name, ID, and number of tiles of each palette, in display order:
+    pub palette_tiles: Vec<(String, PaletteId, usize)>,
+    // Number of areas counted separately for each theme, and their total number of screens:
+    pub num_areas: usize,
+    pub num_screens: usize,
+    // Total size of the project data files:
+    pub disk_bytes: u64,
+}
+
+impl ProjectStats {
+    pub fn num_tiles(&self) -> usize {
+        self.palette_tiles.iter().map(|p| p.2).sum()
+    }
+
+    // Estimated ROM space taken by the tilesets, palettes, and tilemaps:
+    pub fn rom_bytes(&self) -> (usize, usize, usize) {
+        (
+            self.num_tiles() * TILE_ROM_BYTES,
+            self.palette_tiles.len() * PALETTE_ROM_BYTES,
+            self.num_screens * SCREEN_ROM_BYTES,
+        )
+    }
+}
+
+// Order in which the tiles of the current palette are displayed in the tileset: the tile
+// index shown at each position, or index order if empty.
+#[derive(Clone, Debug, Default)]
+pub struct TilesetOrder(Vec<TileIdx>);
+
+impl TilesetOrder {
+    pub fn is_sorted(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    // Index of the tile shown at the given position of the tileset:
+    pub fn tile_at(&self, pos: TileIdx) -> TileIdx {
+        self.0.get(pos as usize).copied().unwrap_or(pos)
+    }
+
+    // Position of the given tile in the tileset:
+    pub fn position_of(&self, tile_idx: TileIdx) -> TileIdx {
+        self.0
+            .iter()
+            .position(|&i| i == tile_idx)
+            .map(|p| p as TileIdx)
+            .unwrap_or(tile_idx)
+    }
+}
+
+// A destination tile of a move which is already used in areas, so that moving onto it would
+// collapse its uses together with the moved tile's:
+#[derive(Clone, Debug)]
+pub struct MoveCollision {
+    pub palette_id: PaletteId,
+    pub tile_idx: TileIdx,
+    pub num_uses: usize,
+}
+
 pub enum Dialogue {
     Settings,
     ImportROMConfirm,
     ImportROMProgress,
+    ImportTilesetPNG {
+        path: PathBuf,
+        quantize: bool,
+    },
+    ImportTilesetPNGReport {
+        report: String,
+    },
+    ProjectArchiveReport {
+        report: String,
+    },
     AddPalette {
         name: String,
         id: PaletteId,
@@ -266,7 +1487,24 @@ pub enum Dialogue {
     RenamePalette {
         name: String,
     },
-    DeletePalette,
+    DeletePalette {
+        // Areas (in any theme) which still use the palette:
+        references: Vec<AreaId>,
+        // Palette to remap the references to:
+        remap_to: Option<PaletteId>,
+    },
+    TilePages {
+        name: String,
+    },
+    CompareTiles {
+        a: TileIdx,
+        b: TileIdx,
+    },
+    MergePalettes {
+        require_identical_tiles: bool,
+        // Groups of palettes to merge, each with the surviving palette first:
+        groups: Vec<Vec<PaletteId>>,
+    },
     AddArea {
         name: AreaName,
         size: (u8, u8),
@@ -275,6 +1513,10 @@ pub enum Dialogue {
         name: AreaName,
     },
     DeleteArea,
+    ScreenNotes {
+        screen: (u8, u8),
+        content: iced::widget::text_editor::Content,
+    },
     AddTheme {
         name: ThemeName,
     },
@@ -282,22 +1524,104 @@ pub enum Dialogue {
         name: ThemeName,
     },
     DeleteTheme,
+    ThemeColors,
+    WorldOverview {
+        overview: WorldOverview,
+        // Screen selected for linking, and the side and screen to link it to:
+        link_from: Option<ScreenId>,
+        link_side: Side,
+        link_to: ScreenId,
+    },
     Help,
     RebuildProject,
-    ModifiedReload,
-    MovingTilesProgress,
+    ModifiedReload {
+        // Changed files that conflict with unsaved edits:
+        paths: Vec<PathBuf>,
+    },
+    MovingTilesProgress {
+        // Fraction of the areas processed so far:
+        progress: f32,
+    },
     MoveTiles {
         src_selection: TileBlock,
         dst_selection: TileBlock,
+        collisions: Vec<MoveCollision>,
+    },
+    RemapPaletteInRegion {
+        from: Option<PaletteId>,
+        to: Option<PaletteId>,
+    },
+    ReplacePaletteReferences {
+        from: Option<PaletteId>,
+        to: Option<PaletteId>,
+        // Number of cells and areas (across all themes) using `from`:
+        from_references: Option<(usize, usize)>,
+        // Whether `to` is already used, in which case the replacement can't be undone:
+        to_in_use: bool,
+    },
+    ConfirmFill {
+        // Number of cells the fill would change, and the fill to apply if confirmed:
+        count: usize,
+        fill: Message,
+    },
+    AreaLoadError {
+        area_id: AreaId,
+        error: String,
+        // The message that failed to switch to the area, to send again when retrying:
+        retry: Message,
+    },
+    CreateSnapshot {
+        name: String,
+    },
+    SaveStamp {
+        name: String,
+    },
+    RestoreSnapshot {
+        name: String,
+    },
+    ProjectStats {
+        stats: ProjectStats,
+    },
+    RomDiffProgress,
+    RomDiffReport {
+        report: String,
+    },
+    ConfirmExit {
+        window_id: iced::window::Id,
+    },
+    RecoverAutosave {
+        // Project-relative paths of autosaved files that differ from the project files:
+        changes: Vec<PathBuf>,
     },
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TileBlock {
     pub size: (TileCoord, TileCoord),
     pub palettes: Vec<Vec<PaletteId>>,
     pub tiles: Vec<Vec<TileIdx>>,
     pub flips: Vec<Vec<Flip>>,
+    // Cells belonging to the selection (e.g. from a magic-wand selection); `None` selects
+    // the whole rectangle. Cells outside the mask are left untouched when painting/moving.
+    // (Boxed to keep `Message` variants carrying selections small.)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask: Option<Box<Vec<Vec<bool>>>>,
+}
+
+impl TileBlock {
+    pub fn is_selected(&self, x: TileCoord, y: TileCoord) -> bool {
+        self.mask.as_ref().is_none_or(|m| m[y as usize][x as usize])
+    }
+}
+
+// A named multi-tile structure (e.g. a house or tree) saved in the project for reuse, along
+// with the graphics of its tiles when it was saved, used for its thumbnail:
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Stamp {
+    #[serde(skip_serializing, skip_deserializing)]
+    pub name: String,
+    pub block: TileBlock,
+    pub gfx: Vec<Vec<Tile>>,
 }
 
 // At the moment, Iced's support for tracking widget focus is fairly incomplete,
@@ -321,6 +1645,18 @@ pub enum SidePanelView {
     #[default]
     Tileset,
     Area,
+    History,
+    Palettes,
+    Stamps,
+}
+
+// An undoable edit: the message that made it, the message reversing it, and a description
+// of it for the history panel.
+#[derive(Debug, Clone)]
+pub struct UndoEntry {
+    pub label: String,
+    pub message: Message,
+    pub reverse_message: Message,
 }
 
 #[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
@@ -330,28 +1666,117 @@ pub enum AreaPosition {
     Side,
 }
 
-#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Tool {
     #[default]
     Select,
     Brush,
     Move,
+    Fill,
+    Wand,
 }
 
+// Direction in which a mirrored copy of the selection is placed: a horizontal mirror is
+// placed to the right of the selection, and a vertical one below it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MirrorAxis {
+    Horizontal,
+    Vertical,
+}
+
+// Determines which neighboring tiles a fill spreads into, relative to the starting tile:
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum FillMode {
+    // Same palette, tile, and flip
+    #[default]
+    Exact,
+    // Same tile (and flip), with any palette
+    SameTile,
+    // Same palette, with any tile
+    SamePalette,
+}
+
+impl FillMode {
+    pub const ALL: [FillMode; 3] = [FillMode::Exact, FillMode::SameTile, FillMode::SamePalette];
+}
+
+impl std::fmt::Display for FillMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                FillMode::Exact => "Exact match",
+                FillMode::SameTile => "Same tile",
+                FillMode::SamePalette => "Same palette",
+            }
+        )
+    }
+}
+
+// Which data of the area cells is shown in the area views. Other than `Tiles`, the cells are
+// tinted by their palette or flip, for spotting mis-assigned cells:
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum ScreenLayer {
+    #[default]
+    Tiles,
+    Palettes,
+    Flips,
+}
+
+impl ScreenLayer {
+    pub const ALL: [ScreenLayer; 3] = [
+        ScreenLayer::Tiles,
+        ScreenLayer::Palettes,
+        ScreenLayer::Flips,
+    ];
+}
+
+impl std::fmt::Display for ScreenLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ScreenLayer::Tiles => "Tiles",
+                ScreenLayer::Palettes => "Palettes",
+                ScreenLayer::Flips => "Flips",
+            }
+        )
+    }
+}
+
+// Maximum number of tiles changed by a single fill:
+pub const MAX_FILL_TILES: usize = 4096;
+// Fills never change more than `MAX_FILL_TILES` cells, so a higher threshold would never ask:
+pub const MAX_FILL_CONFIRM_THRESHOLD: u32 = MAX_FILL_TILES as u32 - 1;
+
 pub struct EditorState {
     pub global_config_path: PathBuf,
     pub global_config: GlobalConfig,
+    // Whether the project directory isn't writable (e.g. on a read-only drive), in which case
+    // the project is only viewed: edits are rejected and nothing is saved to it.
+    pub read_only: bool,
 
     // Project data: Areas are loaded/unloaded dynamically
     // to limit memory usage and start-up time. Everything else is fully loaded.
     pub palettes: Vec<Palette>,
     pub areas: HashMap<AreaId, Area>,
+    // Copies of the loaded areas as of when they were loaded or last explicitly saved, shown
+    // in place of the live areas while `compare_saved` is set (by holding the backtick key):
+    pub saved_areas: HashMap<AreaId, Area>,
+    pub compare_saved: bool,
     pub area_names: Vec<AreaName>,
     pub theme_names: Vec<ThemeName>,
+    // Theme data, for the themes which have a file in the project:
+    pub themes: HashMap<ThemeName, Theme>,
+    pub world_map: WorldMap,
+    pub snapshot_names: Vec<String>,
+    pub stamps: BTreeMap<String, Stamp>,
 
     // Undo functionality:
-    pub undo_stack: Vec<(Message, Message)>,
-    pub redo_stack: Vec<(Message, Message)>,
+    pub undo_stack: Vec<UndoEntry>,
+    pub redo_stack: Vec<UndoEntry>,
 
     // Settings-related data:
     pub rom_path: Option<PathBuf>,
@@ -359,18 +1784,33 @@ pub struct EditorState {
     // General editing state:
     pub focus: Focus,
     pub tool: Tool,
+    pub fill_mode: FillMode,
+    pub screen_layer: ScreenLayer,
     pub shift_brush: bool,
+    // Whether the line brush modifier is held:
+    pub line_brush: bool,
+    pub modifiers: iced::keyboard::Modifiers,
     pub side_panel_view: SidePanelView,
 
     // Palette editing state:
+    // Palettes selected for bulk operations (independent of `palette_idx`, which is used for editing):
+    pub selected_palette_ids: Vec<PaletteId>,
     pub palette_idx: PaletteIdx,
     pub color_idx: Option<ColorIdx>,
     pub selected_color: ColorRGB,
     pub identify_color: bool,
+    // Color indices of the current palette which are always highlighted, regardless of the
+    // selected color (toggled by right-clicking a color):
+    pub pinned_identify_colors: HashSet<ColorIdx>,
+    // Whether the tileset is displayed with the most-used tiles first, using the number of
+    // uses of each palette's tiles across all areas (counted when the sorting is enabled):
+    pub tileset_sort_by_usage: bool,
+    pub tile_use_counts: HashMap<PaletteId, Vec<usize>>,
 
     // Tile editing state:
     pub tile_idx: Option<TileIdx>,
     pub identify_tile: bool,
+    pub preview_palette_id: Option<PaletteId>,
 
     // Graphics editing state:
     pub pixel_coords: Option<(PixelCoord, PixelCoord)>,
@@ -381,26 +1821,68 @@ pub struct EditorState {
     pub selection_source: SelectionSource,
     pub start_coords: Option<(TileCoord, TileCoord)>,
     pub end_coords: Option<(TileCoord, TileCoord)>,
+    // Secondary "reference" selection in the tileset (opposite corners), made by right-dragging
+    // with the select tool, which can be swapped with the primary selection:
+    pub reference_coords: Option<((TileCoord, TileCoord), (TileCoord, TileCoord))>,
     pub hover_coords: Option<(TileCoord, TileCoord)>,
+    // Top-left corner of the completed selection in the area of `selection_source`
+    // (`start_coords`/`end_coords` only track a selection while it is being made):
+    pub selection_coords: Option<(TileCoord, TileCoord)>,
+    // Top-left corner of where the selected tile block was copied from in an area, used for
+    // pasting in place (unlike `selection_coords`, this is kept when switching areas):
+    pub copy_coords: Option<(TileCoord, TileCoord)>,
+    // Offset (in screen pixels) of the dashes outlining a selection in progress:
+    pub marching_ants_phase: u8,
+    // Frame of the tile animations being shown, advanced while they play:
+    pub animation_frame: u32,
+    // Orientation of the selected tile block relative to how it was selected (changed by
+    // flipping or rotating the selection):
+    pub stamp_flip: Flip,
+    // Search text for highlighting tagged tiles in the tileset, and tag to add to tiles:
+    pub tile_tag_search: String,
+    pub tile_tag_input: String,
+    // Color index to be replaced by the selected color in the selected tiles:
+    pub replace_color_from: ColorIdx,
+    // Last click on a palette name in the list of used palettes, for detecting double-clicks:
+    pub last_palette_name_click: Option<(PaletteId, Instant)>,
+    // Scroll offsets of the area views, used to keep the rulers in sync:
+    pub main_area_scroll_offset: Vector,
+    pub side_area_scroll_offset: Vector,
     pub selected_tile_block: TileBlock,
     pub selected_gfx: Vec<Vec<Tile>>,
     pub show_grid_16: bool,
+    // Outline each 8x8 tile in the area views:
+    pub show_grid_8: bool,
     pub snap_grid_16: bool,
+    // Number of times each area tile has been painted during this session:
+    pub paint_counts: HashMap<AreaId, HashMap<(TileCoord, TileCoord), u32>>,
+    pub show_paint_heatmap: bool,
 
     // Filesystem watch (to detect externa modifications)
     pub watcher: Option<notify::RecommendedWatcher>,
     pub watch_paths: Vec<PathBuf>,
     pub watch_enabled: bool,
-    pub files_modified_notification: Arc<Mutex<bool>>,
+    pub files_modified_notification: Arc<Mutex<HashSet<PathBuf>>>,
+    // Hashes of the files last written to the autosave directory, to skip rewriting unchanged data:
+    pub autosave_hashes: HashMap<PathBuf, u64>,
 
     // Other editor state:
     pub dialogue: Option<Dialogue>,
+    pub move_tiles_job: Option<MoveTilesJob>,
 
     // Cached data:
     pub palettes_id_idx_map: HashMap<PaletteId, usize>,
 }
 
 impl EditorState {
+    // Colors of the palette as rendered in areas of the given theme.
+    pub fn theme_palette_colors(&self, theme: &str, pal: &Palette) -> [ColorRGB; 16] {
+        match self.themes.get(theme) {
+            Some(t) => t.palette_colors(pal),
+            None => pal.colors,
+        }
+    }
+
     pub fn main_area(&self) -> &Area {
         &self.areas[&self.main_area_id]
     }
@@ -409,6 +1891,78 @@ impl EditorState {
         self.areas.get_mut(&self.main_area_id.clone()).unwrap()
     }
 
+    // Whether any project data has changes not yet saved to the project files:
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.palettes.iter().any(|p| p.modified)
+            || self.areas.values().any(|a| a.modified)
+            || self.themes.values().any(|t| t.modified)
+            || self.world_map.modified
+    }
+
+    // Display order of the current palette's tiles in the tileset. Ties in the number of
+    // uses (including tiles added since counting) keep their index order.
+    pub fn tileset_order(&self) -> TilesetOrder {
+        if !self.tileset_sort_by_usage {
+            return TilesetOrder::default();
+        }
+        let pal = &self.palettes[self.palette_idx];
+        let counts = self.tile_use_counts.get(&pal.id);
+        let mut order: Vec<TileIdx> = (0..pal.tiles.len() as TileIdx).collect();
+        order.sort_by_key(|&i| {
+            std::cmp::Reverse(counts.and_then(|c| c.get(i as usize)).copied().unwrap_or(0))
+        });
+        TilesetOrder(order)
+    }
+
+    // Whether a cell can't be rendered, due to referencing a missing palette or a tile index
+    // beyond the palette's tile count:
+    pub fn is_broken_cell(&self, cell: &AreaCell) -> bool {
+        match self.palettes_id_idx_map.get(&cell.palette_id) {
+            Some(&i) => cell.tile_idx as usize >= self.palettes[i].tiles.len(),
+            None => true,
+        }
+    }
+
+    // Coordinates of the broken cells of an area, in row-major order.
+    pub fn broken_cells(&self, area: &Area) -> Vec<(TileCoord, TileCoord)> {
+        let mut cells = vec![];
+        for y in 0..area.size.1 as TileCoord * 32 {
+            for x in 0..area.size.0 as TileCoord * 32 {
+                if let Ok(cell) = area.get_cell(x, y) {
+                    if self.is_broken_cell(&cell) {
+                        cells.push((x, y));
+                    }
+                }
+            }
+        }
+        cells
+    }
+
+    // Recount the broken cells of the loaded areas, after their cells or the palettes changed.
+    pub fn update_broken_cells(&mut self) {
+        let counts: Vec<(AreaId, usize)> = self
+            .areas
+            .iter()
+            .map(|(id, area)| (id.clone(), self.broken_cells(area).len()))
+            .collect();
+        for (id, n) in counts {
+            if let Some(area) = self.areas.get_mut(&id) {
+                area.num_broken_cells = n;
+            }
+        }
+    }
+
+    // Coordinates of the cell shown in the cell inspector, i.e. the selected cell of the
+    // main area if a single cell is selected.
+    pub fn inspected_cell(&self) -> Option<(TileCoord, TileCoord)> {
+        if self.selection_source != SelectionSource::Area(AreaPosition::Main)
+            || self.selected_tile_block.size != (1, 1)
+        {
+            return None;
+        }
+        self.selection_coords
+    }
+
     pub fn side_area(&self) -> &Area {
         &self.areas[&self.side_area_id]
     }
@@ -427,15 +1981,41 @@ impl EditorState {
         }
     }
 
+    pub fn area_scroll_offset(&self, position: AreaPosition) -> Vector {
+        match position {
+            AreaPosition::Main => self.main_area_scroll_offset,
+            AreaPosition::Side => self.side_area_scroll_offset,
+        }
+    }
+
+    pub fn area_scroll_offset_mut(&mut self, position: AreaPosition) -> &mut Vector {
+        match position {
+            AreaPosition::Main => &mut self.main_area_scroll_offset,
+            AreaPosition::Side => &mut self.side_area_scroll_offset,
+        }
+    }
+
     pub fn area(&self, position: AreaPosition) -> &Area {
         &self.areas[self.area_id(position)]
     }
 
+    // The area to display at the given position, which is the saved copy while comparing:
+    pub fn displayed_area(&self, position: AreaPosition) -> &Area {
+        let area_id = self.area_id(position);
+        if self.compare_saved {
+            if let Some(area) = self.saved_areas.get(area_id) {
+                return area;
+            }
+        }
+        &self.areas[area_id]
+    }
+
     pub fn area_mut(&mut self, position: AreaPosition) -> &mut Area {
         self.areas.get_mut(&self.area_id(position).clone()).unwrap()
     }
 
-    pub fn set_area(&mut self, position: AreaPosition, area: Area) -> Result<()> {
+    pub fn set_area(&mut self, position: AreaPosition, mut area: Area) -> Result<()> {
+        area.num_broken_cells = self.broken_cells(&area).len();
         let id = area.id();
         self.areas.insert(id.clone(), area);
         match position {
@@ -451,7 +2031,9 @@ impl EditorState {
     }
 
     pub fn load_area(&mut self, area_id: &AreaId) -> Result<()> {
-        let area = load_area(self, area_id)?;
+        let mut area = load_area(self, area_id)?;
+        area.num_broken_cells = self.broken_cells(&area).len();
+        self.saved_areas.insert(area_id.clone(), area.clone());
         self.areas.insert(area_id.clone(), area);
         Ok(())
     }
@@ -473,6 +2055,7 @@ impl EditorState {
         for key in delete_keys {
             save_area(self, &key)?;
             self.areas.remove(&key);
+            self.saved_areas.remove(&key);
         }
         Ok(())
     }
@@ -532,6 +2115,8 @@ pub fn ensure_areas_non_empty(state: &mut EditorState) -> Result<()> {
                     palettes: [[0; 32]; 32],
                     tiles: [[0; 32]; 32],
                     flips: [[Flip::None; 32]; 32],
+                    priorities: [[false; 32]; 32],
+                    notes: String::new(),
                 });
             }
         }
@@ -553,7 +2138,7 @@ pub fn ensure_palettes_non_empty(state: &mut EditorState) {
                 collision: 0,
                 h_flippable: true,
                 v_flippable: true,
-                pixels: [[0; 8]; 8]
+                pixels: [[0; TILE_SIZE]; TILE_SIZE]
             };
             16
         ];
@@ -561,13 +2146,17 @@ pub fn ensure_palettes_non_empty(state: &mut EditorState) {
     }
 }
 
-pub fn get_initial_state() -> Result<EditorState> {
-    let mut state = EditorState {
-        global_config_path: get_global_config_path()?,
+// Editor state with no project loaded, using the global config at the given path:
+pub fn new_editor_state(global_config_path: PathBuf) -> EditorState {
+    EditorState {
+        global_config_path,
         global_config: GlobalConfig::default(),
         rom_path: None,
         palettes: vec![],
         areas: HashMap::new(),
+        saved_areas: HashMap::new(),
+        compare_saved: false,
+        read_only: false,
         main_area_id: AreaId {
             area: "Example".to_string(),
             theme: "Base".to_string(),
@@ -578,34 +2167,68 @@ pub fn get_initial_state() -> Result<EditorState> {
         },
         area_names: vec![],
         theme_names: vec![],
+        themes: HashMap::new(),
+        world_map: WorldMap::default(),
+        snapshot_names: vec![],
+        stamps: BTreeMap::new(),
         undo_stack: vec![],
         redo_stack: vec![],
         tool: Tool::default(),
+        fill_mode: FillMode::default(),
+        screen_layer: ScreenLayer::default(),
         shift_brush: false,
+        line_brush: false,
+        modifiers: iced::keyboard::Modifiers::default(),
         side_panel_view: SidePanelView::default(),
         focus: Focus::None,
+        selected_palette_ids: vec![],
         palette_idx: 0,
         color_idx: None,
         selected_color: [0, 0, 0],
         identify_color: false,
+        pinned_identify_colors: HashSet::new(),
+        tileset_sort_by_usage: false,
+        tile_use_counts: HashMap::new(),
         tile_idx: None,
         identify_tile: false,
+        preview_palette_id: None,
         selection_source: SelectionSource::Area(AreaPosition::Main),
         start_coords: None,
         end_coords: None,
+        reference_coords: None,
         hover_coords: None,
+        selection_coords: None,
+        copy_coords: None,
+        marching_ants_phase: 0,
+        animation_frame: 0,
+        stamp_flip: Flip::None,
+        tile_tag_search: String::new(),
+        tile_tag_input: String::new(),
+        replace_color_from: 0,
+        last_palette_name_click: None,
+        main_area_scroll_offset: Vector::ZERO,
+        side_area_scroll_offset: Vector::ZERO,
         selected_tile_block: TileBlock::default(),
         selected_gfx: vec![],
         show_grid_16: false,
+        show_grid_8: false,
         snap_grid_16: false,
+        paint_counts: HashMap::new(),
+        show_paint_heatmap: false,
         pixel_coords: None,
         watcher: None,
         watch_enabled: false,
         watch_paths: vec![],
-        files_modified_notification: Arc::new(Mutex::new(false)),
+        files_modified_notification: Arc::new(Mutex::new(HashSet::new())),
+        autosave_hashes: HashMap::new(),
         dialogue: None,
+        move_tiles_job: None,
         palettes_id_idx_map: HashMap::new(),
-    };
+    }
+}
+
+pub fn get_initial_state() -> Result<EditorState> {
+    let mut state = new_editor_state(get_global_config_path()?);
     if let Err(err) = persist::load_global_config(&mut state) {
         info!("Unable to load global config, using default: {}", err);
     }
@@ -616,5 +2239,124 @@ pub fn get_initial_state() -> Result<EditorState> {
     ensure_themes_non_empty(&mut state);
     ensure_areas_non_empty(&mut state)?;
     ensure_palettes_non_empty(&mut state);
+    state.tool = state
+        .global_config
+        .startup_tool
+        .tool(state.global_config.last_tool);
     Ok(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Area of the given size (in screens) with blank screens at their correct positions.
+    fn test_area(size: (u8, u8)) -> Area {
+        let mut area = Area {
+            size,
+            screens: vec![Screen::default(); size.0 as usize * size.1 as usize],
+            ..Default::default()
+        };
+        area.fix_screen_positions().unwrap();
+        area
+    }
+
+    #[test]
+    fn fix_wrong_screen_positions() {
+        let mut area = test_area((3, 2));
+        area.screens[1].position = (1, 1);
+        area.screens[5].position = (7, 0);
+        assert_eq!(area.fix_screen_positions().unwrap(), 2);
+        let positions: Vec<(u8, u8)> = area.screens.iter().map(|s| s.position).collect();
+        assert_eq!(
+            positions,
+            vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]
+        );
+        // Nothing is left to fix:
+        assert_eq!(area.fix_screen_positions().unwrap(), 0);
+    }
+
+    #[test]
+    fn inconsistent_screens_are_rejected() {
+        let mut area = test_area((2, 2));
+        assert!(area.check_screens().is_ok());
+        area.screens.pop();
+        assert!(area.check_screens().is_err());
+        assert!(area.fix_screen_positions().is_err());
+        area.size = (3, 1);
+        assert!(area.check_screens().is_ok());
+        area.screens.push(Screen::default());
+        assert!(area.check_screens().is_err());
+    }
+
+    #[test]
+    fn flip_twice_returns_original() {
+        let mut tile = Tile::default();
+        for y in 0..TILE_SIZE {
+            for x in 0..TILE_SIZE {
+                tile.pixels[y][x] = ((y * TILE_SIZE + x) % 16) as ColorIdx;
+            }
+        }
+        let flips = [Flip::None, Flip::Horizontal, Flip::Vertical, Flip::Both];
+        for f in flips {
+            assert_eq!(f.apply_to_tile(f.apply_to_tile(tile)), tile);
+            assert_eq!(f.apply_to_flip(f), Flip::None);
+            if f != Flip::None {
+                assert_ne!(f.apply_to_tile(tile), tile);
+            }
+            // Combining flips gives the same pixels as applying them one after the other:
+            for g in flips {
+                assert_eq!(
+                    g.apply_to_flip(f).apply_to_pixels(tile.pixels),
+                    g.apply_to_pixels(f.apply_to_pixels(tile.pixels))
+                );
+            }
+        }
+        assert_eq!(Flip::Horizontal.flip_horizontally(), Flip::None);
+        assert_eq!(Flip::Both.flip_vertically().flip_vertically(), Flip::Both);
+        assert_eq!(Flip::Horizontal.apply_to_flip(Flip::Vertical), Flip::Both);
+    }
+
+    #[test]
+    fn animated_tile_frames() {
+        let pal = Palette {
+            tiles: vec![Tile::default(); 16],
+            animations: vec![TileAnimation {
+                frames: vec![3, 9, 20],
+            }],
+            ..Default::default()
+        };
+        let shown: Vec<TileIdx> = (0..4).map(|f| pal.animated_tile(3, f)).collect();
+        // A frame beyond the palette's tiles shows the first frame instead:
+        assert_eq!(shown, vec![3, 9, 3, 3]);
+        assert_eq!(pal.animated_tile(9, 1), 9);
+        assert!(pal.animates_any(8..10));
+        assert!(!pal.animates_any(10..16));
+    }
+
+    #[test]
+    fn world_links_serialize_independent_of_edit_order() {
+        let screen = |area: &str, x: u8, y: u8| ScreenId {
+            area: area.to_string(),
+            x,
+            y,
+        };
+        let edits = [
+            (screen("B", 0, 0), Side::West, screen("A", 1, 0)),
+            (screen("A", 0, 0), Side::North, screen("C", 2, 3)),
+            (screen("C", 0, 1), Side::East, screen("B", 0, 1)),
+        ];
+        let mut forward = WorldMap::default();
+        for (from, side, to) in &edits {
+            forward.links = forward.with_link(from, *side, Some(to));
+        }
+        // The same links, made in reverse order and from the other side of each:
+        let mut backward = WorldMap::default();
+        for (from, side, to) in edits.iter().rev() {
+            backward.links = backward.with_link(to, side.opposite(), Some(from));
+        }
+        let forward_json = serde_json::to_vec_pretty(&forward).unwrap();
+        assert_eq!(forward_json, serde_json::to_vec_pretty(&forward).unwrap());
+        assert_eq!(forward_json, serde_json::to_vec_pretty(&backward).unwrap());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     message::Message,
-    state::{EditorState, Flip, PaletteId, Tile, TileBlock, TileCoord, TileIdx},
+    persist::count_palette_references,
+    state::{ColorRGB, EditorState, Flip, PaletteId, Screen, Tile, TileBlock, TileCoord, TileIdx},
 };
 
 use anyhow::{Context, Result};
@@ -22,22 +23,107 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
         Message::Event(_) => UndoAction::None,
         Message::Focus(_) => UndoAction::None,
         Message::WindowClose(_) => UndoAction::None,
+        Message::ConfirmWindowClose(_) => UndoAction::None,
+        Message::SaveAndCloseWindow(_) => UndoAction::None,
+        Message::DiscardAndCloseWindow(_) => UndoAction::None,
         Message::SaveProject => UndoAction::None,
+        Message::AnimateSelection => UndoAction::None,
+        Message::AnimateTiles => UndoAction::None,
+        Message::SaveProjectFiles => UndoAction::None,
+        Message::RecoverAutosaveDialogue => UndoAction::None,
+        Message::RecoverAutosave(_) => UndoAction::Irreversible,
+        Message::DiscardAutosave => UndoAction::None,
         Message::OpenProject => UndoAction::None,
         Message::ModifiedReload => UndoAction::None,
+        Message::ExternalChange(_) => UndoAction::Irreversible,
         Message::RebuildProjectDialogue => UndoAction::None,
         Message::RebuildProject => UndoAction::None,
         Message::ProjectOpened(_) => UndoAction::Irreversible,
         Message::SettingsDialogue => UndoAction::None,
         Message::HelpDialogue => UndoAction::None,
+        Message::ProjectStatsDialogue => UndoAction::None,
         Message::SetPixelSize(_) => UndoAction::None,
         Message::SetGridAlpha(_) => UndoAction::None,
+        Message::SetGridColor(_) => UndoAction::None,
+        Message::SetMajorGridInterval(_) => UndoAction::None,
+        Message::SetMajorGridColor(_) => UndoAction::None,
+        Message::SetSelectionDashColor(..) => UndoAction::None,
+        Message::SetSelectionDashSpeed(_) => UndoAction::None,
+        Message::SetMajorGridAlpha(_) => UndoAction::None,
+        Message::SetHighlightColor(_) => UndoAction::None,
+        Message::SetHighlightAlpha(_) => UndoAction::None,
+        Message::SetTileHoverHighlight(_) => UndoAction::None,
+        Message::SetDoubleClickRename(_) => UndoAction::None,
+        Message::SetKeepSelectionOnToolSwitch(_) => UndoAction::None,
+        Message::SetTilePreviewZoom(_) => UndoAction::None,
+        Message::SetPixelGridMinSize(_) => UndoAction::None,
+        Message::SetAutoAdvanceColor(_) => UndoAction::None,
+        Message::SetTrimEmptyEdgesOnSave(_) => UndoAction::None,
+        Message::SetPlayAnimations(_) => UndoAction::None,
+        Message::SetImportAnimatedTiles(_) => UndoAction::None,
+        Message::SetPerAreaZoom(_) => UndoAction::None,
+        Message::SetAutosaveToShadow(_) => UndoAction::None,
+        Message::SetLinearSelect(_) => UndoAction::None,
+        Message::SetDisplayHex(_) => UndoAction::None,
+        Message::SetMaxUndoLevels(_) => UndoAction::None,
+        Message::SetUndoMemoryMb(_) => UndoAction::None,
+        Message::SetContactSheetThumbnailSize(_) => UndoAction::None,
+        Message::SetContactSheetColumns(_) => UndoAction::None,
+        Message::SetFillConfirmThreshold(_) => UndoAction::None,
+        Message::SetBrushOpacity(_) => UndoAction::None,
+        Message::SetTilesetBrushPalette(_) => UndoAction::None,
+        Message::SetLineBrushModifier(_) => UndoAction::None,
+        Message::SetOutOfBoundsMove(_) => UndoAction::None,
+        Message::SetMoveSnap(_) => UndoAction::None,
+        Message::SetMoveSnapStep(_) => UndoAction::None,
+        Message::SetSelectionSnapStep(_) => UndoAction::None,
+        Message::SetTilesetBackdrop(_) => UndoAction::None,
+        Message::SetTilesetBackdropColor(_) => UndoAction::None,
+        Message::SetColorProfile(_) => UndoAction::None,
+        Message::SetPreviewFilter(_) => UndoAction::None,
+        Message::SetPaletteLayout(_) => UndoAction::None,
+        Message::SetMouseButtons(_) => UndoAction::None,
+        Message::SetConfirmExit(_) => UndoAction::None,
+        Message::SetStartupTool(_) => UndoAction::None,
+        Message::SetPaletteFileNaming(_) => UndoAction::None,
+        Message::SetColorLimitPolicy(_) => UndoAction::None,
+        Message::SetColorMatchPolicy(_) => UndoAction::None,
+        Message::SetAreaMargin(_) => UndoAction::None,
+        Message::SetCenterArea(_) => UndoAction::None,
+        Message::ExportContactSheetDialogue => UndoAction::None,
+        Message::ExportContactSheet(_) => UndoAction::None,
+        Message::ExportPalettesBinDialogue => UndoAction::None,
+        Message::ExportSelectionTilesetDialogue => UndoAction::None,
+        Message::ExportSelectionTileset(_) => UndoAction::None,
+        Message::ExportPalettesBin(_) => UndoAction::None,
+        Message::ExportPaletteJsonDialogue => UndoAction::None,
+        Message::ExportPaletteJson(_) => UndoAction::None,
+        Message::ExportPaletteChrDialogue => UndoAction::None,
+        Message::ExportPaletteChr(_) => UndoAction::None,
+        Message::ImportPalettesBinDialogue => UndoAction::None,
+        Message::ImportPalettesBin(_) => UndoAction::Irreversible,
+        Message::ExportProjectArchiveDialogue => UndoAction::None,
+        Message::ExportProjectArchive(_) => UndoAction::None,
+        Message::ImportProjectArchiveDialogue => UndoAction::None,
+        Message::ImportProjectArchive(_) => UndoAction::None,
+        Message::ImportProjectArchiveTo { .. } => UndoAction::None,
+        Message::ProjectArchiveReport(_) => UndoAction::None,
         Message::CloseDialogue => UndoAction::None,
         Message::ImportDialogue => UndoAction::None,
         Message::ImportConfirm(_) => UndoAction::None,
         Message::ImportROMProgress => UndoAction::None,
         Message::ImportROM => UndoAction::Irreversible,
+        Message::DiffAgainstRomDialogue => UndoAction::None,
+        Message::DiffAgainstRom(_) => UndoAction::None,
+        Message::RunRomDiff(_) => UndoAction::None,
+        Message::ImportTilesetPNGDialogue => UndoAction::None,
+        Message::ImportChr(_) => UndoAction::None,
+        Message::ImportChrFile { .. } => UndoAction::Irreversible,
+        Message::ImportTilesetPNGConfirm(_) => UndoAction::None,
+        Message::SetImportTilesetPNGQuantize(_) => UndoAction::None,
+        Message::ImportTilesetPNG { .. } => UndoAction::Irreversible,
         Message::SelectPalette(_) => UndoAction::None,
+        Message::SelectPaletteIdx(_) => UndoAction::None,
         Message::AddPaletteDialogue => UndoAction::None,
         Message::SetAddPaletteName(_) => UndoAction::None,
         Message::SetAddPaletteID(_) => UndoAction::None,
@@ -52,6 +138,27 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
             }
         }
         Message::RestorePalette(pal) => UndoAction::Ok(Message::DeletePalette(pal.id)),
+        Message::RemapPaletteInRegionDialogue => UndoAction::None,
+        Message::SetRemapRegionFrom(_) => UndoAction::None,
+        Message::SetRemapRegionTo(_) => UndoAction::None,
+        // The remapping is applied (and made undoable) through `AreaBrush`:
+        Message::RemapPaletteInRegion { .. } => UndoAction::None,
+        Message::ReplacePaletteReferencesDialogue => UndoAction::None,
+        Message::SetReplaceReferencesFrom(_) => UndoAction::None,
+        Message::SetReplaceReferencesTo(_) => UndoAction::None,
+        &Message::ReplacePaletteReferences { from, to } => {
+            // Replacing back is only an exact reversal if no cells used `to` beforehand:
+            if count_palette_references(state, to)?.0 == 0 {
+                UndoAction::Ok(Message::ReplacePaletteReferences { from: to, to: from })
+            } else {
+                UndoAction::Irreversible
+            }
+        }
+        Message::SetDeletePaletteRemapTo(_) => UndoAction::None,
+        Message::RemapPalette { .. } => UndoAction::Irreversible,
+        Message::MergePalettesDialogue => UndoAction::None,
+        Message::SetMergePalettesIdenticalTiles(_) => UndoAction::None,
+        Message::MergePalettes(_) => UndoAction::Irreversible,
         Message::RenamePaletteDialogue => UndoAction::None,
         Message::SetRenamePaletteName(_) => UndoAction::None,
         Message::RenamePalette { id, name: _ } => {
@@ -66,6 +173,10 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
         }
         Message::HideModal => UndoAction::None,
         Message::SelectColor(_, _) => UndoAction::None,
+        Message::ToggleIdentifyColor(_) => UndoAction::None,
+        Message::SetTilesetSortByUsage(_) => UndoAction::None,
+        Message::SetSidePanelView(_) => UndoAction::None,
+        Message::JumpToHistory(_) => UndoAction::None,
         &Message::BrushColor {
             palette_id,
             color_idx,
@@ -81,9 +192,21 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
                 color: state.palettes[idx].colors[color_idx as usize],
             })
         }
+        Message::ClickPaletteName(_) => UndoAction::None,
+        Message::ClearPaletteSelection => UndoAction::None,
+        // The brightness adjustment is applied (and made undoable) through `SetPalettesColors`:
+        Message::AdjustPalettesBrightness { .. } => UndoAction::None,
+        Message::SetPalettesColors(colors) => {
+            let palette_ids: Vec<PaletteId> = colors.iter().map(|&(id, _)| id).collect();
+            UndoAction::Ok(Message::SetPalettesColors(palettes_colors(
+                state,
+                &palette_ids,
+            )?))
+        }
         Message::ChangeRed(_) => UndoAction::None,
         Message::ChangeGreen(_) => UndoAction::None,
         Message::ChangeBlue(_) => UndoAction::None,
+        Message::SelectPreviewPalette(_) => UndoAction::None,
         &Message::AddTileRow(palette_id) => UndoAction::Ok(Message::DeleteTileRow(palette_id)),
         Message::DeleteTileRow(palette_id) => {
             let idx = *state
@@ -91,12 +214,79 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
                 .get(palette_id)
                 .context("palette not found")?;
             let pal = &state.palettes[idx];
-            let row = pal.tiles[pal.tiles.len() - 16..].to_vec();
-            UndoAction::Ok(Message::RestoreTileRow(*palette_id, row))
+            let start = pal.tiles.len() - 16;
+            let row = pal.tiles[start..].to_vec();
+            let tags = pal
+                .tags
+                .range(start as TileIdx..)
+                .map(|(&t, tags)| (t, tags.clone()))
+                .collect();
+            UndoAction::Ok(Message::RestoreTileRow(*palette_id, row, tags))
         }
-        &Message::RestoreTileRow(palette_id, _) => {
+        &Message::RestoreTileRow(palette_id, _, _) => {
             UndoAction::Ok(Message::DeleteTileRow(palette_id))
         }
+        &Message::InsertTileRow(palette_id, row) => {
+            UndoAction::Ok(Message::DeleteTileRowAt(palette_id, row))
+        }
+        &Message::DeleteTileRowAt(palette_id, row) => {
+            let idx = *state
+                .palettes_id_idx_map
+                .get(&palette_id)
+                .context("palette not found")?;
+            let pal = &state.palettes[idx];
+            let tiles = pal
+                .tiles
+                .get(row * 16..row * 16 + 16)
+                .context("tile row out of range")?
+                .to_vec();
+            let start = (row * 16) as TileIdx;
+            let tags = pal
+                .tags
+                .range(start..start + 16)
+                .map(|(&t, tags)| (t, tags.clone()))
+                .collect();
+            UndoAction::Ok(Message::RestoreTileRowAt(palette_id, row, tiles, tags))
+        }
+        &Message::RestoreTileRowAt(palette_id, row, _, _) => {
+            UndoAction::Ok(Message::DeleteTileRowAt(palette_id, row))
+        }
+        Message::CompareTiles(_, _) => UndoAction::None,
+        Message::CopyTileAsText(_) => UndoAction::None,
+        Message::PasteTileFromClipboard => UndoAction::None,
+        Message::PasteTileFromText(_) => UndoAction::None,
+        Message::TilePagesDialogue => UndoAction::None,
+        Message::SetTilePageName(_) => UndoAction::None,
+        &Message::SetTilePages { palette_id, .. } => {
+            let idx = *state
+                .palettes_id_idx_map
+                .get(&palette_id)
+                .context("palette not found")?;
+            UndoAction::Ok(Message::SetTilePages {
+                palette_id,
+                pages: state.palettes[idx].pages.clone(),
+            })
+        }
+        Message::ScrollToTilePage(_) => UndoAction::None,
+        Message::SetTileTagSearch(_) => UndoAction::None,
+        Message::SetTileTagInput(_) => UndoAction::None,
+        // Tagging is applied (and made undoable) through `SetTileTags`:
+        Message::TagSelectedTiles => UndoAction::None,
+        Message::UntagSelectedTiles => UndoAction::None,
+        Message::SetReplaceColorFrom(_) => UndoAction::None,
+        // Replacing is applied (and made undoable) through `TilesetBrush`:
+        Message::ReplaceColorInSelectedTiles => UndoAction::None,
+        Message::ReplaceColorInTiles { .. } => UndoAction::None,
+        &Message::SetTileTags { palette_id, .. } => {
+            let idx = *state
+                .palettes_id_idx_map
+                .get(&palette_id)
+                .context("palette not found")?;
+            UndoAction::Ok(Message::SetTileTags {
+                palette_id,
+                tags: state.palettes[idx].tags.clone(),
+            })
+        }
         &Message::SetTilePriority {
             palette_id,
             tile_idx,
@@ -231,6 +421,58 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
             color: state.areas[area_id].bg_color,
         }),
         Message::DeleteAreaDialogue => UndoAction::None,
+        Message::ScreenNotesDialogue => UndoAction::None,
+        Message::SelectNotesScreen(_) => UndoAction::None,
+        Message::EditScreenNotes(_) => UndoAction::None,
+        Message::SaveScreenNotes => UndoAction::None,
+        Message::SetScreenNotes {
+            area_id,
+            screen,
+            notes: _,
+        } => {
+            let area = &state.areas[area_id];
+            let screen_idx = screen.1 as usize * area.size.0 as usize + screen.0 as usize;
+            UndoAction::Ok(Message::SetScreenNotes {
+                area_id: area_id.clone(),
+                screen: *screen,
+                notes: area.screens[screen_idx].notes.clone(),
+            })
+        }
+        Message::EditAreaCell(_) => UndoAction::None,
+        Message::SetAreaCell {
+            area_id,
+            coords,
+            cell: _,
+        } => UndoAction::Ok(Message::SetAreaCell {
+            area_id: area_id.clone(),
+            coords: *coords,
+            cell: state.areas[area_id].get_cell(coords.0, coords.1)?,
+        }),
+        Message::WorldOverviewDialogue => UndoAction::None,
+        Message::OpenWorldScreen(_) => UndoAction::None,
+        Message::SelectLinkScreen(_) => UndoAction::None,
+        Message::SetLinkSide(_) => UndoAction::None,
+        Message::SetLinkTargetArea(_) => UndoAction::None,
+        Message::SetLinkTargetX(_) => UndoAction::None,
+        Message::SetLinkTargetY(_) => UndoAction::None,
+        Message::LinkScreens { .. } => UndoAction::None,
+        Message::SetWorldLinks(_) => {
+            UndoAction::Ok(Message::SetWorldLinks(state.world_map.links.clone()))
+        }
+        Message::TrimEmptyEdges(_) => UndoAction::None,
+        Message::DeleteSelection => UndoAction::None,
+        // The copy is placed (and made undoable) through `AreaBrush`:
+        Message::MirrorSelection { .. } => UndoAction::None,
+        Message::SelectNextBrokenCell => UndoAction::None,
+        Message::CheckBrokenCells => UndoAction::None,
+        Message::SetAreaScreens { area_id, .. } => {
+            let area = state.areas.get(area_id).context("area not loaded")?;
+            UndoAction::Ok(Message::SetAreaScreens {
+                area_id: area_id.clone(),
+                size: area.size,
+                screens: area.screens.clone(),
+            })
+        }
         Message::DeleteArea(_) => UndoAction::Irreversible,
         Message::SelectTheme(_, _) => UndoAction::None,
         Message::AddThemeDialogue => UndoAction::None,
@@ -244,11 +486,37 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
         }),
         Message::DeleteThemeDialogue => UndoAction::None,
         Message::DeleteTheme(_) => UndoAction::Irreversible,
+        Message::ThemeColorsDialogue => UndoAction::None,
+        Message::EditThemeColor(..) => UndoAction::None,
+        Message::SetThemeColor {
+            theme,
+            palette_id,
+            color_idx,
+            color: _,
+        } => UndoAction::Ok(Message::SetThemeColor {
+            theme: theme.clone(),
+            palette_id: *palette_id,
+            color_idx: *color_idx,
+            color: state
+                .themes
+                .get(theme)
+                .and_then(|t| t.color_overrides.get(palette_id))
+                .and_then(|o| o.get(color_idx))
+                .copied(),
+        }),
         Message::HoverArea(_) => UndoAction::None,
         Message::HoverAreaEnd => UndoAction::None,
+        Message::ScrollArea(..) => UndoAction::None,
         Message::StartTileSelection(_, _) => UndoAction::None,
         Message::ProgressTileSelection(_) => UndoAction::None,
         Message::EndTileSelection(_) => UndoAction::None,
+        Message::SelectTileRun(_, _) => UndoAction::None,
+        Message::MagicWandSelect { .. } => UndoAction::None,
+        Message::SetReferenceSelection(_) => UndoAction::None,
+        Message::SwapReferenceSelection => UndoAction::None,
+        // The paste is applied (and made undoable) through `AreaBrush`:
+        Message::PasteInPlace => UndoAction::None,
+        Message::PasteAtCursor => UndoAction::None,
         Message::AreaBrush {
             position,
             area_id,
@@ -286,6 +554,7 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
                 palettes,
                 tiles,
                 flips,
+                mask: None,
             };
             UndoAction::Ok(Message::AreaBrush {
                 position: *position,
@@ -295,6 +564,59 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
                 palette_only: *palette_only,
             })
         }
+        &Message::AreaFill {
+            position,
+            ref area_id,
+            coords,
+            selection: _,
+            mode,
+            palette_only,
+            ..
+        } => {
+            // Undo by restoring the original contents of the bounding box of the filled region:
+            let area = &state.areas[area_id];
+            let region = area.fill_region(coords.x, coords.y, mode)?;
+            let left = region.iter().map(|p| p.0).min().unwrap();
+            let right = region.iter().map(|p| p.0).max().unwrap();
+            let top = region.iter().map(|p| p.1).min().unwrap();
+            let bottom = region.iter().map(|p| p.1).max().unwrap();
+            let mut palettes: Vec<Vec<PaletteId>> = vec![];
+            let mut tiles: Vec<Vec<TileIdx>> = vec![];
+            let mut flips: Vec<Vec<Flip>> = vec![];
+            for y in top..=bottom {
+                palettes.push(
+                    (left..=right)
+                        .map(|x| area.get_palette(x, y).unwrap())
+                        .collect(),
+                );
+                tiles.push(
+                    (left..=right)
+                        .map(|x| area.get_tile(x, y).unwrap())
+                        .collect(),
+                );
+                flips.push(
+                    (left..=right)
+                        .map(|x| area.get_flip(x, y).unwrap())
+                        .collect(),
+                );
+            }
+            UndoAction::Ok(Message::AreaBrush {
+                position,
+                area_id: area_id.clone(),
+                coords: Point::new(left, top),
+                selection: TileBlock {
+                    size: (right - left + 1, bottom - top + 1),
+                    palettes,
+                    tiles,
+                    flips,
+                    mask: None,
+                },
+                palette_only,
+            })
+        }
+        Message::SetFillMode(_) => UndoAction::None,
+        Message::SetScreenLayer(_) => UndoAction::None,
+        Message::PickScreenColor { .. } => UndoAction::None,
         Message::OpenTile { .. } => UndoAction::None,
         Message::MovingTilesProgress { .. } => UndoAction::None,
         &Message::MoveTiles {
@@ -311,7 +633,130 @@ pub fn get_undo_action(state: &EditorState, message: &Message) -> Result<UndoAct
                 check_reversible: false,
             })
         }
+        Message::MoveTilesStep => UndoAction::None,
+        Message::MoveProgress(_) => UndoAction::None,
+        // Cancelling also takes the move's entry back off the undo history:
+        Message::CancelMove => UndoAction::None,
         Message::MoveTilesConfirmDialogue { .. } => UndoAction::None,
+        Message::CreateSnapshotDialogue => UndoAction::None,
+        Message::SetCreateSnapshotName(_) => UndoAction::None,
+        Message::CreateSnapshot(_) => UndoAction::None,
+        Message::RestoreSnapshotDialogue(_) => UndoAction::None,
+        Message::RestoreSnapshot(_) => UndoAction::Irreversible,
+        Message::SaveStampDialogue => UndoAction::None,
+        Message::SetSaveStampName(_) => UndoAction::None,
+        // Stamps are saved to the project immediately, outside of the undo history:
+        Message::SaveStamp(_) => UndoAction::None,
+        Message::LoadStamp(_) => UndoAction::None,
+        Message::DeleteStamp(_) => UndoAction::None,
     };
     Ok(action)
 }
+
+fn palettes_colors(
+    state: &EditorState,
+    palette_ids: &[PaletteId],
+) -> Result<Vec<(PaletteId, [ColorRGB; 16])>> {
+    let mut out = vec![];
+    for &id in palette_ids {
+        let idx = *state
+            .palettes_id_idx_map
+            .get(&id)
+            .context("palette not found")?;
+        out.push((id, state.palettes[idx].colors));
+    }
+    Ok(out)
+}
+
+fn tile_block_size(block: &TileBlock) -> usize {
+    let cells = block.size.0 as usize * block.size.1 as usize;
+    let mask_size = if block.mask.is_some() { cells } else { 0 };
+    cells * (size_of::<PaletteId>() + size_of::<TileIdx>() + size_of::<Flip>()) + mask_size
+}
+
+// Rough estimate of the memory used by a message kept in the undo history,
+// counting the data of the large payloads (tile blocks, tiles, and screens).
+pub fn message_size(message: &Message) -> usize {
+    let payload = match message {
+        Message::RestorePalette(pal) => pal.tiles.len() * size_of::<Tile>(),
+        Message::RestoreTileRow(_, tiles, _) | Message::RestoreTileRowAt(_, _, tiles, _) => {
+            tiles.len() * size_of::<Tile>()
+        }
+        Message::SetPalettesColors(colors) => {
+            colors.len() * size_of::<(PaletteId, [ColorRGB; 16])>()
+        }
+        Message::TilesetBrush {
+            selected_gfx,
+            tile_block,
+            ..
+        } => {
+            selected_gfx.iter().map(|row| row.len()).sum::<usize>() * size_of::<Tile>()
+                + tile_block.as_ref().map_or(0, tile_block_size)
+        }
+        Message::SetAreaScreens { screens, .. } => screens
+            .iter()
+            .map(|s| size_of::<Screen>() + s.notes.len())
+            .sum(),
+        Message::AreaBrush { selection, .. } | Message::AreaFill { selection, .. } => {
+            tile_block_size(selection)
+        }
+        Message::MoveTiles {
+            src_selection,
+            dst_selection,
+            ..
+        } => tile_block_size(src_selection) + tile_block_size(dst_selection),
+        _ => 0,
+    };
+    size_of::<Message>() + payload
+}
+
+// Human-readable description of an undoable message, listed in the history panel.
+pub fn undo_label(message: &Message) -> String {
+    let label = match message {
+        Message::AreaBrush { .. } => "Brush",
+        Message::AreaFill { .. } => "Fill",
+        Message::DeleteSelection => "Delete selection",
+        Message::TilesetBrush { .. } => "Tileset brush",
+        Message::BrushPixel { .. } => "Draw pixel",
+        Message::BrushColor { .. } => "Palette edit",
+        Message::SetPalettesColors(_) => "Palette colors",
+        Message::MoveTiles { .. } => "Move tiles",
+        Message::AddTileRow(_) => "Add tile row",
+        Message::DeleteTileRow(_) => "Delete tile row",
+        Message::RestoreTileRow(..) => "Restore tile row",
+        Message::InsertTileRow(_, _) => "Insert tile row",
+        Message::DeleteTileRowAt(_, _) => "Delete tile row",
+        Message::RestoreTileRowAt(..) => "Restore tile row",
+        Message::AddPalette { .. } => "Add palette",
+        Message::DeletePalette(_) => "Delete palette",
+        Message::RestorePalette(_) => "Restore palette",
+        Message::RenamePalette { .. } => "Rename palette",
+        Message::SetAreaScreens { .. } => "Area screens",
+        _ => {
+            // Use the message name, split into words (e.g. "SetTilePriority" as
+            // "Set tile priority"):
+            let debug = format!("{:?}", message);
+            let name = debug
+                .split(|c: char| !c.is_alphanumeric())
+                .next()
+                .unwrap_or_default();
+            let chars: Vec<char> = name.chars().collect();
+            let mut out = String::new();
+            for (i, &c) in chars.iter().enumerate() {
+                if i > 0 && c.is_uppercase() {
+                    // Start a word unless inside an acronym (e.g. "BGColor" as "bg color"):
+                    let prev_lower = chars[i - 1].is_lowercase();
+                    let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+                    if prev_lower || next_lower {
+                        out.push(' ');
+                    }
+                    out.extend(c.to_lowercase());
+                } else {
+                    out.push(c);
+                }
+            }
+            return out;
+        }
+    };
+    label.to_string()
+}
@@ -1,3 +1,5 @@
+use std::{ops::Range, path::PathBuf, time::Instant};
+
 use hashbrown::HashMap;
 use iced::{
     keyboard::{self, key},
@@ -7,27 +9,50 @@ use itertools::Itertools;
 use log::{error, info, warn};
 
 use crate::{
-    import::Importer,
+    archive::{export_project_archive, import_project_archive},
+    contact_sheet::export_contact_sheet,
+    export::{
+        export_all_palettes_bin, export_chr, export_palette_colors, export_selection_tileset,
+    },
+    helpers::{palette_label, tile_pixels_from_text, tile_pixels_to_text},
+    import::{diff_against_rom, Importer},
+    import::{import_chr, import_palettes_bin},
     message::{Message, SelectionSource},
     persist::{
-        self, clear_pngs, copy_area_theme, delete_area, delete_area_theme, delete_palette,
-        load_area_list, remap_tiles, rename_area, rename_area_theme, save_area, save_area_png,
-        save_palettes, scan_used_tiles,
+        self, all_area_ids, clear_pngs, copy_area_theme, count_palette_references, count_tile_uses,
+        create_snapshot, delete_area, delete_area_theme, delete_palette, delete_stamp,
+        delete_theme, external_change_conflicts, find_broken_cells, find_palette_references,
+        import_tileset_png, load_area_list, project_stats, reload_external_change,
+        remap_area_tiles, remap_tiles, rename_area, rename_area_theme, rename_theme,
+        replace_palette_references, restore_area, restore_snapshot, save_area, save_area_png,
+        save_palettes, save_stamp,
     },
     state::{
-        Area, AreaId, AreaPosition, ColorIdx, ColorRGB, Dialogue, EditorState, Flip, Focus,
-        PaletteId, Screen, SidePanelView, Tile, TileBlock, TileIdx, Tool, MAX_PIXEL_SIZE,
-        MIN_PIXEL_SIZE,
+        ensure_palettes_non_empty, Area, AreaId, AreaPosition, ColorIdx, ColorRGB, ColorValue,
+        ConfirmExit, Dialogue, EditorState, FillMode, Flip, Focus, MirrorAxis, MoveCollision,
+        MoveHistory, MoveTilesJob, Palette, PaletteId, Screen, ScreenId, Side, SidePanelView,
+        Stamp, Theme, Tile, TileBlock, TileCoord, TileIdx, Tool, UndoEntry, DEFAULT_PIXEL_SIZE,
+        DOUBLE_CLICK_INTERVAL, MAX_AREA_MARGIN, MAX_CONTACT_SHEET_COLUMNS,
+        MAX_FILL_CONFIRM_THRESHOLD, MAX_MAJOR_GRID_INTERVAL, MAX_MOVE_SNAP_STEP,
+        MAX_PIXEL_GRID_MIN_SIZE, MAX_PIXEL_SIZE, MAX_SELECTION_DASH_SPEED, MAX_SELECTION_SNAP_STEP,
+        MAX_THUMBNAIL_SIZE, MAX_TILE_PREVIEW_ZOOM, MIN_PIXEL_SIZE, MIN_THUMBNAIL_SIZE, TILE_SIZE,
     },
-    undo::{get_undo_action, UndoAction},
-    view::{open_project, open_rom},
+    undo::{get_undo_action, message_size, undo_label, UndoAction},
+    view::{
+        open_chr, open_palettes_bin, open_png, open_project, open_project_archive, open_rom,
+        pick_archive_destination, save_contact_sheet, save_palette_chr, save_palette_colors,
+        save_palettes_bin, save_project_archive, save_selection_tileset, scroll_to_last_screen,
+        scroll_to_screen, scroll_to_tile,
+    },
+    world::build_world_overview,
 };
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 
 fn select_tileset_tile(state: &mut EditorState, tile_idx: TileIdx) -> Result<()> {
     state.tile_idx = Some(tile_idx);
-    state.start_coords = Some((tile_idx % 16, tile_idx / 16));
-    state.end_coords = Some((tile_idx % 16, tile_idx / 16));
+    let pos = state.tileset_order().position_of(tile_idx);
+    state.start_coords = Some((pos % 16, pos / 16));
+    state.end_coords = Some((pos % 16, pos / 16));
     state.selection_source = SelectionSource::Tileset;
     state.focus = Focus::TilesetTile;
     Ok(())
@@ -140,6 +165,8 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             }
             Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
                 state.shift_brush = modifiers.shift();
+                state.line_brush = state.global_config.line_brush_modifier.is_held(*modifiers);
+                state.modifiers = *modifiers;
                 match state.focus {
                     Focus::None => {}
                     Focus::PickArea(_) => {}
@@ -162,11 +189,7 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             }) => {
                 state.tool = Tool::Select;
                 state.dialogue = None;
-                state.color_idx = None;
-                state.tile_idx = None;
-                state.selected_gfx = vec![];
-                state.start_coords = None;
-                state.end_coords = None;
+                clear_selection(state);
             }
             Event::Keyboard(keyboard::Event::KeyPressed {
                 key: keyboard::Key::Named(key::Named::ArrowRight),
@@ -202,8 +225,10 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                     }
                     Focus::TilesetTile => {
                         if let Some(idx) = state.tile_idx {
-                            if (idx as usize) + 1 < state.palettes[state.palette_idx].tiles.len() {
-                                let new_idx = idx + 1;
+                            let order = state.tileset_order();
+                            let pos = order.position_of(idx);
+                            if (pos as usize) + 1 < state.palettes[state.palette_idx].tiles.len() {
+                                let new_idx = order.tile_at(pos + 1);
                                 select_tileset_tile(state, new_idx)?;
                             }
                         }
@@ -244,8 +269,10 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                     }
                     Focus::TilesetTile => {
                         if let Some(idx) = state.tile_idx {
-                            if idx > 0 {
-                                let new_idx = idx - 1;
+                            let order = state.tileset_order();
+                            let pos = order.position_of(idx);
+                            if pos > 0 {
+                                let new_idx = order.tile_at(pos - 1);
                                 select_tileset_tile(state, new_idx)?;
                             }
                         }
@@ -311,8 +338,10 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                     }
                     Focus::TilesetTile => {
                         if let Some(idx) = state.tile_idx {
-                            if (idx as usize) + 16 < state.palettes[state.palette_idx].tiles.len() {
-                                let new_idx = idx + 16;
+                            let order = state.tileset_order();
+                            let pos = order.position_of(idx);
+                            if (pos as usize) + 16 < state.palettes[state.palette_idx].tiles.len() {
+                                let new_idx = order.tile_at(pos + 16);
                                 select_tileset_tile(state, new_idx)?;
                             }
                         }
@@ -378,14 +407,40 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                     }
                     Focus::TilesetTile => {
                         if let Some(idx) = state.tile_idx {
-                            if (idx as usize) >= 16 {
-                                let new_idx = idx - 16;
+                            let order = state.tileset_order();
+                            let pos = order.position_of(idx);
+                            if pos >= 16 {
+                                let new_idx = order.tile_at(pos - 16);
                                 select_tileset_tile(state, new_idx)?;
                             }
                         }
                     }
                 }
             }
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(key::Named::Delete),
+                ..
+            }) if state.dialogue.is_none() => {
+                return Ok(Some(Task::done(Message::DeleteSelection)));
+            }
+            // Palette navigation keys. These are not received while a text field is focused,
+            // since the text input captures the key events.
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(key::Named::PageUp),
+                ..
+            }) => {
+                return Ok(Some(Task::done(Message::SelectPaletteIdx(
+                    state.palette_idx.saturating_sub(1),
+                ))));
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(key::Named::PageDown),
+                ..
+            }) => {
+                return Ok(Some(Task::done(Message::SelectPaletteIdx(
+                    state.palette_idx + 1,
+                ))));
+            }
             Event::Keyboard(keyboard::Event::KeyPressed {
                 modified_key: keyboard::Key::Character(c),
                 modifiers,
@@ -396,22 +451,60 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                         "r" => {
                             return Ok(Some(Task::done(Message::RebuildProjectDialogue)));
                         }
+                        "s" => {
+                            return Ok(Some(Task::done(Message::SaveProjectFiles)));
+                        }
+                        "v" => {
+                            return Ok(Some(Task::done(Message::PasteAtCursor)));
+                        }
+                        "V" => {
+                            return Ok(Some(Task::done(Message::PasteInPlace)));
+                        }
+                        // "+" is matched too, for layouts where it is typed with Shift:
+                        "=" | "+" => {
+                            return Ok(Some(Task::done(Message::SetPixelSize(
+                                (state.global_config.pixel_size + 1.0).min(MAX_PIXEL_SIZE),
+                            ))));
+                        }
+                        "-" => {
+                            return Ok(Some(Task::done(Message::SetPixelSize(
+                                (state.global_config.pixel_size - 1.0).max(MIN_PIXEL_SIZE),
+                            ))));
+                        }
+                        "0" => {
+                            return Ok(Some(Task::done(Message::SetPixelSize(DEFAULT_PIXEL_SIZE))));
+                        }
                         _ => {}
                     }
                 } else {
                     match c.as_str() {
                         "b" => {
-                            state.tool = Tool::Brush;
+                            set_tool(state, Tool::Brush);
                         }
                         "s" => {
-                            state.tool = Tool::Select;
+                            set_tool(state, Tool::Select);
                         }
                         "m" => {
-                            state.tool = Tool::Move;
+                            set_tool(state, Tool::Move);
+                        }
+                        "f" => {
+                            set_tool(state, Tool::Fill);
+                        }
+                        "w" => {
+                            set_tool(state, Tool::Wand);
                         }
                         "g" => {
                             state.show_grid_16 = !state.show_grid_16;
                         }
+                        "o" => {
+                            state.show_grid_8 = !state.show_grid_8;
+                        }
+                        "k" => {
+                            state.show_paint_heatmap = !state.show_paint_heatmap;
+                        }
+                        "x" => {
+                            return Ok(Some(Task::done(Message::SwapReferenceSelection)));
+                        }
                         "p" => {
                             state.snap_grid_16 = !state.snap_grid_16;
                         }
@@ -421,59 +514,146 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                         "a" => {
                             state.side_panel_view = SidePanelView::Area;
                         }
+                        "y" => {
+                            state.side_panel_view = SidePanelView::History;
+                        }
+                        "l" => {
+                            state.side_panel_view = SidePanelView::Palettes;
+                        }
+                        "j" => {
+                            state.side_panel_view = SidePanelView::Stamps;
+                        }
                         "h" => {
-                            for i in 0..state.selected_tile_block.size.1 as usize {
-                                state.selected_tile_block.palettes[i].reverse();
-                                state.selected_tile_block.tiles[i].reverse();
-                                state.selected_tile_block.flips[i].reverse();
-                                state.selected_gfx[i].reverse();
-                                for j in 0..state.selected_tile_block.size.0 as usize {
-                                    state.selected_tile_block.flips[i][j] =
-                                        state.selected_tile_block.flips[i][j].flip_horizontally();
-                                    state.selected_gfx[i][j] =
-                                        Flip::Horizontal.apply_to_tile(state.selected_gfx[i][j]);
-                                }
-                            }
+                            flip_selection_horizontally(state);
                         }
                         "v" => {
-                            state.selected_tile_block.palettes.reverse();
-                            state.selected_tile_block.tiles.reverse();
-                            state.selected_tile_block.flips.reverse();
-                            state.selected_gfx.reverse();
-                            for i in 0..state.selected_tile_block.size.1 as usize {
-                                for j in 0..state.selected_tile_block.size.0 as usize {
-                                    state.selected_tile_block.flips[i][j] =
-                                        state.selected_tile_block.flips[i][j].flip_vertically();
-                                    state.selected_gfx[i][j] =
-                                        Flip::Vertical.apply_to_tile(state.selected_gfx[i][j]);
+                            flip_selection_vertically(state);
+                        }
+                        "r" => {
+                            // Tiles can only be flipped, so a 180 degree turn is the only
+                            // rotation that can be expressed in the tilemap:
+                            flip_selection_horizontally(state);
+                            flip_selection_vertically(state);
+                        }
+                        "H" => {
+                            return Ok(Some(Task::done(Message::MirrorSelection {
+                                axis: MirrorAxis::Horizontal,
+                            })));
+                        }
+                        "V" => {
+                            return Ok(Some(Task::done(Message::MirrorSelection {
+                                axis: MirrorAxis::Vertical,
+                            })));
+                        }
+                        "F" => {
+                            // Cycle the stamp through the orientations in turn:
+                            // none -> horizontal -> both -> vertical -> none.
+                            match state.stamp_flip {
+                                Flip::None | Flip::Both => flip_selection_horizontally(state),
+                                Flip::Horizontal | Flip::Vertical => {
+                                    flip_selection_vertically(state)
                                 }
                             }
                         }
                         "-" => {
-                            state.global_config.pixel_size =
-                                (state.global_config.pixel_size - 1.0).max(MIN_PIXEL_SIZE);
+                            set_pixel_size(
+                                state,
+                                (state.global_config.pixel_size - 1.0).max(MIN_PIXEL_SIZE),
+                            );
                         }
                         "=" => {
-                            state.global_config.pixel_size =
-                                (state.global_config.pixel_size + 1.0).min(MAX_PIXEL_SIZE);
+                            set_pixel_size(
+                                state,
+                                (state.global_config.pixel_size + 1.0).min(MAX_PIXEL_SIZE),
+                            );
+                        }
+                        "`" => {
+                            state.compare_saved = true;
+                        }
+                        "[" => {
+                            return Ok(Some(Task::done(Message::SelectPaletteIdx(
+                                state.palette_idx.saturating_sub(1),
+                            ))));
+                        }
+                        "]" => {
+                            return Ok(Some(Task::done(Message::SelectPaletteIdx(
+                                state.palette_idx + 1,
+                            ))));
                         }
                         _ => {}
                     }
                 }
             }
+            Event::Keyboard(keyboard::Event::KeyReleased {
+                key: keyboard::Key::Character(c),
+                ..
+            }) if c.as_str() == "`" => {
+                state.compare_saved = false;
+            }
             _ => {}
         },
         &Message::Focus(focus) => {
             state.focus = focus;
         }
+        Message::AnimateSelection => {
+            // Advance by the configured number of pixels, wrapping at the length of the dash
+            // pattern:
+            state.marching_ants_phase =
+                (state.marching_ants_phase + state.global_config.selection_dash_speed) % 8;
+        }
+        Message::AnimateTiles => {
+            state.animation_frame = state.animation_frame.wrapping_add(1);
+        }
         Message::SaveProject => {
-            if *state.files_modified_notification.lock().unwrap() {
-                *state.files_modified_notification.lock().unwrap() = false;
-                state.dialogue = Some(Dialogue::ModifiedReload);
+            let modified_paths: Vec<PathBuf> = state
+                .files_modified_notification
+                .lock()
+                .unwrap()
+                .drain()
+                .collect();
+            if !modified_paths.is_empty() {
+                return Ok(Some(Task::done(Message::ExternalChange(modified_paths))));
+            }
+            if let Some(Dialogue::RecoverAutosave { .. }) = state.dialogue {
+                // Don't touch the project until the user decides what to do with the autosave.
+                return Ok(None);
+            }
+            remember_last_screen(state);
+            if state.global_config.autosave_to_shadow {
+                persist::autosave_project(state)?;
             } else {
                 persist::save_project(state)?;
             }
         }
+        Message::SaveProjectFiles => {
+            trim_modified_areas(state)?;
+            persist::save_project(state)?;
+            state.saved_areas = state
+                .areas
+                .iter()
+                .map(|(id, area)| (id.clone(), area.clone()))
+                .collect();
+        }
+        Message::RecoverAutosaveDialogue => {
+            if state.global_config.project_dir.is_none() {
+                return Ok(None);
+            }
+            let changes = persist::autosave_changes(state)?;
+            if changes.is_empty() {
+                // Nothing to recover, so just remove any stale autosave data:
+                persist::clear_autosave(state)?;
+                return Ok(None);
+            }
+            state.dialogue = Some(Dialogue::RecoverAutosave { changes });
+        }
+        Message::RecoverAutosave(changes) => {
+            persist::recover_autosave(state, changes)?;
+            state.dialogue = None;
+        }
+        Message::DiscardAutosave => {
+            persist::clear_autosave(state)?;
+            state.dialogue = None;
+        }
         Message::OpenProject => {
             return Ok(Some(Task::perform(open_project(), Message::ProjectOpened)));
         }
@@ -481,6 +661,37 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             persist::load_project(state)?;
             state.dialogue = None;
         }
+        Message::ExternalChange(paths) => {
+            let paths: Vec<&PathBuf> = paths
+                .iter()
+                .filter(|p| p.extension().is_some_and(|e| e == "json"))
+                .collect();
+            if paths.is_empty() {
+                return Ok(None);
+            }
+            // Reload the files that don't conflict with unsaved edits right away, keeping the
+            // conflicting ones for the user to decide on:
+            let mut conflicts = vec![];
+            for &path in &paths {
+                if external_change_conflicts(state, path)? {
+                    conflicts.push(path.clone());
+                } else {
+                    reload_external_change(state, path)?;
+                }
+            }
+            if !conflicts.is_empty() {
+                if let Some(Dialogue::ModifiedReload { paths }) = &mut state.dialogue {
+                    for path in conflicts {
+                        if !paths.contains(&path) {
+                            paths.push(path);
+                        }
+                    }
+                } else {
+                    // Don't clobber unsaved edits: let the user decide whether to reload.
+                    state.dialogue = Some(Dialogue::ModifiedReload { paths: conflicts });
+                }
+            }
+        }
         Message::RebuildProjectDialogue => {
             state.dialogue = Some(Dialogue::RebuildProject);
             return Ok(Some(Task::done(Message::RebuildProject)));
@@ -513,16 +724,52 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             state.dialogue = None;
         }
         &Message::WindowClose(id) => {
+            let confirm = match state.global_config.confirm_exit {
+                ConfirmExit::Never => false,
+                ConfirmExit::IfModified => state.has_unsaved_changes(),
+                ConfirmExit::Always => true,
+            };
+            if confirm && state.global_config.project_dir.is_some() {
+                state.dialogue = Some(Dialogue::ConfirmExit { window_id: id });
+                return Ok(None);
+            }
+            return Ok(Some(Task::done(Message::ConfirmWindowClose(id))));
+        }
+        &Message::ConfirmWindowClose(id) => {
+            trim_modified_areas(state)?;
+            if state.global_config.autosave_to_shadow {
+                // Keep the unsaved edits in the autosave (along with its lock), so that they
+                // are offered for recovery when the project is next opened:
+                persist::autosave_project(state)?;
+            } else {
+                persist::save_project(state)?;
+            }
+            return Ok(Some(window::close(id)));
+        }
+        &Message::SaveAndCloseWindow(id) => {
+            trim_modified_areas(state)?;
             persist::save_project(state)?;
             return Ok(Some(window::close(id)));
         }
+        &Message::DiscardAndCloseWindow(id) => {
+            persist::save_global_config(state)?;
+            if !state.read_only {
+                persist::clear_autosave(state)?;
+            }
+            return Ok(Some(window::close(id)));
+        }
         Message::ProjectOpened(path) => {
             match path {
                 Some(p) => {
                     info!("Opening project at {}", p.display());
                     // Ensure that the old project has been persisted before loading the new:
                     if state.global_config.project_dir.is_some() {
-                        persist::save_project(state)?;
+                        trim_modified_areas(state)?;
+                        if state.global_config.autosave_to_shadow {
+                            persist::autosave_project(state)?;
+                        } else {
+                            persist::save_project(state)?;
+                        }
                     }
 
                     // Update the global config to be set to the new project:
@@ -531,6 +778,10 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                     persist::save_global_config(state)?;
                     persist::load_project(state)?;
                     state.dialogue = None;
+                    return Ok(Some(Task::batch([
+                        scroll_to_last_screen(state),
+                        Task::done(Message::RecoverAutosaveDialogue),
+                    ])));
                 }
                 None => {
                     if state.global_config.project_dir.is_none() {
@@ -546,14 +797,422 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
         Message::HelpDialogue => {
             state.dialogue = Some(Dialogue::Help);
         }
+        Message::ProjectStatsDialogue => {
+            let stats = project_stats(state)?;
+            state.dialogue = Some(Dialogue::ProjectStats { stats });
+        }
         &Message::SetPixelSize(pixel_size) => {
-            state.global_config.pixel_size = pixel_size;
+            set_pixel_size(state, pixel_size);
+        }
+        &Message::SetPerAreaZoom(enabled) => {
+            state.global_config.per_area_zoom = enabled;
+            if enabled {
+                let pixel_size = state.global_config.pixel_size;
+                set_pixel_size(state, pixel_size);
+            }
             state.global_config.modified = true;
         }
         &Message::SetGridAlpha(grid_alpha) => {
             state.global_config.grid_alpha = grid_alpha;
             state.global_config.modified = true;
         }
+        &Message::SetHighlightColor(highlight_color) => {
+            state.global_config.highlight_color = highlight_color;
+            state.global_config.modified = true;
+        }
+        &Message::SetHighlightAlpha(highlight_alpha) => {
+            state.global_config.highlight_alpha = highlight_alpha;
+            state.global_config.modified = true;
+        }
+        &Message::SetGridColor(grid_color) => {
+            state.global_config.grid_color = grid_color;
+            state.global_config.modified = true;
+        }
+        &Message::SetMajorGridInterval(interval) => {
+            state.global_config.major_grid_interval = interval.min(MAX_MAJOR_GRID_INTERVAL);
+            state.global_config.modified = true;
+        }
+        &Message::SetMajorGridColor(color) => {
+            state.global_config.major_grid_color = color;
+            state.global_config.modified = true;
+        }
+        &Message::SetMajorGridAlpha(alpha) => {
+            state.global_config.major_grid_alpha = alpha;
+            state.global_config.modified = true;
+        }
+        &Message::SetSelectionDashColor(i, color) => {
+            let Some(c) = state.global_config.selection_dash_colors.get_mut(i) else {
+                return Ok(None);
+            };
+            *c = color;
+            state.global_config.modified = true;
+        }
+        &Message::SetSelectionDashSpeed(speed) => {
+            state.global_config.selection_dash_speed = speed.min(MAX_SELECTION_DASH_SPEED);
+            state.global_config.modified = true;
+        }
+        &Message::SetAutosaveToShadow(autosave_to_shadow) => {
+            state.global_config.autosave_to_shadow = autosave_to_shadow;
+            state.global_config.modified = true;
+            if !autosave_to_shadow {
+                // Persist the pending edits, which also clears the autosave directory:
+                persist::save_project(state)?;
+            }
+        }
+        &Message::SetLinearSelect(linear_select) => {
+            state.global_config.linear_select = linear_select;
+            state.global_config.modified = true;
+        }
+        &Message::SetFillConfirmThreshold(threshold) => {
+            state.global_config.fill_confirm_threshold = threshold.min(MAX_FILL_CONFIRM_THRESHOLD);
+            state.global_config.modified = true;
+        }
+        &Message::SetBrushOpacity(opacity) => {
+            state.global_config.brush_opacity = opacity.clamp(0.01, 1.0);
+            state.global_config.modified = true;
+        }
+        &Message::SetTilesetBrushPalette(tileset_brush_palette) => {
+            state.global_config.tileset_brush_palette = tileset_brush_palette;
+            state.global_config.modified = true;
+        }
+        &Message::SetMaxUndoLevels(levels) => {
+            state.global_config.max_undo_levels = levels.max(1);
+            state.global_config.modified = true;
+            trim_undo_stack(state);
+        }
+        &Message::SetUndoMemoryMb(mb) => {
+            state.global_config.undo_memory_mb = mb.max(1);
+            state.global_config.modified = true;
+            trim_undo_stack(state);
+        }
+        &Message::SetDisplayHex(display_hex) => {
+            state.global_config.display_hex = display_hex;
+            state.global_config.modified = true;
+        }
+        &Message::SetTrimEmptyEdgesOnSave(trim) => {
+            state.global_config.trim_empty_edges_on_save = trim;
+            state.global_config.modified = true;
+        }
+        &Message::SetPlayAnimations(play) => {
+            state.global_config.play_animations = play;
+            state.global_config.modified = true;
+        }
+        &Message::SetImportAnimatedTiles(animated_tiles) => {
+            state.global_config.import_options.animated_tiles = animated_tiles;
+            state.global_config.modified = true;
+        }
+        &Message::SetTilePreviewZoom(zoom) => {
+            state.global_config.tile_preview_zoom = zoom.min(MAX_TILE_PREVIEW_ZOOM);
+            state.global_config.modified = true;
+        }
+        &Message::SetPixelGridMinSize(size) => {
+            state.global_config.pixel_grid_min_size = size.min(MAX_PIXEL_GRID_MIN_SIZE);
+            state.global_config.modified = true;
+        }
+        &Message::SetAutoAdvanceColor(auto_advance_color) => {
+            state.global_config.auto_advance_color = auto_advance_color;
+            state.global_config.modified = true;
+        }
+        &Message::SetKeepSelectionOnToolSwitch(keep) => {
+            state.global_config.keep_selection_on_tool_switch = keep;
+            state.global_config.modified = true;
+        }
+        &Message::SetDoubleClickRename(double_click_rename) => {
+            state.global_config.double_click_rename = double_click_rename;
+            state.global_config.modified = true;
+        }
+        &Message::SetTileHoverHighlight(tile_hover_highlight) => {
+            state.global_config.tile_hover_highlight = tile_hover_highlight;
+            state.global_config.modified = true;
+        }
+        &Message::SetContactSheetThumbnailSize(size) => {
+            state.global_config.contact_sheet_thumbnail_size =
+                size.clamp(MIN_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE);
+            state.global_config.modified = true;
+        }
+        &Message::SetContactSheetColumns(columns) => {
+            state.global_config.contact_sheet_columns = columns.clamp(1, MAX_CONTACT_SHEET_COLUMNS);
+            state.global_config.modified = true;
+        }
+        &Message::SetColorProfile(profile) => {
+            state.global_config.color_profile = profile;
+            state.global_config.modified = true;
+        }
+        &Message::SetPreviewFilter(filter) => {
+            state.global_config.preview_filter = filter;
+            state.global_config.modified = true;
+        }
+        &Message::SetAreaMargin(margin) => {
+            state.global_config.area_margin = margin.min(MAX_AREA_MARGIN);
+            state.global_config.modified = true;
+        }
+        &Message::SetCenterArea(center) => {
+            state.global_config.center_area = center;
+            state.global_config.modified = true;
+        }
+        &Message::SetConfirmExit(confirm_exit) => {
+            state.global_config.confirm_exit = confirm_exit;
+            state.global_config.modified = true;
+        }
+        &Message::SetColorLimitPolicy(policy) => {
+            state.global_config.color_limit_policy = policy;
+            state.global_config.modified = true;
+        }
+        &Message::SetColorMatchPolicy(policy) => {
+            state.global_config.color_match_policy = policy;
+            state.global_config.modified = true;
+        }
+        &Message::SetStartupTool(startup_tool) => {
+            state.global_config.startup_tool = startup_tool;
+            state.global_config.modified = true;
+        }
+        &Message::SetPaletteFileNaming(naming) => {
+            let old_naming = state.global_config.palette_file_naming;
+            if naming == old_naming {
+                return Ok(None);
+            }
+            if state.read_only {
+                warn!("Palette files can't be renamed in a read-only project.");
+                return Ok(None);
+            }
+            // Save first, so that no pending autosave is left under the old file names:
+            persist::save_project(state)?;
+            state.global_config.palette_file_naming = naming;
+            state.global_config.modified = true;
+            persist::rename_palette_files(state, old_naming)?;
+        }
+        &Message::SetMouseButtons(mouse_buttons) => {
+            state.global_config.mouse_buttons = mouse_buttons;
+            state.global_config.modified = true;
+        }
+        &Message::SetPaletteLayout(layout) => {
+            state.global_config.palette_layout = layout;
+            state.global_config.modified = true;
+        }
+        &Message::SetLineBrushModifier(modifier) => {
+            state.global_config.line_brush_modifier = modifier;
+            state.global_config.modified = true;
+        }
+        &Message::SetOutOfBoundsMove(mode) => {
+            state.global_config.out_of_bounds_move = mode;
+            state.global_config.modified = true;
+        }
+        &Message::SetMoveSnap(snap) => {
+            state.global_config.move_snap = snap;
+            state.global_config.modified = true;
+        }
+        &Message::SetMoveSnapStep(step) => {
+            state.global_config.move_snap_step = step.clamp(1, MAX_MOVE_SNAP_STEP);
+            state.global_config.modified = true;
+        }
+        &Message::SetSelectionSnapStep(step) => {
+            state.global_config.selection_snap_step = step.clamp(1, MAX_SELECTION_SNAP_STEP);
+            state.global_config.modified = true;
+        }
+        &Message::SetTilesetBackdrop(backdrop) => {
+            state.global_config.tileset_backdrop = backdrop;
+            state.global_config.modified = true;
+        }
+        &Message::SetTilesetBackdropColor(color) => {
+            state.global_config.tileset_backdrop_color = color;
+            state.global_config.modified = true;
+        }
+        Message::ExportContactSheetDialogue => {
+            return Ok(Some(Task::perform(
+                save_contact_sheet(),
+                Message::ExportContactSheet,
+            )));
+        }
+        Message::ExportContactSheet(path) => {
+            if let Some(path) = path {
+                export_contact_sheet(state, path)?;
+            }
+        }
+        Message::ExportPalettesBinDialogue => {
+            return Ok(Some(Task::perform(
+                save_palettes_bin(),
+                Message::ExportPalettesBin,
+            )));
+        }
+        Message::ExportPalettesBin(path) => {
+            if let Some(path) = path {
+                export_all_palettes_bin(&state.palettes, path)?;
+            }
+        }
+        Message::ExportPaletteJsonDialogue => {
+            let name = state.palettes[state.palette_idx].name.clone();
+            return Ok(Some(Task::perform(
+                save_palette_colors(name),
+                Message::ExportPaletteJson,
+            )));
+        }
+        Message::ExportPaletteJson(path) => {
+            if let Some(path) = path {
+                export_palette_colors(&state.palettes[state.palette_idx], path)?;
+            }
+        }
+        Message::ExportPaletteChrDialogue => {
+            let name = state.palettes[state.palette_idx].name.clone();
+            return Ok(Some(Task::perform(
+                save_palette_chr(name),
+                Message::ExportPaletteChr,
+            )));
+        }
+        Message::ExportPaletteChr(path) => {
+            if let Some(path) = path {
+                let pal = &state.palettes[state.palette_idx];
+                let pal_path = export_chr(pal, path)?;
+                info!(
+                    "Exported {} tiles of palette {} to {} and {}",
+                    pal.tiles.len(),
+                    pal.name,
+                    path.display(),
+                    pal_path.display()
+                );
+            }
+        }
+        Message::ExportSelectionTilesetDialogue => {
+            if state.selected_gfx.is_empty() {
+                warn!("No tiles selected to export.");
+                return Ok(None);
+            }
+            return Ok(Some(Task::perform(
+                save_selection_tileset(),
+                Message::ExportSelectionTileset,
+            )));
+        }
+        Message::ExportSelectionTileset(path) => {
+            let Some(path) = path else {
+                return Ok(None);
+            };
+            let block = &state.selected_tile_block;
+            let mut tiles: Vec<Vec<Tile>> = vec![];
+            let mut tile_colors: Vec<Vec<[ColorRGB; 16]>> = vec![];
+            for (y, gfx_row) in state.selected_gfx.iter().enumerate() {
+                let mut row = vec![];
+                let mut colors_row = vec![];
+                for (x, &tile) in gfx_row.iter().enumerate() {
+                    let palette_id = block.palettes[y][x];
+                    let colors = state
+                        .palettes_id_idx_map
+                        .get(&palette_id)
+                        .map_or([[0; 3]; 16], |&i| state.palettes[i].colors);
+                    if block.is_selected(x as TileCoord, y as TileCoord) {
+                        row.push(tile);
+                    } else {
+                        row.push(Tile::default());
+                    }
+                    colors_row.push(colors);
+                }
+                tiles.push(row);
+                tile_colors.push(colors_row);
+            }
+            let first_palette_id = block.palettes[0][0];
+            if block
+                .palettes
+                .iter()
+                .flatten()
+                .any(|&p| p != first_palette_id)
+            {
+                warn!(
+                    "The selection uses multiple palettes; the exported JSON includes the colors of palette {}.",
+                    first_palette_id
+                );
+            }
+            let json_path =
+                export_selection_tileset(path, &tiles, &tile_colors, tile_colors[0][0])?;
+            info!(
+                "Exported {}x{} tiles to {} and {}",
+                block.size.0,
+                block.size.1,
+                path.display(),
+                json_path.display()
+            );
+        }
+        Message::ImportPalettesBinDialogue => {
+            return Ok(Some(Task::perform(
+                open_palettes_bin(),
+                Message::ImportPalettesBin,
+            )));
+        }
+        Message::ImportPalettesBin(path) => {
+            let Some(path) = path else {
+                return Ok(None);
+            };
+            let first_id = state
+                .palettes
+                .iter()
+                .map(|x| x.id)
+                .max()
+                .unwrap()
+                .checked_add(1)
+                .context("No palette IDs are left after the highest palette ID.")?;
+            let palettes = import_palettes_bin(path, first_id)?;
+            for pal in &palettes {
+                if state.palettes.iter().any(|p| p.name == pal.name) {
+                    warn!("Palette name {} already exists.", pal.name);
+                    return Ok(None);
+                }
+            }
+            info!(
+                "Imported {} palettes (IDs {} and up) from {}",
+                palettes.len(),
+                first_id,
+                path.display()
+            );
+            state.palettes.extend(palettes);
+            update_palette_order(state);
+        }
+        Message::ExportProjectArchiveDialogue => {
+            return Ok(Some(Task::perform(
+                save_project_archive(),
+                Message::ExportProjectArchive,
+            )));
+        }
+        Message::ExportProjectArchive(path) => {
+            if let Some(path) = path {
+                export_project_archive(state, path)?;
+            }
+        }
+        Message::ImportProjectArchiveDialogue => {
+            return Ok(Some(Task::perform(
+                open_project_archive(),
+                Message::ImportProjectArchive,
+            )));
+        }
+        Message::ImportProjectArchive(path) => {
+            if let Some(archive) = path {
+                let archive = archive.clone();
+                return Ok(Some(Task::perform(
+                    pick_archive_destination(),
+                    move |dir| Message::ImportProjectArchiveTo {
+                        archive: archive.clone(),
+                        dir,
+                    },
+                )));
+            }
+        }
+        Message::ImportProjectArchiveTo { archive, dir } => {
+            let Some(dir) = dir else {
+                return Ok(None);
+            };
+            let report = match import_project_archive(archive, dir) {
+                Ok(report) => report,
+                Err(e) => {
+                    warn!("Unable to import project archive: {}", e);
+                    return Ok(None);
+                }
+            };
+            return Ok(Some(
+                Task::done(Message::ProjectOpened(Some(dir.clone())))
+                    .chain(Task::done(Message::ProjectArchiveReport(report))),
+            ));
+        }
+        Message::ProjectArchiveReport(report) => {
+            state.dialogue = Some(Dialogue::ProjectArchiveReport {
+                report: report.clone(),
+            });
+        }
         Message::CloseDialogue => {
             state.dialogue = None;
         }
@@ -577,23 +1236,107 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             Importer::import(state, &path.clone())?;
             state.dialogue = None;
         }
-        Message::SelectPalette(name) => {
-            for i in 0..state.palettes.len() {
-                if name == &format!("{}: {}", state.palettes[i].id, state.palettes[i].name) {
-                    state.palette_idx = i;
-                    state.color_idx = None;
-                    state.tile_idx = None;
-                    break;
-                }
-            }
+        Message::DiffAgainstRomDialogue => {
+            return Ok(Some(Task::perform(open_rom(), Message::DiffAgainstRom)));
         }
-        Message::AddPaletteDialogue => {
-            let id = state.palettes.iter().map(|x| x.id).max().unwrap() + 1;
-            state.dialogue = Some(Dialogue::AddPalette {
-                name: "".to_string(),
-                id,
-            });
-            return Ok(Some(iced::widget::text_input::focus("AddPalette")));
+        Message::DiffAgainstRom(path) => {
+            let Some(path) = path else {
+                return Ok(None);
+            };
+            state.dialogue = Some(Dialogue::RomDiffProgress);
+            return Ok(Some(Task::done(Message::RunRomDiff(path.clone()))));
+        }
+        Message::RunRomDiff(path) => {
+            // Close the progress dialogue even if the comparison fails:
+            state.dialogue = None;
+            let report = diff_against_rom(state, path)?;
+            info!("{}", report);
+            state.dialogue = Some(Dialogue::RomDiffReport { report });
+        }
+        &Message::ImportChr(palette_id) => {
+            return Ok(Some(Task::perform(open_chr(), move |path| {
+                Message::ImportChrFile { palette_id, path }
+            })));
+        }
+        Message::ImportChrFile { palette_id, path } => {
+            let Some(path) = path else {
+                return Ok(None);
+            };
+            let tiles = import_chr(path)?;
+            let palette_idx = *state
+                .palettes_id_idx_map
+                .get(palette_id)
+                .context("palette not found")?;
+            let pal = &mut state.palettes[palette_idx];
+            // Replace the graphics of the palette's first tiles, keeping their other attributes
+            // (as with a PNG import), and add rows of tiles as needed:
+            let new_len = tiles.len().div_ceil(16).max(1) * 16;
+            pal.tiles
+                .resize(new_len.max(pal.tiles.len()), Tile::default());
+            for (i, tile) in tiles.iter().enumerate() {
+                pal.tiles[i].pixels = tile.pixels;
+            }
+            pal.modified = true;
+            info!(
+                "Imported {} tiles into palette {} from {}",
+                tiles.len(),
+                palette_id,
+                path.display()
+            );
+        }
+        Message::ImportTilesetPNGDialogue => {
+            return Ok(Some(Task::perform(
+                open_png(),
+                Message::ImportTilesetPNGConfirm,
+            )));
+        }
+        Message::ImportTilesetPNGConfirm(path) => {
+            if let Some(path) = path {
+                state.dialogue = Some(Dialogue::ImportTilesetPNG {
+                    path: path.clone(),
+                    quantize: false,
+                });
+            }
+        }
+        Message::SetImportTilesetPNGQuantize(q) => {
+            if let Some(Dialogue::ImportTilesetPNG { quantize, .. }) = &mut state.dialogue {
+                *quantize = *q;
+            }
+        }
+        Message::ImportTilesetPNG {
+            palette_id,
+            path,
+            quantize,
+        } => {
+            let report = import_tileset_png(state, *palette_id, path, *quantize)?;
+            info!("{}", report);
+            state.dialogue = Some(Dialogue::ImportTilesetPNGReport { report });
+        }
+        &Message::SelectPaletteIdx(idx) => {
+            let idx = idx.min(state.palettes.len() - 1);
+            if idx != state.palette_idx {
+                state.palette_idx = idx;
+                state.color_idx = None;
+                state.tile_idx = None;
+            }
+        }
+        Message::SelectPalette(name) => {
+            for i in 0..state.palettes.len() {
+                if name == &palette_label(&state.palettes[i], state.global_config.display_hex) {
+                    state.palette_idx = i;
+                    state.color_idx = None;
+                    state.tile_idx = None;
+                    break;
+                }
+            }
+        }
+        Message::AddPaletteDialogue => {
+            let id = state.palettes.iter().map(|x| x.id).max().unwrap() + 1;
+            state.dialogue = Some(Dialogue::AddPalette {
+                name: "".to_string(),
+                id,
+            });
+            return Ok(Some(iced::widget::text_input::focus("AddPalette")));
         }
         Message::SetAddPaletteName(new_name) => match &mut state.dialogue {
             Some(Dialogue::AddPalette { name, .. }) => {
@@ -663,12 +1406,187 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             state.palettes[state.palette_idx].name = name.clone();
             state.palettes[state.palette_idx].modified = true;
             persist::save_project(state)?;
-            delete_palette(state, &old_name)?;
+            delete_palette(state, state.palettes[state.palette_idx].id, &old_name)?;
             update_palette_order(state);
             state.dialogue = None;
         }
         Message::DeletePaletteDialogue => {
-            state.dialogue = Some(Dialogue::DeletePalette);
+            let id = state.palettes[state.palette_idx].id;
+            let references = find_palette_references(state, id)?;
+            let remap_to = state.palettes.iter().map(|p| p.id).find(|&p| p != id);
+            state.dialogue = Some(Dialogue::DeletePalette {
+                references,
+                remap_to,
+            });
+        }
+        Message::SetDeletePaletteRemapTo(name) => {
+            let hex = state.global_config.display_hex;
+            let id = state
+                .palettes
+                .iter()
+                .find(|p| &palette_label(p, hex) == name)
+                .map(|p| p.id);
+            if let Some(Dialogue::DeletePalette { remap_to, .. }) = &mut state.dialogue {
+                *remap_to = id;
+            }
+        }
+        &Message::RemapPalette { from, to } => {
+            let (Some(&from_idx), Some(&to_idx)) = (
+                state.palettes_id_idx_map.get(&from),
+                state.palettes_id_idx_map.get(&to),
+            ) else {
+                warn!("Palette not found.");
+                return Ok(None);
+            };
+            if from == to {
+                return Ok(None);
+            }
+            let num_tiles = state.palettes[from_idx].tiles.len();
+            if state.palettes[to_idx].tiles.len() < num_tiles {
+                warn!(
+                    "Palette {} has fewer tiles than palette {}; some remapped tiles will be out of range.",
+                    to, from
+                );
+            }
+            // Areas are remapped on disk, so first save any unsaved edits to the loaded areas:
+            for area_id in state.areas.keys().cloned().collect::<Vec<_>>() {
+                persist::save_area(state, &area_id)?;
+            }
+            let mapping: HashMap<(PaletteId, TileIdx), (PaletteId, TileIdx, Flip)> = (0..num_tiles
+                as TileIdx)
+                .map(|t| ((from, t), (to, t, Flip::None)))
+                .collect();
+            remap_tiles(state, &mapping)?;
+            return Ok(Some(Task::done(Message::DeletePaletteDialogue)));
+        }
+        Message::RemapPaletteInRegionDialogue => {
+            state.dialogue = Some(Dialogue::RemapPaletteInRegion {
+                from: None,
+                to: None,
+            });
+        }
+        Message::SetRemapRegionFrom(name) | Message::SetRemapRegionTo(name) => {
+            let hex = state.global_config.display_hex;
+            let id = state
+                .palettes
+                .iter()
+                .find(|p| &palette_label(p, hex) == name)
+                .map(|p| p.id);
+            if let Some(Dialogue::RemapPaletteInRegion { from, to }) = &mut state.dialogue {
+                if let Message::SetRemapRegionFrom(_) = message {
+                    *from = id;
+                } else {
+                    *to = id;
+                }
+            }
+        }
+        Message::ReplacePaletteReferencesDialogue => {
+            state.dialogue = Some(Dialogue::ReplacePaletteReferences {
+                from: None,
+                to: None,
+                from_references: None,
+                to_in_use: false,
+            });
+        }
+        Message::SetReplaceReferencesFrom(name) | Message::SetReplaceReferencesTo(name) => {
+            let hex = state.global_config.display_hex;
+            let Some(id) = state
+                .palettes
+                .iter()
+                .find(|p| &palette_label(p, hex) == name)
+                .map(|p| p.id)
+            else {
+                return Ok(None);
+            };
+            let (num_cells, num_areas) = count_palette_references(state, id)?;
+            if let Some(Dialogue::ReplacePaletteReferences {
+                from,
+                to,
+                from_references,
+                to_in_use,
+            }) = &mut state.dialogue
+            {
+                if let Message::SetReplaceReferencesFrom(_) = message {
+                    *from = Some(id);
+                    *from_references = Some((num_cells, num_areas));
+                } else {
+                    *to = Some(id);
+                    *to_in_use = num_cells > 0;
+                }
+            }
+        }
+        &Message::ReplacePaletteReferences { from, to } => {
+            if !state.palettes_id_idx_map.contains_key(&to) {
+                warn!("Palette {} not found.", to);
+                return Ok(None);
+            }
+            if from == to {
+                return Ok(None);
+            }
+            let (num_cells, num_areas) = replace_palette_references(state, from, to)?;
+            info!(
+                "Replaced palette {} with palette {} in {} cells of {} areas.",
+                from, to, num_cells, num_areas
+            );
+            state.dialogue = None;
+        }
+        &Message::RemapPaletteInRegion { from, to } => {
+            if !state.palettes_id_idx_map.contains_key(&to) {
+                warn!("Palette {} not found.", to);
+                return Ok(None);
+            }
+            let area = state.main_area();
+            let (left, top, mut block) = match (state.selection_source, state.selection_coords) {
+                (SelectionSource::Area(AreaPosition::Main), Some((x, y))) => {
+                    (x, y, state.selected_tile_block.clone())
+                }
+                _ => {
+                    let size = (area.size.0 as TileCoord * 32, area.size.1 as TileCoord * 32);
+                    (
+                        0,
+                        0,
+                        TileBlock {
+                            size,
+                            palettes: vec![vec![0; size.0 as usize]; size.1 as usize],
+                            tiles: vec![vec![0; size.0 as usize]; size.1 as usize],
+                            flips: vec![vec![Flip::None; size.0 as usize]; size.1 as usize],
+                            mask: None,
+                        },
+                    )
+                }
+            };
+            // Only brush the cells which currently use palette `from`:
+            let mut mask = vec![vec![false; block.size.0 as usize]; block.size.1 as usize];
+            let mut count = 0;
+            for y in 0..block.size.1 {
+                for x in 0..block.size.0 {
+                    let (xu, yu) = (x as usize, y as usize);
+                    if !block.is_selected(x, y) {
+                        continue;
+                    }
+                    if let Ok(palette_id) = area.get_palette(left + x, top + y) {
+                        if palette_id == from {
+                            block.palettes[yu][xu] = to;
+                            mask[yu][xu] = true;
+                            count += 1;
+                        }
+                    }
+                }
+            }
+            block.mask = Some(Box::new(mask));
+            state.dialogue = None;
+            if count == 0 {
+                info!("No cells in the region use palette {}.", from);
+                return Ok(None);
+            }
+            info!("Changed {} cells from palette {} to {}.", count, from, to);
+            return Ok(Some(Task::done(Message::AreaBrush {
+                position: AreaPosition::Main,
+                area_id: state.main_area_id.clone(),
+                coords: Point { x: left, y: top },
+                selection: block,
+                palette_only: true,
+            })));
         }
         &Message::DeletePalette(id) => {
             if state.palettes.len() == 1 {
@@ -680,13 +1598,26 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                 .palettes_id_idx_map
                 .get(&id)
                 .context("palette not found")?;
+            let references = find_palette_references(state, id)?;
+            if !references.is_empty() {
+                warn!(
+                    "Palette {} is still used in {} area(s), which will show broken tiles.",
+                    id,
+                    references.len()
+                );
+            }
             let name = state.palettes[palette_idx].name.clone();
-            persist::delete_palette(state, &name)?;
-            if state.palette_idx < state.palettes.len() {
-                state.palettes.remove(palette_idx);
-                if state.palette_idx == state.palettes.len() {
-                    state.palette_idx -= 1;
-                }
+            persist::delete_palette(state, id, &name)?;
+            state.palettes.remove(palette_idx);
+            ensure_palettes_non_empty(state);
+            // Keep the same palette selected, if it wasn't the deleted one:
+            if state.palette_idx > palette_idx {
+                state.palette_idx -= 1;
+            }
+            state.palette_idx = state.palette_idx.min(state.palettes.len() - 1);
+            state.selected_palette_ids.retain(|&p| p != id);
+            if state.preview_palette_id == Some(id) {
+                state.preview_palette_id = None;
             }
             update_palette_order(state);
             state.tile_idx = None;
@@ -702,9 +1633,106 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             state.color_idx = None;
             update_palette_order(state);
         }
+        Message::MergePalettesDialogue => {
+            state.dialogue = Some(Dialogue::MergePalettes {
+                require_identical_tiles: true,
+                groups: find_duplicate_palettes(state, true),
+            });
+        }
+        &Message::SetMergePalettesIdenticalTiles(require_identical_tiles) => {
+            state.dialogue = Some(Dialogue::MergePalettes {
+                require_identical_tiles,
+                groups: find_duplicate_palettes(state, require_identical_tiles),
+            });
+        }
+        Message::MergePalettes(groups) => {
+            let mut mapping: HashMap<(PaletteId, TileIdx), (PaletteId, TileIdx, Flip)> =
+                HashMap::new();
+            for group in groups {
+                let survivor_id = group[0];
+                let survivor_idx = *state
+                    .palettes_id_idx_map
+                    .get(&survivor_id)
+                    .context("palette not found")?;
+                for &dup_id in &group[1..] {
+                    let dup_idx = *state
+                        .palettes_id_idx_map
+                        .get(&dup_id)
+                        .context("palette not found")?;
+                    let dup_tiles = state.palettes[dup_idx].tiles.clone();
+                    let survivor = &mut state.palettes[survivor_idx];
+                    for (t, tile) in dup_tiles.iter().enumerate() {
+                        // Reuse an identical tile in the surviving palette if there is one,
+                        // otherwise carry the tile over:
+                        let new_t = match survivor.tiles.iter().position(|x| same_tile(x, tile)) {
+                            Some(i) => i,
+                            None => {
+                                survivor.tiles.push(*tile);
+                                survivor.tiles.len() - 1
+                            }
+                        };
+                        mapping.insert(
+                            (dup_id, t as TileIdx),
+                            (survivor_id, new_t as TileIdx, Flip::None),
+                        );
+                    }
+                    let new_len = survivor.tiles.len().div_ceil(16) * 16;
+                    survivor.tiles.resize(new_len, Tile::default());
+                    survivor.modified = true;
+                }
+            }
+
+            // Update the references to the duplicate palettes, then delete them:
+            remap_tiles(state, &mapping)?;
+            for group in groups {
+                for dup_id in &group[1..] {
+                    let dup_idx = *state
+                        .palettes_id_idx_map
+                        .get(dup_id)
+                        .context("palette not found")?;
+                    let name = state.palettes[dup_idx].name.clone();
+                    info!("Merging palette {} into palette {}", dup_id, group[0]);
+                    persist::delete_palette(state, *dup_id, &name)?;
+                    state.palettes.remove(dup_idx);
+                    state.palette_idx = state.palette_idx.min(state.palettes.len() - 1);
+                    update_palette_order(state);
+                }
+            }
+            state.tile_idx = None;
+            state.color_idx = None;
+            state.dialogue = None;
+        }
         Message::HideModal => {
             state.dialogue = None;
         }
+        &Message::SetSidePanelView(view) => {
+            state.side_panel_view = view;
+        }
+        Message::JumpToHistory(_) => {
+            // Handled in `update`, along with undo and redo.
+        }
+        &Message::SetTilesetSortByUsage(sort) => {
+            if sort {
+                state.tile_use_counts = count_tile_uses(state)?;
+            }
+            state.tileset_sort_by_usage = sort;
+            // Tileset selections are in display coordinates, so they don't carry over:
+            state.reference_coords = None;
+            if state.selection_source == SelectionSource::Tileset {
+                match state.tile_idx {
+                    Some(idx) => select_tileset_tile(state, idx)?,
+                    None => {
+                        state.start_coords = None;
+                        state.end_coords = None;
+                    }
+                }
+            }
+        }
+        &Message::ToggleIdentifyColor(color_idx) => {
+            if !state.pinned_identify_colors.remove(&color_idx) {
+                state.pinned_identify_colors.insert(color_idx);
+            }
+        }
         &Message::SelectColor(pal_idx, color_idx) => {
             if pal_idx != state.palette_idx {
                 state.tile_idx = None;
@@ -728,6 +1756,79 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             state.palettes[pal_idx].colors[color_idx as usize] = color;
             state.palettes[pal_idx].modified = true;
         }
+        &Message::ClickPaletteName(id) => {
+            let Some(&idx) = state.palettes_id_idx_map.get(&id) else {
+                return Ok(None);
+            };
+            if state.modifiers.control() {
+                if let Some(i) = state.selected_palette_ids.iter().position(|&x| x == id) {
+                    state.selected_palette_ids.remove(i);
+                } else {
+                    state.selected_palette_ids.push(id);
+                }
+            } else if state.modifiers.shift() && !state.selected_palette_ids.is_empty() {
+                // Extend the selection over the range of used palettes from the last clicked one:
+                let anchor = *state.selected_palette_ids.last().unwrap();
+                let (lo, hi) = (anchor.min(id), anchor.max(id));
+                for pal_id in state.main_area().get_unique_palettes() {
+                    if pal_id >= lo
+                        && pal_id <= hi
+                        && state.palettes_id_idx_map.contains_key(&pal_id)
+                        && !state.selected_palette_ids.contains(&pal_id)
+                    {
+                        state.selected_palette_ids.push(pal_id);
+                    }
+                }
+                // Keep the clicked palette as the anchor for further range selections:
+                state.selected_palette_ids.retain(|&x| x != id);
+                state.selected_palette_ids.push(id);
+            } else {
+                state.selected_palette_ids = vec![id];
+                if idx != state.palette_idx {
+                    state.palette_idx = idx;
+                    state.color_idx = None;
+                    state.tile_idx = None;
+                }
+                let now = Instant::now();
+                let double_click = matches!(state.last_palette_name_click,
+                    Some((last_id, t)) if last_id == id && now - t < DOUBLE_CLICK_INTERVAL);
+                if double_click && state.global_config.double_click_rename {
+                    state.last_palette_name_click = None;
+                    return Ok(Some(Task::done(Message::RenamePaletteDialogue)));
+                }
+                state.last_palette_name_click = Some((id, now));
+            }
+        }
+        Message::ClearPaletteSelection => {
+            state.selected_palette_ids.clear();
+        }
+        Message::AdjustPalettesBrightness { palette_ids, delta } => {
+            let mut colors = vec![];
+            for &id in palette_ids {
+                let idx = *state
+                    .palettes_id_idx_map
+                    .get(&id)
+                    .context("palette not found")?;
+                let new_colors = state.palettes[idx]
+                    .colors
+                    .map(|c| c.map(|x| (x as i8 + delta).clamp(0, 31) as ColorValue));
+                colors.push((id, new_colors));
+            }
+            return Ok(Some(Task::done(Message::SetPalettesColors(colors))));
+        }
+        Message::SetPalettesColors(colors) => {
+            for &(id, new_colors) in colors {
+                let idx = *state
+                    .palettes_id_idx_map
+                    .get(&id)
+                    .context("palette not found")?;
+                state.palettes[idx].colors = new_colors;
+                state.palettes[idx].modified = true;
+            }
+            if let Some(color_idx) = state.color_idx {
+                state.selected_color = state.palettes[state.palette_idx].colors[color_idx as usize];
+            }
+        }
         &Message::ChangeRed(c) => {
             if let Some(color_idx) = state.color_idx {
                 let pal_idx = state.palette_idx;
@@ -764,6 +1865,15 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                 })));
             }
         }
+        Message::SelectPreviewPalette(name) => {
+            state.preview_palette_id = None;
+            for pal in &state.palettes {
+                if name == &palette_label(pal, state.global_config.display_hex) {
+                    state.preview_palette_id = Some(pal.id);
+                    break;
+                }
+            }
+        }
         Message::AddTileRow(palette_id) => {
             let idx = *state
                 .palettes_id_idx_map
@@ -781,23 +1891,324 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                 warn!("Not allowed to delete the last row of tiles.");
                 return Ok(None);
             }
-            let new_size = state.palettes[state.palette_idx].tiles.len() - 16;
-            state.palettes[state.palette_idx]
+            let new_size = state.palettes[idx].tiles.len() - 16;
+            if state.palettes[idx].animates_any(new_size as TileIdx..TileIdx::MAX) {
+                warn!("Not deleting the tile row: its tiles are used in tile animations.");
+                return Ok(None);
+            }
+            let pal = &mut state.palettes[idx];
+            pal.tiles.resize(new_size, Tile::default());
+            pal.tags.retain(|&t, _| (t as usize) < new_size);
+            pal.modified = true;
+            if idx == state.palette_idx {
+                if let Some(tile_idx) = state.tile_idx {
+                    if tile_idx >= new_size as TileIdx {
+                        state.tile_idx = Some(new_size as TileIdx - 1);
+                    }
+                }
+                // Replace a tileset selection that includes deleted tiles by the selected tile:
+                let block = &state.selected_tile_block;
+                let stale = state.selection_source == SelectionSource::Tileset
+                    && (0..block.size.1 as usize).any(|y| {
+                        (0..block.size.0 as usize).any(|x| {
+                            block.palettes[y][x] == *palette_id
+                                && block.tiles[y][x] >= new_size as TileIdx
+                        })
+                    });
+                if stale {
+                    state.start_coords = None;
+                    state.selection_coords = None;
+                    state.copy_coords = None;
+                    state.end_coords = None;
+                    match state.tile_idx {
+                        Some(tile_idx) => {
+                            state.selected_tile_block = TileBlock {
+                                size: (1, 1),
+                                palettes: vec![vec![*palette_id]],
+                                tiles: vec![vec![tile_idx]],
+                                flips: vec![vec![Flip::None]],
+                                mask: None,
+                            };
+                            state.selected_gfx =
+                                get_selected_gfx(state, &state.selected_tile_block);
+                        }
+                        None => {
+                            state.selected_tile_block = TileBlock::default();
+                            state.selected_gfx = vec![];
+                        }
+                    }
+                    state.stamp_flip = Flip::None;
+                }
+            }
+        }
+        Message::RestoreTileRow(palette_id, tiles, tags) => {
+            let idx = *state
+                .palettes_id_idx_map
+                .get(palette_id)
+                .context("palette not found")?;
+            let pal = &mut state.palettes[idx];
+            pal.tiles.extend(tiles);
+            pal.tags.extend(tags.clone());
+            pal.modified = true;
+        }
+        &Message::InsertTileRow(palette_id, row) => {
+            let idx = *state
+                .palettes_id_idx_map
+                .get(&palette_id)
+                .context("palette not found")?;
+            if row * 16 > state.palettes[idx].tiles.len() {
+                warn!("Tile row {} is out of range.", row);
+                return Ok(None);
+            }
+            insert_tile_row(state, palette_id, row, vec![Tile::default(); 16])?;
+        }
+        &Message::DeleteTileRowAt(palette_id, row) => {
+            let idx = *state
+                .palettes_id_idx_map
+                .get(&palette_id)
+                .context("palette not found")?;
+            let num_tiles = state.palettes[idx].tiles.len();
+            if num_tiles <= 16 {
+                warn!("Not allowed to delete the last row of tiles.");
+                return Ok(None);
+            }
+            if row * 16 + 16 > num_tiles {
+                warn!("Tile row {} is out of range.", row);
+                return Ok(None);
+            }
+            if state.palettes[idx].animates_any(row as TileIdx * 16..row as TileIdx * 16 + 16) {
+                warn!(
+                    "Not deleting tile row {}: its tiles are used in tile animations.",
+                    row
+                );
+                return Ok(None);
+            }
+            let uses = count_tile_uses(state)?;
+            let num_uses: usize = uses
+                .get(&palette_id)
+                .map_or(0, |u| u[row * 16..row * 16 + 16].iter().sum());
+            if num_uses > 0 {
+                warn!(
+                    "Not deleting tile row {}: its tiles are used {} times in areas.",
+                    row, num_uses
+                );
+                return Ok(None);
+            }
+            delete_tile_row(state, palette_id, row)?;
+        }
+        Message::RestoreTileRowAt(palette_id, row, tiles, tags) => {
+            insert_tile_row(state, *palette_id, *row, tiles.clone())?;
+            let idx = state.palettes_id_idx_map[palette_id];
+            state.palettes[idx].tags.extend(tags.clone());
+        }
+        &Message::CompareTiles(a, b) => {
+            let num_tiles = state.palettes[state.palette_idx].tiles.len();
+            if a as usize >= num_tiles || b as usize >= num_tiles {
+                warn!("Tile index out of range: {} tiles in palette.", num_tiles);
+                return Ok(None);
+            }
+            state.dialogue = Some(Dialogue::CompareTiles { a, b });
+        }
+        &Message::CopyTileAsText(tile_idx) => {
+            let Some(tile) = state.palettes[state.palette_idx]
                 .tiles
-                .resize(new_size, Tile::default());
-            if let Some(idx) = state.tile_idx {
-                if idx >= new_size as TileIdx {
-                    state.tile_idx = Some(new_size as TileIdx - 1);
+                .get(tile_idx as usize)
+            else {
+                warn!("Tile {} not found.", tile_idx);
+                return Ok(None);
+            };
+            return Ok(Some(iced::clipboard::write(tile_pixels_to_text(
+                &tile.pixels,
+            ))));
+        }
+        Message::PasteTileFromClipboard => {
+            return Ok(Some(
+                iced::clipboard::read().map(|t| Message::PasteTileFromText(t.unwrap_or_default())),
+            ));
+        }
+        Message::PasteTileFromText(text) => {
+            let Some(tile_idx) = state.tile_idx else {
+                warn!("No tile selected to paste into.");
+                return Ok(None);
+            };
+            let pixels = match tile_pixels_from_text(text) {
+                Ok(pixels) => pixels,
+                Err(e) => {
+                    warn!("Unable to paste tile from text: {}", e);
+                    return Ok(None);
                 }
+            };
+            let pal = &state.palettes[state.palette_idx];
+            let mut tile = pal.tiles[tile_idx as usize];
+            tile.pixels = pixels;
+            return Ok(Some(Task::done(Message::TilesetBrush {
+                palette_id: pal.id,
+                coords: Point {
+                    x: tile_idx % 16,
+                    y: tile_idx / 16,
+                },
+                selected_gfx: vec![vec![tile]],
+                tile_block: None,
+            })));
+        }
+        Message::TilePagesDialogue => {
+            state.dialogue = Some(Dialogue::TilePages {
+                name: "".to_string(),
+            });
+            return Ok(Some(iced::widget::text_input::focus("TilePageName")));
+        }
+        Message::SetTilePageName(new_name) => {
+            if let Some(Dialogue::TilePages { name }) = &mut state.dialogue {
+                *name = new_name.clone();
             }
-            state.palettes[state.palette_idx].modified = true;
         }
-        Message::RestoreTileRow(palette_id, tiles) => {
+        Message::SetTilePages { palette_id, pages } => {
+            let idx = *state
+                .palettes_id_idx_map
+                .get(palette_id)
+                .context("palette not found")?;
+            if pages.iter().any(|(name, _)| name.is_empty()) {
+                warn!("Empty page name is invalid.");
+                return Ok(None);
+            }
+            state.palettes[idx].pages = pages.clone();
+            state.palettes[idx].modified = true;
+            if let Some(Dialogue::TilePages { name }) = &mut state.dialogue {
+                name.clear();
+            }
+        }
+        &Message::ScrollToTilePage(page_idx) => {
+            let Some((_, range)) = state.palettes[state.palette_idx].pages.get(page_idx) else {
+                warn!("Tile page {} not found.", page_idx);
+                return Ok(None);
+            };
+            return Ok(Some(scroll_to_tile(
+                state.tileset_order().position_of(range.start),
+            )));
+        }
+        Message::SetTileTagSearch(search) => {
+            state.tile_tag_search = search.clone();
+        }
+        Message::SetTileTagInput(tag) => {
+            state.tile_tag_input = tag.clone();
+        }
+        Message::TagSelectedTiles | Message::UntagSelectedTiles => {
+            let tag = state.tile_tag_input.trim().to_string();
+            if tag.is_empty() {
+                warn!("Empty tag is invalid.");
+                return Ok(None);
+            }
+            let tile_idxs = selected_tileset_tiles(state);
+            if tile_idxs.is_empty() {
+                warn!("No tiles selected in the tileset.");
+                return Ok(None);
+            }
+            let pal = &state.palettes[state.palette_idx];
+            let mut tags = pal.tags.clone();
+            for idx in tile_idxs {
+                let tile_tags = tags.entry(idx).or_default();
+                if let Message::TagSelectedTiles = message {
+                    if !tile_tags.contains(&tag) {
+                        tile_tags.push(tag.clone());
+                    }
+                } else {
+                    tile_tags.retain(|t| t != &tag);
+                }
+            }
+            tags.retain(|_, t| !t.is_empty());
+            return Ok(Some(Task::done(Message::SetTileTags {
+                palette_id: pal.id,
+                tags,
+            })));
+        }
+        &Message::SetReplaceColorFrom(color_idx) => {
+            state.replace_color_from = color_idx;
+        }
+        Message::ReplaceColorInSelectedTiles => {
+            let Some(to) = state.color_idx else {
+                warn!("No color selected to replace with.");
+                return Ok(None);
+            };
+            let tiles = selected_tileset_tiles(state);
+            if tiles.is_empty() {
+                warn!("No tiles selected in the tileset.");
+                return Ok(None);
+            }
+            return Ok(Some(Task::done(Message::ReplaceColorInTiles {
+                palette_id: state.palettes[state.palette_idx].id,
+                tiles,
+                from: state.replace_color_from,
+                to,
+            })));
+        }
+        &Message::ReplaceColorInTiles {
+            palette_id,
+            ref tiles,
+            from,
+            to,
+        } => {
+            let pal_idx = *state
+                .palettes_id_idx_map
+                .get(&palette_id)
+                .context("undefined palette")?;
+            let pal = &state.palettes[pal_idx];
+            let tiles: Vec<TileIdx> = tiles
+                .iter()
+                .copied()
+                .filter(|&i| (i as usize) < pal.tiles.len())
+                .collect();
+            if tiles.is_empty() || from == to {
+                return Ok(None);
+            }
+            // Brush the bounding rectangle of the tiles (in the 16-column tileset layout),
+            // leaving the tiles that aren't included unchanged:
+            let x0 = tiles.iter().map(|i| i % 16).min().unwrap();
+            let x1 = tiles.iter().map(|i| i % 16).max().unwrap();
+            let y0 = tiles.iter().map(|i| i / 16).min().unwrap();
+            let y1 = tiles.iter().map(|i| i / 16).max().unwrap();
+            let mut num_pixels = 0;
+            let mut selected_gfx: Vec<Vec<Tile>> = vec![];
+            for y in y0..=y1 {
+                let mut row: Vec<Tile> = vec![];
+                for x in x0..=x1 {
+                    let i = y * 16 + x;
+                    let mut tile = pal.tiles.get(i as usize).copied().unwrap_or_default();
+                    if tiles.contains(&i) {
+                        for c in tile.pixels.iter_mut().flatten() {
+                            if *c == from {
+                                *c = to;
+                                num_pixels += 1;
+                            }
+                        }
+                    }
+                    row.push(tile);
+                }
+                selected_gfx.push(row);
+            }
+            if num_pixels == 0 {
+                info!("No pixels of color {} in the selected tiles.", from);
+                return Ok(None);
+            }
+            info!(
+                "Replaced {} pixels of color {} with color {} in {} tiles.",
+                num_pixels,
+                from,
+                to,
+                tiles.len()
+            );
+            return Ok(Some(Task::done(Message::TilesetBrush {
+                palette_id,
+                coords: Point { x: x0, y: y0 },
+                selected_gfx,
+                tile_block: None,
+            })));
+        }
+        Message::SetTileTags { palette_id, tags } => {
             let idx = *state
                 .palettes_id_idx_map
                 .get(palette_id)
                 .context("palette not found")?;
-            state.palettes[idx].tiles.extend(tiles);
+            state.palettes[idx].tags = tags.clone();
             state.palettes[idx].modified = true;
         }
         &Message::SetTilePriority {
@@ -876,8 +2287,8 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                             if src_pal_id != palette_id {
                                 let src_pal_idx = state.palettes_id_idx_map[&src_pal_id];
                                 let src_pal = &state.palettes[src_pal_idx];
-                                for py in 0..8 {
-                                    for px in 0..8 {
+                                for py in 0..TILE_SIZE {
+                                    for px in 0..TILE_SIZE {
                                         let src_color_idx = tile.pixels[py][px] as usize;
                                         if src_color_idx == 0 {
                                             continue;
@@ -923,17 +2334,23 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             pal.modified = true;
         }
         &Message::SelectArea(position, ref name) => {
-            let area_id = &state.main_area_id;
-            state.switch_area(
-                position,
-                &AreaId {
-                    area: name.clone(),
-                    theme: area_id.theme.clone(),
-                },
-            )?;
+            let area_id = AreaId {
+                area: name.clone(),
+                theme: state.main_area_id.theme.clone(),
+            };
+            if !switch_area_or_report(state, position, area_id, message) {
+                return Ok(None);
+            }
+            if position == AreaPosition::Main && state.global_config.per_area_zoom {
+                if let Some(&pixel_size) = state.global_config.area_zoom.get(name) {
+                    state.global_config.pixel_size = pixel_size;
+                    state.global_config.modified = true;
+                }
+            }
             if let SelectionSource::Area(p) = state.selection_source {
                 if p == position {
                     state.start_coords = None;
+                    state.selection_coords = None;
                     state.end_coords = None;
                 }
             }
@@ -992,8 +2409,11 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                                 palettes: [[0; 32]; 32],
                                 tiles: [[0; 32]; 32],
                                 flips: [[Flip::None; 32]; 32],
+                                priorities: [[false; 32]; 32],
+                                notes: String::new(),
                             })
                             .collect(),
+                        num_broken_cells: 0,
                     },
                 )?;
                 save_area(state, &state.main_area_id.clone())?;
@@ -1079,6 +2499,253 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             state.switch_area(AreaPosition::Main, area_id)?;
             state.main_area_mut().bg_color = color;
         }
+        Message::ScreenNotesDialogue => {
+            let screen = match (state.selection_source, state.start_coords) {
+                (SelectionSource::Area(AreaPosition::Main), Some((x, y))) => {
+                    ((x / 32) as u8, (y / 32) as u8)
+                }
+                _ => (0, 0),
+            };
+            return Ok(Some(Task::done(Message::SelectNotesScreen(format!(
+                "{}, {}",
+                screen.0, screen.1
+            )))));
+        }
+        Message::SelectNotesScreen(name) => {
+            let area = state.main_area();
+            let Some((sx, sy)) = (0..area.size.1)
+                .flat_map(|sy| (0..area.size.0).map(move |sx| (sx, sy)))
+                .find(|(sx, sy)| name == &format!("{}, {}", sx, sy))
+            else {
+                warn!("Screen {} not found.", name);
+                return Ok(None);
+            };
+            let screen_idx = sy as usize * area.size.0 as usize + sx as usize;
+            state.dialogue = Some(Dialogue::ScreenNotes {
+                screen: (sx, sy),
+                content: widget::text_editor::Content::with_text(&area.screens[screen_idx].notes),
+            });
+        }
+        Message::EditScreenNotes(action) => {
+            if let Some(Dialogue::ScreenNotes { content, .. }) = &mut state.dialogue {
+                content.perform(action.clone());
+            }
+        }
+        Message::SaveScreenNotes => {
+            // The notes are set (undoably) through `SetScreenNotes`, which leaves the dialogue
+            // alone so that undoing doesn't discard notes being typed:
+            let Some(Dialogue::ScreenNotes { screen, content }) = state.dialogue.take() else {
+                return Ok(None);
+            };
+            return Ok(Some(Task::done(Message::SetScreenNotes {
+                area_id: state.main_area_id.clone(),
+                screen,
+                notes: content.text().trim_end().to_string(),
+            })));
+        }
+        Message::SetScreenNotes {
+            area_id,
+            screen,
+            notes,
+        } => {
+            state.switch_area(AreaPosition::Main, area_id)?;
+            let area = state.main_area_mut();
+            let screen_idx = screen.1 as usize * area.size.0 as usize + screen.0 as usize;
+            area.screens[screen_idx].notes = notes.clone();
+            area.modified = true;
+        }
+        &Message::EditAreaCell(cell) => {
+            let Some(coords) = state.inspected_cell() else {
+                return Ok(None);
+            };
+            return Ok(Some(Task::done(Message::SetAreaCell {
+                area_id: state.main_area_id.clone(),
+                coords,
+                cell,
+            })));
+        }
+        Message::SetAreaCell {
+            area_id,
+            coords,
+            cell,
+        } => {
+            if !state.palettes_id_idx_map.contains_key(&cell.palette_id) {
+                warn!("Palette {} not found.", cell.palette_id);
+                return Ok(None);
+            }
+            state.switch_area(AreaPosition::Main, area_id)?;
+            let area = state.main_area_mut();
+            area.set_cell(coords.0, coords.1, *cell)?;
+            area.modified = true;
+        }
+        Message::WorldOverviewDialogue => {
+            let overview = build_world_overview(state)?;
+            state.dialogue = Some(Dialogue::WorldOverview {
+                overview,
+                link_from: None,
+                link_side: Side::East,
+                link_to: ScreenId {
+                    area: state.main_area().name.clone(),
+                    x: 0,
+                    y: 0,
+                },
+            });
+        }
+        Message::OpenWorldScreen(screen) => {
+            state.dialogue = None;
+            let area_id = AreaId {
+                area: screen.area.clone(),
+                theme: state.main_area_id.theme.clone(),
+            };
+            state.switch_area(AreaPosition::Main, &area_id)?;
+            if state.selection_source == SelectionSource::Area(AreaPosition::Main) {
+                state.start_coords = None;
+                state.selection_coords = None;
+                state.end_coords = None;
+            }
+            return Ok(Some(scroll_to_screen(
+                screen.x,
+                screen.y,
+                state.global_config.pixel_size,
+            )));
+        }
+        Message::SelectLinkScreen(screen) => {
+            if let Some(Dialogue::WorldOverview { link_from, .. }) = &mut state.dialogue {
+                *link_from = Some(screen.clone());
+            }
+        }
+        &Message::SetLinkSide(side) => {
+            if let Some(Dialogue::WorldOverview { link_side, .. }) = &mut state.dialogue {
+                *link_side = side;
+            }
+        }
+        Message::SetLinkTargetArea(area) => {
+            if let Some(Dialogue::WorldOverview { link_to, .. }) = &mut state.dialogue {
+                *link_to = ScreenId {
+                    area: area.clone(),
+                    x: 0,
+                    y: 0,
+                };
+            }
+        }
+        &Message::SetLinkTargetX(x) => {
+            if let Some(Dialogue::WorldOverview { link_to, .. }) = &mut state.dialogue {
+                link_to.x = x;
+            }
+        }
+        &Message::SetLinkTargetY(y) => {
+            if let Some(Dialogue::WorldOverview { link_to, .. }) = &mut state.dialogue {
+                link_to.y = y;
+            }
+        }
+        Message::LinkScreens { from, side, to } => {
+            if let Some(to) = to {
+                if to.area == from.area {
+                    warn!("Screens within an area are already adjacent by their positions.");
+                    return Ok(None);
+                }
+                let area_id = AreaId {
+                    area: to.area.clone(),
+                    theme: state.main_area_id.theme.clone(),
+                };
+                let size = match state.areas.get(&area_id) {
+                    Some(area) => area.size,
+                    None => persist::load_area(state, &area_id)?.size,
+                };
+                if to.x >= size.0 || to.y >= size.1 {
+                    warn!("Screen {} is outside of the area.", to);
+                    return Ok(None);
+                }
+            }
+            let links = state.world_map.with_link(from, *side, to.as_ref());
+            return Ok(Some(Task::done(Message::SetWorldLinks(links))));
+        }
+        Message::SetWorldLinks(links) => {
+            state.world_map.links = links.clone();
+            state.world_map.modified = true;
+            if matches!(state.dialogue, Some(Dialogue::WorldOverview { .. })) {
+                let new_overview = build_world_overview(state)?;
+                if let Some(Dialogue::WorldOverview { overview, .. }) = &mut state.dialogue {
+                    *overview = new_overview;
+                }
+            }
+        }
+        Message::SelectNextBrokenCell => {
+            let cells = state.broken_cells(state.main_area());
+            // Continue searching after the currently selected cell, wrapping around:
+            let current = match (state.selection_source, state.selection_coords) {
+                (SelectionSource::Area(AreaPosition::Main), Some((x, y))) => Some((y, x)),
+                _ => None,
+            };
+            let next = cells
+                .iter()
+                .find(|&&(x, y)| current.is_none_or(|c| (y, x) > c))
+                .or(cells.first());
+            let Some(&(x, y)) = next else {
+                info!("No broken cells in area {}.", state.main_area().name);
+                return Ok(None);
+            };
+            state.dialogue = None;
+            let p = Point::new(x, y);
+            return Ok(Some(Task::batch([
+                Task::done(Message::StartTileSelection(
+                    p,
+                    SelectionSource::Area(AreaPosition::Main),
+                ))
+                .chain(Task::done(Message::EndTileSelection(p))),
+                scroll_to_screen(
+                    (x / 32) as u8,
+                    (y / 32) as u8,
+                    state.global_config.pixel_size,
+                ),
+            ])));
+        }
+        Message::CheckBrokenCells => {
+            let broken = find_broken_cells(state)?;
+            if broken.is_empty() {
+                info!("No broken cells in the project.");
+            }
+            for (area_id, n) in broken {
+                warn!(
+                    "Area {} (theme {}) has {} cells referencing a missing palette or a tile index beyond the palette's tiles.",
+                    area_id.area, area_id.theme, n
+                );
+            }
+        }
+        Message::TrimEmptyEdges(area_id) => {
+            let Some(message) = trim_empty_edges_message(state, area_id)? else {
+                info!("Area {} has no empty edges to trim.", area_id.area);
+                return Ok(None);
+            };
+            return Ok(Some(Task::done(message)));
+        }
+        Message::SetAreaScreens {
+            area_id,
+            size,
+            screens,
+        } => {
+            ensure!(
+                screens.len() == size.0 as usize * size.1 as usize,
+                "{} screens given for an area of size {}x{}",
+                screens.len(),
+                size.0,
+                size.1
+            );
+            let area = state.areas.get_mut(area_id).context("area not loaded")?;
+            area.size = *size;
+            area.screens = screens.clone();
+            area.modified = true;
+            // Clear any selection in the resized area, since its coordinates may no longer be valid:
+            for position in [AreaPosition::Main, AreaPosition::Side] {
+                if state.selection_source == SelectionSource::Area(position)
+                    && state.area_id(position) == area_id
+                {
+                    state.start_coords = None;
+                    state.selection_coords = None;
+                    state.end_coords = None;
+                }
+            }
+        }
         Message::DeleteAreaDialogue => {
             state.dialogue = Some(Dialogue::DeleteArea);
         }
@@ -1111,13 +2778,13 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             state.dialogue = None;
         }
         &Message::SelectTheme(position, ref theme) => {
-            state.switch_area(
-                position,
-                &AreaId {
-                    area: state.area(position).name.clone(),
-                    theme: theme.clone(),
-                },
-            )?;
+            let area_id = AreaId {
+                area: state.area(position).name.clone(),
+                theme: theme.clone(),
+            };
+            if !switch_area_or_report(state, position, area_id, message) {
+                return Ok(None);
+            }
         }
         Message::AddThemeDialogue => {
             state.dialogue = Some(Dialogue::AddTheme {
@@ -1147,6 +2814,12 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             for area_name in &state.area_names.clone() {
                 copy_area_theme(state, area_name, &old_theme, &theme_name)?;
             }
+            if let Some(theme) = state.themes.get(&old_theme) {
+                let mut theme = theme.clone();
+                theme.name = theme_name.clone();
+                theme.modified = true;
+                state.themes.insert(theme_name.clone(), theme);
+            }
             state.switch_area(
                 AreaPosition::Main,
                 &AreaId {
@@ -1185,6 +2858,7 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             for area_name in &state.area_names.clone() {
                 rename_area_theme(state, area_name, old_name, new_name)?;
             }
+            rename_theme(state, old_name, new_name)?;
             load_area_list(state)?;
             if &state.main_area_id.theme == old_name {
                 state.switch_area(
@@ -1206,6 +2880,41 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             }
             state.dialogue = None;
         }
+        Message::ThemeColorsDialogue => {
+            state.dialogue = Some(Dialogue::ThemeColors);
+        }
+        &Message::EditThemeColor(color_idx, color) => {
+            return Ok(Some(Task::done(Message::SetThemeColor {
+                theme: state.main_area().theme.clone(),
+                palette_id: state.palettes[state.palette_idx].id,
+                color_idx,
+                color,
+            })));
+        }
+        Message::SetThemeColor {
+            theme,
+            palette_id,
+            color_idx,
+            color,
+        } => {
+            let t = state.themes.entry(theme.clone()).or_insert_with(|| Theme {
+                name: theme.clone(),
+                ..Default::default()
+            });
+            let overrides = t.color_overrides.entry(*palette_id).or_default();
+            match color {
+                Some(c) => {
+                    overrides.insert(*color_idx, *c);
+                }
+                None => {
+                    overrides.remove(color_idx);
+                }
+            }
+            if overrides.is_empty() {
+                t.color_overrides.remove(palette_id);
+            }
+            t.modified = true;
+        }
         Message::DeleteThemeDialogue => {
             state.dialogue = Some(Dialogue::DeleteTheme);
         }
@@ -1218,6 +2927,7 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             for area_name in &state.area_names.clone() {
                 delete_area_theme(state, area_name, theme_name)?;
             }
+            delete_theme(state, theme_name)?;
             load_area_list(state)?;
             if &state.main_area_id.theme == theme_name {
                 state.switch_area(
@@ -1245,6 +2955,9 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
         Message::HoverAreaEnd => {
             state.hover_coords = None;
         }
+        &Message::ScrollArea(position, offset) => {
+            *state.area_scroll_offset_mut(position) = offset;
+        }
         &Message::StartTileSelection(p, source) => {
             state.selection_source = source;
             state.start_coords = Some((p.x, p.y));
@@ -1260,6 +2973,18 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                 return Ok(None);
             };
 
+            // Linear selection follows the index order, so it doesn't apply to a sorted tileset:
+            let order = state.tileset_order();
+            if state.selection_source == SelectionSource::Tileset
+                && state.global_config.linear_select
+                && !order.is_sorted()
+            {
+                return Ok(Some(Task::done(Message::SelectTileRun(
+                    p0.1 * 16 + p0.0,
+                    p1.1 * 16 + p1.0,
+                ))));
+            }
+
             let left = p0.0.min(p1.0);
             let mut right = p0.0.max(p1.0);
             let top = p0.1.min(p1.1);
@@ -1295,7 +3020,7 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                         }
                         SelectionSource::Tileset => {
                             pal_row.push(state.palettes[state.palette_idx].id);
-                            tile_row.push(y * 16 + x);
+                            tile_row.push(order.tile_at(y * 16 + x));
                             flip_row.push(Flip::None)
                         }
                     }
@@ -1309,11 +3034,18 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                 palettes,
                 tiles,
                 flips,
+                mask: None,
             };
             let s = &state.selected_tile_block;
 
             state.selected_gfx = get_selected_gfx(state, &state.selected_tile_block);
+            state.stamp_flip = Flip::None;
             state.start_coords = None;
+            state.selection_coords = match state.selection_source {
+                SelectionSource::Area(_) => Some((left, top)),
+                SelectionSource::Tileset => None,
+            };
+            state.copy_coords = state.selection_coords;
             state.end_coords = None;
             if left == right && top == bottom {
                 return Ok(Some(Task::done(Message::OpenTile {
@@ -1324,6 +3056,239 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                 state.tile_idx = None;
             }
         }
+        &Message::SetReferenceSelection(coords) => {
+            state.reference_coords = coords.map(|(p0, p1)| ((p0.x, p0.y), (p1.x, p1.y)));
+        }
+        Message::SwapReferenceSelection => {
+            if state.tileset_sort_by_usage {
+                warn!("Swapping selections isn't supported while the tileset is sorted by usage.");
+                return Ok(None);
+            }
+            // The primary selection is only swappable if it consists of tileset tiles
+            // of the current palette:
+            let s = &state.selected_tile_block;
+            let primary = if state.selection_source == SelectionSource::Tileset
+                && s.size.0 > 0
+                && s.size.1 > 0
+                && s.palettes[0][0] == state.palettes[state.palette_idx].id
+            {
+                let t = s.tiles[0][0];
+                let (x, y) = (t % 16, t / 16);
+                Some(((x, y), ((x + s.size.0 - 1).min(15), y + s.size.1 - 1)))
+            } else {
+                None
+            };
+            let Some((p0, p1)) = state.reference_coords else {
+                warn!("No reference selection to swap with.");
+                return Ok(None);
+            };
+            state.reference_coords = primary;
+            return Ok(Some(
+                Task::done(Message::StartTileSelection(
+                    Point::new(p0.0, p0.1),
+                    SelectionSource::Tileset,
+                ))
+                .chain(Task::done(Message::EndTileSelection(Point::new(
+                    p1.0, p1.1,
+                )))),
+            ));
+        }
+        &Message::SelectTileRun(i0, i1) => {
+            // Select the consecutive tiles between the two (inclusive) as a single row:
+            let pal = &state.palettes[state.palette_idx];
+            if pal.tiles.is_empty() {
+                return Ok(None);
+            }
+            let last = i0.max(i1).min(pal.tiles.len() as TileIdx - 1);
+            let first = i0.min(i1).min(last);
+            let n = (last - first + 1) as usize;
+            state.focus = Focus::TilesetTile;
+            state.selected_tile_block = TileBlock {
+                size: (n as TileCoord, 1),
+                palettes: vec![vec![pal.id; n]],
+                tiles: vec![(first..=last).collect()],
+                flips: vec![vec![Flip::None; n]],
+                mask: None,
+            };
+            state.selected_gfx = get_selected_gfx(state, &state.selected_tile_block);
+            state.stamp_flip = Flip::None;
+            state.start_coords = None;
+            state.selection_coords = None;
+            state.copy_coords = None;
+            state.end_coords = None;
+            if n == 1 {
+                return Ok(Some(Task::done(Message::OpenTile {
+                    palette_id: state.palettes[state.palette_idx].id,
+                    tile_idx: first,
+                })));
+            } else {
+                state.tile_idx = None;
+            }
+        }
+        &Message::MagicWandSelect { position, coords } => {
+            // Select the connected region of tiles matching the clicked one (in palette, tile,
+            // and flip), as a mask within its bounding box:
+            let area = state.area(position);
+            let region = area.fill_region(coords.x, coords.y, FillMode::Exact)?;
+            let left = region.iter().map(|p| p.0).min().unwrap();
+            let right = region.iter().map(|p| p.0).max().unwrap();
+            let top = region.iter().map(|p| p.1).min().unwrap();
+            let bottom = region.iter().map(|p| p.1).max().unwrap();
+            let mut mask =
+                vec![vec![false; (right - left + 1) as usize]; (bottom - top + 1) as usize];
+            for &(x, y) in &region {
+                mask[(y - top) as usize][(x - left) as usize] = true;
+            }
+            let mut palettes: Vec<Vec<PaletteId>> = vec![];
+            let mut tiles: Vec<Vec<TileIdx>> = vec![];
+            let mut flips: Vec<Vec<Flip>> = vec![];
+            for y in top..=bottom {
+                palettes.push(
+                    (left..=right)
+                        .map(|x| area.get_palette(x, y))
+                        .collect::<Result<_>>()?,
+                );
+                tiles.push(
+                    (left..=right)
+                        .map(|x| area.get_tile(x, y))
+                        .collect::<Result<_>>()?,
+                );
+                flips.push(
+                    (left..=right)
+                        .map(|x| area.get_flip(x, y))
+                        .collect::<Result<_>>()?,
+                );
+            }
+            state.selection_source = SelectionSource::Area(position);
+            state.focus = Focus::Area(position);
+            state.selected_tile_block = TileBlock {
+                size: (right - left + 1, bottom - top + 1),
+                palettes,
+                tiles,
+                flips,
+                mask: if region.len() > 1 {
+                    Some(Box::new(mask))
+                } else {
+                    None
+                },
+            };
+            state.selected_gfx = get_selected_gfx(state, &state.selected_tile_block);
+            state.stamp_flip = Flip::None;
+            state.start_coords = None;
+            state.selection_coords = Some((left, top));
+            state.copy_coords = Some((left, top));
+            state.end_coords = None;
+            if region.len() == 1 {
+                let s = &state.selected_tile_block;
+                return Ok(Some(Task::done(Message::OpenTile {
+                    palette_id: s.palettes[0][0],
+                    tile_idx: s.tiles[0][0],
+                })));
+            } else {
+                state.tile_idx = None;
+            }
+        }
+        Message::PasteInPlace | Message::PasteAtCursor => {
+            if state.selected_gfx.is_empty() {
+                warn!("No tiles selected to paste.");
+                return Ok(None);
+            }
+            let coords = if let Message::PasteInPlace = message {
+                state.copy_coords
+            } else {
+                state.hover_coords
+            };
+            let Some((x, y)) = coords else {
+                warn!("No position to paste at.");
+                return Ok(None);
+            };
+            let position = match state.focus {
+                Focus::Area(position) => position,
+                _ => AreaPosition::Main,
+            };
+            // Cells falling outside of the area are skipped by the brush:
+            return Ok(Some(Task::done(Message::AreaBrush {
+                position,
+                area_id: state.area_id(position).clone(),
+                coords: Point::new(x, y),
+                selection: state.selected_tile_block.clone(),
+                palette_only: false,
+            })));
+        }
+        Message::DeleteSelection => {
+            let (SelectionSource::Area(position), Some((x, y))) =
+                (state.selection_source, state.selection_coords)
+            else {
+                return Ok(None);
+            };
+            // Clear the selected cells to tile 0 of the first palette (i.e. the one with the
+            // lowest ID), as a single brush stroke so that it can be undone:
+            let s = &state.selected_tile_block;
+            let (w, h) = (s.size.0 as usize, s.size.1 as usize);
+            let selection = TileBlock {
+                size: s.size,
+                palettes: vec![vec![state.palettes[0].id; w]; h],
+                tiles: vec![vec![0; w]; h],
+                flips: vec![vec![Flip::None; w]; h],
+                mask: s.mask.clone(),
+            };
+            return Ok(Some(Task::done(Message::AreaBrush {
+                position,
+                area_id: state.area_id(position).clone(),
+                coords: Point::new(x, y),
+                selection,
+                palette_only: false,
+            })));
+        }
+        &Message::MirrorSelection { axis } => {
+            let (SelectionSource::Area(position), Some((x, y))) =
+                (state.selection_source, state.selection_coords)
+            else {
+                warn!("No area selection to mirror.");
+                return Ok(None);
+            };
+            // Place a flipped copy of the selection adjacent to it; cells falling outside of
+            // the area are skipped by the brush:
+            let mut selection = state.selected_tile_block.clone();
+            let (w, h) = (selection.size.0 as usize, selection.size.1 as usize);
+            let coords = match axis {
+                MirrorAxis::Horizontal => {
+                    for i in 0..h {
+                        selection.palettes[i].reverse();
+                        selection.tiles[i].reverse();
+                        selection.flips[i].reverse();
+                        if let Some(mask) = &mut selection.mask {
+                            mask[i].reverse();
+                        }
+                        for flip in &mut selection.flips[i] {
+                            *flip = flip.flip_horizontally();
+                        }
+                    }
+                    Point::new(x + w as TileCoord, y)
+                }
+                MirrorAxis::Vertical => {
+                    selection.palettes.reverse();
+                    selection.tiles.reverse();
+                    selection.flips.reverse();
+                    if let Some(mask) = &mut selection.mask {
+                        mask.reverse();
+                    }
+                    for row in &mut selection.flips {
+                        for flip in row {
+                            *flip = flip.flip_vertically();
+                        }
+                    }
+                    Point::new(x, y + h as TileCoord)
+                }
+            };
+            return Ok(Some(Task::done(Message::AreaBrush {
+                position,
+                area_id: state.area_id(position).clone(),
+                coords,
+                selection,
+                palette_only: false,
+            })));
+        }
         &Message::AreaBrush {
             position,
             ref area_id,
@@ -1335,9 +3300,18 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             let s = selection;
             let p = coords;
             let area = state.area_mut(position);
+            let mut painted = vec![];
             for y in 0..s.size.1 {
                 for x in 0..s.size.0 {
-                    let _ = area.set_palette(p.x + x, p.y + y, s.palettes[y as usize][x as usize]);
+                    if !s.is_selected(x, y) {
+                        continue;
+                    }
+                    if area
+                        .set_palette(p.x + x, p.y + y, s.palettes[y as usize][x as usize])
+                        .is_ok()
+                    {
+                        painted.push((p.x + x, p.y + y));
+                    }
                     if !palette_only {
                         let _ = area.set_tile(p.x + x, p.y + y, s.tiles[y as usize][x as usize]);
                         let _ = area.set_flip(p.x + x, p.y + y, s.flips[y as usize][x as usize]);
@@ -1345,6 +3319,83 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
                 }
             }
             area.modified = true;
+            count_paint(state, area_id, painted);
+        }
+        &Message::AreaFill {
+            position,
+            ref area_id,
+            coords,
+            ref selection,
+            mode,
+            palette_only,
+            check_size,
+        } => {
+            if selection.size.0 == 0 || selection.size.1 == 0 {
+                return Ok(None);
+            }
+            state.switch_area(position, area_id)?;
+            let area = state.area_mut(position);
+            let mut region = area.fill_region(coords.x, coords.y, mode)?;
+            // Tile the selection across the filled region, aligned to the area grid, leaving
+            // tiles outside of the selection mask unchanged:
+            region.retain(|&(x, y)| {
+                selection.is_selected(x % selection.size.0, y % selection.size.1)
+            });
+            let threshold = state.global_config.fill_confirm_threshold as usize;
+            if check_size && threshold > 0 && region.len() > threshold {
+                let mut fill = message.clone();
+                if let Message::AreaFill { check_size, .. } = &mut fill {
+                    *check_size = false;
+                }
+                state.dialogue = Some(Dialogue::ConfirmFill {
+                    count: region.len(),
+                    fill,
+                });
+                return Ok(None);
+            }
+            state.dialogue = None;
+            let area = state.area_mut(position);
+            for &(x, y) in &region {
+                let sx = (x % selection.size.0) as usize;
+                let sy = (y % selection.size.1) as usize;
+                area.set_palette(x, y, selection.palettes[sy][sx])?;
+                if !palette_only {
+                    area.set_tile(x, y, selection.tiles[sy][sx])?;
+                    area.set_flip(x, y, selection.flips[sy][sx])?;
+                }
+            }
+            area.modified = true;
+            count_paint(state, area_id, region);
+        }
+        &Message::SetFillMode(mode) => {
+            state.fill_mode = mode;
+        }
+        &Message::SetScreenLayer(layer) => {
+            state.screen_layer = layer;
+        }
+        &Message::PickScreenColor {
+            position,
+            x,
+            y,
+            pixel,
+        } => {
+            let area = state.area(position);
+            let cell = area.get_cell(x, y)?;
+            if state.is_broken_cell(&cell) {
+                warn!("Cell ({}, {}) has a missing palette or tile.", x, y);
+                return Ok(None);
+            }
+            let pal = &state.palettes[state.palettes_id_idx_map[&cell.palette_id]];
+            let tile = cell.flip.apply_to_tile(pal.tiles[cell.tile_idx as usize]);
+            let color_idx = tile.pixels[pixel.1 as usize][pixel.0 as usize];
+            // Resolve the color as it is displayed, with the area's theme and background:
+            let mut colors = state.theme_palette_colors(&area.theme, pal);
+            colors[0] = area.bg_color;
+            let Some(&color) = colors.get(color_idx as usize) else {
+                warn!("Invalid color index {} at cell ({}, {}).", color_idx, x, y);
+                return Ok(None);
+            };
+            state.selected_color = color;
         }
         &Message::OpenTile {
             palette_id,
@@ -1363,7 +3414,7 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             dst_selection,
             check_reversible,
         } => {
-            state.dialogue = Some(Dialogue::MovingTilesProgress);
+            state.dialogue = Some(Dialogue::MovingTilesProgress { progress: 0.0 });
             return Ok(Some(Task::done(Message::MoveTiles {
                 src_selection: src_selection.clone(),
                 dst_selection: dst_selection.clone(),
@@ -1375,6 +3426,10 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             dst_selection,
             check_reversible,
         } => {
+            if state.move_tiles_job.is_some() {
+                warn!("Not moving tiles: another move is still in progress.");
+                return Ok(None);
+            }
             assert!(src_selection.size == dst_selection.size);
             let mut mapping: HashMap<(PaletteId, TileIdx), (PaletteId, TileIdx, Flip)> =
                 HashMap::new();
@@ -1382,6 +3437,9 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             // Validate that the selected tiles are unique, and create the mapping:
             for y in 0..src_selection.size.1 {
                 for x in 0..src_selection.size.0 {
+                    if !src_selection.is_selected(x, y) {
+                        continue;
+                    }
                     let src_palette_id = src_selection.palettes[y as usize][x as usize];
                     let src_tile_idx = src_selection.tiles[y as usize][x as usize];
                     let src_flip = src_selection.flips[y as usize][x as usize];
@@ -1407,6 +3465,9 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             // Validate that the source and destination tiles are disjoint:
             for y in 0..dst_selection.size.1 {
                 for x in 0..dst_selection.size.0 {
+                    if !dst_selection.is_selected(x, y) {
+                        continue;
+                    }
                     let dst_palette_id = dst_selection.palettes[y as usize][x as usize];
                     let dst_tile_idx = dst_selection.tiles[y as usize][x as usize];
                     if mapping.contains_key(&(dst_palette_id, dst_tile_idx)) {
@@ -1418,37 +3479,180 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
             }
 
             if *check_reversible {
-                // Ensure that the destination tiles are unused, so that
-                // the operation will be reversible:
-                let used_tiles = scan_used_tiles(state)?;
+                // Ensure that the destination tiles are unused, so that the operation will be
+                // reversible, or otherwise confirm which ones would be collapsed:
+                let use_counts = count_tile_uses(state)?;
+                let mut collisions: Vec<MoveCollision> = vec![];
                 for y in 0..dst_selection.size.1 {
                     for x in 0..dst_selection.size.0 {
-                        let dst_palette_id = dst_selection.palettes[y as usize][x as usize];
-                        let dst_tile_idx = dst_selection.tiles[y as usize][x as usize];
-                        if used_tiles.contains(&(dst_palette_id, dst_tile_idx)) {
-                            return Ok(Some(Task::done(Message::MoveTilesConfirmDialogue {
-                                src_selection: src_selection.clone(),
-                                dst_selection: dst_selection.clone(),
-                            })));
+                        if !dst_selection.is_selected(x, y) {
+                            continue;
+                        }
+                        let palette_id = dst_selection.palettes[y as usize][x as usize];
+                        let tile_idx = dst_selection.tiles[y as usize][x as usize];
+                        let num_uses = use_counts
+                            .get(&palette_id)
+                            .and_then(|c| c.get(tile_idx as usize))
+                            .copied()
+                            .unwrap_or(0);
+                        if num_uses > 0 {
+                            collisions.push(MoveCollision {
+                                palette_id,
+                                tile_idx,
+                                num_uses,
+                            });
                         }
                     }
                 }
+                if !collisions.is_empty() {
+                    return Ok(Some(Task::done(Message::MoveTilesConfirmDialogue {
+                        src_selection: src_selection.clone(),
+                        dst_selection: dst_selection.clone(),
+                        collisions,
+                    })));
+                }
             }
 
-            // Update the references to the tiles:
-            remap_tiles(state, &mapping)?;
-
-            state.dialogue = None;
+            // Update the references to the tiles, one area per step:
+            let remaining = all_area_ids(state);
+            state.move_tiles_job = Some(MoveTilesJob {
+                mapping,
+                total: remaining.len(),
+                remaining,
+                originals: vec![],
+                history: None,
+            });
+            state.dialogue = Some(Dialogue::MovingTilesProgress { progress: 0.0 });
+            return Ok(Some(Task::done(Message::MoveTilesStep)));
+        }
+        Message::MoveTilesStep => {
+            let Some(progress) = move_tiles_step(state)? else {
+                return Ok(None);
+            };
+            return Ok(Some(Task::done(Message::MoveProgress(progress))));
+        }
+        &Message::MoveProgress(progress) => {
+            if state.move_tiles_job.is_none() {
+                // The move was cancelled.
+                return Ok(None);
+            }
+            state.dialogue = Some(Dialogue::MovingTilesProgress { progress });
+            return Ok(Some(Task::done(Message::MoveTilesStep)));
+        }
+        Message::CancelMove => {
+            let Some(job) = state.move_tiles_job.take() else {
+                return Ok(None);
+            };
+            let num_areas = job.originals.len();
+            roll_back_move_tiles(state, job)?;
+            info!("Cancelled moving tiles; restored {} areas.", num_areas);
         }
         Message::MoveTilesConfirmDialogue {
             src_selection,
             dst_selection,
+            collisions,
         } => {
             state.dialogue = Some(Dialogue::MoveTiles {
                 src_selection: src_selection.clone(),
                 dst_selection: dst_selection.clone(),
+                collisions: collisions.clone(),
             });
         }
+        Message::CreateSnapshotDialogue => {
+            state.dialogue = Some(Dialogue::CreateSnapshot {
+                name: "".to_string(),
+            });
+            return Ok(Some(iced::widget::text_input::focus("CreateSnapshot")));
+        }
+        Message::SetCreateSnapshotName(new_name) => {
+            if let Some(Dialogue::CreateSnapshot { name }) = &mut state.dialogue {
+                *name = new_name.clone();
+            }
+        }
+        Message::CreateSnapshot(name) => {
+            if name.is_empty() {
+                warn!("Empty snapshot name is invalid.");
+                return Ok(None);
+            }
+            if let Err(e) = persist::check_file_name("snapshot", name) {
+                warn!("{}", e);
+                return Ok(None);
+            }
+            if state.snapshot_names.contains(name) {
+                // Don't overwrite an existing snapshot.
+                warn!("Snapshot name {} already exists.", name);
+                return Ok(None);
+            }
+            create_snapshot(state, name)?;
+            state.dialogue = Some(Dialogue::Settings);
+        }
+        Message::RestoreSnapshotDialogue(name) => {
+            state.dialogue = Some(Dialogue::RestoreSnapshot { name: name.clone() });
+        }
+        Message::RestoreSnapshot(name) => {
+            restore_snapshot(state, name)?;
+            state.dialogue = None;
+        }
+        Message::SaveStampDialogue => {
+            if state.selected_gfx.is_empty() {
+                warn!("No selection to save as a stamp.");
+                return Ok(None);
+            }
+            state.dialogue = Some(Dialogue::SaveStamp {
+                name: "".to_string(),
+            });
+            return Ok(Some(iced::widget::text_input::focus("SaveStamp")));
+        }
+        Message::SetSaveStampName(new_name) => {
+            if let Some(Dialogue::SaveStamp { name }) = &mut state.dialogue {
+                *name = new_name.clone();
+            }
+        }
+        Message::SaveStamp(name) => {
+            if name.is_empty() {
+                warn!("Empty stamp name is invalid.");
+                return Ok(None);
+            }
+            if state.stamps.contains_key(name) {
+                // Don't overwrite an existing stamp.
+                warn!("Stamp name {} already exists.", name);
+                return Ok(None);
+            }
+            if state.selected_gfx.is_empty() {
+                warn!("No selection to save as a stamp.");
+                return Ok(None);
+            }
+            let stamp = Stamp {
+                name: name.clone(),
+                block: state.selected_tile_block.clone(),
+                gfx: state.selected_gfx.clone(),
+            };
+            save_stamp(state, stamp)?;
+            state.dialogue = None;
+            state.side_panel_view = SidePanelView::Stamps;
+        }
+        Message::LoadStamp(name) => {
+            let Some(stamp) = state.stamps.get(name) else {
+                warn!("Stamp {} not found.", name);
+                return Ok(None);
+            };
+            let block = stamp.block.clone();
+            // Placing the stamp goes through the brush like any other selection:
+            set_tool(state, Tool::Brush);
+            state.selection_source = SelectionSource::Area(AreaPosition::Main);
+            state.selection_coords = None;
+            state.copy_coords = None;
+            state.start_coords = None;
+            state.end_coords = None;
+            state.stamp_flip = Flip::None;
+            // The graphics are taken from the current palettes rather than the stored ones,
+            // so the preview matches what will be painted:
+            state.selected_gfx = get_selected_gfx(state, &block);
+            state.selected_tile_block = block;
+        }
+        Message::DeleteStamp(name) => {
+            delete_stamp(state, name)?;
+        }
     }
     Ok(Some(Task::none()))
 }
@@ -1456,33 +3660,48 @@ pub fn try_update(state: &mut EditorState, message: &Message) -> Result<Option<T
 pub fn update(state: &mut EditorState, mut message: Message) -> Task<Message> {
     // Handle undo/redo controls:
     let mut undo = false;
+    let mut history = None;
     match &message {
         Message::Event(Event::Keyboard(keyboard::Event::KeyPressed {
             key: keyboard::Key::Character(c),
             modifiers,
             ..
         })) if modifiers.control() && c == "z" => {
+            if state.move_tiles_job.is_some() {
+                // The history can't be changed until the move in progress is finished:
+                warn!("Not undoing or redoing while tiles are being moved.");
+                return Task::none();
+            }
             if modifiers.shift() {
                 // Redo:
-                if let Some((msg, rev_msg)) = state.redo_stack.pop() {
-                    state.undo_stack.push((msg.clone(), rev_msg));
-                    message = msg;
+                if let Some(entry) = state.redo_stack.pop() {
+                    message = entry.message.clone();
+                    state.undo_stack.push(entry);
                     undo = true;
+                    history = Some(MoveHistory::Redone);
                 }
             } else {
                 // Undo:
-                if let Some((msg, rev_msg)) = state.undo_stack.pop() {
-                    state.redo_stack.push((msg, rev_msg.clone()));
-                    message = rev_msg;
+                if let Some(entry) = state.undo_stack.pop() {
+                    message = entry.reverse_message.clone();
+                    state.redo_stack.push(entry);
                     undo = true;
+                    history = Some(MoveHistory::Undone);
                 }
             }
         }
+        &Message::JumpToHistory(n) => {
+            if state.move_tiles_job.is_some() {
+                warn!("Not undoing or redoing while tiles are being moved.");
+                return Task::none();
+            }
+            return jump_to_history(state, n);
+        }
         _ => {}
     }
 
-    if let Some((last_message, _)) = state.undo_stack.last() {
-        if !undo && should_debounce(&message, last_message) {
+    if let Some(last) = state.undo_stack.last() {
+        if !undo && should_debounce(&message, &last.message) {
             return Task::none();
         }
     }
@@ -1500,8 +3719,35 @@ pub fn update(state: &mut EditorState, mut message: Message) -> Task<Message> {
         }
     };
 
-    match try_update(state, &message) {
+    if state.read_only && writes_project(&message, &undo_action) {
+        warn!("The project is view-only, as its directory is not writable.");
+        return Task::none();
+    }
+
+    // Messages that can change the cells or the palettes, after which the broken cells of the
+    // loaded areas are recounted (rather than on every redraw):
+    let recount = undo
+        || !matches!(undo_action, UndoAction::None)
+        || matches!(
+            message,
+            Message::ModifiedReload | Message::MoveTilesStep | Message::CancelMove
+        );
+
+    let task = match try_update(state, &message) {
         Ok(Some(t)) => {
+            // Advance the color after drawing a pixel (but not when undoing/redoing one):
+            if !undo && state.global_config.auto_advance_color {
+                if let Message::BrushPixel { color_idx, .. } = message {
+                    state.color_idx = Some((color_idx + 1) % 16);
+                }
+            }
+            // A move of tiles only changes the history once it has started (rather than e.g.
+            // asking to confirm collisions first), and the change is recorded on the move so
+            // that it can be reverted if the move is cancelled or fails:
+            let is_move = matches!(message, Message::MoveTiles { .. });
+            if is_move && state.move_tiles_job.is_none() {
+                return t;
+            }
             // The update was successful, so update the undo stack if applicable:
             match undo_action {
                 UndoAction::None => {}
@@ -1510,8 +3756,19 @@ pub fn update(state: &mut EditorState, mut message: Message) -> Task<Message> {
                     state.redo_stack.clear();
                 }
                 UndoAction::Ok(reverse_message) => {
-                    state.undo_stack.push((message, reverse_message));
+                    state.undo_stack.push(UndoEntry {
+                        label: undo_label(&message),
+                        message,
+                        reverse_message,
+                    });
                     state.redo_stack.clear();
+                    trim_undo_stack(state);
+                    history = Some(MoveHistory::Pushed);
+                }
+            }
+            if is_move {
+                if let Some(job) = &mut state.move_tiles_job {
+                    job.history = history;
                 }
             }
             t
@@ -1531,12 +3788,298 @@ pub fn update(state: &mut EditorState, mut message: Message) -> Task<Message> {
             if let Err(e) = state.enable_watch_file_changes() {
                 error!("Error re-enabling watcher: {}\n{}", e, e.backtrace());
             }
-            return Task::none();
+            Task::none()
+        }
+    };
+    if recount {
+        state.update_broken_cells();
+    }
+    task
+}
+
+// The (undoable) message removing the empty edge screens of an area, if it has any.
+fn trim_empty_edges_message(state: &EditorState, area_id: &AreaId) -> Result<Option<Message>> {
+    let area = state.areas.get(area_id).context("area not loaded")?;
+    let (xs, ys) = area.trimmed_bounds();
+    if (xs.clone(), ys.clone()) == (0..area.size.0, 0..area.size.1) {
+        return Ok(None);
+    }
+    let mut screens = vec![];
+    for y in ys.clone() {
+        for x in xs.clone() {
+            let mut screen = area.screens[y as usize * area.size.0 as usize + x as usize].clone();
+            screen.position = (x - xs.start, y - ys.start);
+            screens.push(screen);
+        }
+    }
+    let size = (xs.len() as u8, ys.len() as u8);
+    info!(
+        "Trimmed area {} from {}x{} to {}x{} screens.",
+        area_id.area, area.size.0, area.size.1, size.0, size.1
+    );
+    Ok(Some(Message::SetAreaScreens {
+        area_id: area_id.clone(),
+        size,
+        screens,
+    }))
+}
+
+// If enabled, trim the empty edges of the modified areas before an explicit save, closing the
+// window, or switching projects. (Not on autosave, which would trim rows or columns the user
+// has only just added.) Each trim is applied as an undoable step.
+fn trim_modified_areas(state: &mut EditorState) -> Result<()> {
+    if !state.global_config.trim_empty_edges_on_save || state.read_only {
+        return Ok(());
+    }
+    let area_ids: Vec<AreaId> = [AreaPosition::Main, AreaPosition::Side]
+        .into_iter()
+        .map(|position| state.area_id(position).clone())
+        .unique()
+        .filter(|area_id| state.areas[area_id].modified)
+        .collect();
+    for area_id in &area_ids {
+        if let Some(message) = trim_empty_edges_message(state, area_id)? {
+            let _ = update(state, message);
+        }
+    }
+    Ok(())
+}
+
+// Compare tiles, ignoring their index.
+fn same_tile(a: &Tile, b: &Tile) -> bool {
+    Tile { id: None, ..*a } == Tile { id: None, ..*b }
+}
+
+// Find groups of palettes with identical colors (and optionally identical tiles).
+// Each group is ordered by palette ID, with the first palette being the one to keep.
+pub fn find_duplicate_palettes(
+    state: &EditorState,
+    require_identical_tiles: bool,
+) -> Vec<Vec<PaletteId>> {
+    let mut groups: Vec<Vec<PaletteId>> = vec![];
+    let mut palettes: Vec<&Palette> = state.palettes.iter().collect();
+    palettes.sort_by_key(|p| p.id);
+    for pal in palettes {
+        let matching_group = groups.iter_mut().find(|g| {
+            let survivor = &state.palettes[state.palettes_id_idx_map[&g[0]]];
+            survivor.colors == pal.colors
+                && (!require_identical_tiles
+                    || (survivor.tiles.len() == pal.tiles.len()
+                        && survivor
+                            .tiles
+                            .iter()
+                            .zip(&pal.tiles)
+                            .all(|(a, b)| same_tile(a, b))))
+        });
+        match matching_group {
+            Some(g) => g.push(pal.id),
+            None => groups.push(vec![pal.id]),
+        }
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+fn count_paint(
+    state: &mut EditorState,
+    area_id: &AreaId,
+    coords: impl IntoIterator<Item = (TileCoord, TileCoord)>,
+) {
+    let counts = state.paint_counts.entry(area_id.clone()).or_default();
+    for c in coords {
+        *counts.entry(c).or_default() += 1;
+    }
+}
+
+// Whether a message changes the project files, so that it's rejected in a view-only project.
+// Edits are recognized by having an undo action (or being irreversible), apart from opening
+// or reloading the project:
+fn writes_project(message: &Message, undo_action: &UndoAction) -> bool {
+    match message {
+        Message::ProjectOpened(_) | Message::ExternalChange(_) => false,
+        Message::DiscardAutosave
+        | Message::RebuildProject
+        | Message::CreateSnapshot(_)
+        | Message::SaveStamp(_)
+        | Message::DeleteStamp(_)
+        | Message::TrimEmptyEdges(_) => true,
+        _ => !matches!(undo_action, UndoAction::None),
+    }
+}
+
+// Remap the next area of the move in progress, returning the fraction of the areas done so
+// far, or `None` if the move is finished (or there is none). If an area fails, the areas
+// already remapped are restored and the move is abandoned.
+fn move_tiles_step(state: &mut EditorState) -> Result<Option<f32>> {
+    let Some(mut job) = state.move_tiles_job.take() else {
+        return Ok(None);
+    };
+    let Some(area_id) = job.remaining.pop() else {
+        state.dialogue = None;
+        return Ok(None);
+    };
+    match remap_area_tiles(state, &area_id, &job.mapping) {
+        Ok(original) => job.originals.push((area_id, original)),
+        Err(e) => {
+            roll_back_move_tiles(state, job)?;
+            return Err(e.context(format!("Error moving tiles in {:?}", area_id)));
+        }
+    }
+    let progress = job.originals.len() as f32 / job.total.max(1) as f32;
+    state.move_tiles_job = Some(job);
+    Ok(Some(progress))
+}
+
+// Run the move in progress (if any) to completion without yielding to the UI, e.g. when
+// replaying moves from the undo history.
+fn finish_move_tiles(state: &mut EditorState) -> Result<()> {
+    while move_tiles_step(state)?.is_some() {}
+    Ok(())
+}
+
+// Put back the areas remapped so far by a move, along with the undo history, and close its
+// progress dialogue.
+fn roll_back_move_tiles(state: &mut EditorState, job: MoveTilesJob) -> Result<()> {
+    state.dialogue = None;
+    match job.history {
+        Some(MoveHistory::Pushed) => {
+            state.undo_stack.pop();
+        }
+        Some(MoveHistory::Undone) => {
+            if let Some(entry) = state.redo_stack.pop() {
+                state.undo_stack.push(entry);
+            }
+        }
+        Some(MoveHistory::Redone) => {
+            if let Some(entry) = state.undo_stack.pop() {
+                state.redo_stack.push(entry);
+            }
+        }
+        None => {}
+    }
+    for (area_id, area) in job.originals.into_iter().rev() {
+        restore_area(state, &area_id, area)?;
+    }
+    Ok(())
+}
+
+// Undo or redo edits until the given number of them are applied, i.e. until the undo stack
+// has that length:
+fn jump_to_history(state: &mut EditorState, n: usize) -> Task<Message> {
+    let mut tasks = vec![];
+    while state.undo_stack.len() != n {
+        let undoing = state.undo_stack.len() > n;
+        let entry = if undoing {
+            state.undo_stack.pop()
+        } else {
+            state.redo_stack.pop()
+        };
+        let Some(entry) = entry else {
+            break;
+        };
+        let message = if undoing {
+            entry.reverse_message.clone()
+        } else {
+            entry.message.clone()
+        };
+        // A move of tiles is run to completion before the next edit is replayed, rather than
+        // one area per update:
+        match try_update(state, &message).and_then(|t| finish_move_tiles(state).map(|_| t)) {
+            Ok(t) => tasks.extend(t),
+            Err(e) => {
+                error!(
+                    "Error jumping in the undo history: {}\n{}",
+                    e,
+                    e.backtrace()
+                );
+                // Keep the entry where it was, and stop:
+                if undoing {
+                    state.undo_stack.push(entry);
+                } else {
+                    state.redo_stack.push(entry);
+                }
+                break;
+            }
+        }
+        if undoing {
+            state.redo_stack.push(entry);
+        } else {
+            state.undo_stack.push(entry);
         }
     }
+    state.update_broken_cells();
+    Task::batch(tasks)
+}
+
+// Drop the oldest undo entries beyond the configured number of levels or memory budget.
+// The most recent entry is always kept, even if it alone exceeds the budget.
+fn trim_undo_stack(state: &mut EditorState) {
+    let max_levels = state.global_config.max_undo_levels as usize;
+    let budget = state.global_config.undo_memory_mb as usize * 1024 * 1024;
+    let sizes: Vec<usize> = state
+        .undo_stack
+        .iter()
+        .map(|e| message_size(&e.message) + message_size(&e.reverse_message))
+        .collect();
+    let mut total: usize = sizes.iter().sum();
+    let mut n = 0;
+    while n + 1 < sizes.len() && (sizes.len() - n > max_levels || total > budget) {
+        total -= sizes[n];
+        n += 1;
+    }
+    if n > 0 {
+        info!("Dropping {} oldest undo entries", n);
+        state.undo_stack.drain(..n);
+    }
+}
+
+// Record where editing is at in the global config, so it can be restored next time:
+fn remember_last_screen(state: &mut EditorState) {
+    let screen_size = 256.0 * state.global_config.pixel_size;
+    let offset = state.main_area_scroll_offset;
+    let size = state.main_area().size;
+    let screen = ScreenId {
+        area: state.main_area_id.area.clone(),
+        x: ((offset.x / screen_size).round() as u8).min(size.0.saturating_sub(1)),
+        y: ((offset.y / screen_size).round() as u8).min(size.1.saturating_sub(1)),
+    };
+    let config = &mut state.global_config;
+    if config.last_screen.as_ref() != Some(&screen)
+        || config.last_theme.as_ref() != Some(&state.main_area_id.theme)
+        || config.last_tool != state.tool
+    {
+        config.last_screen = Some(screen);
+        config.last_theme = Some(state.main_area_id.theme.clone());
+        config.last_tool = state.tool;
+        config.modified = true;
+    }
+}
+
+// Tiles of the current palette that are selected in the tileset:
+pub fn selected_tileset_tiles(state: &EditorState) -> Vec<TileIdx> {
+    if state.selection_source != SelectionSource::Tileset {
+        return state.tile_idx.into_iter().collect();
+    }
+    let block = &state.selected_tile_block;
+    let palette_id = state.palettes[state.palette_idx].id;
+    let mut tiles = vec![];
+    for y in 0..block.size.1 {
+        for x in 0..block.size.0 {
+            let (xu, yu) = (x as usize, y as usize);
+            if block.is_selected(x, y) && block.palettes[yu][xu] == palette_id {
+                tiles.push(block.tiles[yu][xu]);
+            }
+        }
+    }
+    tiles.sort();
+    tiles.dedup();
+    tiles
 }
 
 pub fn update_palette_order(state: &mut EditorState) {
+    state.palette_idx = state
+        .palette_idx
+        .min(state.palettes.len().saturating_sub(1));
     let id = state.palettes[state.palette_idx].id;
     state.palettes.sort_by(|x, y| x.id.cmp(&y.id));
     state.palettes_id_idx_map.clear();
@@ -1548,7 +4091,230 @@ pub fn update_palette_order(state: &mut EditorState) {
     }
 }
 
-pub fn get_selected_gfx(state: &EditorState, s: &TileBlock) -> Vec<Vec<Tile>> {
+pub fn set_pixel_size(state: &mut EditorState, pixel_size: f32) {
+    state.global_config.pixel_size = pixel_size;
+    if state.global_config.per_area_zoom {
+        state
+            .global_config
+            .area_zoom
+            .insert(state.main_area_id.area.clone(), pixel_size);
+    }
+    state.global_config.modified = true;
+}
+
+// Switch an area view to the given area, returning whether it succeeded. If the area can't be
+// loaded (e.g. its file is missing or malformed), the view is left as it was and the error is
+// shown in a dialogue offering to retry the message that made the switch.
+fn switch_area_or_report(
+    state: &mut EditorState,
+    position: AreaPosition,
+    area_id: AreaId,
+    message: &Message,
+) -> bool {
+    match state.switch_area(position, &area_id) {
+        Ok(()) => {
+            if let Some(Dialogue::AreaLoadError { .. }) = state.dialogue {
+                state.dialogue = None;
+            }
+            true
+        }
+        Err(e) => {
+            warn!(
+                "Unable to load area {} (theme {}): {}",
+                area_id.area, area_id.theme, e
+            );
+            state.dialogue = Some(Dialogue::AreaLoadError {
+                area_id,
+                error: format!("{:#}", e),
+                retry: message.clone(),
+            });
+            false
+        }
+    }
+}
+
+// Insert a row of tiles into a palette's tileset before the given row.
+fn insert_tile_row(
+    state: &mut EditorState,
+    palette_id: PaletteId,
+    row: usize,
+    tiles: Vec<Tile>,
+) -> Result<()> {
+    let idx = *state
+        .palettes_id_idx_map
+        .get(&palette_id)
+        .context("palette not found")?;
+    let pos = row * 16;
+    let num_tiles = state.palettes[idx].tiles.len();
+    state.palettes[idx].tiles.splice(pos..pos, tiles);
+    state.palettes[idx].modified = true;
+    shift_tile_references(state, palette_id, pos as TileIdx..num_tiles as TileIdx, 16)
+}
+
+// Delete a row of tiles from a palette's tileset, along with the tags of its tiles.
+fn delete_tile_row(state: &mut EditorState, palette_id: PaletteId, row: usize) -> Result<()> {
+    let idx = *state
+        .palettes_id_idx_map
+        .get(&palette_id)
+        .context("palette not found")?;
+    let pos = row * 16;
+    let num_tiles = state.palettes[idx].tiles.len();
+    let pal = &mut state.palettes[idx];
+    pal.tiles.drain(pos..pos + 16);
+    pal.tags
+        .retain(|&t, _| !(pos as TileIdx..pos as TileIdx + 16).contains(&t));
+    pal.modified = true;
+
+    // Drop a selection of tiles that no longer exist:
+    let removed = pos as TileIdx..pos as TileIdx + 16;
+    let block = &state.selected_tile_block;
+    let stale = (0..block.size.1 as usize).any(|y| {
+        (0..block.size.0 as usize)
+            .any(|x| block.palettes[y][x] == palette_id && removed.contains(&block.tiles[y][x]))
+    });
+    if stale {
+        clear_selection(state);
+        state.selected_tile_block = TileBlock::default();
+    }
+    if idx == state.palette_idx && state.tile_idx.is_some_and(|t| removed.contains(&t)) {
+        state.tile_idx = None;
+    }
+    shift_tile_references(
+        state,
+        palette_id,
+        pos as TileIdx + 16..num_tiles as TileIdx,
+        -16,
+    )
+}
+
+// Shift references to a range of a palette's tiles (in the areas, and in the palette's tags
+// and pages, and the selection) by the given offset, following tiles that were moved by
+// inserting or deleting tiles before them.
+fn shift_tile_references(
+    state: &mut EditorState,
+    palette_id: PaletteId,
+    range: Range<TileIdx>,
+    offset: i32,
+) -> Result<()> {
+    let shift = |t: TileIdx| (t as i32 + offset) as TileIdx;
+
+    // Areas are remapped on disk, so first save any unsaved edits to the loaded areas:
+    for area_id in state.areas.keys().cloned().collect::<Vec<_>>() {
+        persist::save_area(state, &area_id)?;
+    }
+    let mapping: HashMap<(PaletteId, TileIdx), (PaletteId, TileIdx, Flip)> = range
+        .clone()
+        .map(|t| ((palette_id, t), (palette_id, shift(t), Flip::None)))
+        .collect();
+    remap_tiles(state, &mapping)?;
+
+    let idx = *state
+        .palettes_id_idx_map
+        .get(&palette_id)
+        .context("palette not found")?;
+    let pal = &mut state.palettes[idx];
+    pal.tags = std::mem::take(&mut pal.tags)
+        .into_iter()
+        .map(|(t, tags)| (if range.contains(&t) { shift(t) } else { t }, tags))
+        .collect();
+    // Page boundaries within deleted tiles move to where the tiles were deleted:
+    let shift_bound = |b: TileIdx| {
+        if b >= range.start {
+            shift(b)
+        } else if offset < 0 && b as i32 > range.start as i32 + offset {
+            shift(range.start)
+        } else {
+            b
+        }
+    };
+    for (_, r) in &mut pal.pages {
+        *r = shift_bound(r.start)..shift_bound(r.end);
+    }
+    for t in pal.animations.iter_mut().flat_map(|a| &mut a.frames) {
+        if range.contains(t) {
+            *t = shift(*t);
+        }
+    }
+
+    if idx == state.palette_idx {
+        if let Some(t) = state.tile_idx {
+            if range.contains(&t) {
+                state.tile_idx = Some(shift(t));
+            }
+        }
+    }
+    let block = &mut state.selected_tile_block;
+    for (pal_row, tile_row) in block.palettes.iter().zip(block.tiles.iter_mut()) {
+        for (&p, t) in pal_row.iter().zip(tile_row.iter_mut()) {
+            if p == palette_id && range.contains(t) {
+                *t = shift(*t);
+            }
+        }
+    }
+    if !state.selected_gfx.is_empty() {
+        state.selected_gfx = get_selected_gfx(state, &state.selected_tile_block);
+    }
+    Ok(())
+}
+
+// Clear the selection, along with the selected color and tile. Apart from Escape (and
+// switching tools, if the selection isn't kept), the selection only changes when a new one
+// is made, or when it no longer refers to valid tiles:
+fn clear_selection(state: &mut EditorState) {
+    state.color_idx = None;
+    state.tile_idx = None;
+    state.selected_gfx = vec![];
+    state.start_coords = None;
+    state.selection_coords = None;
+    state.copy_coords = None;
+    state.end_coords = None;
+    state.reference_coords = None;
+}
+
+fn set_tool(state: &mut EditorState, tool: Tool) {
+    if tool != state.tool && !state.global_config.keep_selection_on_tool_switch {
+        clear_selection(state);
+    }
+    state.tool = tool;
+}
+
+fn flip_selection_horizontally(state: &mut EditorState) {
+    for i in 0..state.selected_tile_block.size.1 as usize {
+        state.selected_tile_block.palettes[i].reverse();
+        state.selected_tile_block.tiles[i].reverse();
+        state.selected_tile_block.flips[i].reverse();
+        if let Some(mask) = &mut state.selected_tile_block.mask {
+            mask[i].reverse();
+        }
+        state.selected_gfx[i].reverse();
+        for j in 0..state.selected_tile_block.size.0 as usize {
+            state.selected_tile_block.flips[i][j] =
+                state.selected_tile_block.flips[i][j].flip_horizontally();
+            state.selected_gfx[i][j] = Flip::Horizontal.apply_to_tile(state.selected_gfx[i][j]);
+        }
+    }
+    state.stamp_flip = state.stamp_flip.flip_horizontally();
+}
+
+fn flip_selection_vertically(state: &mut EditorState) {
+    state.selected_tile_block.palettes.reverse();
+    state.selected_tile_block.tiles.reverse();
+    state.selected_tile_block.flips.reverse();
+    if let Some(mask) = &mut state.selected_tile_block.mask {
+        mask.reverse();
+    }
+    state.selected_gfx.reverse();
+    for i in 0..state.selected_tile_block.size.1 as usize {
+        for j in 0..state.selected_tile_block.size.0 as usize {
+            state.selected_tile_block.flips[i][j] =
+                state.selected_tile_block.flips[i][j].flip_vertically();
+            state.selected_gfx[i][j] = Flip::Vertical.apply_to_tile(state.selected_gfx[i][j]);
+        }
+    }
+    state.stamp_flip = state.stamp_flip.flip_vertically();
+}
+
+fn get_selected_gfx(state: &EditorState, s: &TileBlock) -> Vec<Vec<Tile>> {
     let mut gfx = vec![];
     for y in 0..s.size.1 {
         let mut gfx_row: Vec<Tile> = vec![];
@@ -1571,3 +4337,91 @@ pub fn get_selected_gfx(state: &EditorState, s: &TileBlock) -> Vec<Vec<Tile>> {
     }
     gfx
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::new_editor_state;
+
+    // Editor state for a fresh project in a temporary directory, with the given palettes saved.
+    fn test_state(name: &str, palette_ids: &[PaletteId]) -> EditorState {
+        let dir = std::env::temp_dir().join(format!(
+            "z3_overworld_editor_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut state = new_editor_state(dir.join("config.json"));
+        state.global_config.project_dir = Some(dir);
+        for &id in palette_ids {
+            state.palettes.push(Palette {
+                modified: true,
+                name: format!("Palette{}", id),
+                id,
+                tiles: vec![Tile::default(); 32],
+                ..Default::default()
+            });
+        }
+        update_palette_order(&mut state);
+        persist::save_palettes(&mut state).unwrap();
+        state
+    }
+
+    fn remove_test_project(state: &EditorState) {
+        let dir = state.global_config.project_dir.as_ref().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn delete_palettes_down_to_zero() {
+        let mut state = test_state("delete_palettes", &[3, 5, 8]);
+        state.palette_idx = 2;
+        for id in [8, 3, 5] {
+            let _ = try_update(&mut state, &Message::DeletePalette(id)).unwrap();
+            assert!(!state.palettes.is_empty());
+            assert!(state.palette_idx < state.palettes.len());
+            assert_eq!(state.palettes_id_idx_map.len(), state.palettes.len());
+            for (i, pal) in state.palettes.iter().enumerate() {
+                assert_eq!(state.palettes_id_idx_map[&pal.id], i);
+            }
+        }
+        // The last palette is kept rather than leaving the project without palettes:
+        assert_eq!(state.palettes.len(), 1);
+        assert_eq!(state.palettes[0].id, 5);
+        assert_eq!(state.palette_idx, 0);
+        remove_test_project(&state);
+    }
+
+    #[test]
+    fn delete_tile_row_with_last_tile_selected() {
+        let mut state = test_state("delete_tile_row", &[1]);
+        state.palette_idx = 0;
+        state.tile_idx = Some(31);
+        state.selection_source = SelectionSource::Tileset;
+        state.selected_tile_block = TileBlock {
+            size: (2, 1),
+            palettes: vec![vec![1, 1]],
+            tiles: vec![vec![30, 31]],
+            flips: vec![vec![Flip::None, Flip::None]],
+            mask: None,
+        };
+        state.selected_gfx = get_selected_gfx(&state, &state.selected_tile_block);
+
+        let _ = try_update(&mut state, &Message::DeleteTileRow(1)).unwrap();
+        let num_tiles = state.palettes[0].tiles.len();
+        assert_eq!(num_tiles, 16);
+        assert_eq!(state.tile_idx, Some(15));
+        let block = &state.selected_tile_block;
+        assert!(block
+            .tiles
+            .iter()
+            .flatten()
+            .all(|&t| (t as usize) < num_tiles));
+        assert_eq!(state.selected_gfx.len(), block.size.1 as usize);
+        assert!(state
+            .selected_gfx
+            .iter()
+            .all(|row| row.len() == block.size.0 as usize));
+        remove_test_project(&state);
+    }
+}
@@ -2,34 +2,51 @@ mod area;
 mod graphics;
 mod palette;
 mod settings;
+mod stamps;
 mod tiles;
 
 use std::path::PathBuf;
 
 use area::{
-    add_area_view, add_theme_view, area_grid_view, delete_area_view, delete_theme_view,
-    edit_area_view, main_area_controls, rename_theme_view, side_area_controls,
+    add_area_view, add_theme_view, area_grid_view, area_load_error_view, broken_cells_indicator,
+    cell_inspector_view, confirm_fill_view, delete_area_view, delete_theme_view, edit_area_view,
+    main_area_controls, remap_palette_in_region_view, rename_theme_view,
+    replace_palette_references_view, screen_notes_view, side_area_controls, stamp_status_view,
+    theme_colors_view, world_overview_view,
 };
-use graphics::graphics_view;
+pub use area::{scroll_to_last_screen, scroll_to_screen};
+use graphics::{compare_tiles_view, graphics_view};
 use iced::{
     alignment::Vertical,
     widget::{
-        button, center, column, container, horizontal_space, mouse_area, opaque, responsive, row,
-        stack, text, Column, Space,
+        button, canvas, center, column, container, horizontal_space, mouse_area, opaque, pick_list,
+        responsive, row, scrollable, stack, text, text_input, Column, Space,
     },
-    Element, Font, Length, Theme,
+    Element, Font, Length, Point, Size, Theme,
 };
-use iced_aw::quad;
+use iced_aw::{number_input, quad};
 use palette::{
-    add_palette_view, delete_palette_view, rename_palette_view, selected_palette_view,
-    used_palettes_view,
+    add_palette_view, all_palettes_view, delete_palette_view, merge_palettes_view,
+    rename_palette_view, selected_palette_view, used_palettes_view,
+};
+use settings::{
+    create_snapshot_view, import_rom_confirm_view, import_rom_progress_view, recover_autosave_view,
+    restore_snapshot_view, rom_diff_progress_view, settings_view,
+};
+use stamps::{save_stamp_view, stamps_view};
+pub use tiles::scroll_to_tile;
+use tiles::{
+    import_tileset_png_report_view, import_tileset_png_view, move_tiles_view,
+    moving_tiles_progress_view, tile_pages_view, tile_view,
 };
-use settings::{import_rom_confirm_view, import_rom_progress_view, settings_view};
-use tiles::{move_tiles_view, moving_tiles_progress_view, tile_view};
 
 use crate::{
+    helpers::{format_number, parse_number},
     message::Message,
-    state::{AreaPosition, Dialogue, EditorState, SidePanelView},
+    state::{
+        AreaPosition, Dialogue, EditorState, FillMode, ProjectStats, ScreenLayer, SidePanelView,
+        Tool,
+    },
 };
 
 pub async fn open_project() -> Option<PathBuf> {
@@ -40,6 +57,111 @@ pub async fn open_project() -> Option<PathBuf> {
     picked_dir.map(|x| x.path().to_owned())
 }
 
+pub async fn open_png() -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Select a PNG tileset ...")
+        .add_filter("PNG image", &["png"])
+        .pick_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn open_chr() -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Select SNES 4bpp graphics ...")
+        .add_filter("SNES 4bpp graphics", &["chr", "bin"])
+        .pick_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn save_selection_tileset() -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Save selected tiles as ...")
+        .add_filter("PNG image", &["png"])
+        .set_file_name("tiles.png")
+        .save_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn save_contact_sheet() -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Save contact sheet as ...")
+        .add_filter("PNG image", &["png"])
+        .set_file_name("contact_sheet.png")
+        .save_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn save_palettes_bin() -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Save palettes as ...")
+        .add_filter("Binary data", &["bin"])
+        .set_file_name("palettes.bin")
+        .save_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn save_palette_colors(name: String) -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Save palette colors as ...")
+        .add_filter("JSON color list", &["json"])
+        .add_filter("CSS custom properties", &["css"])
+        .set_file_name(format!("{}.json", name))
+        .save_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn save_palette_chr(name: String) -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Save palette tiles as ...")
+        .add_filter("SNES 4bpp graphics", &["chr"])
+        .set_file_name(format!("{}.chr", name))
+        .save_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn open_palettes_bin() -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Select palette data ...")
+        .add_filter("Binary data", &["bin", "pal"])
+        .pick_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn save_project_archive() -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Save project archive as ...")
+        .add_filter("ZIP archive", &["zip"])
+        .set_file_name("project.zip")
+        .save_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn open_project_archive() -> Option<PathBuf> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_title("Select a project archive ...")
+        .add_filter("ZIP archive", &["zip"])
+        .pick_file()
+        .await;
+    picked_file.map(|x| x.path().to_owned())
+}
+
+pub async fn pick_archive_destination() -> Option<PathBuf> {
+    let picked_dir = rfd::AsyncFileDialog::new()
+        .set_title("Select an empty folder for the imported project ...")
+        .pick_folder()
+        .await;
+    picked_dir.map(|x| x.path().to_owned())
+}
+
 pub async fn open_rom() -> Option<PathBuf> {
     let picked_dir = rfd::AsyncFileDialog::new()
         .set_title("Select a ROM ...")
@@ -89,6 +211,54 @@ pub fn modal_background_style(theme: &Theme) -> container::Style {
     }
 }
 
+// Input for a tile number or palette ID, in the base chosen by the `display_hex` setting.
+// Entries that don't parse or are out of range are ignored.
+pub fn index_input<'a>(
+    value: u16,
+    max: u16,
+    hex: bool,
+    width: f32,
+    on_change: impl Fn(u16) -> Message + Copy + 'static,
+    on_submit: Option<Message>,
+) -> Element<'a, Message> {
+    if hex {
+        text_input("", &format_number(value, true))
+            .on_input(move |s| match parse_number(&s, true) {
+                Some(x) if x <= max => on_change(x),
+                _ => Message::Nothing,
+            })
+            .on_submit_maybe(on_submit)
+            .width(width)
+            .into()
+    } else {
+        let input = number_input(&value, 0..=max, on_change).width(width);
+        match on_submit {
+            Some(msg) => input.on_submit(msg).into(),
+            None => input.into(),
+        }
+    }
+}
+
+// Add a rectangle to the path, starting `phase` pixels along its top edge. Shifting the
+// starting point moves the dash pattern of a dashed stroke, making the "marching ants" of
+// a selection in progress march as the phase advances.
+fn marching_rectangle(
+    builder: &mut canvas::path::Builder,
+    top_left: Point,
+    size: Size,
+    phase: f32,
+) {
+    let (x0, y0) = (top_left.x, top_left.y);
+    let (x1, y1) = (x0 + size.width, y0 + size.height);
+    let start = x0 + phase % size.width.max(1.0);
+    builder.move_to(Point::new(start, y0));
+    builder.line_to(Point::new(x1, y0));
+    builder.line_to(Point::new(x1, y1));
+    builder.line_to(Point::new(x0, y1));
+    builder.line_to(Point::new(x0, y0));
+    builder.line_to(Point::new(start, y0));
+}
+
 fn vertical_separator() -> quad::Quad {
     quad::Quad {
         quad_color: iced::Color::from([0.5; 3]).into(),
@@ -102,24 +272,228 @@ fn vertical_separator() -> quad::Quad {
     }
 }
 
+pub fn confirm_exit_view(
+    state: &EditorState,
+    window_id: iced::window::Id,
+) -> Element<'static, Message> {
+    if state.global_config.autosave_to_shadow && state.has_unsaved_changes() {
+        return container(
+            column![
+                text("Exit the editor?"),
+                text("Unsaved changes are only in the autosave, not yet in the project files."),
+                text("A kept autosave is offered for recovery when the project is next opened."),
+                row![
+                    button(text("Save to project"))
+                        .style(button::success)
+                        .on_press(Message::SaveAndCloseWindow(window_id)),
+                    button(text("Keep autosave"))
+                        .style(button::secondary)
+                        .on_press(Message::ConfirmWindowClose(window_id)),
+                    button(text("Discard changes"))
+                        .style(button::danger)
+                        .on_press(Message::DiscardAndCloseWindow(window_id)),
+                    horizontal_space(),
+                    button(text("Cancel"))
+                        .style(button::secondary)
+                        .on_press(Message::CloseDialogue),
+                ]
+                .spacing(10),
+            ]
+            .spacing(10),
+        )
+        .width(550)
+        .padding(25)
+        .style(modal_background_style)
+        .into();
+    }
+    container(
+        column![
+            text("Exit the editor?"),
+            text(if state.has_unsaved_changes() {
+                "Unsaved changes will be saved to the project files."
+            } else {
+                "There are no unsaved changes."
+            }),
+            row![
+                button(text("Exit"))
+                    .style(button::danger)
+                    .on_press(Message::ConfirmWindowClose(window_id)),
+                button(text("Cancel"))
+                    .style(button::secondary)
+                    .on_press(Message::CloseDialogue),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10),
+    )
+    .width(400)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} bytes", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.2} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+pub fn project_stats_view<'a>(
+    state: &'a EditorState,
+    stats: &'a ProjectStats,
+) -> Element<'a, Message> {
+    let (tile_bytes, palette_bytes, tilemap_bytes) = stats.rom_bytes();
+    let stat_row =
+        |label: &'static str, value: String| row![text(label).width(200), text(value)].spacing(10);
+
+    let mut palettes_col = Column::new().spacing(5);
+    for (name, id, num_tiles) in &stats.palette_tiles {
+        palettes_col = palettes_col.push(
+            row![
+                text(format!(
+                    "{} {}",
+                    format_number(*id, state.global_config.display_hex),
+                    name
+                ))
+                .width(Length::Fill),
+                text(format!("{} tiles", num_tiles)),
+            ]
+            .spacing(10),
+        );
+    }
+
+    container(
+        column![
+            text("Project statistics"),
+            stat_row("Palettes", stats.palette_tiles.len().to_string()),
+            stat_row("Tiles", stats.num_tiles().to_string()),
+            stat_row(
+                "Areas (all themes)",
+                format!("{} ({} screens)", stats.num_areas, stats.num_screens)
+            ),
+            stat_row("Size on disk", format_bytes(stats.disk_bytes)),
+            text("Estimated ROM size:"),
+            stat_row("Tilesets", format_bytes(tile_bytes as u64)),
+            stat_row("Palettes", format_bytes(palette_bytes as u64)),
+            stat_row("Tilemaps", format_bytes(tilemap_bytes as u64)),
+            stat_row(
+                "Total",
+                format_bytes((tile_bytes + palette_bytes + tilemap_bytes) as u64)
+            ),
+            text("Tiles per palette:"),
+            scrollable(palettes_col).height(200),
+            button(text("Close"))
+                .style(button::secondary)
+                .on_press(Message::CloseDialogue),
+        ]
+        .spacing(10),
+    )
+    .width(450)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
 pub fn help_view(_state: &EditorState) -> Element<Message> {
     let controls = vec![
         ("s", "Select tool", "copy tiles, colors, pixels"),
         ("b", "Brush tool", "paste tiles, colors, pixels"),
+        (
+            "f",
+            "Fill tool",
+            "flood-fill connected tiles with the selection",
+        ),
+        (
+            "w",
+            "Magic wand tool",
+            "select connected matching tiles in an area",
+        ),
         (
             "m",
             "Move tool",
             "move tiles across palettes (replacing all uses)",
         ),
         ("g", "Grid view toggle", "show/hide 16x16 tile grid"),
+        (
+            "o",
+            "Tile outline toggle",
+            "show/hide outlines of 8x8 tiles",
+        ),
         ("p", "Grid snap toggle", "snapping to 16x16 tile grid"),
+        (
+            "k",
+            "Heatmap toggle",
+            "show how often tiles were painted this session",
+        ),
+        (
+            "x",
+            "Swap selections",
+            "swap tileset selection with reference (right-drag)",
+        ),
+        ("Del", "Delete", "clear the selected area tiles"),
+        (
+            "Middle-click",
+            "Eyedropper",
+            "pick the displayed color of an area pixel",
+        ),
         ("h", "Horizontal flip", "flip selection horizontally"),
-        ("v", "Vertical flip", "flip selection horizontally"),
+        ("v", "Vertical flip", "flip selection vertically"),
+        (
+            "` (hold)",
+            "Compare",
+            "show areas as of when loaded or last saved (Ctrl+S)",
+        ),
+        ("r", "Rotate", "rotate selection by 180 degrees"),
+        (
+            "F",
+            "Cycle flips",
+            "cycle selection through flip orientations",
+        ),
         ("t", "Tileset view", "show palettes/tilesets in side panel"),
         ("a", "Area view", "show secondary area in side panel"),
+        ("y", "History view", "show undo history in side panel"),
+        (
+            "l",
+            "Palettes view",
+            "show the colors of all palettes in side panel",
+        ),
+        (
+            "j",
+            "Stamps view",
+            "show saved stamps in side panel, to place or save a selection",
+        ),
+        (
+            "H / V",
+            "Mirror",
+            "place a horizontally/vertically flipped copy next to the area selection",
+        ),
         ("-", "Zoom out", "zoom out area views"),
         ("=", "Zoom in", "zoom in area views"),
+        (
+            "[ ]",
+            "Palette switch",
+            "select previous/next palette (also PgUp/PgDn)",
+        ),
         ("Ctrl", "Identify", "highlight uses of selected tile/color"),
+        (
+            "Ctrl+S",
+            "Save",
+            "save the project files (when autosaving to a separate folder)",
+        ),
+        (
+            "Ctrl+V",
+            "Paste",
+            "stamp the selection at the cursor (with Shift: where it was copied from)",
+        ),
+        (
+            "Ctrl+= / - / 0",
+            "Zoom",
+            "zoom area views in/out, or reset to the default zoom",
+        ),
     ];
     let mut col = Column::new();
     col = col.push(text("Essential keyboard controls:"));
@@ -153,11 +527,18 @@ pub fn rebuild_project_view(_state: &EditorState) -> Element<Message> {
     .into()
 }
 
-pub fn modified_reload_view(_state: &EditorState) -> Element<Message> {
+pub fn modified_reload_view<'a>(state: &'a EditorState, paths: &[PathBuf]) -> Element<'a, Message> {
+    let project_dir = state.global_config.project_dir.clone().unwrap_or_default();
+    let mut files_col = Column::new().spacing(2);
+    for path in paths {
+        let rel_path = path.strip_prefix(&project_dir).unwrap_or(path);
+        files_col = files_col.push(text(rel_path.display().to_string()).size(12));
+    }
     container(
         column![
-            text("File changes detected on disk."),
-            text("Please click 'Accept' to load these changes, to keep the editor in sync with the project data."),
+            text("File changes detected on disk, which conflict with unsaved edits in the editor."),
+            scrollable(files_col).height(Length::Shrink),
+            text("Please click 'Accept' to load these changes (discarding the unsaved edits), or 'Reject' to keep the editor's version."),
             row![
                 button(text("Accept"))
                     .style(button::success)
@@ -183,12 +564,27 @@ pub fn view_dialogue<'a>(
     if let Some(dialogue) = &state.dialogue {
         match dialogue {
             Dialogue::Settings => modal(main_view, settings_view(state), Message::HideModal),
-            Dialogue::AddPalette { name, id } => {
-                modal(main_view, add_palette_view(name, *id), Message::HideModal)
-            }
-            Dialogue::DeletePalette => {
-                modal(main_view, delete_palette_view(state), Message::HideModal)
-            }
+            Dialogue::AddPalette { name, id } => modal(
+                main_view,
+                add_palette_view(state, name, *id),
+                Message::HideModal,
+            ),
+            Dialogue::DeletePalette {
+                references,
+                remap_to,
+            } => modal(
+                main_view,
+                delete_palette_view(state, references, *remap_to),
+                Message::HideModal,
+            ),
+            Dialogue::MergePalettes {
+                require_identical_tiles,
+                groups,
+            } => modal(
+                main_view,
+                merge_palettes_view(state, *require_identical_tiles, groups),
+                Message::HideModal,
+            ),
             Dialogue::RenamePalette { name } => modal(
                 main_view,
                 rename_palette_view(&state, name),
@@ -200,6 +596,11 @@ pub fn view_dialogue<'a>(
             Dialogue::EditArea { name } => {
                 modal(main_view, edit_area_view(state, name), Message::HideModal)
             }
+            Dialogue::ScreenNotes { screen, content } => modal(
+                main_view,
+                screen_notes_view(state, *screen, content),
+                Message::HideModal,
+            ),
             Dialogue::DeleteArea => modal(main_view, delete_area_view(state), Message::HideModal),
             Dialogue::AddTheme { name } => {
                 modal(main_view, add_theme_view(name), Message::HideModal)
@@ -210,6 +611,17 @@ pub fn view_dialogue<'a>(
                 Message::HideModal,
             ),
             Dialogue::DeleteTheme => modal(main_view, delete_theme_view(state), Message::HideModal),
+            Dialogue::ThemeColors => modal(main_view, theme_colors_view(state), Message::HideModal),
+            Dialogue::WorldOverview {
+                overview,
+                link_from,
+                link_side,
+                link_to,
+            } => modal(
+                main_view,
+                world_overview_view(state, overview, link_from.as_ref(), *link_side, link_to),
+                Message::HideModal,
+            ),
             Dialogue::ImportROMConfirm => modal(
                 main_view,
                 import_rom_confirm_view(state),
@@ -218,32 +630,166 @@ pub fn view_dialogue<'a>(
             Dialogue::ImportROMProgress => {
                 modal(main_view, import_rom_progress_view(state), Message::Nothing)
             }
+            Dialogue::ImportTilesetPNG { path, quantize } => modal(
+                main_view,
+                import_tileset_png_view(state, path, *quantize),
+                Message::HideModal,
+            ),
+            Dialogue::CompareTiles { a, b } => modal(
+                main_view,
+                compare_tiles_view(state, *a, *b),
+                Message::HideModal,
+            ),
+            Dialogue::TilePages { name } => {
+                modal(main_view, tile_pages_view(state, name), Message::HideModal)
+            }
+            Dialogue::RomDiffProgress => {
+                modal(main_view, rom_diff_progress_view(), Message::Nothing)
+            }
+            Dialogue::ImportTilesetPNGReport { report }
+            | Dialogue::ProjectArchiveReport { report }
+            | Dialogue::RomDiffReport { report } => modal(
+                main_view,
+                import_tileset_png_report_view(report),
+                Message::HideModal,
+            ),
             Dialogue::Help => modal(main_view, help_view(state), Message::HideModal),
+            &Dialogue::ConfirmExit { window_id } => modal(
+                main_view,
+                confirm_exit_view(state, window_id),
+                Message::HideModal,
+            ),
+            Dialogue::ProjectStats { stats } => modal(
+                main_view,
+                project_stats_view(state, stats),
+                Message::HideModal,
+            ),
             Dialogue::RebuildProject => {
                 modal(main_view, rebuild_project_view(state), Message::Nothing)
             }
-            Dialogue::ModifiedReload => {
-                modal(main_view, modified_reload_view(state), Message::Nothing)
+            Dialogue::RecoverAutosave { changes } => {
+                modal(main_view, recover_autosave_view(changes), Message::Nothing)
             }
-            Dialogue::MovingTilesProgress => modal(
+            Dialogue::ModifiedReload { paths } => modal(
+                main_view,
+                modified_reload_view(state, paths),
+                Message::Nothing,
+            ),
+            &Dialogue::MovingTilesProgress { progress } => modal(
                 main_view,
-                moving_tiles_progress_view(state),
+                moving_tiles_progress_view(state, progress),
                 Message::Nothing,
             ),
             Dialogue::MoveTiles {
                 src_selection,
                 dst_selection,
+                collisions,
             } => modal(
                 main_view,
-                move_tiles_view(state, src_selection, dst_selection),
+                move_tiles_view(state, src_selection, dst_selection, collisions),
                 Message::Nothing,
             ),
+            Dialogue::RemapPaletteInRegion { from, to } => modal(
+                main_view,
+                remap_palette_in_region_view(state, *from, *to),
+                Message::CloseDialogue,
+            ),
+            &Dialogue::ReplacePaletteReferences {
+                from,
+                to,
+                from_references,
+                to_in_use,
+            } => modal(
+                main_view,
+                replace_palette_references_view(state, from, to, from_references, to_in_use),
+                Message::CloseDialogue,
+            ),
+            Dialogue::ConfirmFill { count, fill } => modal(
+                main_view,
+                confirm_fill_view(*count, fill),
+                Message::CloseDialogue,
+            ),
+            Dialogue::AreaLoadError {
+                area_id,
+                error,
+                retry,
+            } => modal(
+                main_view,
+                area_load_error_view(area_id, error, retry),
+                Message::CloseDialogue,
+            ),
+            Dialogue::CreateSnapshot { name } => {
+                modal(main_view, create_snapshot_view(name), Message::HideModal)
+            }
+            Dialogue::SaveStamp { name } => {
+                modal(main_view, save_stamp_view(state, name), Message::HideModal)
+            }
+            Dialogue::RestoreSnapshot { name } => {
+                modal(main_view, restore_snapshot_view(name), Message::HideModal)
+            }
         }
     } else {
         main_view
     }
 }
 
+fn fill_mode_control(state: &EditorState) -> Element<'static, Message> {
+    if state.tool != Tool::Fill {
+        return Space::with_width(0).into();
+    }
+    row![
+        text("Fill"),
+        pick_list(FillMode::ALL, Some(state.fill_mode), Message::SetFillMode).width(140),
+    ]
+    .spacing(10)
+    .align_y(Vertical::Center)
+    .into()
+}
+
+// Edits in the undo history, oldest first, followed by the undone edits that can be redone.
+// Clicking an entry undoes or redoes the edits up to and including it.
+fn history_view<'a>(state: &'a EditorState) -> Element<'a, Message> {
+    let num_applied = state.undo_stack.len();
+    let entry = |label: &str, n: usize| {
+        button(text(label.to_string()).size(12))
+            .width(Length::Fill)
+            .padding([2, 6])
+            .style(if n == num_applied {
+                button::primary
+            } else if n < num_applied {
+                button::secondary
+            } else {
+                button::text
+            })
+            .on_press_maybe((n != num_applied).then_some(Message::JumpToHistory(n)))
+    };
+    let mut col = Column::new().spacing(2).push(entry("(Initial state)", 0));
+    for (i, e) in state.undo_stack.iter().enumerate() {
+        col = col.push(entry(&e.label, i + 1));
+    }
+    for (i, e) in state.redo_stack.iter().rev().enumerate() {
+        col = col.push(entry(&e.label, num_applied + i + 1));
+    }
+    column![
+        row![
+            text("History"),
+            horizontal_space(),
+            text(format!(
+                "{} applied, {} undone",
+                num_applied,
+                state.redo_stack.len()
+            ))
+            .size(12),
+        ]
+        .align_y(Vertical::Center),
+        scrollable(col).height(Length::Fill),
+    ]
+    .padding(10)
+    .spacing(10)
+    .width(440)
+    .into()
+}
+
 pub fn view(state: &EditorState) -> Element<Message> {
     if state.global_config.project_dir.is_none() {
         return Space::new(Length::Fill, Length::Fill).into();
@@ -255,14 +801,48 @@ pub fn view(state: &EditorState) -> Element<Message> {
                 .style(button::secondary)
                 .on_press(Message::SettingsDialogue),
             main_area_controls(state),
-            horizontal_space(),
+        ]
+        .push_maybe(broken_cells_indicator(state))
+        .extend([
+            horizontal_space().into(),
+            text("Layer").into(),
+            pick_list(
+                ScreenLayer::ALL,
+                Some(state.screen_layer),
+                Message::SetScreenLayer,
+            )
+            .width(100)
+            .into(),
+            fill_mode_control(state),
+            button(text("\u{F17E}").font(iced_fonts::BOOTSTRAP_FONT))
+                .style(button::secondary)
+                .on_press(Message::ProjectStatsDialogue)
+                .into(),
             button(text("\u{F505}").font(iced_fonts::BOOTSTRAP_FONT))
                 .style(button::secondary)
-                .on_press(Message::HelpDialogue),
-        ]
+                .on_press(Message::HelpDialogue)
+                .into(),
+        ])
         .spacing(10),
         area_grid_view(state, AreaPosition::Main),
     ]
+    .push_maybe(
+        state
+            .inspected_cell()
+            .map(|coords| cell_inspector_view(state, coords)),
+    )
+    .push_maybe(stamp_status_view(state))
+    .push_maybe(
+        state
+            .compare_saved
+            .then(|| text("Showing the last saved version").size(12)),
+    )
+    .push_maybe(state.read_only.then(|| {
+        text("\u{F33A} View-only: the project directory is not writable, so changes are disabled")
+            .font(iced_fonts::BOOTSTRAP_FONT)
+            .size(12)
+            .style(text::danger)
+    }))
     .padding(10)
     .spacing(10)
     .into();
@@ -285,6 +865,9 @@ pub fn view(state: &EditorState) -> Element<Message> {
         .spacing(10)
         .width(440)
         .into(),
+        SidePanelView::History => history_view(state),
+        SidePanelView::Palettes => all_palettes_view(state),
+        SidePanelView::Stamps => stamps_view(state),
     };
 
     let mut main_view: Element<Message> = row![main_panel, vertical_separator(), side_panel,]
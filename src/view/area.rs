@@ -1,27 +1,36 @@
 // Module for displaying/editing an area
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use iced::{
     alignment::Vertical,
     mouse,
     widget::{
-        button, canvas, column, container, pick_list, row,
-        scrollable::{Direction, Scrollbar},
-        stack, text, text_input, Scrollable, Space,
+        button, canvas, checkbox, column, container, horizontal_space, image, pick_list,
+        responsive, row,
+        scrollable::{self, Direction, Scrollbar},
+        stack, text, text_editor, text_input, Column, Scrollable, Space,
     },
-    Element, Length, Padding, Point, Rectangle, Size,
+    Element, Length, Padding, Point, Rectangle, Size, Task, Vector,
 };
 use iced_aw::number_input;
 
 use crate::{
-    helpers::{alpha_blend, scale_color},
+    helpers::{
+        alpha_blend, broken_tile_color, cell_noise, display_color, format_number,
+        outline_thickness, palette_id_color, palette_label, scale_color,
+    },
     message::{Message, SelectionSource},
     state::{
-        Area, AreaId, AreaPosition, ColorIdx, EditorState, Focus, Palette, PaletteId, Tile,
-        TileBlock, TileCoord, TileIdx, Tool,
+        Area, AreaCell, AreaId, AreaPosition, ColorIdx, ColorProfile, ColorRGB, EditorState,
+        FillMode, Flip, Focus, MouseButtonMapping, Palette, PaletteId, PixelCoord, ScreenId,
+        ScreenLayer, Side, Theme, TileBlock, TileCoord, TileIdx, Tool, TILE_SIZE,
     },
+    world::{WorldOverview, OVERVIEW_SCREEN_SIZE},
 };
 
-use super::modal_background_style;
+use super::{index_input, marching_rectangle, modal_background_style};
+
+const MAIN_AREA_SCROLLABLE_ID: &str = "MainArea";
+const SIDE_AREA_SCROLLABLE_ID: &str = "SideArea";
 
 // We use two separate canvases: one for drawing the tile raster and one for the tile selection.
 // This is to work around a limitation in Iced's rendering pipeline that does not allow drawing
@@ -32,19 +41,72 @@ struct AreaGrid<'a> {
     area_id: AreaId,
     area: &'a Area,
     palettes: &'a [Palette],
+    theme: Option<&'a Theme>,
+    color_profile: ColorProfile,
     palettes_id_idx_map: &'a HashMap<PaletteId, usize>,
     end_coords: Option<(TileCoord, TileCoord)>,
     pixel_size: f32,
     // thickness: f32,
     palette_only_brush: bool,
+    line_brush: bool,
     tile_block: &'a TileBlock,
     identify_tile: bool,
     palette_idx: usize,
     tile_idx: Option<TileIdx>,
     identify_color: bool,
+    pinned_identify_colors: &'a HashSet<ColorIdx>,
+    highlight_color: [u8; 3],
+    highlight_alpha: f32,
     color_idx: Option<ColorIdx>,
     tool: Tool,
+    fill_mode: FillMode,
     snap_grid_16: bool,
+    // Interval to which the start of a selection is snapped:
+    selection_snap_step: TileCoord,
+    layer: ScreenLayer,
+    mouse_buttons: MouseButtonMapping,
+    brush_opacity: f32,
+    // Brush only the tiles of the selection, keeping the palettes of the painted cells:
+    keep_area_palettes: bool,
+    animation_frame: u32,
+}
+
+impl<'a> AreaGrid<'a> {
+    // Brush the selection at the given coordinates. Below full opacity, each cell is painted
+    // with that probability, leaving the rest of the selection masked out.
+    // When keeping the area's palettes, the selection takes on those of the cells under it.
+    fn brush_message(&self, coords: Point<TileCoord>, seed: u32) -> Message {
+        let mut selection = self.tile_block.clone();
+        if self.brush_opacity < 1.0 {
+            let mask: Vec<Vec<bool>> = (0..selection.size.1)
+                .map(|y| {
+                    (0..selection.size.0)
+                        .map(|x| {
+                            selection.is_selected(x, y)
+                                && cell_noise(seed, coords.x + x, coords.y + y) < self.brush_opacity
+                        })
+                        .collect()
+                })
+                .collect();
+            selection.mask = Some(Box::new(mask));
+        }
+        if self.keep_area_palettes {
+            for y in 0..selection.size.1 {
+                for x in 0..selection.size.0 {
+                    if let Ok(p) = self.area.get_palette(coords.x + x, coords.y + y) {
+                        selection.palettes[y as usize][x as usize] = p;
+                    }
+                }
+            }
+        }
+        Message::AreaBrush {
+            position: self.position,
+            area_id: self.area_id.clone(),
+            coords,
+            selection,
+            palette_only: self.palette_only_brush,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
@@ -59,6 +121,10 @@ enum InternalStateAction {
 struct InternalState {
     action: InternalStateAction,
     coords: Option<Point<TileCoord>>,
+    // Starting coordinates of the current brush stroke:
+    stroke_origin: Option<Point<TileCoord>>,
+    // Seed choosing the cells painted by the current stroke, when the brush opacity is below 1:
+    stroke_seed: u32,
 }
 
 fn clamped_position_in(
@@ -68,8 +134,10 @@ fn clamped_position_in(
     pixel_size: f32,
     snap_grid_16: bool,
 ) -> Point<TileCoord> {
-    let x = f32::max(p.x - bounds.x - 1.0 - pixel_size / 2.0, 0.0) / (8.0 * pixel_size);
-    let y = f32::max(p.y - bounds.y - 1.0 - pixel_size / 2.0, 0.0) / (8.0 * pixel_size);
+    let x =
+        f32::max(p.x - bounds.x - 1.0 - pixel_size / 2.0, 0.0) / (TILE_SIZE as f32 * pixel_size);
+    let y =
+        f32::max(p.y - bounds.y - 1.0 - pixel_size / 2.0, 0.0) / (TILE_SIZE as f32 * pixel_size);
     if snap_grid_16 {
         Point {
             x: ((x / 2.0) as TileCoord * 2).min(size.0 as TileCoord * 32 - 2),
@@ -106,9 +174,33 @@ impl<'a> canvas::Program<Message> for AreaGrid<'a> {
         }
         match event {
             canvas::Event::Mouse(mouse_event) => match mouse_event {
+                mouse::Event::ButtonPressed(mouse::Button::Middle) => {
+                    if let Some(p) = cursor.position_over(bounds) {
+                        let coords =
+                            clamped_position_in(p, bounds, self.area.size, self.pixel_size, false);
+                        let pixel = |v: f32, tile: TileCoord| {
+                            let px = (f32::max(v - 1.0 - self.pixel_size / 2.0, 0.0)
+                                / self.pixel_size) as i32;
+                            (px - tile as i32 * TILE_SIZE as i32).clamp(0, TILE_SIZE as i32 - 1)
+                                as PixelCoord
+                        };
+                        return (
+                            canvas::event::Status::Captured,
+                            Some(Message::PickScreenColor {
+                                position: self.position,
+                                x: coords.x,
+                                y: coords.y,
+                                pixel: (
+                                    pixel(p.x - bounds.x, coords.x),
+                                    pixel(p.y - bounds.y, coords.y),
+                                ),
+                            }),
+                        );
+                    }
+                }
                 mouse::Event::ButtonPressed(btn @ (mouse::Button::Left | mouse::Button::Right)) => {
                     if let Some(p) = cursor.position_over(bounds) {
-                        match (self.tool, btn) {
+                        match (self.tool, self.mouse_buttons.map(btn)) {
                             (Tool::Brush, mouse::Button::Left) => {
                                 state.action = InternalStateAction::Brushing;
                                 let coords = clamped_position_in(
@@ -118,30 +210,67 @@ impl<'a> canvas::Program<Message> for AreaGrid<'a> {
                                     self.pixel_size,
                                     self.snap_grid_16,
                                 );
+                                state.stroke_origin = Some(coords);
+                                state.stroke_seed = state.stroke_seed.wrapping_add(1);
+                                return (
+                                    canvas::event::Status::Captured,
+                                    Some(self.brush_message(coords, state.stroke_seed)),
+                                );
+                            }
+                            (Tool::Fill, mouse::Button::Left) => {
+                                let coords = clamped_position_in(
+                                    p,
+                                    bounds,
+                                    self.area.size,
+                                    self.pixel_size,
+                                    false,
+                                );
                                 return (
                                     canvas::event::Status::Captured,
-                                    Some(Message::AreaBrush {
+                                    Some(Message::AreaFill {
                                         position: self.position,
                                         area_id: self.area_id.clone(),
                                         coords,
                                         selection: self.tile_block.clone(),
+                                        mode: self.fill_mode,
                                         palette_only: self.palette_only_brush,
+                                        check_size: true,
+                                    }),
+                                );
+                            }
+                            (Tool::Wand, mouse::Button::Left) => {
+                                let coords = clamped_position_in(
+                                    p,
+                                    bounds,
+                                    self.area.size,
+                                    self.pixel_size,
+                                    false,
+                                );
+                                return (
+                                    canvas::event::Status::Captured,
+                                    Some(Message::MagicWandSelect {
+                                        position: self.position,
+                                        coords,
                                     }),
                                 );
                             }
                             (Tool::Select, mouse::Button::Left | mouse::Button::Right)
-                            | (Tool::Brush, mouse::Button::Right) => {
+                            | (Tool::Brush | Tool::Fill | Tool::Wand, mouse::Button::Right) => {
                                 state.action = InternalStateAction::Selecting;
+                                let mut coords = clamped_position_in(
+                                    p,
+                                    bounds,
+                                    self.area.size,
+                                    self.pixel_size,
+                                    self.snap_grid_16,
+                                );
+                                let step = self.selection_snap_step.max(1);
+                                coords.x -= coords.x % step;
+                                coords.y -= coords.y % step;
                                 return (
                                     canvas::event::Status::Captured,
                                     Some(Message::StartTileSelection(
-                                        clamped_position_in(
-                                            p,
-                                            bounds,
-                                            self.area.size,
-                                            self.pixel_size,
-                                            self.snap_grid_16,
-                                        ),
+                                        coords,
                                         crate::message::SelectionSource::Area(self.position),
                                     )),
                                 );
@@ -208,22 +337,24 @@ impl<'a> canvas::Program<Message> for AreaGrid<'a> {
                     }
                     InternalStateAction::Brushing => {
                         if let Some(p) = cursor.position() {
-                            let coords = clamped_position_in(
+                            let mut coords = clamped_position_in(
                                 p,
                                 bounds,
                                 self.area.size,
                                 self.pixel_size,
                                 self.snap_grid_16,
                             );
+                            if let (true, Some(origin)) = (self.line_brush, state.stroke_origin) {
+                                // Constrain the stroke to the dominant axis from its origin:
+                                if coords.x.abs_diff(origin.x) >= coords.y.abs_diff(origin.y) {
+                                    coords.y = origin.y;
+                                } else {
+                                    coords.x = origin.x;
+                                }
+                            }
                             return (
                                 canvas::event::Status::Captured,
-                                Some(Message::AreaBrush {
-                                    position: self.position,
-                                    area_id: self.area_id.clone(),
-                                    coords,
-                                    selection: self.tile_block.clone(),
-                                    palette_only: self.palette_only_brush,
-                                }),
+                                Some(self.brush_message(coords, state.stroke_seed)),
                             );
                         } else {
                             return (canvas::event::Status::Captured, Some(Message::HoverAreaEnd));
@@ -252,11 +383,14 @@ impl<'a> canvas::Program<Message> for AreaGrid<'a> {
         let mut color_bytes: Vec<Vec<[u8; 3]>> = vec![];
 
         for i in 0..self.palettes.len() {
-            let mut colors = self.palettes[i].colors.clone();
+            let mut colors = match self.theme {
+                Some(theme) => theme.palette_colors(&self.palettes[i]),
+                None => self.palettes[i].colors,
+            };
             colors[0] = self.area.bg_color;
             let cb = colors
                 .iter()
-                .map(|&[r, g, b]| [scale_color(r), scale_color(g), scale_color(b)])
+                .map(|&c| display_color(c, self.color_profile))
                 .collect();
             color_bytes.push(cb);
         }
@@ -275,37 +409,69 @@ impl<'a> canvas::Program<Message> for AreaGrid<'a> {
                 for ty in 0..32 {
                     for tx in 0..32 {
                         let palette_id = screen.palettes[ty][tx];
-                        let Some(&palette_idx) = self.palettes_id_idx_map.get(&palette_id) else {
-                            // TODO: draw some indicator of the broken tile (due to invalid palette reference)
-                            continue;
-                        };
                         let tile_idx = screen.tiles[ty][tx];
-                        if tile_idx as usize >= self.palettes[palette_idx].tiles.len() {
+                        let mut tile_addr =
+                            screen_addr + ty * TILE_SIZE * row_stride + tx * TILE_SIZE * col_stride;
+                        let Some(palette_idx) = self
+                            .palettes_id_idx_map
+                            .get(&palette_id)
+                            .copied()
+                            .filter(|&i| (tile_idx as usize) < self.palettes[i].tiles.len())
+                        else {
+                            // Draw an error pattern for a cell referencing a missing palette or tile:
+                            for py in 0..TILE_SIZE {
+                                let mut addr = tile_addr;
+                                for px in 0..TILE_SIZE {
+                                    data[addr..(addr + 3)]
+                                        .copy_from_slice(&broken_tile_color(px, py));
+                                    data[addr + 3] = 255;
+                                    addr += 4;
+                                }
+                                tile_addr += row_stride;
+                            }
                             continue;
-                        }
+                        };
                         let flip = screen.flips[ty][tx];
-                        let tile = self.palettes[palette_idx].tiles[tile_idx as usize];
+                        let pal = &self.palettes[palette_idx];
+                        let base_tile = pal.tiles[tile_idx as usize];
+                        let tile =
+                            pal.tiles[pal.animated_tile(tile_idx, self.animation_frame) as usize];
                         let tile = flip.apply_to_tile(tile);
                         let cb = &color_bytes[palette_idx];
-                        let mut tile_addr = screen_addr + ty * 8 * row_stride + tx * 8 * col_stride;
 
-                        let illegal_flip = match flip {
-                            crate::state::Flip::None => false,
-                            crate::state::Flip::Horizontal => !tile.h_flippable,
-                            crate::state::Flip::Vertical => !tile.v_flippable,
-                            crate::state::Flip::Both => !tile.h_flippable || !tile.v_flippable,
-                        };
+                        let illegal_flip = (flip.is_horizontal() && !base_tile.h_flippable)
+                            || (flip.is_vertical() && !base_tile.v_flippable);
                         let identify_tile = self.identify_tile
                             && self.palette_idx == palette_idx
                             && self.tile_idx == Some(tile_idx);
-                        for py in 0..8 {
+                        let layer_color = match self.layer {
+                            ScreenLayer::Tiles => None,
+                            ScreenLayer::Palettes => Some(palette_id_color(palette_id)),
+                            ScreenLayer::Flips => Some(match flip {
+                                Flip::None => [128, 128, 128],
+                                Flip::Horizontal => [255, 64, 64],
+                                Flip::Vertical => [64, 128, 255],
+                                Flip::Both => [255, 64, 255],
+                            }),
+                        };
+                        for py in 0..TILE_SIZE {
                             let mut addr = tile_addr;
-                            for px in 0..8 {
+                            for px in 0..TILE_SIZE {
                                 let color_idx = tile.pixels[py][px];
-                                let mut color = cb[color_idx as usize];
-                                let identify_color = self.identify_color
-                                    && self.color_idx == Some(color_idx)
-                                    && self.palette_idx == palette_idx;
+                                let mut color = cb
+                                    .get(color_idx as usize)
+                                    .copied()
+                                    .unwrap_or_else(|| broken_tile_color(px, py));
+                                let identify_color = self.palette_idx == palette_idx
+                                    && ((self.identify_color && self.color_idx == Some(color_idx))
+                                        || self.pinned_identify_colors.contains(&color_idx));
+
+                                if let Some(c) = layer_color {
+                                    // Mostly the layer color, keeping a hint of the tile:
+                                    for i in 0..3 {
+                                        color[i] = ((color[i] as u16 + c[i] as u16 * 3) / 4) as u8;
+                                    }
+                                }
 
                                 if illegal_flip && !self.identify_tile && !self.identify_color {
                                     let red_highlight = [255, 0, 0];
@@ -313,12 +479,14 @@ impl<'a> canvas::Program<Message> for AreaGrid<'a> {
                                     color = alpha_blend(color, red_highlight, alpha);
                                 }
 
-                                let pink_highlight = [255, 105, 180];
                                 if identify_tile {
-                                    let alpha = 0.5;
-                                    color = alpha_blend(color, pink_highlight, alpha);
+                                    color = alpha_blend(
+                                        color,
+                                        self.highlight_color,
+                                        self.highlight_alpha,
+                                    );
                                 } else if identify_color {
-                                    color = pink_highlight;
+                                    color = self.highlight_color;
                                 }
                                 data[addr..(addr + 3)].copy_from_slice(&color);
                                 data[addr + 3] = 255;
@@ -338,62 +506,60 @@ impl<'a> canvas::Program<Message> for AreaGrid<'a> {
                 y: base_y,
             }) = state.coords
             {
-                let base_addr =
-                    (base_y * 8 + 1) as usize * row_stride + (base_x * 8 + 1) as usize * col_stride;
+                let base_addr = (base_y as usize * TILE_SIZE + 1) * row_stride
+                    + (base_x as usize * TILE_SIZE + 1) * col_stride;
                 let alpha = 0.75;
                 for ty in 0..self.tile_block.size.1 as usize {
                     for tx in 0..self.tile_block.size.0 as usize {
                         if tx + base_x as usize >= self.area.size.0 as usize * 32
                             || ty + base_y as usize >= self.area.size.1 as usize * 32
+                            || !self
+                                .tile_block
+                                .is_selected(tx as TileCoord, ty as TileCoord)
                         {
                             continue;
                         }
-                        let palette_id = self.tile_block.palettes[ty][tx];
-                        if let Some(&palette_idx) = self.palettes_id_idx_map.get(&palette_id) {
-                            let tile = if self.palette_only_brush {
-                                let x1 = base_x + tx as TileCoord;
-                                let y1 = base_y + ty as TileCoord;
-                                let tile_idx = self.area.get_tile(x1, y1).unwrap();
-                                let flip = self.area.get_flip(x1, y1).unwrap();
-                                let clamped_tile_idx = std::cmp::min(
-                                    tile_idx,
-                                    self.palettes[palette_idx].tiles.len() as TileIdx - 1,
-                                );
-                                // TODO: indicate out-of-bounds tile index with some consistent broken tile indicator
-                                let t = *self.palettes[palette_idx]
-                                    .tiles
-                                    .get(clamped_tile_idx as usize)
-                                    .unwrap();
-                                flip.apply_to_tile(t)
-                            } else {
-                                let tile_idx = self.tile_block.tiles[ty][tx];
-                                let flip = self.tile_block.flips[ty][tx];
-                                let t = if (tile_idx as usize)
-                                    < self.palettes[palette_idx].tiles.len()
-                                {
-                                    self.palettes[palette_idx].tiles[tile_idx as usize]
-                                } else {
-                                    Tile::default()
-                                };
-                                flip.apply_to_tile(t)
-                            };
-                            let cb = &color_bytes[palette_idx];
-                            let mut tile_addr =
-                                base_addr + ty * 8 * row_stride + tx * 8 * col_stride;
-                            for py in 0..8 {
-                                let mut addr = tile_addr;
-                                for px in 0..8 {
-                                    let color_idx = tile.pixels[py][px];
-                                    let old_color = [data[addr], data[addr + 1], data[addr + 2]];
-                                    let new_color = cb[color_idx as usize];
-                                    let blended_color = alpha_blend(old_color, new_color, alpha);
-                                    data[addr..addr + 3].copy_from_slice(&blended_color);
-                                    addr += 4;
-                                }
-                                tile_addr += row_stride;
+                        let mut palette_id = self.tile_block.palettes[ty][tx];
+                        if self.keep_area_palettes {
+                            if let Ok(p) = self
+                                .area
+                                .get_palette(base_x + tx as TileCoord, base_y + ty as TileCoord)
+                            {
+                                palette_id = p;
                             }
+                        }
+                        let palette_idx = self.palettes_id_idx_map.get(&palette_id).copied();
+                        let (tile_idx, flip) = if self.palette_only_brush {
+                            let x1 = base_x + tx as TileCoord;
+                            let y1 = base_y + ty as TileCoord;
+                            (
+                                self.area.get_tile(x1, y1).unwrap(),
+                                self.area.get_flip(x1, y1).unwrap(),
+                            )
                         } else {
-                            // TODO: draw some indicator of the broken tile (due to invalid palette reference)
+                            (self.tile_block.tiles[ty][tx], self.tile_block.flips[ty][tx])
+                        };
+                        let tile = palette_idx
+                            .and_then(|i| self.palettes[i].tiles.get(tile_idx as usize))
+                            .map(|&t| flip.apply_to_tile(t));
+                        let mut tile_addr =
+                            base_addr + ty * TILE_SIZE * row_stride + tx * TILE_SIZE * col_stride;
+                        for py in 0..TILE_SIZE {
+                            let mut addr = tile_addr;
+                            for px in 0..TILE_SIZE {
+                                let new_color = match (palette_idx, tile) {
+                                    (Some(i), Some(t)) => color_bytes[i]
+                                        .get(t.pixels[py][px] as usize)
+                                        .copied()
+                                        .unwrap_or_else(|| broken_tile_color(px, py)),
+                                    _ => broken_tile_color(px, py),
+                                };
+                                let old_color = [data[addr], data[addr + 1], data[addr + 2]];
+                                let blended_color = alpha_blend(old_color, new_color, alpha);
+                                data[addr..addr + 3].copy_from_slice(&blended_color);
+                                addr += 4;
+                            }
+                            tile_addr += row_stride;
                         }
                     }
                 }
@@ -430,7 +596,7 @@ impl<'a> canvas::Program<Message> for AreaGrid<'a> {
         if cursor.is_over(bounds) {
             match self.tool {
                 Tool::Select => mouse::Interaction::default(),
-                Tool::Brush => mouse::Interaction::Crosshair,
+                Tool::Brush | Tool::Fill | Tool::Wand => mouse::Interaction::Crosshair,
                 Tool::Move => mouse::Interaction::NotAllowed,
             }
         } else {
@@ -439,20 +605,30 @@ impl<'a> canvas::Program<Message> for AreaGrid<'a> {
     }
 }
 
-struct AreaSelect {
+struct AreaSelect<'a> {
     top: TileCoord,
     bottom: TileCoord,
     left: TileCoord,
     right: TileCoord,
     selecting_active: bool,
+    marching_ants_phase: u8,
+    dash_colors: [ColorRGB; 2],
     pixel_size: f32,
     show_grid_16: bool,
+    show_grid_8: bool,
     snap_grid_16: bool,
     grid_alpha: f32,
+    grid_color: ColorRGB,
+    // Interval (in tiles) of the major grid lines, or 0 for none:
+    major_grid_interval: u16,
+    major_grid_color: ColorRGB,
+    major_grid_alpha: f32,
     hover_coords: Option<(TileCoord, TileCoord)>,
+    thickness: f32,
+    paint_counts: Option<&'a HashMap<(TileCoord, TileCoord), u32>>,
 }
 
-impl canvas::Program<Message> for AreaSelect {
+impl<'a> canvas::Program<Message> for AreaSelect<'a> {
     // No internal state
     type State = ();
 
@@ -464,7 +640,13 @@ impl canvas::Program<Message> for AreaSelect {
         bounds: iced::Rectangle,
         _cursor: mouse::Cursor,
     ) -> Vec<canvas::Geometry> {
-        if !self.selecting_active && !self.show_grid_16 && self.hover_coords.is_none() {
+        if !self.selecting_active
+            && !self.show_grid_16
+            && !self.show_grid_8
+            && self.major_grid_interval == 0
+            && self.hover_coords.is_none()
+            && self.paint_counts.is_none()
+        {
             return vec![];
         }
 
@@ -473,6 +655,54 @@ impl canvas::Program<Message> for AreaSelect {
             self.pixel_size * bounds.size().width / (bounds.size().width - self.pixel_size);
         let pixel_size_y =
             self.pixel_size * bounds.size().height / (bounds.size().height - self.pixel_size);
+        if let Some(counts) = self.paint_counts {
+            // Shade painted tiles in red, with opacity relative to the most-painted tile:
+            let max_count = counts.values().copied().max().unwrap_or(1) as f32;
+            for (&(x, y), &count) in counts {
+                frame.fill_rectangle(
+                    Point::new(
+                        x as f32 * pixel_size_x * TILE_SIZE as f32 + pixel_size_x / 2.0,
+                        y as f32 * pixel_size_y * TILE_SIZE as f32 + pixel_size_y / 2.0,
+                    ),
+                    Size::new(
+                        pixel_size_x * TILE_SIZE as f32,
+                        pixel_size_y * TILE_SIZE as f32,
+                    ),
+                    iced::Color::from_rgba(1.0, 0.0, 0.0, 0.15 + 0.5 * count as f32 / max_count),
+                );
+            }
+        }
+        if self.show_grid_8 {
+            // Outline every 8x8 tile, fainter than the 16x16 grid so the two can be told apart:
+            let cols8 = (bounds.size().width / (pixel_size_x * TILE_SIZE as f32)) as u16;
+            let rows8 = (bounds.size().height / (pixel_size_y * TILE_SIZE as f32)) as u16;
+            let path = canvas::Path::new(|p| {
+                for i in 0..=cols8 {
+                    let x = i as f32 * pixel_size_x * TILE_SIZE as f32 + pixel_size_x / 2.0;
+                    p.move_to(Point::new(x, self.pixel_size / 2.0));
+                    p.line_to(Point::new(x, bounds.height - self.pixel_size / 2.0));
+                }
+                for i in 0..=rows8 {
+                    let y = i as f32 * pixel_size_y * TILE_SIZE as f32 + pixel_size_y / 2.0;
+                    p.move_to(Point::new(self.pixel_size / 2.0, y));
+                    p.line_to(Point::new(bounds.width - self.pixel_size / 2.0, y));
+                }
+            });
+            frame.stroke(
+                &path,
+                canvas::Stroke {
+                    style: canvas::stroke::Style::Solid(iced::Color::from_rgba8(
+                        scale_color(self.grid_color[0]),
+                        scale_color(self.grid_color[1]),
+                        scale_color(self.grid_color[2]),
+                        self.grid_alpha * 0.5,
+                    )),
+                    width: 1.0,
+                    ..Default::default()
+                },
+            );
+        }
+
         if self.show_grid_16 {
             let rows16 = (bounds.size().height / (pixel_size_y * 16.0)) as u16;
             let cols16 = (bounds.size().width / (pixel_size_x * 16.0)) as u16;
@@ -492,10 +722,10 @@ impl canvas::Program<Message> for AreaSelect {
             frame.stroke(
                 &path,
                 canvas::Stroke {
-                    style: canvas::stroke::Style::Solid(iced::Color::from_rgba(
-                        1.0,
-                        1.0,
-                        1.0,
+                    style: canvas::stroke::Style::Solid(iced::Color::from_rgba8(
+                        scale_color(self.grid_color[0]),
+                        scale_color(self.grid_color[1]),
+                        scale_color(self.grid_color[2]),
                         self.grid_alpha,
                     )),
                     width: 1.0,
@@ -504,28 +734,66 @@ impl canvas::Program<Message> for AreaSelect {
             );
         }
 
-        if self.selecting_active {
-            let x0 = self.left as f32 * pixel_size_x * 8.0 + pixel_size_x / 2.0;
-            let x1 = (self.right + 1) as f32 * pixel_size_x * 8.0 + pixel_size_x / 2.0;
-            let y0 = self.top as f32 * pixel_size_y * 8.0 + pixel_size_y / 2.0;
-            let y1 = (self.bottom + 1) as f32 * pixel_size_y * 8.0 + pixel_size_y / 2.0;
-            let path = canvas::Path::rectangle(
-                iced::Point { x: x0, y: y0 },
-                Size {
-                    width: x1 - x0,
-                    height: y1 - y0,
+        if self.major_grid_interval > 0 {
+            // Drawn on top of the other grids, which it may coincide with:
+            let step_x = pixel_size_x * TILE_SIZE as f32 * self.major_grid_interval as f32;
+            let step_y = pixel_size_y * TILE_SIZE as f32 * self.major_grid_interval as f32;
+            let cols = (bounds.size().width / step_x) as u16;
+            let rows = (bounds.size().height / step_y) as u16;
+            let path = canvas::Path::new(|p| {
+                for i in 0..=cols {
+                    let x = i as f32 * step_x + pixel_size_x / 2.0;
+                    p.move_to(Point::new(x, self.pixel_size / 2.0));
+                    p.line_to(Point::new(x, bounds.height - self.pixel_size / 2.0));
+                }
+                for i in 0..=rows {
+                    let y = i as f32 * step_y + pixel_size_y / 2.0;
+                    p.move_to(Point::new(self.pixel_size / 2.0, y));
+                    p.line_to(Point::new(bounds.width - self.pixel_size / 2.0, y));
+                }
+            });
+            frame.stroke(
+                &path,
+                canvas::Stroke {
+                    style: canvas::stroke::Style::Solid(iced::Color::from_rgba8(
+                        scale_color(self.major_grid_color[0]),
+                        scale_color(self.major_grid_color[1]),
+                        scale_color(self.major_grid_color[2]),
+                        self.major_grid_alpha,
+                    )),
+                    width: 1.0,
+                    ..Default::default()
                 },
             );
+        }
+
+        if self.selecting_active {
+            let x0 = self.left as f32 * pixel_size_x * TILE_SIZE as f32 + pixel_size_x / 2.0;
+            let x1 = (self.right + 1) as f32 * pixel_size_x * TILE_SIZE as f32 + pixel_size_x / 2.0;
+            let y0 = self.top as f32 * pixel_size_y * TILE_SIZE as f32 + pixel_size_y / 2.0;
+            let y1 =
+                (self.bottom + 1) as f32 * pixel_size_y * TILE_SIZE as f32 + pixel_size_y / 2.0;
+            let path = canvas::Path::new(|p| {
+                marching_rectangle(
+                    p,
+                    iced::Point { x: x0, y: y0 },
+                    Size {
+                        width: x1 - x0,
+                        height: y1 - y0,
+                    },
+                    self.marching_ants_phase as f32,
+                );
+            });
             for i in 0..2 {
                 frame.stroke(
                     &path,
                     canvas::Stroke {
-                        style: if i == 0 {
-                            canvas::stroke::Style::Solid(iced::Color::WHITE)
-                        } else {
-                            canvas::stroke::Style::Solid(iced::Color::BLACK)
-                        },
-                        width: 1.0,
+                        style: canvas::stroke::Style::Solid(iced::Color::from_rgb8(
+                            scale_color(self.dash_colors[i][0]),
+                            scale_color(self.dash_colors[i][1]),
+                            scale_color(self.dash_colors[i][2]),
+                        )),
+                        width: self.thickness,
                         line_dash: canvas::LineDash {
                             offset: i,
                             segments: &[0.0, 0.0, 4.0, 4.0],
@@ -536,13 +804,13 @@ impl canvas::Program<Message> for AreaSelect {
                 );
             }
         } else if let Some(h) = self.hover_coords {
-            let x0 = h.0 as f32 * pixel_size_x * 8.0 + pixel_size_x / 2.0;
-            let mut x1 = (h.0 + 1) as f32 * pixel_size_x * 8.0 + pixel_size_x / 2.0;
-            let y0 = h.1 as f32 * pixel_size_y * 8.0 + pixel_size_y / 2.0;
-            let mut y1 = (h.1 + 1) as f32 * pixel_size_y * 8.0 + pixel_size_y / 2.0;
+            let x0 = h.0 as f32 * pixel_size_x * TILE_SIZE as f32 + pixel_size_x / 2.0;
+            let mut x1 = (h.0 + 1) as f32 * pixel_size_x * TILE_SIZE as f32 + pixel_size_x / 2.0;
+            let y0 = h.1 as f32 * pixel_size_y * TILE_SIZE as f32 + pixel_size_y / 2.0;
+            let mut y1 = (h.1 + 1) as f32 * pixel_size_y * TILE_SIZE as f32 + pixel_size_y / 2.0;
             if self.snap_grid_16 {
-                x1 += 8.0 * pixel_size_x;
-                y1 += 8.0 * pixel_size_y;
+                x1 += TILE_SIZE as f32 * pixel_size_x;
+                y1 += TILE_SIZE as f32 * pixel_size_y;
             }
 
             let path = canvas::Path::rectangle(
@@ -556,7 +824,7 @@ impl canvas::Program<Message> for AreaSelect {
                 &path,
                 canvas::Stroke {
                     style: canvas::stroke::Style::Solid(iced::Color::from_rgba(1.0, 1.0, 1.0, 0.3)),
-                    width: 1.0,
+                    width: self.thickness,
                     ..Default::default()
                 },
             );
@@ -565,11 +833,140 @@ impl canvas::Program<Message> for AreaSelect {
     }
 }
 
+// Thickness of the rulers shown along the top and left edges of the area view:
+const RULER_SIZE: f32 = 24.0;
+
+// Ruler labeled in tile units, kept in sync with the scroll offset of the area view.
+struct AreaRuler {
+    horizontal: bool,
+    offset: f32,
+    pixel_size: f32,
+    num_tiles: TileCoord,
+    hover: Option<TileCoord>,
+}
+
+impl canvas::Program<Message> for AreaRuler {
+    // No internal state
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &iced::Renderer,
+        theme: &iced::Theme,
+        bounds: iced::Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let palette = theme.extended_palette();
+        frame.fill_rectangle(Point::ORIGIN, bounds.size(), palette.background.weak.color);
+
+        let tile_size = TILE_SIZE as f32 * self.pixel_size;
+        let length = if self.horizontal {
+            bounds.width
+        } else {
+            bounds.height
+        };
+        // Position along the ruler of the start of the given tile:
+        let tile_pos = |t: TileCoord| t as f32 * tile_size + self.pixel_size - self.offset;
+
+        if let Some(t) = self.hover {
+            let p = tile_pos(t);
+            let (top_left, size) = if self.horizontal {
+                (Point::new(p, 0.0), Size::new(tile_size, RULER_SIZE))
+            } else {
+                (Point::new(0.0, p), Size::new(RULER_SIZE, tile_size))
+            };
+            frame.fill_rectangle(top_left, size, palette.primary.weak.color);
+        }
+
+        // Label every N tiles, with N chosen so that the labels don't overlap:
+        let mut label_step: TileCoord = 1;
+        while (label_step as f32) * tile_size < 24.0 {
+            label_step *= 2;
+        }
+        let first = ((self.offset - self.pixel_size) / tile_size).max(0.0) as TileCoord;
+        let path = canvas::Path::new(|p| {
+            for t in first..=self.num_tiles {
+                let pos = tile_pos(t);
+                if pos > length {
+                    break;
+                }
+                let tick = if t % label_step == 0 {
+                    RULER_SIZE
+                } else if tile_size >= 4.0 {
+                    RULER_SIZE / 4.0
+                } else {
+                    continue;
+                };
+                if self.horizontal {
+                    p.move_to(Point::new(pos, RULER_SIZE - tick));
+                    p.line_to(Point::new(pos, RULER_SIZE));
+                } else {
+                    p.move_to(Point::new(RULER_SIZE - tick, pos));
+                    p.line_to(Point::new(RULER_SIZE, pos));
+                }
+            }
+        });
+        frame.stroke(
+            &path,
+            canvas::Stroke {
+                style: canvas::stroke::Style::Solid(palette.background.strong.color),
+                width: 1.0,
+                ..Default::default()
+            },
+        );
+
+        for t in (first..self.num_tiles).filter(|t| t % label_step == 0) {
+            let pos = tile_pos(t);
+            if pos > length {
+                break;
+            }
+            frame.fill_text(canvas::Text {
+                content: t.to_string(),
+                position: if self.horizontal {
+                    Point::new(pos + 2.0, 1.0)
+                } else {
+                    Point::new(2.0, pos + 1.0)
+                },
+                color: palette.background.base.text,
+                size: iced::Pixels(10.0),
+                ..Default::default()
+            });
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
 pub fn area_grid_view(state: &EditorState, position: AreaPosition) -> Element<Message> {
-    let area = state.area(position);
+    responsive(move |size| area_grid_view_sized(state, position, size)).into()
+}
+
+fn area_grid_view_sized<'a>(
+    state: &'a EditorState,
+    position: AreaPosition,
+    view_size: Size,
+) -> Element<'a, Message> {
+    let area = state.displayed_area(position);
     let num_cols = area.size.1 * 32;
     let num_rows = area.size.0 * 32;
     let pixel_size = state.global_config.pixel_size;
+    let grid_width = (num_cols as f32 * TILE_SIZE as f32 + 2.0) * pixel_size;
+    let grid_height = (num_rows as f32 * TILE_SIZE as f32 + 2.0) * pixel_size;
+
+    // Space around the area, which when centering is enlarged to fill the rest of the view
+    // (only while the area is smaller than the view, so scrolling is unaffected otherwise):
+    let margin = state.global_config.area_margin as f32;
+    let (pad_left, pad_top) = if state.global_config.center_area {
+        let free_width = view_size.width - RULER_SIZE - 16.0 - grid_width;
+        let free_height = view_size.height - RULER_SIZE - 16.0 - grid_height;
+        (
+            (free_width / 2.0).floor().max(margin),
+            (free_height / 2.0).floor().max(margin),
+        )
+    } else {
+        (margin, margin)
+    };
 
     let mut left = 0;
     let mut right = 0;
@@ -591,57 +988,141 @@ pub fn area_grid_view(state: &EditorState, position: AreaPosition) -> Element<Me
         bottom += 1;
     }
 
-    Scrollable::with_direction(
+    let scroll_offset = state.area_scroll_offset(position);
+    let top_ruler = canvas(AreaRuler {
+        horizontal: true,
+        offset: scroll_offset.x - pad_left,
+        pixel_size,
+        num_tiles: num_cols as TileCoord,
+        hover: state.hover_coords.map(|(x, _)| x),
+    })
+    .width(Length::Fill)
+    .height(RULER_SIZE);
+    let left_ruler = canvas(AreaRuler {
+        horizontal: false,
+        offset: scroll_offset.y - pad_top,
+        pixel_size,
+        num_tiles: num_rows as TileCoord,
+        hover: state.hover_coords.map(|(_, y)| y),
+    })
+    .width(RULER_SIZE)
+    .height(Length::Fill);
+
+    let grid = Scrollable::with_direction(
         column![stack![
             canvas(AreaGrid {
                 position,
                 area_id: state.area_id(position).clone(),
-                area: &state.area(position),
+                area,
                 palettes: &state.palettes,
+                theme: state.themes.get(&area.theme),
+                color_profile: state.global_config.color_profile,
                 palettes_id_idx_map: &state.palettes_id_idx_map,
                 pixel_size,
                 end_coords: state.end_coords,
                 // thickness: 1.0,
                 palette_only_brush: state.shift_brush,
+                line_brush: state.line_brush,
                 tile_block: &state.selected_tile_block,
                 identify_tile: state.identify_tile,
                 palette_idx: state.palette_idx,
                 tile_idx: state.tile_idx,
                 identify_color: state.identify_color,
+                pinned_identify_colors: &state.pinned_identify_colors,
+                highlight_color: state.global_config.highlight_color.map(scale_color),
+                highlight_alpha: state.global_config.highlight_alpha,
                 color_idx: state.color_idx,
                 tool: state.tool,
+                fill_mode: state.fill_mode,
                 snap_grid_16: state.snap_grid_16,
+                selection_snap_step: state.global_config.selection_snap_step,
+                layer: state.screen_layer,
+                mouse_buttons: state.global_config.mouse_buttons,
+                brush_opacity: state.global_config.brush_opacity,
+                keep_area_palettes: state.selection_source == SelectionSource::Tileset
+                    && !state.global_config.tileset_brush_palette,
+                // Show the first frame of the tile animations while they're not playing:
+                animation_frame: if state.global_config.play_animations {
+                    state.animation_frame
+                } else {
+                    0
+                },
             })
-            .width((num_cols as f32 * 8.0 + 2.0) * pixel_size)
-            .height((num_rows as f32 * 8.0 + 2.0) * pixel_size),
+            .width(grid_width)
+            .height(grid_height),
             canvas(AreaSelect {
                 selecting_active: state.selection_source == SelectionSource::Area(position)
                     && state.start_coords.is_some()
                     && state.end_coords.is_some(),
+                marching_ants_phase: state.marching_ants_phase,
+                dash_colors: state.global_config.selection_dash_colors,
                 left,
                 right,
                 top,
                 bottom,
                 pixel_size,
                 show_grid_16: state.show_grid_16,
+                show_grid_8: state.show_grid_8,
                 snap_grid_16: state.snap_grid_16,
                 hover_coords: state.hover_coords,
                 grid_alpha: state.global_config.grid_alpha,
+                grid_color: state.global_config.grid_color,
+                major_grid_interval: state.global_config.major_grid_interval,
+                major_grid_color: state.global_config.major_grid_color,
+                major_grid_alpha: state.global_config.major_grid_alpha,
+                thickness: outline_thickness(pixel_size),
+                paint_counts: if state.show_paint_heatmap {
+                    state.paint_counts.get(state.area_id(position))
+                } else {
+                    None
+                },
             })
-            .width((num_cols as f32 * 8.0 + 2.0) * pixel_size)
-            .height((num_rows as f32 * 8.0 + 2.0) * pixel_size),
+            .width(grid_width)
+            .height(grid_height),
         ]]
-        .padding(Padding::new(0.0).right(16.0).bottom(16.0)),
+        .padding(Padding {
+            top: pad_top,
+            right: margin + 16.0,
+            bottom: margin + 16.0,
+            left: pad_left,
+        }),
         Direction::Both {
             vertical: Scrollbar::default(),
             horizontal: Scrollbar::default(),
         },
     )
+    .id(scrollable::Id::new(match position {
+        AreaPosition::Main => MAIN_AREA_SCROLLABLE_ID,
+        AreaPosition::Side => SIDE_AREA_SCROLLABLE_ID,
+    }))
+    .on_scroll(move |viewport| {
+        let offset = viewport.absolute_offset();
+        Message::ScrollArea(position, Vector::new(offset.x, offset.y))
+    })
     .width(Length::Fill)
-    .height(Length::Fill)
+    .height(Length::Fill);
+
+    column![
+        row![Space::new(RULER_SIZE, RULER_SIZE), top_ruler],
+        row![left_ruler, grid],
+    ]
     .into()
 }
 
+// Warning shown while the main area has cells that can't be rendered, which jumps to them:
+pub fn broken_cells_indicator(state: &EditorState) -> Option<Element<'_, Message>> {
+    let num_broken = state.main_area().num_broken_cells;
+    if num_broken == 0 {
+        return None;
+    }
+    Some(
+        button(text(format!("\u{F33A} {}", num_broken)).font(iced_fonts::BOOTSTRAP_FONT))
+            .style(button::danger)
+            .on_press(Message::SelectNextBrokenCell)
+            .into(),
+    )
+}
+
 pub fn main_area_controls(state: &EditorState) -> Element<Message> {
     row![
         text("Area"),
@@ -670,6 +1151,21 @@ pub fn main_area_controls(state: &EditorState) -> Element<Message> {
             .on_press(Message::AddThemeDialogue),
         button(text("\u{F4CB}").font(iced_fonts::BOOTSTRAP_FONT))
             .on_press(Message::RenameThemeDialogue),
+        button(text("\u{F4B1}").font(iced_fonts::BOOTSTRAP_FONT))
+            .style(button::secondary)
+            .on_press(Message::ThemeColorsDialogue),
+        button(text("\u{F444}").font(iced_fonts::BOOTSTRAP_FONT))
+            .style(button::secondary)
+            .on_press(Message::ScreenNotesDialogue),
+        button(text("\u{F3EE}").font(iced_fonts::BOOTSTRAP_FONT))
+            .style(button::secondary)
+            .on_press(Message::WorldOverviewDialogue),
+        button(text("\u{F4AF}").font(iced_fonts::BOOTSTRAP_FONT))
+            .style(button::secondary)
+            .on_press(Message::RemapPaletteInRegionDialogue),
+        button(text("\u{F12B}").font(iced_fonts::BOOTSTRAP_FONT))
+            .style(button::secondary)
+            .on_press(Message::ReplacePaletteReferencesDialogue),
     ]
     .spacing(10)
     .clip(true)
@@ -788,6 +1284,32 @@ pub fn edit_area_view(state: &EditorState, name: &String) -> Element<'static, Me
             ]
             .spacing(5)
             .align_y(iced::alignment::Vertical::Center),
+            row![
+                text(format!(
+                    "Size: {} x {} screens",
+                    state.main_area().size.0,
+                    state.main_area().size.1
+                )),
+                Space::with_width(Length::Fill),
+                button(text("Trim empty edges"))
+                    .style(button::secondary)
+                    .on_press(Message::TrimEmptyEdges(state.main_area().id())),
+            ]
+            .align_y(Vertical::Center),
+            row![
+                text(format!(
+                    "Broken cells (missing palette or tile): {}",
+                    state.main_area().num_broken_cells
+                )),
+                Space::with_width(Length::Fill),
+                button(text("Select next"))
+                    .style(button::secondary)
+                    .on_press(Message::SelectNextBrokenCell),
+                button(text("Check project"))
+                    .style(button::secondary)
+                    .on_press(Message::CheckBrokenCells),
+            ]
+            .align_y(Vertical::Center),
             row![
                 button(text("Edit area")).on_press(edit_area_msg.clone()),
                 Space::with_width(Length::Fill),
@@ -804,6 +1326,180 @@ pub fn edit_area_view(state: &EditorState, name: &String) -> Element<'static, Me
     .into()
 }
 
+pub fn remap_palette_in_region_view(
+    state: &EditorState,
+    from: Option<PaletteId>,
+    to: Option<PaletteId>,
+) -> Element<'_, Message> {
+    let hex = state.global_config.display_hex;
+    let names: Vec<String> = state
+        .palettes
+        .iter()
+        .map(|p| palette_label(p, hex))
+        .collect();
+    let label = |id: Option<PaletteId>| {
+        id.and_then(|p| state.palettes_id_idx_map.get(&p))
+            .map(|&i| palette_label(&state.palettes[i], hex))
+    };
+    let region = match (state.selection_source, state.selection_coords) {
+        (SelectionSource::Area(AreaPosition::Main), Some(_)) => "the selected cells",
+        _ => "the whole area",
+    };
+    container(
+        column![
+            text(format!("Change the palette of cells within {}.", region)),
+            row![
+                text("From").width(50),
+                pick_list(names.clone(), label(from), Message::SetRemapRegionFrom).width(200),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("To").width(50),
+                pick_list(names, label(to), Message::SetRemapRegionTo).width(200),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                button(text("Cancel"))
+                    .style(button::secondary)
+                    .on_press(Message::CloseDialogue),
+                horizontal_space(),
+                button(text("Change palette")).on_press_maybe(
+                    from.zip(to)
+                        .map(|(from, to)| Message::RemapPaletteInRegion { from, to })
+                ),
+            ]
+        ]
+        .spacing(15),
+    )
+    .width(400)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+pub fn replace_palette_references_view(
+    state: &EditorState,
+    from: Option<PaletteId>,
+    to: Option<PaletteId>,
+    from_references: Option<(usize, usize)>,
+    to_in_use: bool,
+) -> Element<'_, Message> {
+    let hex = state.global_config.display_hex;
+    let names: Vec<String> = state
+        .palettes
+        .iter()
+        .map(|p| palette_label(p, hex))
+        .collect();
+    let label = |id: Option<PaletteId>| {
+        id.and_then(|p| state.palettes_id_idx_map.get(&p))
+            .map(|&i| palette_label(&state.palettes[i], hex))
+    };
+    let summary = match from_references {
+        Some((num_cells, num_areas)) => format!(
+            "{} cells in {} areas will be changed.{}",
+            num_cells,
+            num_areas,
+            if to_in_use {
+                " The new palette is already in use, so this cannot be undone."
+            } else {
+                ""
+            }
+        ),
+        None => String::new(),
+    };
+    container(
+        column![
+            text("Change the palette of cells in every area of the project (in all themes)."),
+            row![
+                text("From").width(50),
+                pick_list(
+                    names.clone(),
+                    label(from),
+                    Message::SetReplaceReferencesFrom
+                )
+                .width(200),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("To").width(50),
+                pick_list(names, label(to), Message::SetReplaceReferencesTo).width(200),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            text(summary),
+            row![
+                button(text("Cancel"))
+                    .style(button::secondary)
+                    .on_press(Message::CloseDialogue),
+                horizontal_space(),
+                button(text("Replace")).on_press_maybe(
+                    from.zip(to)
+                        .filter(|(from, to)| from != to)
+                        .map(|(from, to)| Message::ReplacePaletteReferences { from, to })
+                ),
+            ]
+        ]
+        .spacing(15),
+    )
+    .width(400)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+pub fn confirm_fill_view(count: usize, fill: &Message) -> Element<'static, Message> {
+    container(
+        column![
+            text(format!("This fill will change {} cells.", count)),
+            row![
+                button(text("Cancel"))
+                    .style(button::secondary)
+                    .on_press(Message::CloseDialogue),
+                horizontal_space(),
+                button(text("Fill"))
+                    .style(button::danger)
+                    .on_press(fill.clone()),
+            ]
+        ]
+        .spacing(15),
+    )
+    .width(400)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+pub fn area_load_error_view(
+    area_id: &AreaId,
+    error: &str,
+    retry: &Message,
+) -> Element<'static, Message> {
+    container(
+        column![
+            text(format!(
+                "Unable to load area \"{}\" (theme \"{}\"):",
+                area_id.area, area_id.theme
+            )),
+            text(error.to_string()).size(12),
+            row![
+                button(text("Close"))
+                    .style(button::secondary)
+                    .on_press(Message::CloseDialogue),
+                horizontal_space(),
+                button(text("Retry")).on_press(retry.clone()),
+            ]
+        ]
+        .spacing(15),
+    )
+    .width(400)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
 pub fn delete_area_view(state: &EditorState) -> Element<Message> {
     let name = state.main_area().name.clone();
     container(
@@ -823,6 +1519,52 @@ pub fn delete_area_view(state: &EditorState) -> Element<Message> {
     .into()
 }
 
+pub fn screen_notes_view<'a>(
+    state: &EditorState,
+    screen: (u8, u8),
+    content: &'a text_editor::Content,
+) -> Element<'a, Message> {
+    let area = state.main_area();
+    let mut screen_names: Vec<String> = vec![];
+    for sy in 0..area.size.1 {
+        for sx in 0..area.size.0 {
+            screen_names.push(format!("{}, {}", sx, sy));
+        }
+    }
+    container(
+        column![
+            row![
+                text(format!("Notes for {} screen", area.name)),
+                pick_list(
+                    screen_names,
+                    Some(format!("{}, {}", screen.0, screen.1)),
+                    Message::SelectNotesScreen
+                )
+                .width(100),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            text_editor(content)
+                .on_action(Message::EditScreenNotes)
+                .height(200),
+            row![
+                button(text("Save notes"))
+                    .style(button::success)
+                    .on_press(Message::SaveScreenNotes),
+                Space::with_width(Length::Fill),
+                button(text("Cancel"))
+                    .style(button::secondary)
+                    .on_press(Message::CloseDialogue),
+            ],
+        ]
+        .spacing(10),
+    )
+    .width(450)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
 pub fn add_theme_view(name: &String) -> Element<Message> {
     container(
         column![
@@ -900,3 +1642,379 @@ pub fn delete_theme_view(state: &EditorState) -> Element<Message> {
     .style(modal_background_style)
     .into()
 }
+
+fn color_swatch(color: [u8; 3]) -> Element<'static, Message> {
+    container(Space::new(20, 20))
+        .style(move |_| container::Style {
+            background: Some(iced::Color::from_rgb8(color[0], color[1], color[2]).into()),
+            border: iced::Border::default().width(1).color(iced::Color::WHITE),
+            ..Default::default()
+        })
+        .into()
+}
+
+pub fn theme_colors_view(state: &EditorState) -> Element<'_, Message> {
+    let theme = state.main_area().theme.clone();
+    let pal = &state.palettes[state.palette_idx];
+    let profile = state.global_config.color_profile;
+    let overrides = state
+        .themes
+        .get(&theme)
+        .and_then(|t| t.color_overrides.get(&pal.id));
+    let mut colors_col: Column<Message> = Column::new().spacing(5);
+    for (i, &base_color) in pal.colors.iter().enumerate() {
+        let color_idx = i as ColorIdx;
+        let color = overrides.and_then(|o| o.get(&color_idx)).copied();
+        let mut r = row![
+            text(format!("{}", i)).width(25),
+            color_swatch(display_color(base_color, profile)),
+            checkbox("Override", color.is_some()).on_toggle(move |b| {
+                Message::EditThemeColor(color_idx, if b { Some(base_color) } else { None })
+            }),
+        ]
+        .spacing(10)
+        .align_y(Vertical::Center);
+        if let Some(c) = color {
+            r = r.push(color_swatch(display_color(c, profile)));
+            for channel in 0..3 {
+                r = r.push(
+                    number_input(&c[channel], 0..=31, move |v| {
+                        let mut c = c;
+                        c[channel] = v;
+                        Message::EditThemeColor(color_idx, Some(c))
+                    })
+                    .width(60),
+                );
+            }
+        }
+        colors_col = colors_col.push(r);
+    }
+    container(
+        column![
+            text(format!(
+                "Colors of palette {} in theme \"{}\"",
+                palette_label(pal, state.global_config.display_hex),
+                theme
+            )),
+            text(
+                "Overridden colors replace the palette colors when rendering areas of this theme."
+            ),
+            Scrollable::new(colors_col).height(Length::Shrink),
+            button(text("Close"))
+                .style(button::secondary)
+                .on_press(Message::CloseDialogue),
+        ]
+        .spacing(10),
+    )
+    .width(500)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+// Overlay on the world overview image, highlighting the hovered screen and the screen
+// selected for linking. Left-click opens a screen; right-click selects it for linking.
+struct WorldOverviewSelect<'a> {
+    overview: &'a WorldOverview,
+    link_from: Option<&'a ScreenId>,
+}
+
+impl canvas::Program<Message> for WorldOverviewSelect<'_> {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: canvas::Event,
+        bounds: iced::Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (canvas::event::Status, Option<Message>) {
+        let Some(p) = cursor.position_in(bounds) else {
+            return (canvas::event::Status::Ignored, None);
+        };
+        let x = (p.x / OVERVIEW_SCREEN_SIZE as f32) as u32;
+        let y = (p.y / OVERVIEW_SCREEN_SIZE as f32) as u32;
+        let Some(screen) = self.overview.screen_at(x, y) else {
+            return (canvas::event::Status::Ignored, None);
+        };
+        let msg = match event {
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                Message::OpenWorldScreen(screen.clone())
+            }
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                Message::SelectLinkScreen(screen.clone())
+            }
+            _ => return (canvas::event::Status::Ignored, None),
+        };
+        (canvas::event::Status::Captured, Some(msg))
+    }
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &iced::Renderer,
+        _theme: &iced::Theme,
+        bounds: iced::Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let size = OVERVIEW_SCREEN_SIZE as f32;
+        let mut outline = |x: u32, y: u32, color: iced::Color| {
+            let path = canvas::Path::rectangle(
+                Point::new(x as f32 * size + 1.0, y as f32 * size + 1.0),
+                Size::new(size - 2.0, size - 2.0),
+            );
+            frame.stroke(
+                &path,
+                canvas::Stroke {
+                    style: canvas::stroke::Style::Solid(color),
+                    width: 2.0,
+                    ..Default::default()
+                },
+            );
+        };
+        if let Some(from) = self.link_from {
+            for (&(x, y), screen) in &self.overview.positions {
+                if screen == from {
+                    outline(x, y, iced::Color::from_rgb(1.0, 0.8, 0.0));
+                }
+            }
+        }
+        let mut hovered = None;
+        if let Some(p) = cursor.position_in(bounds) {
+            let x = (p.x / size) as u32;
+            let y = (p.y / size) as u32;
+            if let Some(screen) = self.overview.screen_at(x, y) {
+                outline(x, y, iced::Color::from_rgba(1.0, 1.0, 1.0, 0.6));
+                hovered = Some((x, y, screen));
+            }
+        }
+        if let Some((x, y, screen)) = hovered {
+            frame.fill_text(canvas::Text {
+                content: screen.to_string(),
+                position: Point::new(x as f32 * size + 4.0, y as f32 * size + 4.0),
+                color: iced::Color::WHITE,
+                size: iced::Pixels(10.0),
+                ..Default::default()
+            });
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+pub fn world_overview_view<'a>(
+    state: &'a EditorState,
+    overview: &'a WorldOverview,
+    link_from: Option<&'a ScreenId>,
+    link_side: Side,
+    link_to: &'a ScreenId,
+) -> Element<'a, Message> {
+    let width = overview.size.0 as f32 * OVERVIEW_SCREEN_SIZE as f32;
+    let height = overview.size.1 as f32 * OVERVIEW_SCREEN_SIZE as f32;
+    let map = Scrollable::with_direction(
+        stack![
+            image(overview.image.clone())
+                .filter_method(state.global_config.preview_filter.filter_method())
+                .width(width)
+                .height(height),
+            canvas(WorldOverviewSelect {
+                overview,
+                link_from,
+            })
+            .width(width)
+            .height(height),
+        ],
+        Direction::Both {
+            vertical: Scrollbar::default(),
+            horizontal: Scrollbar::default(),
+        },
+    )
+    .width(Length::Fill)
+    .height(Length::Fixed(height.clamp(64.0, 512.0) + 16.0));
+
+    let link_controls: Element<Message> = match link_from {
+        Some(from) => {
+            let current = match state.world_map.neighbor(from, link_side) {
+                Some(s) => format!("Currently linked to {}.", s),
+                None => "Currently not linked.".to_string(),
+            };
+            column![
+                row![
+                    text(format!("Screen {} borders", from)),
+                    pick_list(
+                        state.area_names.clone(),
+                        Some(link_to.area.clone()),
+                        Message::SetLinkTargetArea
+                    )
+                    .width(150),
+                    number_input(&link_to.x, 0..=7, Message::SetLinkTargetX).width(50),
+                    number_input(&link_to.y, 0..=7, Message::SetLinkTargetY).width(50),
+                    text("on the"),
+                    pick_list(Side::ALL, Some(link_side), Message::SetLinkSide).width(90),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center),
+                row![
+                    text(current),
+                    button(text("Link")).on_press(Message::LinkScreens {
+                        from: from.clone(),
+                        side: link_side,
+                        to: Some(link_to.clone()),
+                    }),
+                    button(text("Unlink"))
+                        .style(button::danger)
+                        .on_press(Message::LinkScreens {
+                            from: from.clone(),
+                            side: link_side,
+                            to: None,
+                        }),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center),
+            ]
+            .spacing(10)
+            .into()
+        }
+        None => text("Right-click a screen to link it to a screen of another area.").into(),
+    };
+
+    container(
+        column![
+            text(format!(
+                "World overview (theme \"{}\"). Click a screen to open it.",
+                state.main_area().theme
+            )),
+            map,
+            link_controls,
+            button(text("Close"))
+                .style(button::secondary)
+                .on_press(Message::CloseDialogue),
+        ]
+        .spacing(10),
+    )
+    .width(800)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+// Scroll the main area view to show the given screen in the top-left.
+pub fn scroll_to_screen(x: u8, y: u8, pixel_size: f32) -> Task<Message> {
+    scrollable::scroll_to(
+        scrollable::Id::new(MAIN_AREA_SCROLLABLE_ID),
+        scrollable::AbsoluteOffset {
+            x: x as f32 * 256.0 * pixel_size,
+            y: y as f32 * 256.0 * pixel_size,
+        },
+    )
+}
+
+// Scroll the main area to the screen that was last edited, if it is still there.
+pub fn scroll_to_last_screen(state: &EditorState) -> Task<Message> {
+    match &state.global_config.last_screen {
+        Some(s)
+            if s.area == state.main_area_id.area
+                && s.x < state.main_area().size.0
+                && s.y < state.main_area().size.1 =>
+        {
+            scroll_to_screen(s.x, s.y, state.global_config.pixel_size)
+        }
+        _ => Task::none(),
+    }
+}
+
+// Status line showing the size and orientation of the brush stamp:
+pub fn stamp_status_view(state: &EditorState) -> Option<Element<'_, Message>> {
+    if state.tool != Tool::Brush || state.selected_gfx.is_empty() {
+        return None;
+    }
+    let (w, h) = state.selected_tile_block.size;
+    let orientation = match state.stamp_flip {
+        Flip::None => "original orientation",
+        Flip::Horizontal => "flipped horizontally",
+        Flip::Vertical => "flipped vertically",
+        Flip::Both => "rotated 180\u{B0}",
+    };
+    Some(
+        text(format!(
+            "Stamp {}x{}: {} (h/v: flip, r: rotate, Shift+F: cycle)",
+            w, h, orientation
+        ))
+        .size(12)
+        .into(),
+    )
+}
+
+// Panel for viewing and editing the tilemap data of a single cell of the main area.
+pub fn cell_inspector_view(
+    state: &EditorState,
+    coords: (TileCoord, TileCoord),
+) -> Element<'_, Message> {
+    let Ok(cell) = state.main_area().get_cell(coords.0, coords.1) else {
+        return Space::new(0, 0).into();
+    };
+    let hex = state.global_config.display_hex;
+    let pal_idx = state.palettes_id_idx_map.get(&cell.palette_id).copied();
+    let num_tiles = pal_idx.map_or(1, |i| state.palettes[i].tiles.len()).max(1);
+    let tile_priority = pal_idx
+        .and_then(|i| state.palettes[i].tiles.get(cell.tile_idx as usize))
+        .is_some_and(|t| t.priority);
+    let palette_ids: Vec<(String, PaletteId)> = state
+        .palettes
+        .iter()
+        .map(|p| (palette_label(p, hex), p.id))
+        .collect();
+    let palette_names: Vec<String> = palette_ids.iter().map(|(name, _)| name.clone()).collect();
+    let selected_palette_name = pal_idx.map(|i| palette_label(&state.palettes[i], hex));
+    let h_flip = cell.flip.is_horizontal();
+    let v_flip = cell.flip.is_vertical();
+
+    row![
+        text(format!(
+            "Cell {}, {}",
+            format_number(coords.0, hex),
+            format_number(coords.1, hex)
+        )),
+        text("Palette"),
+        pick_list(palette_names, selected_palette_name, move |name| {
+            match palette_ids.iter().find(|(n, _)| n == &name) {
+                Some(&(_, palette_id)) => Message::EditAreaCell(AreaCell { palette_id, ..cell }),
+                None => Message::Nothing,
+            }
+        })
+        .width(200),
+        text("Tile"),
+        index_input(
+            cell.tile_idx,
+            (num_tiles - 1) as TileIdx,
+            hex,
+            70.0,
+            move |tile_idx| Message::EditAreaCell(AreaCell { tile_idx, ..cell }),
+            None,
+        ),
+        checkbox("H flip", h_flip).on_toggle(move |_| {
+            Message::EditAreaCell(AreaCell {
+                flip: cell.flip.flip_horizontally(),
+                ..cell
+            })
+        }),
+        checkbox("V flip", v_flip).on_toggle(move |_| {
+            Message::EditAreaCell(AreaCell {
+                flip: cell.flip.flip_vertically(),
+                ..cell
+            })
+        }),
+        checkbox("Priority", cell.priority)
+            .on_toggle(move |priority| Message::EditAreaCell(AreaCell { priority, ..cell })),
+        text(if state.is_broken_cell(&cell) {
+            "Broken: missing palette or tile"
+        } else if tile_priority {
+            "(tile has priority)"
+        } else {
+            ""
+        }),
+    ]
+    .spacing(10)
+    .align_y(Vertical::Center)
+    .into()
+}
@@ -2,19 +2,26 @@
 use iced::{
     alignment::Vertical,
     mouse,
-    widget::{canvas, column, horizontal_space, pick_list, row, text, Column},
+    widget::{button, canvas, column, container, horizontal_space, pick_list, row, text, Column},
     Element, Point, Size,
 };
 use iced_aw::number_input;
 
+use super::{index_input, modal_background_style};
+
 use crate::{
-    message::Message,
-    state::{ColorIdx, ColorRGB, EditorState, PaletteId, PixelCoord, Tile, TileIdx, Tool},
+    helpers::{display_color, palette_label},
+    message::{Message, SelectionSource},
+    state::{
+        ColorIdx, ColorProfile, ColorRGB, EditorState, PaletteId, PixelCoord, Tile, TileIdx, Tool,
+        TILE_SIZE,
+    },
 };
 
 #[derive(Debug)]
 struct GraphicsBox {
     colors: [ColorRGB; 16],
+    color_profile: ColorProfile,
     tile: Tile,
     palette_id: PaletteId,
     tile_idx: TileIdx,
@@ -22,10 +29,61 @@ struct GraphicsBox {
     pixel_coords: Option<(PixelCoord, PixelCoord)>,
     pixel_size: f32,
     thickness: f32,
+    show_pixel_grid: bool,
     color_selected: bool,
     tool: Tool,
 }
 
+// Size (in screen pixels) at which the tile editor draws each pixel:
+const GRAPHICS_PIXEL_SIZE: f32 = 24.0;
+
+// Read-only view of a tile. If `diff_with` is given, pixels whose color index differs
+// from the other tile are highlighted, and matching pixels are dimmed.
+struct TileCompareBox {
+    colors: [ColorRGB; 16],
+    color_profile: ColorProfile,
+    tile: Tile,
+    diff_with: Option<Tile>,
+    pixel_size: f32,
+}
+
+impl canvas::Program<Message> for TileCompareBox {
+    // No internal state
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &iced::Renderer,
+        _theme: &iced::Theme,
+        bounds: iced::Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<canvas::Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        for y in 0..TILE_SIZE {
+            for x in 0..TILE_SIZE {
+                let [r, g, b] = display_color(
+                    self.colors[self.tile.pixels[y][x] as usize],
+                    self.color_profile,
+                );
+                let color = match self.diff_with {
+                    Some(other) if other.pixels[y][x] != self.tile.pixels[y][x] => {
+                        iced::Color::from_rgb(1.0, 0.0, 0.0)
+                    }
+                    Some(_) => iced::Color::from_rgba8(r, g, b, 0.25),
+                    None => iced::Color::from_rgb8(r, g, b),
+                };
+                frame.fill_rectangle(
+                    Point::new(x as f32 * self.pixel_size, y as f32 * self.pixel_size),
+                    Size::new(self.pixel_size, self.pixel_size),
+                    color,
+                );
+            }
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
 #[derive(Default)]
 struct InternalState {
     clicking: bool,
@@ -72,7 +130,7 @@ impl canvas::Program<Message> for GraphicsBox {
         if click {
             let y = (p.y / self.pixel_size) as i32;
             let x = (p.x / self.pixel_size) as i32;
-            if x < 0 || x >= 8 || y < 0 || y >= 8 {
+            if x < 0 || x >= TILE_SIZE as i32 || y < 0 || y >= TILE_SIZE as i32 {
                 return (canvas::event::Status::Ignored, None);
             }
             if self.tool == Tool::Brush {
@@ -110,13 +168,13 @@ impl canvas::Program<Message> for GraphicsBox {
     ) -> Vec<canvas::Geometry> {
         let mut frame = canvas::Frame::new(renderer, bounds.size());
 
-        for y in 0..8 {
-            for x in 0..8 {
+        for y in 0..TILE_SIZE {
+            for x in 0..TILE_SIZE {
                 let color_idx = self.tile.pixels[y][x];
-                let color = self.colors[color_idx as usize];
-                let r = color[0] as f32 / 31.0;
-                let g = color[1] as f32 / 31.0;
-                let b = color[2] as f32 / 31.0;
+                let color = display_color(self.colors[color_idx as usize], self.color_profile);
+                let r = color[0] as f32 / 255.0;
+                let g = color[1] as f32 / 255.0;
+                let b = color[2] as f32 / 255.0;
                 frame.fill_rectangle(
                     iced::Point {
                         x: x as f32 * self.pixel_size + self.thickness,
@@ -131,6 +189,32 @@ impl canvas::Program<Message> for GraphicsBox {
             }
         }
 
+        if self.show_pixel_grid {
+            let grid_color = if theme.extended_palette().is_dark {
+                iced::Color::from_rgba(1.0, 1.0, 1.0, 0.25)
+            } else {
+                iced::Color::from_rgba(0.0, 0.0, 0.0, 0.25)
+            };
+            let path = canvas::Path::new(|p| {
+                let end = TILE_SIZE as f32 * self.pixel_size + self.thickness;
+                for i in 1..TILE_SIZE {
+                    let pos = i as f32 * self.pixel_size + self.thickness;
+                    p.move_to(Point::new(pos, self.thickness));
+                    p.line_to(Point::new(pos, end));
+                    p.move_to(Point::new(self.thickness, pos));
+                    p.line_to(Point::new(end, pos));
+                }
+            });
+            frame.stroke(
+                &path,
+                canvas::Stroke {
+                    width: 1.0,
+                    style: grid_color.into(),
+                    ..Default::default()
+                },
+            );
+        }
+
         if let Some((x, y)) = self.pixel_coords {
             let border_color = if theme.extended_palette().is_dark {
                 iced::Color::WHITE
@@ -180,13 +264,68 @@ pub fn graphics_view(state: &EditorState) -> Element<Message> {
     if let Some(idx) = state.tile_idx {
         let tile = pal.tiles[idx as usize];
         let label_width = 105;
+        // Compare the two selected tiles if exactly two are selected in the tileset,
+        // otherwise start from comparing the tile with itself:
+        let block = &state.selected_tile_block;
+        let compare_msg = if state.selection_source == SelectionSource::Tileset
+            && (block.size == (2, 1) || block.size == (1, 2))
+        {
+            Message::CompareTiles(
+                block.tiles[0][0],
+                *block.tiles.last().unwrap().last().unwrap(),
+            )
+        } else {
+            Message::CompareTiles(idx, idx)
+        };
         col = col
             .push(row![
                 column![
                     row![
                         text("Tile number").width(label_width),
-                        text(format!("${:02X} ({})", idx, idx)),
+                        text(if state.global_config.display_hex {
+                            format!("${:02X} ({})", idx, idx)
+                        } else {
+                            format!("{} (${:02X})", idx, idx)
+                        }),
+                        horizontal_space(),
+                        button(text("Compare").size(12))
+                            .style(button::secondary)
+                            .padding([2, 6])
+                            .on_press(compare_msg),
+                    ]
+                    .spacing(5)
+                    .align_y(Vertical::Center),
+                    row![
+                        text("Pixels").width(label_width),
+                        button(text("Copy").size(12))
+                            .style(button::secondary)
+                            .padding([2, 6])
+                            .on_press(Message::CopyTileAsText(idx)),
+                        button(text("Paste").size(12))
+                            .style(button::secondary)
+                            .padding([2, 6])
+                            .on_press(Message::PasteTileFromClipboard),
                     ]
+                    .spacing(5)
+                    .align_y(Vertical::Center),
+                    row![
+                        text("Replace color").width(label_width),
+                        number_input(
+                            &state.replace_color_from,
+                            0..=15,
+                            Message::SetReplaceColorFrom
+                        )
+                        .width(50),
+                        button(text("With selected").size(12))
+                            .style(button::secondary)
+                            .padding([2, 6])
+                            .on_press_maybe(
+                                state
+                                    .color_idx
+                                    .map(|_| Message::ReplaceColorInSelectedTiles)
+                            ),
+                    ]
+                    .spacing(5)
                     .align_y(Vertical::Center),
                     row![
                         text("Priority").width(label_width),
@@ -248,20 +387,96 @@ pub fn graphics_view(state: &EditorState) -> Element<Message> {
                 horizontal_space(),
                 canvas(GraphicsBox {
                     colors: pal.colors,
+                    color_profile: state.global_config.color_profile,
                     tile,
                     palette_id: pal_id,
                     tile_idx: idx,
                     color_idx: state.color_idx,
                     pixel_coords: state.pixel_coords,
-                    pixel_size: 24.0,
+                    pixel_size: GRAPHICS_PIXEL_SIZE,
                     thickness: 1.0,
+                    show_pixel_grid: state.global_config.pixel_grid_min_size > 0
+                        && GRAPHICS_PIXEL_SIZE >= state.global_config.pixel_grid_min_size as f32,
                     color_selected: state.color_idx.is_some(),
                     tool: state.tool,
                 })
-                .width(24 * 8 + 2)
-                .height(24 * 8 + 4)
+                .width(GRAPHICS_PIXEL_SIZE * TILE_SIZE as f32 + 2.0)
+                .height(GRAPHICS_PIXEL_SIZE * TILE_SIZE as f32 + 4.0)
             ])
             .padding([10, 0]);
     }
     col.into()
 }
+
+pub fn compare_tiles_view(state: &EditorState, a: TileIdx, b: TileIdx) -> Element<'_, Message> {
+    let pal = &state.palettes[state.palette_idx];
+    let max_idx = pal.tiles.len() as TileIdx - 1;
+    let hex = state.global_config.display_hex;
+    let tile_a = pal.tiles.get(a as usize).copied().unwrap_or_default();
+    let tile_b = pal.tiles.get(b as usize).copied().unwrap_or_default();
+    let num_diff = (0..TILE_SIZE)
+        .flat_map(|y| (0..TILE_SIZE).map(move |x| (x, y)))
+        .filter(|&(x, y)| tile_a.pixels[y][x] != tile_b.pixels[y][x])
+        .count();
+    let pixel_size = 16.0;
+    let tile_box = |tile: Tile, diff_with: Option<Tile>| {
+        canvas(TileCompareBox {
+            colors: pal.colors,
+            color_profile: state.global_config.color_profile,
+            tile,
+            diff_with,
+            pixel_size,
+        })
+        .width(pixel_size * TILE_SIZE as f32)
+        .height(pixel_size * TILE_SIZE as f32)
+    };
+    container(
+        column![
+            text(format!(
+                "Compare tiles of palette {}",
+                palette_label(pal, hex)
+            )),
+            row![
+                column![
+                    index_input(
+                        a,
+                        max_idx,
+                        hex,
+                        80.0,
+                        move |a| Message::CompareTiles(a, b),
+                        None
+                    ),
+                    tile_box(tile_a, None),
+                ]
+                .spacing(5),
+                column![
+                    index_input(
+                        b,
+                        max_idx,
+                        hex,
+                        80.0,
+                        move |b| Message::CompareTiles(a, b),
+                        None
+                    ),
+                    tile_box(tile_b, None),
+                ]
+                .spacing(5),
+                column![text("Difference"), tile_box(tile_a, Some(tile_b))].spacing(12),
+            ]
+            .spacing(20)
+            .align_y(Vertical::Bottom),
+            text(if num_diff == 0 {
+                "The tiles have identical pixels.".to_string()
+            } else {
+                format!("{} of 64 pixels differ.", num_diff)
+            }),
+            button(text("Close"))
+                .style(button::secondary)
+                .on_press(Message::CloseDialogue),
+        ]
+        .spacing(15),
+    )
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
@@ -3,18 +3,22 @@ use iced::{
     alignment::Vertical,
     mouse,
     widget::{
-        button, canvas, column, container, pick_list, row, text, text_input, Column, Row, Space,
+        button, canvas, checkbox, column, container, mouse_area, pick_list, row, scrollable, text,
+        text_input, Column, Row, Space,
     },
-    Element, Length, Size,
+    Element, Length, Padding, Size,
 };
 use iced_aw::number_input;
 
 use crate::{
+    helpers::{display_color, format_number, palette_label, scale_color},
     message::Message,
-    state::{ColorIdx, ColorRGB, EditorState, Focus, PaletteId, PaletteIdx, Tool},
+    state::{
+        AreaId, ColorIdx, ColorRGB, EditorState, Focus, PaletteId, PaletteIdx, SidePanelView, Tool,
+    },
 };
 
-use super::modal_background_style;
+use super::{index_input, modal_background_style};
 
 #[derive(Debug)]
 struct ColorBox {
@@ -23,6 +27,9 @@ struct ColorBox {
     b: f32,
     thickness: f32,
     selected: bool,
+    // Whether the color is always highlighted in the tileset and areas:
+    pinned: bool,
+    highlight_color: [u8; 3],
     color_idx: ColorIdx,
     palette_id: PaletteId,
     palette_idx: PaletteIdx,
@@ -60,7 +67,7 @@ impl canvas::Program<Message> for ColorBox {
                                 Some(Message::SelectColor(self.palette_idx, self.color_idx))
                             }
                         }
-                        mouse::Button::Right => None,
+                        mouse::Button::Right => Some(Message::ToggleIdentifyColor(self.color_idx)),
                         _ => None,
                     };
 
@@ -96,6 +103,18 @@ impl canvas::Program<Message> for ColorBox {
             iced::Color::from_rgb(self.r, self.g, self.b),
         );
 
+        if self.pinned {
+            // Mark the color with a corner in the highlight color:
+            let [r, g, b] = self.highlight_color;
+            let corner = canvas::Path::new(|p| {
+                p.move_to(iced::Point::new(thickness, thickness));
+                p.line_to(iced::Point::new(thickness + size.width / 2.0, thickness));
+                p.line_to(iced::Point::new(thickness, thickness + size.height / 2.0));
+                p.close();
+            });
+            frame.fill(&corner, iced::Color::from_rgb8(r, g, b));
+        }
+
         if self.selected {
             let border_color = if theme.extended_palette().is_dark {
                 iced::Color::WHITE
@@ -138,25 +157,33 @@ impl canvas::Program<Message> for ColorBox {
 }
 
 pub fn selected_palette_view(state: &EditorState) -> Element<Message> {
+    let hex = state.global_config.display_hex;
     let palette_names: Vec<String> = state
         .palettes
         .iter()
-        .map(|x| format!("{}: {}", x.id, x.name))
+        .map(|x| palette_label(x, hex))
         .collect();
     let pal = &state.palettes[state.palette_idx];
-    let selected_palette_name = format!("{}: {}", pal.id, pal.name);
+    let selected_palette_name = palette_label(pal, hex);
 
-    let mut colors_row = iced::widget::Row::new();
+    let columns = state.global_config.palette_layout.columns();
+    let mut colors_col = Column::new();
+    let mut colors_row = Row::new();
     let pal = &state.palettes[state.palette_idx];
     let size = 25.0;
-    for i in 0..16 {
+    let display_colors = pal
+        .colors
+        .map(|c| display_color(c, state.global_config.color_profile));
+    for (i, [r, g, b]) in display_colors.into_iter().enumerate() {
         colors_row = colors_row.push(
             canvas(ColorBox {
-                r: pal.colors[i][0] as f32 / 31.0,
-                g: pal.colors[i][1] as f32 / 31.0,
-                b: pal.colors[i][2] as f32 / 31.0,
+                r: r as f32 / 255.0,
+                g: g as f32 / 255.0,
+                b: b as f32 / 255.0,
                 thickness: 2.0,
                 selected: Some(i as ColorIdx) == state.color_idx,
+                pinned: state.pinned_identify_colors.contains(&(i as ColorIdx)),
+                highlight_color: state.global_config.highlight_color.map(scale_color),
                 color_idx: i as ColorIdx,
                 palette_id: state.palettes[state.palette_idx].id,
                 palette_idx: state.palette_idx,
@@ -166,6 +193,10 @@ pub fn selected_palette_view(state: &EditorState) -> Element<Message> {
             .width(size)
             .height(size),
         );
+        if (i + 1) % columns == 0 {
+            colors_col = colors_col.push(colors_row);
+            colors_row = Row::new();
+        }
     }
 
     let rgb_width = 80;
@@ -184,10 +215,16 @@ pub fn selected_palette_view(state: &EditorState) -> Element<Message> {
                 .on_press(Message::AddPaletteDialogue),
             button(text("\u{F4CB}").font(iced_fonts::BOOTSTRAP_FONT))
                 .on_press(Message::RenamePaletteDialogue),
+            button(text("\u{F30A}").font(iced_fonts::BOOTSTRAP_FONT))
+                .style(button::secondary)
+                .on_press(Message::ExportPaletteJsonDialogue),
+            button(text("All"))
+                .style(button::secondary)
+                .on_press(Message::SetSidePanelView(SidePanelView::Palettes)),
         ]
         .spacing(10)
         .align_y(iced::alignment::Vertical::Center),
-        colors_row,
+        colors_col,
     ]
     .spacing(5);
 
@@ -213,7 +250,11 @@ pub fn selected_palette_view(state: &EditorState) -> Element<Message> {
     row![col].padding(10).into()
 }
 
-pub fn add_palette_view(name: &String, id: PaletteId) -> Element<Message> {
+pub fn add_palette_view<'a>(
+    state: &EditorState,
+    name: &'a String,
+    id: PaletteId,
+) -> Element<'a, Message> {
     container(
         column![
             text("Select a name and ID for the new palette"),
@@ -231,12 +272,17 @@ pub fn add_palette_view(name: &String, id: PaletteId) -> Element<Message> {
             .align_y(Vertical::Center),
             row![
                 text("ID: ").width(70),
-                number_input(&id, 0..=255, Message::SetAddPaletteID)
-                    .width(50)
-                    .on_submit(Message::AddPalette {
+                index_input(
+                    id,
+                    255,
+                    state.global_config.display_hex,
+                    50.0,
+                    Message::SetAddPaletteID,
+                    Some(Message::AddPalette {
                         name: name.clone(),
                         id
-                    }),
+                    })
+                ),
             ]
             .spacing(10)
             .align_y(Vertical::Center),
@@ -266,7 +312,8 @@ pub fn rename_palette_view(state: &EditorState, name: &String) -> Element<'stati
         column![
             text(format!(
                 "Rename palette {}: \"{}\"",
-                state.palettes[idx].id, old_name
+                format_number(state.palettes[idx].id, state.global_config.display_hex),
+                old_name
             )),
             text_input("", name)
                 .id("RenamePalette")
@@ -289,51 +336,254 @@ pub fn rename_palette_view(state: &EditorState, name: &String) -> Element<'stati
     .into()
 }
 
-pub fn delete_palette_view(state: &EditorState) -> Element<Message> {
+pub fn delete_palette_view<'a>(
+    state: &'a EditorState,
+    references: &[AreaId],
+    remap_to: Option<PaletteId>,
+) -> Element<'a, Message> {
     let idx = state.palette_idx;
     let name = &state.palettes[idx].name;
+    let id = state.palettes[idx].id;
+    let hex = state.global_config.display_hex;
+    let mut col = column![
+        text(format!(
+            "Delete palette {}: \"{}\"?",
+            format_number(id, hex),
+            name
+        )),
+        text("This will also delete all 8x8 tiles associated to this palette."),
+    ]
+    .spacing(10);
+    if state.palettes.len() == 1 {
+        col = col.push(text("The last remaining palette cannot be deleted."));
+    } else if !references.is_empty() {
+        let mut area_names: Vec<String> = references
+            .iter()
+            .map(|a| format!("{} ({})", a.area, a.theme))
+            .collect();
+        area_names.truncate(10);
+        if references.len() > area_names.len() {
+            area_names.push(format!("and {} more", references.len() - area_names.len()));
+        }
+        let other_palettes: Vec<String> = state
+            .palettes
+            .iter()
+            .filter(|p| p.id != id)
+            .map(|p| palette_label(p, hex))
+            .collect();
+        let remap_name = remap_to
+            .and_then(|p| state.palettes_id_idx_map.get(&p))
+            .map(|&i| palette_label(&state.palettes[i], hex));
+        col = col.push(text(format!(
+            "The palette is still used in: {}",
+            area_names.join(", ")
+        )));
+        col = col.push(
+            row![
+                text("Remap to"),
+                pick_list(other_palettes, remap_name, Message::SetDeletePaletteRemapTo).width(180),
+                button(text("Remap"))
+                    .style(button::secondary)
+                    .on_press_maybe(remap_to.map(|to| Message::RemapPalette { from: id, to })),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+        );
+    }
+    col = col.push(
+        button(text("Delete palette"))
+            .style(button::danger)
+            .on_press_maybe((state.palettes.len() > 1).then_some(Message::DeletePalette(id))),
+    );
+    container(col)
+        .width(350)
+        .padding(25)
+        .style(modal_background_style)
+        .into()
+}
+
+pub fn merge_palettes_view(
+    state: &EditorState,
+    require_identical_tiles: bool,
+    groups: &[Vec<PaletteId>],
+) -> Element<'static, Message> {
+    let hex = state.global_config.display_hex;
+    let label = |id: &PaletteId| match state.palettes_id_idx_map.get(id) {
+        Some(&i) => palette_label(&state.palettes[i], hex),
+        None => format_number(*id, hex),
+    };
+    let mut groups_col: Column<Message> = Column::new().spacing(5);
+    if groups.is_empty() {
+        groups_col = groups_col.push(text("No duplicate palettes found."));
+    }
+    for group in groups {
+        let dups: Vec<String> = group[1..].iter().map(label).collect();
+        groups_col = groups_col.push(text(format!(
+            "Merge palettes {} into palette {}",
+            dups.join(", "),
+            label(&group[0])
+        )));
+    }
     container(
         column![
-            text(format!(
-                "Delete palette {}: \"{}\"?",
-                state.palettes[idx].id, name
-            )),
-            text("This will also delete all 8x8 tiles associated to this palette."),
-            button(text("Delete palette"))
-                .style(button::danger)
-                .on_press(Message::DeletePalette(state.palettes[idx].id)),
+            text("Merge palettes that have identical colors."),
+            checkbox("Require identical tiles", require_identical_tiles)
+                .on_toggle(Message::SetMergePalettesIdenticalTiles),
+            scrollable(groups_col).height(Length::Shrink),
+            text("Tiles missing from the surviving palette will be appended to it."),
+            text("References to the merged palettes will be updated in all areas."),
+            text("This action cannot be undone."),
+            row![
+                button(text("Cancel"))
+                    .style(button::secondary)
+                    .on_press(Message::CloseDialogue),
+                Space::with_width(Length::Fill),
+                button(text("Merge palettes"))
+                    .style(button::danger)
+                    .on_press_maybe(if groups.is_empty() {
+                        None
+                    } else {
+                        Some(Message::MergePalettes(groups.to_vec()))
+                    }),
+            ],
         ]
         .spacing(10),
     )
-    .width(350)
+    .width(450)
+    .max_height(500)
     .padding(25)
     .style(modal_background_style)
     .into()
 }
 
+// Colors of every palette, one row each; clicking a row selects the palette.
+pub fn all_palettes_view(state: &EditorState) -> Element<'_, Message> {
+    let hex = state.global_config.display_hex;
+    let size = 14.0;
+    let mut col = Column::new().spacing(2);
+    for (idx, pal) in state.palettes.iter().enumerate() {
+        let mut swatches = Row::new().spacing(1);
+        for c in pal.colors {
+            let [r, g, b] = display_color(c, state.global_config.color_profile);
+            swatches =
+                swatches.push(
+                    container(Space::new(size, size)).style(move |_| container::Style {
+                        background: Some(iced::Color::from_rgb8(r, g, b).into()),
+                        ..Default::default()
+                    }),
+                );
+        }
+        col = col.push(
+            button(
+                row![
+                    text(palette_label(pal, hex)).size(12).width(Length::Fill),
+                    swatches,
+                ]
+                .spacing(5)
+                .align_y(Vertical::Center),
+            )
+            .width(Length::Fill)
+            .padding([2, 6])
+            .style(if idx == state.palette_idx {
+                button::primary
+            } else {
+                button::text
+            })
+            .on_press(Message::SelectPaletteIdx(idx)),
+        );
+    }
+    column![
+        row![
+            text("All palettes"),
+            Space::with_width(Length::Fill),
+            button(text("Tileset"))
+                .style(button::secondary)
+                .on_press(Message::SetSidePanelView(SidePanelView::Tileset)),
+        ]
+        .align_y(Vertical::Center),
+        scrollable(col).height(Length::Fill),
+    ]
+    .padding(10)
+    .spacing(10)
+    .width(440)
+    .into()
+}
+
 pub fn used_palettes_view(state: &EditorState) -> Element<Message> {
     let mut col: Column<Message> = Column::new();
+    let ids: Vec<PaletteId> = state
+        .selected_palette_ids
+        .iter()
+        .copied()
+        .filter(|id| state.palettes_id_idx_map.contains_key(id))
+        .collect();
+    if !ids.is_empty() {
+        col = col.push(
+            row![
+                text(format!("{} selected", ids.len())).width(125),
+                button(text("Darker")).style(button::secondary).on_press(
+                    Message::AdjustPalettesBrightness {
+                        palette_ids: ids.clone(),
+                        delta: -1,
+                    }
+                ),
+                button(text("Brighter")).style(button::secondary).on_press(
+                    Message::AdjustPalettesBrightness {
+                        palette_ids: ids,
+                        delta: 1,
+                    }
+                ),
+                Space::with_width(Length::Fill),
+                button(text("Clear"))
+                    .style(button::secondary)
+                    .on_press(Message::ClearPaletteSelection),
+            ]
+            .spacing(5)
+            .padding(Padding::new(0.0).bottom(5.0))
+            .align_y(Vertical::Center),
+        );
+    }
     let palette_ids = state.main_area().get_unique_palettes();
     for pal_id in palette_ids {
         let Some(&palette_idx) = state.palettes_id_idx_map.get(&pal_id) else {
-            col = col.push(row![text(format!("{} (does not exist)", pal_id))]);
+            col = col.push(row![text(format!(
+                "{} (does not exist)",
+                format_number(pal_id, state.global_config.display_hex)
+            ))]);
             continue;
         };
         let mut row: Row<Message> = Row::new();
         let pal = &state.palettes[palette_idx as usize];
-        row = row.push(text(pal.name.clone()).width(125));
+        // Palettes can be selected for bulk operations (Ctrl-click to toggle, Shift-click for a range):
+        let selected = state.selected_palette_ids.contains(&pal_id);
+        row = row.push(
+            mouse_area(
+                text(palette_label(pal, state.global_config.display_hex))
+                    .width(125)
+                    .style(move |theme: &iced::Theme| text::Style {
+                        color: selected.then(|| theme.palette().primary),
+                    }),
+            )
+            .on_press(Message::ClickPaletteName(pal_id)),
+        );
         row = row.push(Space::with_width(5));
 
         let size = 18.0;
-        for i in 0..16 {
+        let display_colors = pal
+            .colors
+            .map(|c| display_color(c, state.global_config.color_profile));
+        for (i, [r, g, b]) in display_colors.into_iter().enumerate() {
             row = row.push(
                 canvas(ColorBox {
-                    r: pal.colors[i][0] as f32 / 31.0,
-                    g: pal.colors[i][1] as f32 / 31.0,
-                    b: pal.colors[i][2] as f32 / 31.0,
+                    r: r as f32 / 255.0,
+                    g: g as f32 / 255.0,
+                    b: b as f32 / 255.0,
                     thickness: 1.0,
                     selected: state.palette_idx == palette_idx
                         && Some(i as ColorIdx) == state.color_idx,
+                    pinned: state.palette_idx == palette_idx
+                        && state.pinned_identify_colors.contains(&(i as ColorIdx)),
+                    highlight_color: state.global_config.highlight_color.map(scale_color),
                     color_idx: i as ColorIdx,
                     palette_id: state.palettes[palette_idx].id,
                     palette_idx,
@@ -1,21 +1,81 @@
+use std::path::PathBuf;
+
 use iced::{
     alignment::Vertical,
-    widget::{button, column, container, horizontal_space, row, slider, text, text_input},
-    Element, Length,
+    widget::{
+        button, checkbox, column, container, horizontal_space, pick_list, row, scrollable, slider,
+        text, text_input, Column, Row, Space,
+    },
+    Border, Element, Length,
 };
 use iced_aw::number_input;
 use iced_fonts::BOOTSTRAP_FONT;
 
 use crate::{
+    helpers::scale_color,
     message::Message,
-    state::{EditorState, MAX_PIXEL_SIZE, MIN_PIXEL_SIZE},
+    state::{
+        ColorLimitPolicy, ColorMatchPolicy, ColorProfile, ColorRGB, ConfirmExit, EditorState,
+        ImageFilter, LineBrushModifier, MouseButtonMapping, MoveSnap, OutOfBoundsMove,
+        PaletteFileNaming, PaletteLayout, StartupTool, TilesetBackdrop, MAX_AREA_MARGIN,
+        MAX_CONTACT_SHEET_COLUMNS, MAX_FILL_CONFIRM_THRESHOLD, MAX_MAJOR_GRID_INTERVAL,
+        MAX_MOVE_SNAP_STEP, MAX_PIXEL_GRID_MIN_SIZE, MAX_PIXEL_SIZE, MAX_SELECTION_DASH_SPEED,
+        MAX_SELECTION_SNAP_STEP, MAX_THUMBNAIL_SIZE, MAX_TILE_PREVIEW_ZOOM, MIN_PIXEL_SIZE,
+        MIN_THUMBNAIL_SIZE,
+    },
 };
 
 use super::modal_background_style;
 
+fn color_row(
+    label: &'static str,
+    color: ColorRGB,
+    on_change: impl Fn(ColorRGB) -> Message + Copy + 'static,
+) -> Row<'static, Message> {
+    let [r, g, b] = color.map(scale_color);
+    row![
+        text(label).width(100),
+        container(Space::new(20, 20)).style(move |_| container::Style {
+            background: Some(iced::Color::from_rgb8(r, g, b).into()),
+            border: Border::default().width(1).color(iced::Color::WHITE),
+            ..Default::default()
+        }),
+        text("Red"),
+        number_input(&color[0], 0..=31, move |r| {
+            on_change([r, color[1], color[2]])
+        })
+        .width(70),
+        text("Green"),
+        number_input(&color[1], 0..=31, move |g| {
+            on_change([color[0], g, color[2]])
+        })
+        .width(70),
+        text("Blue"),
+        number_input(&color[2], 0..=31, move |b| {
+            on_change([color[0], color[1], b])
+        })
+        .width(70),
+    ]
+    .spacing(10)
+    .align_y(Vertical::Center)
+}
+
 pub fn settings_view(state: &EditorState) -> Element<Message> {
     let project_dir = state.global_config.project_dir.as_ref().unwrap();
     let zoom_range = MIN_PIXEL_SIZE..=MAX_PIXEL_SIZE;
+    let mut snapshots_col: Column<Message> = Column::new().spacing(5).width(Length::Fill);
+    for name in &state.snapshot_names {
+        snapshots_col = snapshots_col.push(
+            row![
+                text(name.clone()).width(Length::Fill),
+                button(text("Restore"))
+                    .style(button::danger)
+                    .on_press(Message::RestoreSnapshotDialogue(name.clone())),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+        );
+    }
     container(
         column![
             row![
@@ -41,6 +101,8 @@ pub fn settings_view(state: &EditorState) -> Element<Message> {
                     Message::SetPixelSize
                 )
                 .width(60),
+                checkbox("Per area", state.global_config.per_area_zoom)
+                    .on_toggle(Message::SetPerAreaZoom),
             ]
             .spacing(10)
             .align_y(Vertical::Center),
@@ -62,15 +124,530 @@ pub fn settings_view(state: &EditorState) -> Element<Message> {
             ]
             .spacing(10)
             .align_y(Vertical::Center),
+            color_row(
+                "Grid color",
+                state.global_config.grid_color,
+                Message::SetGridColor
+            ),
+            row![
+                text("Major grid").width(100),
+                text("Every"),
+                number_input(
+                    &state.global_config.major_grid_interval,
+                    0..=MAX_MAJOR_GRID_INTERVAL,
+                    Message::SetMajorGridInterval
+                )
+                .width(70),
+                text("tiles"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            color_row(
+                "Major grid color",
+                state.global_config.major_grid_color,
+                Message::SetMajorGridColor
+            )
+            .push(text("Alpha"))
+            .push(
+                number_input(
+                    &((state.global_config.major_grid_alpha * 100.0).round() as u8),
+                    1..=100,
+                    |x| Message::SetMajorGridAlpha(x as f32 / 100.0)
+                )
+                .width(70)
+            ),
+            color_row(
+                "Highlight",
+                state.global_config.highlight_color,
+                Message::SetHighlightColor
+            )
+            .push(text("Alpha"))
+            .push(
+                number_input(
+                    &((state.global_config.highlight_alpha * 100.0).round() as u8),
+                    1..=100,
+                    |x| Message::SetHighlightAlpha(x as f32 / 100.0)
+                )
+                .width(70)
+            ),
+            color_row(
+                "Selection dash 1",
+                state.global_config.selection_dash_colors[0],
+                |c| Message::SetSelectionDashColor(0, c)
+            ),
+            color_row(
+                "Selection dash 2",
+                state.global_config.selection_dash_colors[1],
+                |c| Message::SetSelectionDashColor(1, c)
+            ),
+            row![
+                text("Selection speed").width(100),
+                number_input(
+                    &state.global_config.selection_dash_speed,
+                    0..=MAX_SELECTION_DASH_SPEED,
+                    Message::SetSelectionDashSpeed
+                )
+                .width(70),
+                text("Pixels the selection outline moves per step (0 to hold it still)"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Tileset backdrop").width(100),
+                pick_list(
+                    TilesetBackdrop::ALL,
+                    Some(state.global_config.tileset_backdrop),
+                    Message::SetTilesetBackdrop
+                )
+                .width(130),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            color_row(
+                "Backdrop color",
+                state.global_config.tileset_backdrop_color,
+                Message::SetTilesetBackdropColor
+            ),
+            row![
+                text("Tile select").width(100),
+                checkbox(
+                    "Select runs of consecutive tiles, wrapping across rows",
+                    state.global_config.linear_select
+                )
+                .on_toggle(Message::SetLinearSelect),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Numbers").width(100),
+                checkbox(
+                    "Show tile numbers and palette IDs in hexadecimal",
+                    state.global_config.display_hex
+                )
+                .on_toggle(Message::SetDisplayHex),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Tile hover").width(100),
+                checkbox(
+                    "Highlight the tileset tile under the cursor",
+                    state.global_config.tile_hover_highlight
+                )
+                .on_toggle(Message::SetTileHoverHighlight),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Lists").width(100),
+                checkbox(
+                    "Double-click a palette name to rename it",
+                    state.global_config.double_click_rename
+                )
+                .on_toggle(Message::SetDoubleClickRename),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Selection").width(100),
+                checkbox(
+                    "Keep the selection when switching tools (otherwise cleared, as with Escape)",
+                    state.global_config.keep_selection_on_tool_switch
+                )
+                .on_toggle(Message::SetKeepSelectionOnToolSwitch),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Tile preview").width(100),
+                text("Magnification of the tileset hover preview (0 to disable)"),
+                horizontal_space(),
+                number_input(
+                    &state.global_config.tile_preview_zoom,
+                    0..=MAX_TILE_PREVIEW_ZOOM,
+                    Message::SetTilePreviewZoom
+                )
+                .width(60),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Pixel brush").width(100),
+                checkbox(
+                    "Advance to the next color after drawing each pixel in the tile editor",
+                    state.global_config.auto_advance_color
+                )
+                .on_toggle(Message::SetAutoAdvanceColor),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Pixel grid").width(100),
+                text(
+                    "Minimum pixel size in the tile editor for showing a pixel grid (0 to hide it)"
+                ),
+                horizontal_space(),
+                number_input(
+                    &state.global_config.pixel_grid_min_size,
+                    0..=MAX_PIXEL_GRID_MIN_SIZE,
+                    Message::SetPixelGridMinSize
+                )
+                .width(60),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Area margin").width(100),
+                number_input(
+                    &state.global_config.area_margin,
+                    0..=MAX_AREA_MARGIN,
+                    Message::SetAreaMargin
+                )
+                .width(80),
+                checkbox("Center", state.global_config.center_area)
+                    .on_toggle(Message::SetCenterArea),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Autosave").width(100),
+                checkbox(
+                    "Autosave to a separate folder (Ctrl+S saves the project files)",
+                    state.global_config.autosave_to_shadow
+                )
+                .on_toggle(Message::SetAutosaveToShadow),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Areas").width(100),
+                checkbox(
+                    "Trim empty edge screens on save",
+                    state.global_config.trim_empty_edges_on_save
+                )
+                .on_toggle(Message::SetTrimEmptyEdgesOnSave),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Animation").width(100),
+                checkbox(
+                    "Play tile animations in the areas",
+                    state.global_config.play_animations
+                )
+                .on_toggle(Message::SetPlayAnimations),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Colors").width(100),
+                pick_list(
+                    ColorProfile::ALL,
+                    Some(state.global_config.color_profile),
+                    Message::SetColorProfile
+                )
+                .width(100),
+                text("Display only; exported PNGs are not affected"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Previews").width(100),
+                pick_list(
+                    ImageFilter::ALL,
+                    Some(state.global_config.preview_filter),
+                    Message::SetPreviewFilter
+                )
+                .width(100),
+                text("Filtering of the tile preview, world overview, and contact sheet"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Mouse buttons").width(100),
+                pick_list(
+                    MouseButtonMapping::ALL,
+                    Some(state.global_config.mouse_buttons),
+                    Message::SetMouseButtons
+                )
+                .width(100),
+                text("Swapped: right-click uses the tool, left-click selects"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Confirm exit").width(100),
+                pick_list(
+                    ConfirmExit::ALL,
+                    Some(state.global_config.confirm_exit),
+                    Message::SetConfirmExit
+                )
+                .width(120),
+                text("Ask before closing the window (the project is saved on exit)"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Startup tool").width(100),
+                pick_list(
+                    StartupTool::ALL,
+                    Some(state.global_config.startup_tool),
+                    Message::SetStartupTool
+                )
+                .width(120),
+                text("Tool active when the editor starts"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Palette files").width(100),
+                pick_list(
+                    PaletteFileNaming::ALL,
+                    Some(state.global_config.palette_file_naming),
+                    Message::SetPaletteFileNaming
+                )
+                .width(120),
+                text("Name palette files by name, or by zero-padded ID and name (e.g. 007-Grass)"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Color limit").width(100),
+                pick_list(
+                    ColorLimitPolicy::ALL,
+                    Some(state.global_config.color_limit_policy),
+                    Message::SetColorLimitPolicy
+                )
+                .width(120),
+                text("Handling of PNG imports with more than 16 colors, when quantizing"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Color match").width(100),
+                pick_list(
+                    ColorMatchPolicy::ALL,
+                    Some(state.global_config.color_match_policy),
+                    Message::SetColorMatchPolicy
+                )
+                .width(120),
+                text("Handling of PNG colors not in the palette, when not quantizing"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Palette layout").width(100),
+                pick_list(
+                    PaletteLayout::ALL,
+                    Some(state.global_config.palette_layout),
+                    Message::SetPaletteLayout
+                )
+                .width(100),
+                text("Columns x rows of the selected palette's colors"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Line brush").width(100),
+                pick_list(
+                    LineBrushModifier::ALL,
+                    Some(state.global_config.line_brush_modifier),
+                    Message::SetLineBrushModifier
+                )
+                .width(100),
+                text("Hold while brushing to draw straight lines"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Moving tiles").width(100),
+                pick_list(
+                    OutOfBoundsMove::ALL,
+                    Some(state.global_config.out_of_bounds_move),
+                    Message::SetOutOfBoundsMove
+                )
+                .width(100),
+                text("When some tiles would be moved past the end of the tileset"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Move snap").width(100),
+                pick_list(
+                    MoveSnap::ALL,
+                    Some(state.global_config.move_snap),
+                    Message::SetMoveSnap
+                )
+                .width(120),
+                text("Step"),
+                number_input(
+                    &state.global_config.move_snap_step,
+                    1..=MAX_MOVE_SNAP_STEP,
+                    Message::SetMoveSnapStep
+                )
+                .width(70),
+                text("Alignment of the destination of moved tiles"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Selection snap").width(100),
+                number_input(
+                    &state.global_config.selection_snap_step,
+                    1..=MAX_SELECTION_SNAP_STEP,
+                    Message::SetSelectionSnapStep
+                )
+                .width(70),
+                text("Interval (in tiles) to which the start of an area selection snaps"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Undo").width(100),
+                text("Levels"),
+                number_input(
+                    &state.global_config.max_undo_levels,
+                    1..=100000,
+                    Message::SetMaxUndoLevels
+                )
+                .width(90),
+                text("Memory (MB)"),
+                number_input(
+                    &state.global_config.undo_memory_mb,
+                    1..=16384,
+                    Message::SetUndoMemoryMb
+                )
+                .step(64)
+                .width(90),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Brush opacity").width(100),
+                slider(
+                    0.01..=1.0,
+                    state.global_config.brush_opacity,
+                    Message::SetBrushOpacity
+                )
+                .step(0.01)
+                .width(Length::Fill),
+                number_input(
+                    &((state.global_config.brush_opacity * 100.0).round() as u8),
+                    1..=100,
+                    |x| { Message::SetBrushOpacity(x as f32 / 100.0) }
+                )
+                .width(60),
+                text("% of cells painted by the area brush (for dithered blends)"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Tileset brush").width(100),
+                checkbox(
+                    "Brushing tiles selected in the tileset also assigns their palette",
+                    state.global_config.tileset_brush_palette
+                )
+                .on_toggle(Message::SetTilesetBrushPalette),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Fill confirm").width(100),
+                number_input(
+                    &state.global_config.fill_confirm_threshold,
+                    0..=MAX_FILL_CONFIRM_THRESHOLD,
+                    Message::SetFillConfirmThreshold
+                )
+                .step(100)
+                .width(90),
+                text("Confirm flood fills changing more than this many cells (0 to never ask)"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Contact sheet").width(100),
+                text("Thumbnail size"),
+                number_input(
+                    &state.global_config.contact_sheet_thumbnail_size,
+                    MIN_THUMBNAIL_SIZE..=MAX_THUMBNAIL_SIZE,
+                    Message::SetContactSheetThumbnailSize
+                )
+                .step(16)
+                .width(70),
+                text("Columns"),
+                number_input(
+                    &state.global_config.contact_sheet_columns,
+                    1..=MAX_CONTACT_SHEET_COLUMNS,
+                    Message::SetContactSheetColumns
+                )
+                .width(60),
+                horizontal_space(),
+                button(text("Export"))
+                    .style(button::secondary)
+                    .on_press(Message::ExportContactSheetDialogue),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Palettes").width(100),
+                text("Raw SNES BGR555 colors (16 per palette), ordered by palette ID"),
+                horizontal_space(),
+                button(text("Export"))
+                    .style(button::secondary)
+                    .on_press(Message::ExportPalettesBinDialogue),
+                button(text("Import"))
+                    .style(button::secondary)
+                    .on_press(Message::ImportPalettesBinDialogue),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("CHR/PAL").width(100),
+                text("Tiles of the selected palette as SNES 4bpp graphics, with its colors"),
+                horizontal_space(),
+                button(text("Export"))
+                    .style(button::secondary)
+                    .on_press(Message::ExportPaletteChrDialogue),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Archive").width(100),
+                text("Share or back up the project as a single ZIP file"),
+                horizontal_space(),
+                button(text("Export"))
+                    .style(button::secondary)
+                    .on_press(Message::ExportProjectArchiveDialogue),
+                button(text("Import"))
+                    .style(button::secondary)
+                    .on_press(Message::ImportProjectArchiveDialogue),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Snapshots").width(100),
+                snapshots_col,
+                button(text("\u{F64D}").font(BOOTSTRAP_FONT))
+                    .style(button::success)
+                    .on_press(Message::CreateSnapshotDialogue),
+            ]
+            .spacing(10),
             row![
                 button("Close")
                     .style(button::secondary)
                     .on_press(Message::CloseDialogue),
                 horizontal_space(),
+                button("Merge duplicate palettes")
+                    .style(button::secondary)
+                    .on_press(Message::MergePalettesDialogue),
+                button("Compare with ROM")
+                    .style(button::secondary)
+                    .on_press(Message::DiffAgainstRomDialogue),
                 button("Import from ROM")
                     .style(button::danger)
                     .on_press(Message::ImportDialogue)
             ]
+            .spacing(10)
         ]
         .spacing(20),
     )
@@ -80,12 +657,17 @@ pub fn settings_view(state: &EditorState) -> Element<Message> {
     .into()
 }
 
-pub fn import_rom_confirm_view(_state: &EditorState) -> Element<Message> {
+pub fn import_rom_confirm_view(state: &EditorState) -> Element<Message> {
     container(
         column![
             text("Import project from ROM?"),
             text("This may update existing palettes, tilesets, and areas."),
             text("This action cannot be undone."),
+            checkbox(
+                "Import animated tiles (e.g. water) as tile animations",
+                state.global_config.import_options.animated_tiles
+            )
+            .on_toggle(Message::SetImportAnimatedTiles),
             row![
                 button(text("Import from ROM"))
                     .style(button::danger)
@@ -111,3 +693,92 @@ pub fn import_rom_progress_view(_state: &EditorState) -> Element<Message> {
         .style(modal_background_style)
         .into()
 }
+
+pub fn rom_diff_progress_view() -> Element<'static, Message> {
+    container(text(
+        "Please wait while the ROM is compared with the project.",
+    ))
+    .width(350)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+pub fn create_snapshot_view(name: &str) -> Element<'static, Message> {
+    container(
+        column![
+            text("Create a snapshot of the project."),
+            row![
+                text("Name: ").width(70),
+                text_input("", name)
+                    .id("CreateSnapshot")
+                    .on_input(Message::SetCreateSnapshotName)
+                    .on_submit(Message::CreateSnapshot(name.to_string()))
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            button(text("Create snapshot"))
+                .style(button::success)
+                .on_press(Message::CreateSnapshot(name.to_string())),
+        ]
+        .spacing(10),
+    )
+    .width(450)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+pub fn restore_snapshot_view(name: &str) -> Element<'static, Message> {
+    container(
+        column![
+            text(format!("Restore snapshot \"{}\"?", name)),
+            text("This will replace all palettes, tilesets, and areas in the project."),
+            text("This action cannot be undone."),
+            row![
+                button(text("Restore snapshot"))
+                    .style(button::danger)
+                    .on_press(Message::RestoreSnapshot(name.to_string())),
+                horizontal_space(),
+                button(text("Cancel"))
+                    .style(button::secondary)
+                    .on_press(Message::SettingsDialogue),
+            ]
+        ]
+        .spacing(15),
+    )
+    .width(500)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+pub fn recover_autosave_view(changes: &[PathBuf]) -> Element<'_, Message> {
+    let mut changes_col: Column<Message> = Column::new().spacing(2);
+    for path in changes {
+        changes_col = changes_col.push(text(path.display().to_string()).size(12));
+    }
+    container(
+        column![
+            text("Autosaved changes from a previous session were found, which was not closed normally."),
+            text("The following autosaved files differ from the project files:"),
+            scrollable(changes_col).height(Length::Shrink).width(Length::Fill),
+            text("Recovering will overwrite these project files. This action cannot be undone."),
+            row![
+                button(text("Recover"))
+                    .style(button::success)
+                    .on_press(Message::RecoverAutosave(changes.to_vec())),
+                horizontal_space(),
+                button(text("Discard autosave"))
+                    .style(button::danger)
+                    .on_press(Message::DiscardAutosave),
+            ]
+        ]
+        .spacing(15),
+    )
+    .width(500)
+    .max_height(500)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
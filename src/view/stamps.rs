@@ -0,0 +1,151 @@
+// Module for the library of stamps: named multi-tile structures saved for reuse.
+use iced::{
+    alignment::Vertical,
+    widget::{
+        button, column, container, horizontal_space, image, image::FilterMethod, row, scrollable,
+        text, text_input, Column, Space,
+    },
+    Element, Length,
+};
+
+use crate::{
+    helpers::{broken_tile_color, display_color},
+    message::Message,
+    state::{EditorState, Stamp, TileCoord, TILE_SIZE},
+};
+
+use super::modal_background_style;
+
+// Largest width or height (in pixels) of a stamp thumbnail; smaller stamps are shown at
+// double size:
+const MAX_THUMBNAIL_EXTENT: f32 = 128.0;
+
+// Image of a stamp as it was saved, in the current colors of its palettes. Cells outside the
+// stamp's mask are transparent.
+fn stamp_image(state: &EditorState, stamp: &Stamp) -> image::Handle {
+    let block = &stamp.block;
+    let width = block.size.0 as usize * TILE_SIZE;
+    let height = block.size.1 as usize * TILE_SIZE;
+    let mut data: Vec<u8> = vec![0; width * height * 4];
+    for ty in 0..block.size.1 as usize {
+        for tx in 0..block.size.0 as usize {
+            if !block.is_selected(tx as TileCoord, ty as TileCoord) {
+                continue;
+            }
+            let pal = state
+                .palettes_id_idx_map
+                .get(&block.palettes[ty][tx])
+                .map(|&i| &state.palettes[i]);
+            let tile = stamp.gfx.get(ty).and_then(|row| row.get(tx));
+            for y in 0..TILE_SIZE {
+                for x in 0..TILE_SIZE {
+                    let color = match (pal, tile) {
+                        (Some(pal), Some(tile)) => display_color(
+                            pal.colors[tile.pixels[y][x] as usize],
+                            state.global_config.color_profile,
+                        ),
+                        _ => broken_tile_color(x, y),
+                    };
+                    let i = ((ty * TILE_SIZE + y) * width + tx * TILE_SIZE + x) * 4;
+                    data[i..i + 3].copy_from_slice(&color);
+                    data[i + 3] = 255;
+                }
+            }
+        }
+    }
+    image::Handle::from_rgba(width as u32, height as u32, data)
+}
+
+fn stamp_entry<'a>(state: &'a EditorState, stamp: &'a Stamp) -> Element<'a, Message> {
+    let width = (stamp.block.size.0 as usize * TILE_SIZE) as f32;
+    let height = (stamp.block.size.1 as usize * TILE_SIZE) as f32;
+    let scale = (MAX_THUMBNAIL_EXTENT / width.max(height)).min(2.0);
+    row![
+        container(
+            image(stamp_image(state, stamp))
+                .width(width * scale)
+                .height(height * scale)
+                .filter_method(FilterMethod::Nearest),
+        )
+        .width(MAX_THUMBNAIL_EXTENT),
+        column![
+            text(&stamp.name),
+            text(format!(
+                "{}x{} tiles",
+                stamp.block.size.0, stamp.block.size.1
+            ))
+            .size(12),
+            row![
+                button(text("Place"))
+                    .style(button::secondary)
+                    .on_press(Message::LoadStamp(stamp.name.clone())),
+                button(text("Delete"))
+                    .style(button::danger)
+                    .on_press(Message::DeleteStamp(stamp.name.clone())),
+            ]
+            .spacing(5),
+        ]
+        .spacing(5),
+    ]
+    .spacing(10)
+    .align_y(Vertical::Center)
+    .into()
+}
+
+// Thumbnails of the saved stamps; placing one selects it for painting with the brush.
+pub fn stamps_view(state: &EditorState) -> Element<'_, Message> {
+    let mut col = Column::new().spacing(10);
+    for stamp in state.stamps.values() {
+        col = col.push(stamp_entry(state, stamp));
+    }
+    if state.stamps.is_empty() {
+        col =
+            col.push(text("No stamps saved. Select some tiles to save them as a stamp.").size(12));
+    }
+    column![
+        row![
+            text("Stamps"),
+            horizontal_space(),
+            button(text("Save selection"))
+                .style(button::secondary)
+                .on_press_maybe(
+                    (!state.selected_gfx.is_empty()).then_some(Message::SaveStampDialogue)
+                ),
+        ]
+        .align_y(Vertical::Center),
+        scrollable(col).height(Length::Fill),
+    ]
+    .padding(10)
+    .spacing(10)
+    .width(440)
+    .into()
+}
+
+pub fn save_stamp_view(state: &EditorState, name: &str) -> Element<'static, Message> {
+    let (w, h) = state.selected_tile_block.size;
+    container(
+        column![
+            text(format!("Save the {}x{} tile selection as a stamp.", w, h)),
+            row![
+                text("Name: ").width(70),
+                text_input("", name)
+                    .id("SaveStamp")
+                    .on_input(Message::SetSaveStampName)
+                    .on_submit(Message::SaveStamp(name.to_string()))
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                Space::with_width(Length::Fill),
+                button(text("Save stamp"))
+                    .style(button::success)
+                    .on_press(Message::SaveStamp(name.to_string())),
+            ],
+        ]
+        .spacing(10),
+    )
+    .width(450)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
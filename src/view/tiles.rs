@@ -1,24 +1,42 @@
 // Module for managing the set of 8x8 tiles belonging to a palette.
+use std::{ops::Range, path::Path};
+
+use hashbrown::HashSet;
 use iced::{
     mouse,
     widget::{
-        button, canvas, column, container, horizontal_space, row,
-        scrollable::{Direction, Scrollbar},
-        stack, text, Scrollable,
+        button, canvas, checkbox, column, container, horizontal_space, image,
+        image::FilterMethod,
+        pick_list, progress_bar, row,
+        scrollable::{self, Direction, Scrollbar},
+        stack, text, text_input, Column, Row, Scrollable,
     },
-    Element, Length, Point, Rectangle, Size,
+    Element, Length, Point, Rectangle, Size, Task,
 };
 use log::warn;
 
 use crate::{
-    helpers::{alpha_blend, scale_color},
+    helpers::{
+        alpha_blend, broken_tile_color, display_color, format_number, outline_thickness,
+        palette_label, scale_color,
+    },
     message::{Message, SelectionSource},
     state::{
-        ColorIdx, EditorState, Flip, Palette, PaletteId, Tile, TileBlock, TileCoord, TileIdx, Tool,
+        ColorIdx, ColorLimitPolicy, ColorMatchPolicy, ColorProfile, ColorRGB, EditorState, Flip,
+        ImageFilter, MouseButtonMapping, MoveCollision, MoveSnap, OutOfBoundsMove, Palette,
+        PaletteId, Tile, TileBlock, TileCoord, TileIdx, TilesetBackdrop, TilesetOrder, Tool,
+        TILE_SIZE,
     },
 };
 
-use super::modal_background_style;
+use super::{marching_rectangle, modal_background_style};
+
+// Pixel size used for drawing the tileset:
+const PIXEL_SIZE: usize = 3;
+const SCROLLABLE_ID: &str = "Tileset";
+
+// Pick-list entry for rendering the tileset using the colors of the palette being edited.
+const NO_PREVIEW_PALETTE: &str = "(current)";
 
 // We use two separate canvases: one for drawing the tile raster and one for the tile selection.
 // This is to work around a limitation in Iced's rendering pipeline that does not allow drawing
@@ -26,15 +44,64 @@ use super::modal_background_style;
 
 struct TileGrid<'a> {
     palette: &'a Palette,
+    // Palette whose colors are used to render the tiles (which may differ from
+    // the palette whose tiles are being edited):
+    preview_palette: &'a Palette,
+    color_profile: ColorProfile,
     pixel_size: f32,
     end_coords: Option<(TileCoord, TileCoord)>,
     tile_block: &'a TileBlock,
     selected_gfx: &'a Vec<Vec<Tile>>,
     thickness: f32,
     identify_color: bool,
+    pinned_identify_colors: &'a HashSet<ColorIdx>,
+    highlight_color: [u8; 3],
+    highlight_alpha: f32,
     brush_graphics_only: bool,
     color_idx: Option<ColorIdx>,
     tool: Tool,
+    out_of_bounds_move: OutOfBoundsMove,
+    backdrop: TilesetBackdrop,
+    backdrop_color: ColorRGB,
+    mouse_buttons: MouseButtonMapping,
+    order: TilesetOrder,
+    move_destination: MoveDestination,
+}
+
+// Placement of the selection by the Move tool, shared by the tile grid (which moves the tiles)
+// and the selection overlay (which previews the destination).
+#[derive(Clone, Copy, Debug)]
+struct MoveDestination {
+    snap: MoveSnap,
+    step: TileCoord,
+    size: (TileCoord, TileCoord),
+    // Position of the selection in the tileset, if it was selected there:
+    origin: Option<(TileCoord, TileCoord)>,
+}
+
+impl MoveDestination {
+    fn new(state: &EditorState) -> Self {
+        let s = &state.selected_tile_block;
+        let origin = (state.selection_source == SelectionSource::Tileset
+            && s.size.0 > 0
+            && s.size.1 > 0
+            && s.palettes[0][0] == state.palettes[state.palette_idx].id)
+            .then(|| (s.tiles[0][0] % 16, s.tiles[0][0] / 16));
+        MoveDestination {
+            snap: state.global_config.move_snap,
+            step: state.global_config.move_snap_step,
+            size: s.size,
+            origin,
+        }
+    }
+
+    // Top-left corner of the destination, with the cursor over the given tile:
+    fn at(&self, p: Point<TileCoord>) -> Point<TileCoord> {
+        let (x, y) = self
+            .snap
+            .snap((p.x, p.y), self.size, self.origin, self.step);
+        Point::new(x, y)
+    }
 }
 
 #[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
@@ -42,6 +109,7 @@ enum InternalStateAction {
     #[default]
     None,
     Selecting,
+    ReferenceSelecting,
     Brushing,
 }
 
@@ -49,6 +117,7 @@ enum InternalStateAction {
 struct InternalState {
     action: InternalStateAction,
     coords: Option<Point<TileCoord>>,
+    reference_start: Option<Point<TileCoord>>,
 }
 
 fn clamped_position_in(
@@ -57,8 +126,8 @@ fn clamped_position_in(
     rows: usize,
     pixel_size: f32,
 ) -> Point<TileCoord> {
-    let x = (f32::max(p.x - bounds.x, 0.0) / (8.0 * pixel_size)) as TileCoord;
-    let y = (f32::max(p.y - bounds.y, 0.0) / (8.0 * pixel_size)) as TileCoord;
+    let x = (f32::max(p.x - bounds.x, 0.0) / (TILE_SIZE as f32 * pixel_size)) as TileCoord;
+    let y = (f32::max(p.y - bounds.y, 0.0) / (TILE_SIZE as f32 * pixel_size)) as TileCoord;
     Point {
         x: x.min(15),
         y: y.min(rows as TileCoord - 1),
@@ -87,30 +156,36 @@ impl<'a> canvas::Program<Message> for TileGrid<'a> {
             canvas::Event::Mouse(mouse_event) => match mouse_event {
                 mouse::Event::ButtonPressed(btn @ (mouse::Button::Left | mouse::Button::Right)) => {
                     if let Some(p) = cursor.position_over(bounds) {
-                        match (self.tool, btn) {
+                        match (self.tool, self.mouse_buttons.map(btn)) {
                             (Tool::Brush, mouse::Button::Left) => {
-                                state.action = InternalStateAction::Brushing;
                                 let coords = clamped_position_in(
                                     p,
                                     bounds,
                                     self.palette.tiles.len() / 16,
                                     self.pixel_size,
                                 );
+                                let Some(msg) = self.brush_message(coords) else {
+                                    warn!("Only single tiles can be brushed while the tileset is sorted by usage.");
+                                    return (canvas::event::Status::Ignored, None);
+                                };
+                                state.action = InternalStateAction::Brushing;
+                                return (canvas::event::Status::Captured, Some(msg));
+                            }
+                            (Tool::Select, mouse::Button::Right) => {
+                                state.action = InternalStateAction::ReferenceSelecting;
+                                let p0 = clamped_position_in(
+                                    p,
+                                    bounds,
+                                    self.palette.tiles.len() / 16,
+                                    self.pixel_size,
+                                );
+                                state.reference_start = Some(p0);
                                 return (
                                     canvas::event::Status::Captured,
-                                    Some(Message::TilesetBrush {
-                                        palette_id: self.palette.id,
-                                        coords,
-                                        selected_gfx: self.selected_gfx.clone(),
-                                        tile_block: if self.brush_graphics_only {
-                                            None
-                                        } else {
-                                            Some(self.tile_block.clone())
-                                        },
-                                    }),
+                                    Some(Message::SetReferenceSelection(Some((p0, p0)))),
                                 );
                             }
-                            (Tool::Select, mouse::Button::Left | mouse::Button::Right)
+                            (Tool::Select, mouse::Button::Left)
                             | (Tool::Brush | Tool::Move, mouse::Button::Right) => {
                                 state.action = InternalStateAction::Selecting;
                                 return (
@@ -128,46 +203,84 @@ impl<'a> canvas::Program<Message> for TileGrid<'a> {
                             }
                             (Tool::Move, mouse::Button::Left) => {
                                 state.action = InternalStateAction::None;
-                                let dst_coords = clamped_position_in(
+                                if self.order.is_sorted() {
+                                    warn!("Tiles can't be moved while the tileset is sorted by usage.");
+                                    return (canvas::event::Status::Ignored, None);
+                                }
+                                let dst_coords = self.move_destination.at(clamped_position_in(
                                     p,
                                     bounds,
                                     self.palette.tiles.len() / 16,
                                     self.pixel_size,
-                                );
+                                ));
                                 let dst_palette_id = self.palette.id;
                                 let mut palettes: Vec<Vec<PaletteId>> = vec![];
                                 let mut tiles: Vec<Vec<TileIdx>> = vec![];
                                 let mut flips: Vec<Vec<Flip>> = vec![];
+                                // Selected cells whose destination is in bounds:
+                                let mut mask: Vec<Vec<bool>> = vec![];
+                                let mut num_out_of_bounds = 0;
+                                let mut num_in_bounds = 0;
                                 for y in 0..self.tile_block.size.1 {
                                     let mut pal_row: Vec<PaletteId> = vec![];
                                     let mut tile_row: Vec<TileIdx> = vec![];
                                     let mut flip_row: Vec<Flip> = vec![];
+                                    let mut mask_row: Vec<bool> = vec![];
                                     for x in 0..self.tile_block.size.0 {
                                         let x1 = dst_coords.x + x;
                                         let y1 = dst_coords.y + y;
                                         let i1 = y1 * 16 + x1;
-                                        if x1 >= 16 || i1 as usize >= self.palette.tiles.len() {
-                                            warn!("Not moving tiles: some destination tiles are out-of-bounds.");
-                                            return (canvas::event::Status::Ignored, None);
+                                        let selected = self.tile_block.is_selected(x, y);
+                                        let in_bounds =
+                                            x1 < 16 && (i1 as usize) < self.palette.tiles.len();
+                                        if selected && !in_bounds {
+                                            num_out_of_bounds += 1;
+                                        } else if selected {
+                                            num_in_bounds += 1;
                                         }
                                         pal_row.push(dst_palette_id);
-                                        tile_row.push(y1 * 16 + x1);
-                                        flip_row.push(Flip::None)
+                                        // Out-of-bounds cells are masked out, so their tile doesn't matter:
+                                        tile_row.push(if in_bounds { i1 } else { 0 });
+                                        flip_row.push(Flip::None);
+                                        mask_row.push(selected && in_bounds);
                                     }
                                     palettes.push(pal_row);
                                     tiles.push(tile_row);
                                     flips.push(flip_row);
+                                    mask.push(mask_row);
+                                }
+                                let mut src_selection = self.tile_block.clone();
+                                let mut dst_mask = self.tile_block.mask.clone();
+                                if num_out_of_bounds > 0 {
+                                    if self.out_of_bounds_move == OutOfBoundsMove::Cancel {
+                                        warn!(
+                                            "Not moving tiles: {} destination tiles are out-of-bounds.",
+                                            num_out_of_bounds
+                                        );
+                                        return (canvas::event::Status::Ignored, None);
+                                    }
+                                    if num_in_bounds == 0 {
+                                        warn!("Not moving tiles: all destination tiles are out-of-bounds.");
+                                        return (canvas::event::Status::Ignored, None);
+                                    }
+                                    warn!(
+                                        "Clipped {} tiles whose destination is out-of-bounds.",
+                                        num_out_of_bounds
+                                    );
+                                    src_selection.mask = Some(Box::new(mask));
+                                    dst_mask = src_selection.mask.clone();
                                 }
                                 let dst_selection = TileBlock {
                                     size: (self.tile_block.size.0, self.tile_block.size.1),
                                     palettes,
                                     tiles,
                                     flips,
+                                    mask: dst_mask,
                                 };
                                 return (
                                     canvas::event::Status::Captured,
                                     Some(Message::MovingTilesProgress {
-                                        src_selection: self.tile_block.clone(),
+                                        src_selection,
                                         dst_selection,
                                         check_reversible: true,
                                     }),
@@ -201,6 +314,20 @@ impl<'a> canvas::Program<Message> for TileGrid<'a> {
                 }
                 mouse::Event::CursorMoved { .. } => match state.action {
                     InternalStateAction::None => {}
+                    InternalStateAction::ReferenceSelecting => {
+                        if let (Some(p), Some(p0)) = (cursor.position(), state.reference_start) {
+                            let p1 = clamped_position_in(
+                                p,
+                                bounds,
+                                self.palette.tiles.len() / 16,
+                                self.pixel_size,
+                            );
+                            return (
+                                canvas::event::Status::Captured,
+                                Some(Message::SetReferenceSelection(Some((p0, p1)))),
+                            );
+                        }
+                    }
                     InternalStateAction::Selecting => {
                         if let Some(p) = cursor.position() {
                             return (
@@ -222,19 +349,7 @@ impl<'a> canvas::Program<Message> for TileGrid<'a> {
                                 self.palette.tiles.len() / 16,
                                 self.pixel_size,
                             );
-                            return (
-                                canvas::event::Status::Captured,
-                                Some(Message::TilesetBrush {
-                                    palette_id: self.palette.id,
-                                    coords,
-                                    selected_gfx: self.selected_gfx.clone(),
-                                    tile_block: if self.brush_graphics_only {
-                                        None
-                                    } else {
-                                        Some(self.tile_block.clone())
-                                    },
-                                }),
-                            );
+                            return (canvas::event::Status::Captured, self.brush_message(coords));
                         }
                     }
                 },
@@ -257,36 +372,53 @@ impl<'a> canvas::Program<Message> for TileGrid<'a> {
         let pixel_size = self.pixel_size;
         let thickness = self.thickness;
         let color_bytes: Vec<[u8; 3]> = self
-            .palette
+            .preview_palette
             .colors
             .iter()
-            .map(|&[r, g, b]| [scale_color(r), scale_color(g), scale_color(b)])
+            .map(|&c| display_color(c, self.color_profile))
             .collect();
 
+        let backdrop_color = self.backdrop_color.map(scale_color);
+        let backdrop_dark = backdrop_color.map(|c| c / 2);
+
         let tiles = &self.palette.tiles;
         let num_cols = 16;
         let num_rows = (tiles.len() + num_cols - 1) / num_cols;
 
         let mut data: Vec<u8> = vec![];
         data.reserve_exact(num_rows * num_cols * 64 * 4);
-        for y in 0..num_rows * 8 {
-            for x in 0..num_cols * 8 {
-                let tile_x = x / 8;
-                let tile_y = y / 8;
-                let pixel_x = x % 8;
-                let pixel_y = y % 8;
-                let tile_idx = tile_y * num_cols + tile_x;
-                if tile_idx >= tiles.len() {
+        for y in 0..num_rows * TILE_SIZE {
+            for x in 0..num_cols * TILE_SIZE {
+                let tile_x = x / TILE_SIZE;
+                let tile_y = y / TILE_SIZE;
+                let pixel_x = x % TILE_SIZE;
+                let pixel_y = y % TILE_SIZE;
+                let pos = tile_y * num_cols + tile_x;
+                if pos >= tiles.len() {
                     data.extend([0, 0, 0, 0]);
                     continue;
                 }
+                let tile_idx = self.order.tile_at(pos as TileIdx) as usize;
                 let tile = &self.palette.tiles[tile_idx];
                 let color_idx = tile.pixels[pixel_y][pixel_x];
-                let mut color = color_bytes[color_idx as usize];
-                if self.identify_color && self.color_idx == Some(color_idx) {
-                    let alpha = 0.5;
-                    let pink_highlight = [255, 105, 180];
-                    color = alpha_blend(color, pink_highlight, alpha);
+                let mut color = match self.backdrop {
+                    TilesetBackdrop::Solid if color_idx == 0 => backdrop_color,
+                    TilesetBackdrop::Checkerboard if color_idx == 0 => {
+                        if (x / 4 + y / 4) % 2 == 0 {
+                            backdrop_color
+                        } else {
+                            backdrop_dark
+                        }
+                    }
+                    _ => color_bytes
+                        .get(color_idx as usize)
+                        .copied()
+                        .unwrap_or_else(|| broken_tile_color(pixel_x, pixel_y)),
+                };
+                if (self.identify_color && self.color_idx == Some(color_idx))
+                    || self.pinned_identify_colors.contains(&color_idx)
+                {
+                    color = alpha_blend(color, self.highlight_color, self.highlight_alpha);
                 }
                 data.extend(&color);
                 data.push(255); // alpha channel
@@ -294,8 +426,8 @@ impl<'a> canvas::Program<Message> for TileGrid<'a> {
         }
 
         let image = iced::advanced::image::Image::new(iced::advanced::image::Handle::from_rgba(
-            (num_cols * 8) as u32,
-            (num_rows * 8) as u32,
+            (num_cols * TILE_SIZE) as u32,
+            (num_rows * TILE_SIZE) as u32,
             data,
         ))
         .filter_method(iced::widget::image::FilterMethod::Nearest)
@@ -305,8 +437,8 @@ impl<'a> canvas::Program<Message> for TileGrid<'a> {
             Rectangle::new(
                 Point::new(thickness, thickness),
                 Size {
-                    width: num_cols as f32 * 8.0 * (pixel_size as f32),
-                    height: num_rows as f32 * 8.0 * (pixel_size as f32),
+                    width: num_cols as f32 * TILE_SIZE as f32 * (pixel_size as f32),
+                    height: num_rows as f32 * TILE_SIZE as f32 * (pixel_size as f32),
                 },
             ),
             image,
@@ -326,6 +458,7 @@ impl<'a> canvas::Program<Message> for TileGrid<'a> {
                 Tool::Select => mouse::Interaction::default(),
                 Tool::Brush => mouse::Interaction::Crosshair,
                 Tool::Move => mouse::Interaction::Move,
+                Tool::Fill | Tool::Wand => mouse::Interaction::NotAllowed,
             }
         } else {
             mouse::Interaction::default()
@@ -333,57 +466,226 @@ impl<'a> canvas::Program<Message> for TileGrid<'a> {
     }
 }
 
-struct TileSelect {
+impl<'a> TileGrid<'a> {
+    // Brush the selection with its top-left corner at the given position. In a sorted tileset,
+    // neighboring positions don't hold neighboring tiles, so only single tiles can be brushed.
+    fn brush_message(&self, coords: Point<TileCoord>) -> Option<Message> {
+        let coords = if self.order.is_sorted() {
+            if self.selected_gfx.len() != 1 || self.selected_gfx[0].len() != 1 {
+                return None;
+            }
+            let tile_idx = self.order.tile_at(coords.y * 16 + coords.x);
+            Point::new(tile_idx % 16, tile_idx / 16)
+        } else {
+            coords
+        };
+        Some(Message::TilesetBrush {
+            palette_id: self.palette.id,
+            coords,
+            selected_gfx: self.selected_gfx.clone(),
+            tile_block: if self.brush_graphics_only {
+                None
+            } else {
+                Some(self.tile_block.clone())
+            },
+        })
+    }
+}
+
+struct TileSelect<'a> {
+    tiles: &'a [Tile],
+    order: TilesetOrder,
+    colors: [[u8; 3]; 16],
+    // Magnification of the preview of the hovered tile, shown if larger than `pixel_size`:
+    preview_zoom: f32,
+    preview_filter: ImageFilter,
     top: TileCoord,
     bottom: TileCoord,
     left: TileCoord,
     right: TileCoord,
     active: bool,
     selecting: bool,
+    marching_ants_phase: u8,
+    dash_colors: [ColorRGB; 2],
     pixel_size: f32,
     thickness: f32,
+    rows: usize,
+    hover_highlight: bool,
+    // First and last tile index of a linear selection in progress:
+    run: Option<(TileIdx, TileIdx)>,
+    // Opposite corners of the reference selection:
+    reference: Option<((TileCoord, TileCoord), (TileCoord, TileCoord))>,
+    // Tiles with a tag matching the tag search:
+    tag_matches: Vec<TileIdx>,
+    // Where the Move tool would place the selection, outlined at the hovered tile:
+    move_preview: Option<MoveDestination>,
 }
 
-impl canvas::Program<Message> for TileSelect {
-    // No internal state
-    type State = ();
+impl<'a> canvas::Program<Message> for TileSelect<'a> {
+    type State = InternalState;
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: canvas::Event,
+        bounds: iced::Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (canvas::event::Status, Option<Message>) {
+        // Track the hovered tile, without capturing the event (so that it still
+        // reaches the tile grid underneath):
+        if let canvas::Event::Mouse(_) = event {
+            state.coords = cursor
+                .position_over(bounds)
+                .map(|p| clamped_position_in(p, bounds, self.rows, self.pixel_size));
+        }
+        (canvas::event::Status::Ignored, None)
+    }
 
     fn draw(
         &self,
-        _state: &(),
+        state: &InternalState,
         renderer: &iced::Renderer,
         _theme: &iced::Theme,
         bounds: iced::Rectangle,
-        _cursor: mouse::Cursor,
+        cursor: mouse::Cursor,
     ) -> Vec<canvas::Geometry> {
-        if !self.active {
+        let hover_coords = if self.hover_highlight {
+            state.coords
+        } else {
+            None
+        };
+        let preview = if self.preview_zoom > self.pixel_size {
+            state
+                .coords
+                .and_then(|c| self.tiles.get(self.order.tile_at(c.y * 16 + c.x) as usize))
+                .zip(cursor.position_in(bounds))
+        } else {
+            None
+        };
+        if !self.active
+            && hover_coords.is_none()
+            && self.reference.is_none()
+            && preview.is_none()
+            && self.tag_matches.is_empty()
+            && self.move_preview.is_none()
+        {
             return vec![];
         }
         let mut frame = canvas::Frame::new(renderer, bounds.size());
 
         let pixel_size = self.pixel_size;
 
-        let x0 = self.left as f32 * pixel_size * 8.0 + self.thickness * 0.5;
-        let x1 = (self.right + 1) as f32 * pixel_size * 8.0 + self.thickness * 0.5;
-        let y0 = self.top as f32 * pixel_size * 8.0 + self.thickness * 0.5;
-        let y1 = (self.bottom + 1) as f32 * pixel_size * 8.0 + self.thickness * 0.5;
-        let path = canvas::Path::rectangle(
-            iced::Point { x: x0, y: y0 },
-            Size {
-                width: x1 - x0,
-                height: y1 - y0,
-            },
-        );
+        for &idx in &self.tag_matches {
+            frame.fill_rectangle(
+                Point::new(
+                    (idx % 16) as f32 * pixel_size * TILE_SIZE as f32 + self.thickness,
+                    (idx / 16) as f32 * pixel_size * TILE_SIZE as f32 + self.thickness,
+                ),
+                Size::new(pixel_size * TILE_SIZE as f32, pixel_size * TILE_SIZE as f32),
+                iced::Color::from_rgba(1.0, 1.0, 0.0, 0.4),
+            );
+        }
+
+        if let Some(h) = hover_coords {
+            let x0 = h.x as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+            let y0 = h.y as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+            let path = canvas::Path::rectangle(
+                iced::Point { x: x0, y: y0 },
+                Size {
+                    width: pixel_size * TILE_SIZE as f32,
+                    height: pixel_size * TILE_SIZE as f32,
+                },
+            );
+            frame.stroke(
+                &path,
+                canvas::Stroke {
+                    style: canvas::stroke::Style::Solid(iced::Color::from_rgba(1.0, 1.0, 1.0, 0.5)),
+                    width: self.thickness,
+                    ..Default::default()
+                },
+            );
+        }
+        if let Some((p0, p1)) = self.reference {
+            let x0 = p0.0.min(p1.0) as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+            let x1 =
+                (p0.0.max(p1.0) + 1) as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+            let y0 = p0.1.min(p1.1) as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+            let y1 =
+                (p0.1.max(p1.1) + 1) as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+            frame.stroke(
+                &canvas::Path::rectangle(Point::new(x0, y0), Size::new(x1 - x0, y1 - y0)),
+                canvas::Stroke {
+                    style: canvas::stroke::Style::Solid(iced::Color::from_rgb8(255, 140, 0)),
+                    width: self.thickness,
+                    ..Default::default()
+                },
+            );
+        }
+        if let (Some(dst), Some(c)) = (self.move_preview, state.coords) {
+            let p = dst.at(c);
+            let tile_size = pixel_size * TILE_SIZE as f32;
+            let x0 = p.x as f32 * tile_size + self.thickness * 0.5;
+            let y0 = p.y as f32 * tile_size + self.thickness * 0.5;
+            let width = dst.size.0.min(16 - p.x.min(16)) as f32 * tile_size;
+            let height = dst.size.1 as f32 * tile_size;
+            frame.stroke(
+                &canvas::Path::rectangle(Point::new(x0, y0), Size::new(width, height)),
+                canvas::Stroke {
+                    style: canvas::stroke::Style::Solid(iced::Color::from_rgb8(0, 160, 255)),
+                    width: self.thickness,
+                    ..Default::default()
+                },
+            );
+        }
+        if let Some((tile, p)) = preview {
+            self.draw_preview(&mut frame, bounds, tile, p);
+        }
+        if !self.active {
+            return vec![frame.into_geometry()];
+        }
+
+        // Outline the tiles from (left, top) to (right, bottom), inclusive, on each row:
+        let rectangles: Vec<(TileCoord, TileCoord, TileCoord, TileCoord)> = match self.run {
+            Some((first, last)) => (first / 16..=last / 16)
+                .map(|y| {
+                    let left = if y == first / 16 { first % 16 } else { 0 };
+                    let right = if y == last / 16 { last % 16 } else { 15 };
+                    (left, right, y, y)
+                })
+                .collect(),
+            None => vec![(self.left, self.right, self.top, self.bottom)],
+        };
+        let path = canvas::Path::new(|p| {
+            for &(left, right, top, bottom) in &rectangles {
+                let x0 = left as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+                let x1 = (right + 1) as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+                let y0 = top as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+                let y1 = (bottom + 1) as f32 * pixel_size * TILE_SIZE as f32 + self.thickness * 0.5;
+                marching_rectangle(
+                    p,
+                    iced::Point { x: x0, y: y0 },
+                    Size {
+                        width: x1 - x0,
+                        height: y1 - y0,
+                    },
+                    if self.selecting {
+                        self.marching_ants_phase as f32
+                    } else {
+                        0.0
+                    },
+                );
+            }
+        });
         if self.selecting {
             for i in 0..2 {
                 frame.stroke(
                     &path,
                     canvas::Stroke {
-                        style: if i == 0 {
-                            canvas::stroke::Style::Solid(iced::Color::WHITE)
-                        } else {
-                            canvas::stroke::Style::Solid(iced::Color::BLACK)
-                        },
+                        style: canvas::stroke::Style::Solid(iced::Color::from_rgb8(
+                            scale_color(self.dash_colors[i][0]),
+                            scale_color(self.dash_colors[i][1]),
+                            scale_color(self.dash_colors[i][2]),
+                        )),
                         width: self.thickness,
                         line_dash: canvas::LineDash {
                             offset: i,
@@ -407,17 +709,97 @@ impl canvas::Program<Message> for TileSelect {
     }
 }
 
+impl<'a> TileSelect<'a> {
+    // Draw a magnified copy of the tile near the cursor (at `p`, relative to the canvas),
+    // kept inside the canvas bounds.
+    fn draw_preview(
+        &self,
+        frame: &mut canvas::Frame,
+        bounds: iced::Rectangle,
+        tile: &Tile,
+        p: Point,
+    ) {
+        let size = self.preview_zoom * TILE_SIZE as f32;
+        let border = 2.0;
+        let offset = 16.0;
+        let mut x = p.x + offset;
+        let mut y = p.y + offset;
+        if x + size + border > bounds.width {
+            x = p.x - offset - size;
+        }
+        if y + size + border > bounds.height {
+            y = p.y - offset - size;
+        }
+        let x = x.max(border);
+        let y = y.max(border);
+
+        let mut data: Vec<u8> = Vec::with_capacity(64 * 4);
+        for row in &tile.pixels {
+            for &color_idx in row {
+                data.extend(self.colors[color_idx as usize]);
+                data.push(255);
+            }
+        }
+        let image = iced::advanced::image::Image::new(iced::advanced::image::Handle::from_rgba(
+            TILE_SIZE as u32,
+            TILE_SIZE as u32,
+            data,
+        ))
+        .filter_method(self.preview_filter.filter_method())
+        .snap(true);
+        frame.fill_rectangle(
+            Point::new(x - border, y - border),
+            Size::new(size + 2.0 * border, size + 2.0 * border),
+            iced::Color::WHITE,
+        );
+        frame.draw_image(
+            Rectangle::new(Point::new(x, y), Size::new(size, size)),
+            image,
+        );
+    }
+}
+
+// Scroll the tileset so that the row containing the given tile is at the top.
+pub fn scroll_to_tile(tile_idx: TileIdx) -> Task<Message> {
+    scrollable::scroll_to(
+        scrollable::Id::new(SCROLLABLE_ID),
+        scrollable::AbsoluteOffset {
+            x: 0.0,
+            y: (tile_idx as usize / 16 * TILE_SIZE * PIXEL_SIZE) as f32,
+        },
+    )
+}
+
+// Range of tile rows covered by the current tileset selection, if any.
+fn selected_tile_rows(state: &EditorState) -> Option<Range<TileIdx>> {
+    let (top, bottom) = match (state.selection_source, state.start_coords, state.end_coords) {
+        (SelectionSource::Tileset, Some(p0), Some(p1)) => (p0.1.min(p1.1), p0.1.max(p1.1)),
+        _ => {
+            let row = state.tile_idx? / 16;
+            (row, row)
+        }
+    };
+    let num_tiles = state.palettes[state.palette_idx].tiles.len() as TileIdx;
+    Some(top * 16..((bottom + 1) * 16).min(num_tiles))
+}
+
 pub fn tile_view(state: &EditorState, size: Size, reserved_height: f32) -> Element<Message> {
     let num_cols = 16;
     let num_rows = (state.palettes[state.palette_idx].tiles.len() + num_cols - 1) / num_cols;
-    let pixel_size = 3;
-    let height = num_rows * pixel_size * 8 + 10;
+    let pixel_size = PIXEL_SIZE;
+    let height = num_rows * pixel_size * TILE_SIZE + 10;
 
     let mut left = 0;
     let mut right = 0;
     let mut top = 0;
     let mut bottom = 0;
     let mut selecting = false;
+    let mut run = None;
+    let order = state.tileset_order();
+    let move_destination = MoveDestination::new(state);
+    let move_preview =
+        (state.tool == Tool::Move && !order.is_sorted() && !state.selected_gfx.is_empty())
+            .then_some(move_destination);
 
     match (state.start_coords, state.end_coords) {
         (Some(p0), Some(p1)) => {
@@ -426,9 +808,17 @@ pub fn tile_view(state: &EditorState, size: Size, reserved_height: f32) -> Eleme
             top = p0.1.min(p1.1);
             bottom = p0.1.max(p1.1);
             selecting = true;
+            if state.selection_source == SelectionSource::Tileset
+                && state.global_config.linear_select
+                && !order.is_sorted()
+            {
+                let (i0, i1) = (p0.1 * 16 + p0.0, p1.1 * 16 + p1.0);
+                run = Some((i0.min(i1), i0.max(i1)));
+            }
         }
         _ => {
             if let Some(idx) = state.tile_idx {
+                let idx = order.position_of(idx);
                 left = idx % num_cols as TileCoord;
                 right = left;
                 top = idx / num_cols as TileCoord;
@@ -437,6 +827,42 @@ pub fn tile_view(state: &EditorState, size: Size, reserved_height: f32) -> Eleme
         }
     }
 
+    let preview_palette = state
+        .preview_palette_id
+        .and_then(|id| state.palettes_id_idx_map.get(&id))
+        .map(|&idx| &state.palettes[idx])
+        .unwrap_or(&state.palettes[state.palette_idx]);
+    let mut preview_names: Vec<String> = vec![NO_PREVIEW_PALETTE.to_string()];
+    preview_names.extend(
+        state
+            .palettes
+            .iter()
+            .map(|x| palette_label(x, state.global_config.display_hex)),
+    );
+    let selected_preview_name = if state.preview_palette_id.is_some() {
+        palette_label(preview_palette, state.global_config.display_hex)
+    } else {
+        NO_PREVIEW_PALETTE.to_string()
+    };
+
+    let pal = &state.palettes[state.palette_idx];
+    let search = state.tile_tag_search.trim().to_lowercase();
+    let tag_matches: Vec<TileIdx> = if search.is_empty() {
+        vec![]
+    } else {
+        pal.tags
+            .iter()
+            .filter(|(_, tags)| tags.iter().any(|t| t.to_lowercase().contains(&search)))
+            .map(|(&idx, _)| order.position_of(idx))
+            .collect()
+    };
+    let selected_tags = state
+        .tile_idx
+        .and_then(|idx| pal.tags.get(&idx))
+        .map(|tags| tags.join(", "))
+        .unwrap_or_default();
+    let has_tag_input = !state.tile_tag_input.trim().is_empty();
+
     let col = column![
         row![
             text("Tiles"),
@@ -446,26 +872,142 @@ pub fn tile_view(state: &EditorState, size: Size, reserved_height: f32) -> Eleme
             button(text("\u{F63B}").font(iced_fonts::BOOTSTRAP_FONT))
                 .style(button::danger)
                 .on_press(Message::DeleteTileRow(state.palettes[state.palette_idx].id)),
+            button(text("\u{F1BC}").font(iced_fonts::BOOTSTRAP_FONT))
+                .style(button::secondary)
+                .on_press(Message::ImportTilesetPNGDialogue),
+            button(text("CHR"))
+                .style(button::secondary)
+                .on_press(Message::ImportChr(state.palettes[state.palette_idx].id)),
+            button(text("\u{F1A2}").font(iced_fonts::BOOTSTRAP_FONT))
+                .style(button::secondary)
+                .on_press(Message::TilePagesDialogue),
+            button(text("\u{F30A}").font(iced_fonts::BOOTSTRAP_FONT))
+                .style(button::secondary)
+                .on_press_maybe(
+                    (!state.selected_gfx.is_empty())
+                        .then_some(Message::ExportSelectionTilesetDialogue)
+                ),
+            checkbox("By usage", state.tileset_sort_by_usage)
+                .on_toggle(Message::SetTilesetSortByUsage)
+                .text_size(12),
+            horizontal_space(),
+            text("Preview"),
+            pick_list(
+                preview_names,
+                Some(selected_preview_name),
+                Message::SelectPreviewPalette
+            )
+            .text_size(12)
+            .width(180),
         ]
         .spacing(10)
         .align_y(iced::alignment::Vertical::Center),
+        Row::with_children(
+            state.palettes[state.palette_idx]
+                .pages
+                .iter()
+                .enumerate()
+                .map(|(i, (name, _))| {
+                    button(text(name.clone()).size(12))
+                        .style(button::secondary)
+                        .padding([2, 6])
+                        .on_press(Message::ScrollToTilePage(i))
+                        .into()
+                })
+        )
+        .spacing(5)
+        .wrap(),
+        row![
+            text_input("Search tags", &state.tile_tag_search)
+                .on_input(Message::SetTileTagSearch)
+                .size(12)
+                .width(150),
+            text(if search.is_empty() {
+                String::new()
+            } else {
+                format!("{} matching", tag_matches.len())
+            })
+            .size(12),
+            horizontal_space(),
+            text_input("Tag", &state.tile_tag_input)
+                .on_input(Message::SetTileTagInput)
+                .on_submit_maybe(has_tag_input.then_some(Message::TagSelectedTiles))
+                .size(12)
+                .width(100),
+            button(text("Tag").size(12))
+                .style(button::secondary)
+                .padding([2, 6])
+                .on_press_maybe(has_tag_input.then_some(Message::TagSelectedTiles)),
+            button(text("Untag").size(12))
+                .style(button::secondary)
+                .padding([2, 6])
+                .on_press_maybe(has_tag_input.then_some(Message::UntagSelectedTiles)),
+        ]
+        .spacing(5)
+        .align_y(iced::alignment::Vertical::Center),
+        row![
+            text(if selected_tags.is_empty() {
+                String::new()
+            } else {
+                format!("Tags: {}", selected_tags)
+            })
+            .size(12),
+            horizontal_space(),
+            // Insert a blank row before the row of the selected tile, or delete that row:
+            button(text("Insert row").size(12))
+                .style(button::secondary)
+                .padding([2, 6])
+                .on_press_maybe(
+                    state
+                        .tile_idx
+                        .map(|t| Message::InsertTileRow(pal.id, t as usize / 16))
+                ),
+            button(text("Delete row").size(12))
+                .style(button::danger)
+                .padding([2, 6])
+                .on_press_maybe(
+                    state
+                        .tile_idx
+                        .map(|t| Message::DeleteTileRowAt(pal.id, t as usize / 16))
+                ),
+        ]
+        .spacing(5)
+        .align_y(iced::alignment::Vertical::Center),
         Scrollable::with_direction(
             column![stack![
                 canvas(TileGrid {
                     palette: &state.palettes[state.palette_idx],
+                    preview_palette,
+                    color_profile: state.global_config.color_profile,
                     pixel_size: pixel_size as f32,
                     end_coords: state.end_coords,
                     tile_block: &state.selected_tile_block,
                     selected_gfx: &state.selected_gfx,
-                    thickness: 1.0,
+                    thickness: outline_thickness(pixel_size as f32),
                     identify_color: state.identify_color,
+                    pinned_identify_colors: &state.pinned_identify_colors,
+                    highlight_color: state.global_config.highlight_color.map(scale_color),
+                    highlight_alpha: state.global_config.highlight_alpha,
                     color_idx: state.color_idx,
                     tool: state.tool,
                     brush_graphics_only: state.shift_brush,
+                    out_of_bounds_move: state.global_config.out_of_bounds_move,
+                    backdrop: state.global_config.tileset_backdrop,
+                    backdrop_color: state.global_config.tileset_backdrop_color,
+                    mouse_buttons: state.global_config.mouse_buttons,
+                    order: order.clone(),
+                    move_destination,
                 })
                 .width(384 + 4)
-                .height((num_rows * 8 * pixel_size + 4) as f32),
+                .height((num_rows * TILE_SIZE * pixel_size + 4) as f32),
                 canvas(TileSelect {
+                    tiles: &state.palettes[state.palette_idx].tiles,
+                    order,
+                    colors: preview_palette
+                        .colors
+                        .map(|c| display_color(c, state.global_config.color_profile)),
+                    preview_zoom: state.global_config.tile_preview_zoom as f32,
+                    preview_filter: state.global_config.preview_filter,
                     active: state.tile_idx.is_some()
                         || (state.selection_source == SelectionSource::Tileset
                             && state.start_coords.is_some()
@@ -475,14 +1017,23 @@ pub fn tile_view(state: &EditorState, size: Size, reserved_height: f32) -> Eleme
                     top,
                     bottom,
                     selecting,
+                    marching_ants_phase: state.marching_ants_phase,
+                    dash_colors: state.global_config.selection_dash_colors,
                     pixel_size: pixel_size as f32,
-                    thickness: 1.0,
+                    thickness: outline_thickness(pixel_size as f32),
+                    rows: num_rows,
+                    hover_highlight: state.global_config.tile_hover_highlight,
+                    run,
+                    reference: state.reference_coords,
+                    tag_matches,
+                    move_preview,
                 })
                 .width(384 + 4)
-                .height((num_rows * 8 * pixel_size + 4) as f32)
+                .height((num_rows * TILE_SIZE * pixel_size + 4) as f32)
             ],],
             Direction::Vertical(Scrollbar::default())
         )
+        .id(scrollable::Id::new(SCROLLABLE_ID))
         .width(420)
         .height(if height as f32 + reserved_height > size.height {
             Length::Fill
@@ -494,24 +1045,100 @@ pub fn tile_view(state: &EditorState, size: Size, reserved_height: f32) -> Eleme
     row![col].padding(10).into()
 }
 
-pub fn moving_tiles_progress_view(_state: &EditorState) -> Element<Message> {
-    container(text(
-        "Please wait while the tiles are moved across the project.",
-    ))
+pub fn moving_tiles_progress_view(_state: &EditorState, progress: f32) -> Element<Message> {
+    container(
+        column![
+            text("Please wait while the tiles are moved across the project."),
+            progress_bar(0.0..=1.0, progress).height(10),
+            row![
+                horizontal_space(),
+                button(text("Cancel"))
+                    .style(button::danger)
+                    .on_press(Message::CancelMove),
+            ],
+        ]
+        .spacing(15),
+    )
     .width(350)
     .padding(25)
     .style(modal_background_style)
     .into()
 }
 
+// Image of a tile in the colors of its palette, for small previews:
+fn tile_image(state: &EditorState, palette_id: PaletteId, tile_idx: TileIdx) -> image::Handle {
+    let mut data: Vec<u8> = Vec::with_capacity(TILE_SIZE * TILE_SIZE * 4);
+    let pal = state
+        .palettes_id_idx_map
+        .get(&palette_id)
+        .map(|&i| &state.palettes[i]);
+    let tile = pal.and_then(|p| p.tiles.get(tile_idx as usize));
+    for y in 0..TILE_SIZE {
+        for x in 0..TILE_SIZE {
+            let color = match (pal, tile) {
+                (Some(pal), Some(tile)) => display_color(
+                    pal.colors[tile.pixels[y][x] as usize],
+                    state.global_config.color_profile,
+                ),
+                _ => broken_tile_color(x, y),
+            };
+            data.extend(color);
+            data.push(255);
+        }
+    }
+    image::Handle::from_rgba(TILE_SIZE as u32, TILE_SIZE as u32, data)
+}
+
 pub fn move_tiles_view(
-    _state: &EditorState,
+    state: &EditorState,
     src_selection: &TileBlock,
     dst_selection: &TileBlock,
+    collisions: &[MoveCollision],
 ) -> Element<'static, Message> {
+    let num_uses: usize = collisions.iter().map(|c| c.num_uses).sum();
+    let mut collisions_col = Column::new().spacing(2);
+    for c in collisions {
+        collisions_col = collisions_col.push(
+            row![
+                image(tile_image(state, c.palette_id, c.tile_idx))
+                    .width(24)
+                    .height(24)
+                    .filter_method(FilterMethod::Nearest),
+                text(format!(
+                    "Palette {} tile {}: {} {}",
+                    format_number(c.palette_id, state.global_config.display_hex),
+                    format_number(c.tile_idx, state.global_config.display_hex),
+                    c.num_uses,
+                    if c.num_uses == 1 { "use" } else { "uses" }
+                ))
+                .size(12),
+            ]
+            .spacing(10)
+            .align_y(iced::alignment::Vertical::Center),
+        );
+    }
     container(
         column![
-            text("Destination tiles are in use."),
+            text(format!(
+                "{} destination {} in use, with {} {} in areas:",
+                collisions.len(),
+                if collisions.len() == 1 {
+                    "tile is"
+                } else {
+                    "tiles are"
+                },
+                num_uses,
+                if num_uses == 1 {
+                    "reference"
+                } else {
+                    "references"
+                }
+            )),
+            Scrollable::new(collisions_col).height(if collisions.len() > 8 {
+                Length::Fixed(220.0)
+            } else {
+                Length::Shrink
+            }),
             text("Moving tiles onto them will collapse the tiles together."),
             text("This action cannot be undone."),
             row![
@@ -535,3 +1162,167 @@ pub fn move_tiles_view(
     .style(modal_background_style)
     .into()
 }
+
+pub fn import_tileset_png_view(
+    state: &EditorState,
+    path: &Path,
+    quantize: bool,
+) -> Element<'static, Message> {
+    let palette = &state.palettes[state.palette_idx];
+    container(
+        column![
+            text(format!(
+                "Import tiles from \"{}\" into palette {}?",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                palette_label(palette, state.global_config.display_hex)
+            )),
+            text("The pixels of existing tiles will be overwritten, starting from the first tile."),
+            checkbox("Quantize image colors into the palette", quantize)
+                .on_toggle(Message::SetImportTilesetPNGQuantize),
+            text(if quantize {
+                "The palette colors will be replaced with the image colors."
+            } else {
+                match state.global_config.color_match_policy {
+                    ColorMatchPolicy::Exact => "Image colors must all match palette colors.",
+                    ColorMatchPolicy::Nearest => {
+                        "Image colors will be mapped to the nearest palette colors."
+                    }
+                }
+            }),
+            if quantize {
+                row![
+                    text("If the image has more than 16 colors"),
+                    pick_list(
+                        ColorLimitPolicy::ALL,
+                        Some(state.global_config.color_limit_policy),
+                        Message::SetColorLimitPolicy
+                    )
+                    .width(150),
+                ]
+            } else {
+                row![
+                    text("If image colors aren't in the palette"),
+                    pick_list(
+                        ColorMatchPolicy::ALL,
+                        Some(state.global_config.color_match_policy),
+                        Message::SetColorMatchPolicy
+                    )
+                    .width(150),
+                ]
+            }
+            .spacing(10)
+            .align_y(iced::alignment::Vertical::Center),
+            text("This action cannot be undone."),
+            row![
+                button(text("Cancel"))
+                    .style(button::secondary)
+                    .on_press(Message::CloseDialogue),
+                horizontal_space(),
+                button(text("Import tiles")).style(button::danger).on_press(
+                    Message::ImportTilesetPNG {
+                        palette_id: palette.id,
+                        path: path.to_owned(),
+                        quantize,
+                    }
+                ),
+            ]
+        ]
+        .spacing(15),
+    )
+    .width(500)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+pub fn import_tileset_png_report_view(report: &str) -> Element<'static, Message> {
+    container(
+        column![
+            text(report.to_string()),
+            button(text("Close"))
+                .style(button::secondary)
+                .on_press(Message::CloseDialogue),
+        ]
+        .spacing(15),
+    )
+    .width(500)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
+
+pub fn tile_pages_view<'a>(state: &'a EditorState, name: &'a str) -> Element<'a, Message> {
+    let pal = &state.palettes[state.palette_idx];
+    let mut pages_col: Column<Message> = Column::new().spacing(5);
+    for (i, (page_name, range)) in pal.pages.iter().enumerate() {
+        let mut pages = pal.pages.clone();
+        pages.remove(i);
+        pages_col = pages_col.push(
+            row![
+                text(page_name.clone()).width(Length::Fill),
+                text(format!(
+                    "Tiles {}-{}",
+                    format_number(range.start, state.global_config.display_hex),
+                    format_number(range.end.saturating_sub(1), state.global_config.display_hex)
+                ))
+                .width(120),
+                button(text("\u{F63B}").font(iced_fonts::BOOTSTRAP_FONT))
+                    .style(button::danger)
+                    .on_press(Message::SetTilePages {
+                        palette_id: pal.id,
+                        pages,
+                    }),
+            ]
+            .spacing(10)
+            .align_y(iced::alignment::Vertical::Center),
+        );
+    }
+
+    let range = selected_tile_rows(state);
+    let add_msg = range.clone().map(|range| {
+        let mut pages = pal.pages.clone();
+        pages.push((name.to_string(), range));
+        pages.sort_by_key(|(_, r)| r.start);
+        Message::SetTilePages {
+            palette_id: pal.id,
+            pages,
+        }
+    });
+    container(
+        column![
+            text(format!(
+                "Tile pages for palette {}",
+                palette_label(pal, state.global_config.display_hex)
+            )),
+            pages_col,
+            text(match &range {
+                Some(r) => format!(
+                    "New page covers the selected rows (tiles {}-{}).",
+                    r.start,
+                    r.end.saturating_sub(1)
+                ),
+                None => "Select tiles in the tileset to define a new page.".to_string(),
+            }),
+            row![
+                text("Name: "),
+                text_input("", name)
+                    .id("TilePageName")
+                    .on_input(Message::SetTilePageName)
+                    .on_submit_maybe(add_msg.clone()),
+                button(text("Add page"))
+                    .style(button::success)
+                    .on_press_maybe(add_msg),
+            ]
+            .spacing(10)
+            .align_y(iced::alignment::Vertical::Center),
+            button(text("Close"))
+                .style(button::secondary)
+                .on_press(Message::CloseDialogue),
+        ]
+        .spacing(15),
+    )
+    .width(450)
+    .padding(25)
+    .style(modal_background_style)
+    .into()
+}
@@ -0,0 +1,178 @@
+// Module for the world overview: a zoomed-out map of all screens in the project (for the
+// current theme), laid out according to how the screens border each other.
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use hashbrown::{HashMap, HashSet};
+use iced::advanced::image::Handle;
+
+use crate::{
+    helpers::downscale_pixel,
+    persist::{area_color_bytes, draw_screen, load_area},
+    state::{AreaId, EditorState, ScreenId, Side, WorldMap},
+};
+
+// Width and height of each screen in the overview image, in pixels:
+pub const OVERVIEW_SCREEN_SIZE: usize = 64;
+const SCALE: usize = 256 / OVERVIEW_SCREEN_SIZE;
+const BG_COLOR: [u8; 4] = [32, 32, 32, 255];
+
+pub struct WorldOverview {
+    // Position of each screen in the overview, in screens:
+    pub positions: HashMap<(u32, u32), ScreenId>,
+    // Size of the overview, in screens:
+    pub size: (u32, u32),
+    pub image: Handle,
+}
+
+impl WorldOverview {
+    pub fn screen_at(&self, x: u32, y: u32) -> Option<&ScreenId> {
+        self.positions.get(&(x, y))
+    }
+}
+
+// Place the screens of the given areas (by name and size) on a grid, so that bordering
+// screens are next to each other. Each connected group of screens is laid out separately,
+// from left to right. If links are inconsistent (i.e. would put two screens in the same
+// place), the later screen starts a new group instead.
+pub fn layout_world(
+    world_map: &WorldMap,
+    areas: &[(String, (u8, u8))],
+) -> (HashMap<(u32, u32), ScreenId>, (u32, u32)) {
+    let sizes: HashMap<&str, (u8, u8)> = areas.iter().map(|(n, s)| (n.as_str(), *s)).collect();
+    let neighbor = |screen: &ScreenId, side: Side| -> Option<ScreenId> {
+        if let Some(s) = world_map.neighbor(screen, side) {
+            // Ignore links to screens that no longer exist (e.g. after resizing an area):
+            let size = sizes.get(s.area.as_str())?;
+            return (s.x < size.0 && s.y < size.1).then(|| s.clone());
+        }
+        let size = sizes[screen.area.as_str()];
+        let (dx, dy) = side.offset();
+        let (x, y) = (screen.x as i32 + dx, screen.y as i32 + dy);
+        if x < 0 || y < 0 || x >= size.0 as i32 || y >= size.1 as i32 {
+            return None;
+        }
+        Some(ScreenId {
+            area: screen.area.clone(),
+            x: x as u8,
+            y: y as u8,
+        })
+    };
+
+    let mut positions: HashMap<(u32, u32), ScreenId> = HashMap::new();
+    let mut placed: HashSet<ScreenId> = HashSet::new();
+    let mut width: u32 = 0;
+    let mut height: u32 = 0;
+    for (area_name, size) in areas {
+        for y in 0..size.1 {
+            for x in 0..size.0 {
+                let start = ScreenId {
+                    area: area_name.clone(),
+                    x,
+                    y,
+                };
+                if placed.contains(&start) {
+                    continue;
+                }
+
+                // Breadth-first search over the group of connected screens:
+                let mut group: HashMap<(i32, i32), ScreenId> = HashMap::new();
+                let mut queue = VecDeque::new();
+                group.insert((0, 0), start.clone());
+                placed.insert(start.clone());
+                queue.push_back((start, (0, 0)));
+                while let Some((screen, (px, py))) = queue.pop_front() {
+                    for side in Side::ALL {
+                        let Some(n) = neighbor(&screen, side) else {
+                            continue;
+                        };
+                        let (dx, dy) = side.offset();
+                        let pos = (px + dx, py + dy);
+                        if placed.contains(&n) || group.contains_key(&pos) {
+                            continue;
+                        }
+                        group.insert(pos, n.clone());
+                        placed.insert(n.clone());
+                        queue.push_back((n, pos));
+                    }
+                }
+
+                let min_x = group.keys().map(|p| p.0).min().unwrap();
+                let min_y = group.keys().map(|p| p.1).min().unwrap();
+                let max_x = group.keys().map(|p| p.0).max().unwrap();
+                let max_y = group.keys().map(|p| p.1).max().unwrap();
+                // Leave a gap of one screen between groups:
+                let offset_x = if width == 0 { 0 } else { width + 1 };
+                for ((gx, gy), screen) in group {
+                    positions.insert(
+                        ((gx - min_x) as u32 + offset_x, (gy - min_y) as u32),
+                        screen,
+                    );
+                }
+                width = offset_x + (max_x - min_x + 1) as u32;
+                height = height.max((max_y - min_y + 1) as u32);
+            }
+        }
+    }
+    (positions, (width, height))
+}
+
+// Render the overview of every area (in the theme of the main area), loading areas
+// from disk if they aren't already loaded.
+pub fn build_world_overview(state: &EditorState) -> Result<WorldOverview> {
+    let theme = state.main_area_id.theme.clone();
+    let mut sizes: Vec<(String, (u8, u8))> = vec![];
+    let mut screens: HashMap<ScreenId, Vec<u8>> = HashMap::new();
+    for area_name in &state.area_names {
+        let area_id = AreaId {
+            area: area_name.clone(),
+            theme: theme.clone(),
+        };
+        let loaded;
+        let area = match state.areas.get(&area_id) {
+            Some(area) => area,
+            None => {
+                loaded = load_area(state, &area_id)?;
+                &loaded
+            }
+        };
+        sizes.push((area_name.clone(), area.size));
+        let color_bytes = area_color_bytes(state, area);
+        for sy in 0..area.size.1 {
+            for sx in 0..area.size.0 {
+                let screen = &area.screens[sy as usize * area.size.0 as usize + sx as usize];
+                let mut data = vec![0; 256 * 256 * 3];
+                draw_screen(state, screen, &color_bytes, &mut data, 0, 256 * 3);
+                let id = ScreenId {
+                    area: area_name.clone(),
+                    x: sx,
+                    y: sy,
+                };
+                screens.insert(id, data);
+            }
+        }
+    }
+
+    let (positions, size) = layout_world(&state.world_map, &sizes);
+    let width = size.0 as usize * OVERVIEW_SCREEN_SIZE;
+    let height = size.1 as usize * OVERVIEW_SCREEN_SIZE;
+    let filter = state.global_config.preview_filter;
+    let mut data = BG_COLOR.repeat(width * height);
+    for (&(px, py), id) in &positions {
+        let screen = &screens[id];
+        for oy in 0..OVERVIEW_SCREEN_SIZE {
+            for ox in 0..OVERVIEW_SCREEN_SIZE {
+                let color = downscale_pixel(screen, 256, ox, oy, SCALE, filter);
+                let x = px as usize * OVERVIEW_SCREEN_SIZE + ox;
+                let y = py as usize * OVERVIEW_SCREEN_SIZE + oy;
+                let addr = (y * width + x) * 4;
+                data[addr..addr + 3].copy_from_slice(&color);
+            }
+        }
+    }
+    Ok(WorldOverview {
+        positions,
+        size,
+        image: Handle::from_rgba(width as u32, height as u32, data),
+    })
+}